@@ -41,6 +41,11 @@ export interface Manifest {
   claimGeneratorHints: Record<string, unknown> | null;
   claimGeneratorInfo: ClaimGeneratorInfo[];
 
+  /**
+   * Icon accessor for the first claim generator that provides one, if available
+   */
+  claimGeneratorIcon: Thumbnail | null;
+
   /**
    * Instance ID from `xmpMM:InstanceID` in XMP metadata.
    */
@@ -101,13 +106,22 @@ export function createManifest(
     ),
   );
 
+  const claimGeneratorInfo = manifestData.claim_generator_info ?? [];
+  const claimGeneratorIcon = claimGeneratorInfo.reduce<Thumbnail | null>(
+    (icon, info) =>
+      icon ??
+      (info.icon ? createThumbnail(manifestData.resources, info.icon) : null),
+    null,
+  );
+
   return {
     title: manifestData.title,
     format: manifestData.format,
     vendor: manifestData.vendor ?? null,
     claimGenerator: manifestData.claim_generator,
     claimGeneratorHints: manifestData.claim_generator_hints ?? null,
-    claimGeneratorInfo: manifestData.claim_generator_info ?? [],
+    claimGeneratorInfo,
+    claimGeneratorIcon,
     instanceId: manifestData.instance_id,
     signatureInfo: manifestData.signature_info ?? null,
     credentials: manifestData.credentials ?? [],
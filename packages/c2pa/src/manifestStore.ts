@@ -66,7 +66,8 @@ function parseJsonTypeHints(
     ) {
       const val = x[SERDE_ARBITRARY_PRECISION_KEY];
       if (val > Number.MAX_SAFE_INTEGER) {
-        this.update(BigInt(val));
+        // Engines without BigInt support get the decimal string instead, matching the compat toolkit build
+        this.update(typeof BigInt === 'function' ? BigInt(val) : val);
       } else {
         this.update(parseInt(val, 10));
       }
@@ -7,7 +7,7 @@
  * it.
  */
 
-import { ActionV1, C2paActionsAssertion } from '@contentauth/toolkit';
+import { Action, ActionV1, C2paActionsAssertion } from '@contentauth/toolkit';
 import debug from 'debug';
 import each from 'lodash/each';
 import compact from 'lodash/fp/compact';
@@ -127,7 +127,10 @@ export async function selectEditsAndActivity(
     manifest.assertions.get('com.adobe.dictionary')[0] ??
     manifest.assertions.get('adobe.dictionary')[0];
 
-  const [actionAssertion] = manifest.assertions.get('c2pa.actions');
+  const [actionAssertion] = [
+    ...manifest.assertions.get('c2pa.actions'),
+    ...manifest.assertions.get('c2pa.actions.v2'),
+  ];
 
   if (!actionAssertion) {
     return null;
@@ -146,7 +149,7 @@ export async function selectEditsAndActivity(
 }
 
 async function getPhotoshopCategorizedActions(
-  actions: ActionV1[],
+  actions: Action[],
   dictionaryUrl: string,
   locale = DEFAULT_LOCALE,
   iconVariant: IconVariant = 'dark',
@@ -8,7 +8,7 @@
  */
 
 import type {
-  ActionV1,
+  Action,
   Assertion,
   C2paActionsAssertion,
   ManifestAssertion,
@@ -39,7 +39,7 @@ export type GenAiAssertion = ManifestAssertion | LegacyAssertion;
 
 export interface GenerativeInfo {
   assertion: GenAiAssertion;
-  action?: ActionV1;
+  action?: Action;
   type:
     | 'legacy'
     | 'trainedAlgorithmicMedia'
@@ -73,11 +73,14 @@ export function selectGenerativeInfo(
         ];
       }
 
-      // Check for actions v1 assertion
-      if (assertion.label === 'c2pa.actions') {
+      // Check for actions assertions of either version
+      if (
+        assertion.label === 'c2pa.actions' ||
+        assertion.label === 'c2pa.actions.v2'
+      ) {
         const { actions } = (assertion as C2paActionsAssertion).data;
         const genAiActions: GenerativeInfo[] = actions.reduce<GenerativeInfo[]>(
-          (actionAcc, action: ActionV1) => {
+          (actionAcc, action: Action) => {
             const { digitalSourceType } = action;
             if (
               digitalSourceType &&
@@ -87,7 +90,11 @@ export function selectGenerativeInfo(
                 assertion,
                 action: action,
                 type: formatGenAiDigitalSourceTypes(digitalSourceType),
-                softwareAgent: action.softwareAgent,
+                // Version 2 actions describe the agent with a claim generator info
+                softwareAgent:
+                  typeof action.softwareAgent === 'string'
+                    ? action.softwareAgent
+                    : action.softwareAgent?.name,
               } as GenerativeInfo);
             }
 
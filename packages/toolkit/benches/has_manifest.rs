@@ -0,0 +1,45 @@
+// Copyright 2024 Adobe
+// All Rights Reserved.
+//
+// NOTICE: Adobe permits you to use, modify, and distribute this file in
+// accordance with the terms of the Adobe license agreement accompanying
+// it.
+
+//! Compares `hasManifest` against a full manifest store read on a large asset.
+//!
+//! Run with `cargo bench --bench has_manifest`.
+use c2pa::ManifestStore;
+use c2pa_js_toolkit::presence::{has_manifest, ManifestPresence};
+use criterion::{criterion_group, criterion_main, Criterion};
+
+const ASSET_SIZE: usize = 50 * 1024 * 1024;
+
+/// Pads the entropy-coded data of a fixture out to `ASSET_SIZE`
+fn large_jpeg() -> Vec<u8> {
+    let fixture = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+    let mut asset = fixture[..fixture.len() - 2].to_vec();
+    asset.resize(ASSET_SIZE - 2, 0);
+    asset.extend_from_slice(&[0xFF, 0xD9]);
+    asset
+}
+
+fn bench_has_manifest(c: &mut Criterion) {
+    let asset = large_jpeg();
+    assert_eq!(
+        has_manifest(&asset[..], "image/jpeg"),
+        ManifestPresence::Embedded
+    );
+
+    let mut group = c.benchmark_group("50MB jpeg");
+    group.sample_size(10);
+    group.bench_function("hasManifest", |b| {
+        b.iter(|| has_manifest(&asset[..], "image/jpeg"))
+    });
+    group.bench_function("full read", |b| {
+        b.iter(|| ManifestStore::from_bytes("image/jpeg", &asset, true))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_has_manifest);
+criterion_main!(benches);
@@ -22,6 +22,7 @@ async function runProcess(cmd, args, opts) {
     const proc = spawn(cmd, args.split(' '), {
       cwd: opts.packageDir,
       shell: opts.shell,
+      env: { ...process.env, ...opts.env },
     });
 
     proc.stdout.on('data', (data) => {
@@ -46,13 +47,34 @@ async function buildWasm(opts) {
   const cmd = `wasm-pack`;
   const mode = opts.dev ? 'dev' : 'release';
   const verbosity = opts.verbose ? 'verbose' : 'quiet';
-  const args = `build --weak-refs --${verbosity} --out-name toolkit --${mode} --target web`;
+  // Older engines may lack `FinalizationRegistry`, so the compat build doesn't rely on weak refs
+  const weakRefs = opts.compat ? '' : '--weak-refs ';
+  const features = opts.compat ? ' -- --features compat' : '';
+  const args = `build ${weakRefs}--${verbosity} --out-dir ${opts.outDir} --out-name toolkit --${mode} --target web${features}`;
 
-  opts.verbose && console.log(`Building in ${mode.toUpperCase()} mode`);
+  opts.verbose &&
+    console.log(
+      `Building in ${mode.toUpperCase()} mode${opts.compat ? ' (compat)' : ''}`,
+    );
 
   await runProcess(cmd, args, opts);
 }
 
+/**
+ * Checks that `types/generated.ts` matches the Rust types, or regenerates it when `--update-schema` is passed
+ * @param {*} opts Options for this script invocation
+ */
+async function checkGeneratedTypes(opts) {
+  const args = `test --quiet --lib --features json_schema schema::`;
+
+  opts = {
+    ...opts,
+    env: opts.updateSchema ? { UPDATE_TYPES: '1' } : {},
+  };
+
+  await runProcess('cargo', args, opts);
+}
+
 /**
  * Builds TypeScript types for export
  * @param {*} opts Options for this script invocation
@@ -68,7 +90,12 @@ async function buildTypes(opts) {
     shell: true,
   };
 
-  await runProcess(tscPath, `--project ${tsconfigPath}`, opts);
+  const outDir = join(opts.packageDir, opts.outDir, 'types');
+  await runProcess(
+    tscPath,
+    `--project ${tsconfigPath} --outDir ${outDir}`,
+    opts,
+  );
 }
 
 /**
@@ -76,7 +103,7 @@ async function buildTypes(opts) {
  * @param {*} opts Options for this script invocation
  */
 async function generateIntegrity(opts) {
-  const distDir = join(opts.packageDir, 'pkg');
+  const distDir = join(opts.packageDir, opts.outDir);
   const integrityPath = join(distDir, 'integrity.json');
   const files = await fg(['toolkit*'], {
     absolute: true,
@@ -107,20 +134,35 @@ async function generateIntegrity(opts) {
  * Main entry point - this does the following:
  * - uses wasm-pack to build the Wasm from c2pa-rs
  * - Removes unneeded files
+ * - Verifies the TypeScript types generated from the Rust structures are up to date
  * - Builds any TypeScript types for export
  * - Generates an `integrity.json` file to be used by the main c2pa package
+ *
+ * Passing `--compat` builds the variant for engines without BigInt support into `pkg-compat`.
  */
 async function build() {
   const opts = {
     dev: !!process.argv.find((x) => x === '--dev'),
     verbose: !!process.argv.find((x) => x === '--verbose'),
+    updateSchema: !!process.argv.find((x) => x === '--update-schema'),
+    compat: !!process.argv.find((x) => x === '--compat'),
     packageDir: await packageDirectory(),
   };
+  opts.outDir = opts.compat ? 'pkg-compat' : 'pkg';
+
+  if (opts.updateSchema) {
+    await checkGeneratedTypes(opts);
+    return;
+  }
 
   try {
-    const toDelete = ['./pkg/.gitignore', './pkg/package.json'];
+    const toDelete = [
+      `./${opts.outDir}/.gitignore`,
+      `./${opts.outDir}/package.json`,
+    ];
     await buildWasm(opts);
     await rimraf(toDelete);
+    await checkGeneratedTypes(opts);
     await buildTypes(opts);
     await generateIntegrity(opts);
   } catch (err) {
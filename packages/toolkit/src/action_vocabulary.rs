@@ -0,0 +1,213 @@
+// Copyright 2024 Adobe
+// All Rights Reserved.
+//
+// NOTICE: Adobe permits you to use, modify, and distribute this file in
+// accordance with the terms of the Adobe license agreement accompanying
+// it.
+
+//! Custom action vocabularies registered by the application, such as `com.example.reviewed`.
+//!
+//! Actions outside the `c2pa.` namespace are otherwise opaque strings. Once registered, the
+//! actions of a manifest that use them are annotated with `registered: true`, the registered
+//! description as `registeredDescription`, and the required parameters they lack, so that a UI
+//! can show them like the standard ones. Registration only affects how manifests are read, never
+//! how they validate.
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+/// Action namespaces reserved by the C2PA specification
+const RESERVED_PREFIXES: &[&str] = &["c2pa.", "stds."];
+
+/// A custom action, as passed from JavaScript
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VocabularyEntry {
+    pub action: String,
+    #[serde(default)]
+    pub required_parameters: Vec<String>,
+    pub description: Option<String>,
+}
+
+/// What is merged into an action that uses a registered vocabulary entry
+#[derive(Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActionAnnotation {
+    pub registered: bool,
+    /// Kept apart from the action's own `description`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub registered_description: Option<String>,
+    /// The required parameters the action doesn't have
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub missing_parameters: Vec<String>,
+}
+
+thread_local! {
+    static VOCABULARY: RefCell<HashMap<String, VocabularyEntry>> = RefCell::new(HashMap::new());
+}
+
+/// Adds entries to the vocabulary, replacing any registered for the same actions
+///
+/// Nothing is registered if any entry is empty or in a reserved namespace.
+pub fn register(entries: Vec<VocabularyEntry>) -> Result<()> {
+    if let Some(entry) = entries.iter().find(|entry| {
+        entry.action.trim().is_empty()
+            || RESERVED_PREFIXES
+                .iter()
+                .any(|prefix| entry.action.starts_with(prefix))
+    }) {
+        return Err(Error::ReservedAction(entry.action.clone()));
+    }
+
+    VOCABULARY.with(|cell| {
+        let mut vocabulary = cell.borrow_mut();
+        for entry in entries {
+            vocabulary.insert(entry.action.clone(), entry);
+        }
+    });
+    Ok(())
+}
+
+pub fn clear() {
+    VOCABULARY.with(|cell| cell.borrow_mut().clear());
+}
+
+pub fn is_empty() -> bool {
+    VOCABULARY.with(|cell| cell.borrow().is_empty())
+}
+
+/// Annotates an action with its vocabulary entry, given the names of its parameters, or returns
+/// `None` if the action isn't registered
+pub fn annotate(action: &str, parameters: &[String]) -> Option<ActionAnnotation> {
+    VOCABULARY.with(|cell| {
+        let vocabulary = cell.borrow();
+        let entry = vocabulary.get(action)?;
+        Some(ActionAnnotation {
+            registered: true,
+            registered_description: entry.description.clone(),
+            missing_parameters: entry
+                .required_parameters
+                .iter()
+                .filter(|parameter| !parameters.contains(parameter))
+                .cloned()
+                .collect(),
+        })
+    })
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
+
+    fn entry(action: &str, required_parameters: &[&str]) -> VocabularyEntry {
+        VocabularyEntry {
+            action: action.to_owned(),
+            required_parameters: required_parameters.iter().map(|p| p.to_string()).collect(),
+            description: Some(format!("{} by the review team", action)),
+        }
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_action_vocabulary() {
+        clear();
+        register(vec![
+            entry("com.example.reviewed", &["reviewer", "ticket"]),
+            entry("com.example.ai-upscaled", &[]),
+        ])
+        .unwrap();
+        assert!(!is_empty());
+
+        let parameters = vec!["reviewer".to_owned()];
+        assert_eq!(
+            annotate("com.example.reviewed", &parameters),
+            Some(ActionAnnotation {
+                registered: true,
+                registered_description: Some("com.example.reviewed by the review team".to_owned()),
+                missing_parameters: vec!["ticket".to_owned()],
+            })
+        );
+        assert_eq!(
+            annotate("com.example.ai-upscaled", &[])
+                .unwrap()
+                .missing_parameters,
+            Vec::<String>::new()
+        );
+        assert_eq!(annotate("com.example.unknown", &[]), None);
+        assert_eq!(annotate("c2pa.edited", &[]), None);
+
+        // A batch with a reserved action registers nothing
+        for action in ["c2pa.reviewed", "stds.reviewed", " "] {
+            let err =
+                register(vec![entry("com.example.approved", &[]), entry(action, &[])]).unwrap_err();
+            assert!(matches!(err, Error::ReservedAction(_)));
+        }
+        assert_eq!(annotate("com.example.approved", &[]), None);
+
+        clear();
+        assert!(is_empty());
+        assert_eq!(annotate("com.example.reviewed", &parameters), None);
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test]
+    pub fn test_register_action_vocabulary() {
+        use crate::get_path;
+        use wasm_bindgen::JsValue;
+
+        let entries = js_sys::JSON::parse(
+            r#"[{ "action": "com.example.reviewed", "requiredParameters": ["reviewer"],
+                  "description": "Reviewed by the standards desk" }]"#,
+        )
+        .unwrap();
+        crate::register_action_vocabulary(entries).unwrap();
+        let store = js_sys::JSON::parse(
+            r#"{ "manifests": { "urn:uuid:1": { "assertions": [{ "label": "c2pa.actions.v2",
+                 "data": { "actions": [
+                     { "action": "com.example.reviewed", "description": "Checked the caption" },
+                     { "action": "com.example.reviewed", "parameters": { "reviewer": "A" } },
+                     { "action": "c2pa.edited" }
+                 ] } }] } } }"#,
+        )
+        .unwrap();
+        crate::add_action_vocabulary(&store).unwrap();
+
+        let actions = get_path(
+            &store,
+            &[
+                "manifests",
+                "urn:uuid:1",
+                "assertions",
+                "0",
+                "data",
+                "actions",
+            ],
+        );
+        assert_eq!(get_path(&actions, &["0", "registered"]), true);
+        assert_eq!(
+            get_path(&actions, &["0", "description"]),
+            "Checked the caption"
+        );
+        assert_eq!(
+            get_path(&actions, &["0", "registeredDescription"]),
+            "Reviewed by the standards desk"
+        );
+        assert_eq!(
+            get_path(&actions, &["0", "missingParameters", "0"]),
+            "reviewer"
+        );
+        assert_eq!(
+            get_path(&actions, &["1", "missingParameters"]),
+            JsValue::UNDEFINED
+        );
+        assert_eq!(get_path(&actions, &["2", "registered"]), JsValue::UNDEFINED);
+
+        let reserved = js_sys::JSON::parse(r#"[{ "action": "c2pa.reviewed" }]"#).unwrap();
+        let err = crate::register_action_vocabulary(reserved).unwrap_err();
+        assert_eq!(get_path(&err, &["code"]), "reservedAction");
+        crate::clear_action_vocabulary();
+    }
+}
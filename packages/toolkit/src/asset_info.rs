@@ -0,0 +1,544 @@
+// Copyright 2024 Adobe
+// All Rights Reserved.
+//
+// NOTICE: Adobe permits you to use, modify, and distribute this file in
+// accordance with the terms of the Adobe license agreement accompanying
+// it.
+
+//! Reads the dimensions and duration of an asset from its container headers.
+//!
+//! Only headers are read, nothing is decoded. Anything that can't be found or parsed is left out
+//! rather than failing, since this is extra information alongside the manifest store.
+use crate::segments::{JpegSegments, PngChunks, JPEG_APP1, PNG_SIGNATURE};
+use serde::Serialize;
+use std::convert::TryFrom;
+
+/// Prefix of the EXIF data in a JPEG `APP1` segment, which some WebP writers copy as well
+const EXIF_SIGNATURE: &[u8] = b"Exif\0\0";
+
+/// The TIFF `ImageWidth` tag
+const IMAGE_WIDTH_TAG: u64 = 0x0100;
+/// The TIFF `ImageLength` tag, which is the height
+const IMAGE_LENGTH_TAG: u64 = 0x0101;
+/// The EXIF `Orientation` tag
+const ORIENTATION_TAG: u64 = 0x0112;
+
+/// Dimensions of images and videos, and duration of videos and audio
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetInfo {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// The EXIF orientation, from 1 to 8, which `width` and `height` don't take into account
+    pub orientation: Option<u16>,
+    /// In seconds
+    pub duration: Option<f64>,
+    pub track_count: Option<u32>,
+}
+
+/// Returns what the asset's headers tell about it, identifying its format from its leading bytes
+pub fn asset_info(data: &[u8]) -> AssetInfo {
+    let riff_type = match data.get(..12) {
+        Some([b'R', b'I', b'F', b'F', _, _, _, _, riff_type @ ..]) => Some(riff_type),
+        _ => None,
+    };
+
+    if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        jpeg_info(data)
+    } else if data.starts_with(&PNG_SIGNATURE) {
+        png_info(data)
+    } else if data.starts_with(b"II*\0") || data.starts_with(b"MM\0*") {
+        tiff_info(data)
+    } else if riff_type == Some(b"WEBP") {
+        webp_info(data)
+    } else if riff_type == Some(b"WAVE") {
+        wav_info(data)
+    } else if data.get(4..8) == Some(b"ftyp") {
+        bmff_info(data)
+    } else {
+        AssetInfo::default()
+    }
+}
+
+/// Reads a big endian unsigned integer of `len` bytes at `offset`
+pub fn read_be(data: &[u8], offset: usize, len: usize) -> Option<u64> {
+    let bytes = data.get(offset..offset.checked_add(len)?)?;
+    Some(bytes.iter().fold(0, |value, b| (value << 8) | *b as u64))
+}
+
+/// Reads a little endian unsigned integer of `len` bytes at `offset`
+fn read_le(data: &[u8], offset: usize, len: usize) -> Option<u64> {
+    let bytes = data.get(offset..offset.checked_add(len)?)?;
+    Some(
+        bytes
+            .iter()
+            .rev()
+            .fold(0, |value, b| (value << 8) | *b as u64),
+    )
+}
+
+/// Reads an unsigned integer of `len` bytes at `offset`, in the byte order of the data
+type ReadInt = fn(&[u8], usize, usize) -> Option<u64>;
+
+/// An entry of the first IFD of TIFF or EXIF data
+pub(crate) struct IfdEntry {
+    read: ReadInt,
+    field_type: u64,
+    /// Where the value is, or its offset if it doesn't fit in the entry
+    value_offset: usize,
+}
+
+/// Finds the entry for `tag` in the first IFD of TIFF or EXIF data, in either byte order
+pub(crate) fn ifd0_entry(tiff: &[u8], tag: u64) -> Option<IfdEntry> {
+    let read: ReadInt = match tiff.get(..2)? {
+        b"MM" => read_be,
+        b"II" => read_le,
+        _ => return None,
+    };
+
+    // BigTIFF widens the IFD offset, the entry count and the entries
+    let (ifd_offset, count_len, entry_len, value_offset) = match read(tiff, 2, 2)? {
+        43 => (read(tiff, 8, 8)?, 8, 20, 12),
+        _ => (read(tiff, 4, 4)?, 2, 12, 8),
+    };
+    let ifd_offset = usize::try_from(ifd_offset).ok()?;
+    let count = read(tiff, ifd_offset, count_len)?;
+    let entries = ifd_offset.checked_add(count_len)?;
+    // The count can claim far more entries than there are, so the walk stops at the end of the data
+    let (entry, _) = (0..count)
+        .map_while(|index| {
+            let offset = usize::try_from(index).ok()?.checked_mul(entry_len)?;
+            let entry = entries.checked_add(offset)?;
+            Some((entry, read(tiff, entry, 2)?))
+        })
+        .find(|(_, entry_tag)| *entry_tag == tag)?;
+
+    Some(IfdEntry {
+        read,
+        field_type: read(tiff, entry + 2, 2)?,
+        value_offset: entry + value_offset,
+    })
+}
+
+/// Returns the value of a `SHORT` or `LONG` tag in the first IFD of TIFF or EXIF data
+fn ifd0_value(tiff: &[u8], tag: u64) -> Option<u64> {
+    let entry = ifd0_entry(tiff, tag)?;
+    // Values that fit in the entry are stored in it, left aligned
+    match entry.field_type {
+        3 => (entry.read)(tiff, entry.value_offset, 2),
+        4 => (entry.read)(tiff, entry.value_offset, 4),
+        _ => None,
+    }
+}
+
+/// Finds the `Orientation` tag in the first IFD of EXIF data
+fn exif_orientation(tiff: &[u8]) -> Option<u16> {
+    ifd0_value(tiff, ORIENTATION_TAG)
+        .and_then(|orientation| u16::try_from(orientation).ok())
+        .filter(|orientation| (1..=8).contains(orientation))
+}
+
+/// Reads the dimensions and orientation from the first IFD
+fn tiff_info(data: &[u8]) -> AssetInfo {
+    AssetInfo {
+        width: ifd0_value(data, IMAGE_WIDTH_TAG).and_then(|width| u32::try_from(width).ok()),
+        height: ifd0_value(data, IMAGE_LENGTH_TAG).and_then(|height| u32::try_from(height).ok()),
+        orientation: exif_orientation(data),
+        ..AssetInfo::default()
+    }
+}
+
+/// Reads the dimensions from the start of frame segment and the orientation from the EXIF
+fn jpeg_info(data: &[u8]) -> AssetInfo {
+    let mut info = AssetInfo::default();
+
+    for segment in JpegSegments::new(data) {
+        let marker = segment.marker;
+        match marker {
+            // Start of frame, except for the DHT, JPG and DAC markers sharing the range
+            0xC0..=0xCF if !matches!(marker, 0xC4 | 0xC8 | 0xCC) => {
+                let segment = segment.data(data);
+                info.height = read_be(segment, 1, 2).map(|height| height as u32);
+                info.width = read_be(segment, 3, 2).map(|width| width as u32);
+            }
+            JPEG_APP1 if info.orientation.is_none() => {
+                info.orientation = segment
+                    .data(data)
+                    .strip_prefix(EXIF_SIGNATURE)
+                    .and_then(exif_orientation);
+            }
+            _ => {}
+        }
+    }
+
+    info
+}
+
+/// Reads the dimensions from the `IHDR` chunk and the orientation from the `eXIf` chunk
+fn png_info(data: &[u8]) -> AssetInfo {
+    let mut info = AssetInfo::default();
+
+    for chunk in PngChunks::new(data) {
+        match &chunk.chunk_type {
+            b"IHDR" => {
+                let chunk = chunk.data(data);
+                info.width = read_be(chunk, 0, 4).map(|width| width as u32);
+                info.height = read_be(chunk, 4, 4).map(|height| height as u32);
+            }
+            b"eXIf" => info.orientation = exif_orientation(chunk.data(data)),
+            _ => {}
+        }
+    }
+
+    info
+}
+
+/// Calls `visit` with the type and data of each chunk of a RIFF file
+fn riff_chunks<'a>(data: &'a [u8], mut visit: impl FnMut(&[u8], &'a [u8])) {
+    let mut pos: usize = 12;
+    while let Some(header) = pos.checked_add(8).and_then(|start| data.get(pos..start)) {
+        let len = read_le(header, 4, 4).unwrap_or_default() as usize;
+        let start = pos + 8;
+        let end = start.saturating_add(len).min(data.len());
+        visit(&header[..4], &data[start..end]);
+        // Chunks are padded to an even size
+        pos = match len
+            .checked_add(len % 2)
+            .and_then(|len| start.checked_add(len))
+        {
+            Some(next) => next,
+            None => break,
+        };
+    }
+}
+
+/// Reads the dimensions from the first chunk giving them and the orientation from the `EXIF`
+/// chunk
+fn webp_info(data: &[u8]) -> AssetInfo {
+    let mut info = AssetInfo::default();
+
+    riff_chunks(data, |chunk_type, chunk| {
+        let dimensions = match chunk_type {
+            // The canvas size, less one, on 24 bits each
+            b"VP8X" => read_le(chunk, 4, 3)
+                .zip(read_le(chunk, 7, 3))
+                .map(|(width, height)| (width + 1, height + 1)),
+            // The frame size on 14 bits each, after the frame tag and start code
+            b"VP8 " => read_le(chunk, 6, 2)
+                .zip(read_le(chunk, 8, 2))
+                .map(|(width, height)| (width & 0x3FFF, height & 0x3FFF)),
+            // The image size, less one, on 14 bits each, after the signature byte
+            b"VP8L" => {
+                read_le(chunk, 1, 4).map(|bits| ((bits & 0x3FFF) + 1, ((bits >> 14) & 0x3FFF) + 1))
+            }
+            b"EXIF" => {
+                let tiff = chunk.strip_prefix(EXIF_SIGNATURE).unwrap_or(chunk);
+                info.orientation = exif_orientation(tiff);
+                None
+            }
+            _ => None,
+        };
+        if let (Some((width, height)), None) = (dimensions, info.width) {
+            info.width = Some(width as u32);
+            info.height = Some(height as u32);
+        }
+    });
+
+    info
+}
+
+/// Computes the duration from the byte rate of the `fmt ` chunk and the size of the `data` chunk
+fn wav_info(data: &[u8]) -> AssetInfo {
+    let mut byte_rate = None;
+    let mut data_len = None;
+    riff_chunks(data, |chunk_type, chunk| match chunk_type {
+        b"fmt " => byte_rate = read_le(chunk, 8, 4).filter(|byte_rate| *byte_rate > 0),
+        b"data" => data_len = Some(chunk.len() as u64),
+        _ => {}
+    });
+
+    AssetInfo {
+        duration: byte_rate
+            .zip(data_len)
+            .map(|(byte_rate, data_len)| data_len as f64 / byte_rate as f64),
+        // The channels of a WAV file are interleaved in a single track
+        track_count: Some(1),
+        ..AssetInfo::default()
+    }
+}
+
+/// Calls `visit` with the type and contents of each box in `data`
+pub fn bmff_boxes<'a>(data: &'a [u8], mut visit: impl FnMut(&[u8], &'a [u8])) {
+    let mut pos = 0;
+    while let Some(box_type) = data.get(pos + 4..pos + 8) {
+        let (header_len, len) = match read_be(data, pos, 4) {
+            // The size is on 64 bits after the type
+            Some(1) => (16, read_be(data, pos + 8, 8)),
+            // The box extends to the end of its parent
+            Some(0) => (8, Some((data.len() - pos) as u64)),
+            len => (8, len),
+        };
+        let len = match len.and_then(|len| usize::try_from(len).ok()) {
+            Some(len) if len >= header_len => len,
+            _ => return,
+        };
+        let end = pos.saturating_add(len).min(data.len());
+        visit(
+            box_type,
+            data.get(pos + header_len..end).unwrap_or_default(),
+        );
+        pos = end;
+    }
+}
+
+/// Reads the duration from the `mvhd` box, the number of `trak` boxes, and the dimensions of the
+/// first visual track from its `tkhd` box
+fn bmff_info(data: &[u8]) -> AssetInfo {
+    let mut info = AssetInfo::default();
+
+    bmff_boxes(data, |box_type, moov| {
+        if box_type != b"moov" {
+            return;
+        }
+        let mut track_count = 0;
+        bmff_boxes(moov, |box_type, contents| match box_type {
+            b"mvhd" => info.duration = mvhd_duration(contents),
+            b"trak" => {
+                track_count += 1;
+                bmff_boxes(contents, |box_type, tkhd| {
+                    if let (b"tkhd", None) = (box_type, info.width) {
+                        if let Some((width, height)) = tkhd_dimensions(tkhd) {
+                            info.width = Some(width);
+                            info.height = Some(height);
+                        }
+                    }
+                });
+            }
+            _ => {}
+        });
+        info.track_count = Some(track_count);
+    });
+
+    info
+}
+
+/// The duration of the movie in seconds, unless it is unknown
+fn mvhd_duration(mvhd: &[u8]) -> Option<f64> {
+    // Version 1 widens the creation time, modification time and duration
+    let (timescale, duration) = match mvhd.first()? {
+        1 => (read_be(mvhd, 20, 4)?, read_be(mvhd, 24, 8)?),
+        _ => (read_be(mvhd, 12, 4)?, read_be(mvhd, 16, 4)?),
+    };
+    let unknown = match mvhd.first()? {
+        1 => u64::MAX,
+        _ => u32::MAX as u64,
+    };
+
+    if timescale == 0 || duration == unknown {
+        return None;
+    }
+    Some(duration as f64 / timescale as f64)
+}
+
+/// The width and height of a track, if it is a visual track
+fn tkhd_dimensions(tkhd: &[u8]) -> Option<(u32, u32)> {
+    // Version 1 widens the creation time, modification time and duration
+    let offset = match tkhd.first()? {
+        1 => 88,
+        _ => 76,
+    };
+    // Both are 16.16 fixed point numbers
+    let width = (read_be(tkhd, offset, 4)? >> 16) as u32;
+    let height = (read_be(tkhd, offset + 4, 4)? >> 16) as u32;
+
+    Some((width, height)).filter(|&(width, height)| width > 0 && height > 0)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
+
+    /// Builds EXIF data with an `Orientation` tag
+    fn exif(orientation: u16) -> Vec<u8> {
+        let mut tiff = b"MM\0*".to_vec();
+        tiff.extend_from_slice(&8u32.to_be_bytes());
+        tiff.extend_from_slice(&1u16.to_be_bytes());
+        tiff.extend_from_slice(&(ORIENTATION_TAG as u16).to_be_bytes());
+        // A single SHORT, left aligned in the value field
+        tiff.extend_from_slice(&3u16.to_be_bytes());
+        tiff.extend_from_slice(&1u32.to_be_bytes());
+        tiff.extend_from_slice(&orientation.to_be_bytes());
+        tiff.extend_from_slice(&[0, 0]);
+        // No next IFD
+        tiff.extend_from_slice(&0u32.to_be_bytes());
+        tiff
+    }
+
+    /// Builds a RIFF file of `riff_type` with the given chunks
+    fn riff(riff_type: &[u8], chunks: &[(&[u8], &[u8])]) -> Vec<u8> {
+        let mut contents = riff_type.to_vec();
+        for (chunk_type, chunk) in chunks {
+            contents.extend_from_slice(chunk_type);
+            contents.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+            contents.extend_from_slice(chunk);
+            if chunk.len() % 2 == 1 {
+                contents.push(0);
+            }
+        }
+
+        let mut result = b"RIFF".to_vec();
+        result.extend_from_slice(&(contents.len() as u32).to_le_bytes());
+        result.extend_from_slice(&contents);
+        result
+    }
+
+    /// Builds an ISO BMFF box
+    fn bmff_box(box_type: &[u8], contents: &[&[u8]]) -> Vec<u8> {
+        let len: usize = contents.iter().map(|content| content.len()).sum();
+        let mut result = ((8 + len) as u32).to_be_bytes().to_vec();
+        result.extend_from_slice(box_type);
+        for content in contents {
+            result.extend_from_slice(content);
+        }
+        result
+    }
+
+    /// Builds a version 0 `tkhd` box
+    fn tkhd(width: u32, height: u32) -> Vec<u8> {
+        let mut contents = vec![0; 76];
+        contents.extend_from_slice(&(width << 16).to_be_bytes());
+        contents.extend_from_slice(&(height << 16).to_be_bytes());
+        bmff_box(b"tkhd", &[&contents])
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_image_info() {
+        let caicai = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+        let info = asset_info(caicai);
+        assert_eq!((info.width, info.height), (Some(2048), Some(1365)));
+        assert_eq!(info.orientation, None);
+        assert_eq!((info.duration, info.track_count), (None, None));
+
+        let cloud = include_bytes!("../../../tools/testing/fixtures/images/cloud.jpg");
+        let info = asset_info(cloud);
+        assert_eq!((info.width, info.height), (Some(375), Some(375)));
+        assert_eq!(info.orientation, Some(1));
+
+        // An EXIF segment inserted after the start of image marker
+        let mut segment = EXIF_SIGNATURE.to_vec();
+        segment.extend_from_slice(&exif(6));
+        let mut rotated = vec![0xFF, 0xD8, 0xFF, 0xE1];
+        rotated.extend_from_slice(&((segment.len() + 2) as u16).to_be_bytes());
+        rotated.extend_from_slice(&segment);
+        rotated.extend_from_slice(&caicai[2..]);
+        assert_eq!(asset_info(&rotated).orientation, Some(6));
+
+        // The EXIF data is itself a TIFF header and IFD, here without dimensions
+        let info = asset_info(&exif(5));
+        assert_eq!(info.orientation, Some(5));
+        assert_eq!((info.width, info.height), (None, None));
+
+        let png = include_bytes!("../../../tools/testing/fixtures/images/c2pa-actions-1.2.png");
+        let info = asset_info(png);
+        assert_eq!((info.width, info.height), (Some(2100), Some(1500)));
+        assert_eq!(info.orientation, None);
+
+        let webp = riff(
+            b"WEBP",
+            &[
+                (b"VP8X", &[0x08, 0, 0, 0, 0x3F, 0x01, 0, 0xEF, 0, 0]),
+                (b"EXIF", &exif(8)),
+                (b"VP8L", &[0x2F, 0, 0, 0, 0]),
+            ],
+        );
+        let info = asset_info(&webp);
+        assert_eq!((info.width, info.height), (Some(320), Some(240)));
+        assert_eq!(info.orientation, Some(8));
+
+        // The lossy frame header: a frame tag, the start code, then 14 bit dimensions
+        let lossy = riff(
+            b"WEBP",
+            &[(
+                b"VP8 ",
+                &[0, 0, 0, 0x9D, 0x01, 0x2A, 0x40, 0x01, 0xF0, 0x00],
+            )],
+        );
+        let info = asset_info(&lossy);
+        assert_eq!((info.width, info.height), (Some(320), Some(240)));
+
+        // 99 by 51, stored less one as 14 bit fields
+        let bits: u32 = 98 | (50 << 14);
+        let mut vp8l = vec![0x2F];
+        vp8l.extend_from_slice(&bits.to_le_bytes());
+        let lossless = riff(b"WEBP", &[(b"VP8L", &vp8l)]);
+        let info = asset_info(&lossless);
+        assert_eq!((info.width, info.height), (Some(99), Some(51)));
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_oversized_headers() {
+        // A chunk length that overflows the offset of the next chunk on 32-bit targets
+        let mut png = PNG_SIGNATURE.to_vec();
+        png.extend_from_slice(&0xFFFF_FFF4u32.to_be_bytes());
+        png.extend_from_slice(b"tEXt");
+        png.extend_from_slice(&[0; 16]);
+        assert_eq!(asset_info(&png).width, None);
+
+        // A segment running past the end of the data
+        let jpeg = [0xFF, 0xD8, 0xFF, 0xC0, 0xFF, 0xFF, 0x08, 0x00, 0x10];
+        assert_eq!(asset_info(&jpeg).height, None);
+
+        // A RIFF chunk length whose padding overflows the offset of the next chunk on 32-bit
+        // targets
+        let mut wav = b"RIFF\0\0\0\0WAVEfmt ".to_vec();
+        wav.extend_from_slice(&0xFFFF_FFFFu32.to_le_bytes());
+        wav.extend_from_slice(&[0; 16]);
+        assert_eq!(asset_info(&wav).duration, None);
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_media_info() {
+        // 16-bit stereo at 8 kHz, so 32000 bytes per second, with 2.5 seconds of samples
+        let mut fmt = 1u16.to_le_bytes().to_vec();
+        fmt.extend_from_slice(&2u16.to_le_bytes());
+        fmt.extend_from_slice(&8000u32.to_le_bytes());
+        fmt.extend_from_slice(&32000u32.to_le_bytes());
+        fmt.extend_from_slice(&4u16.to_le_bytes());
+        fmt.extend_from_slice(&16u16.to_le_bytes());
+        let wav = riff(b"WAVE", &[(b"fmt ", &fmt), (b"data", &[0; 80000])]);
+        let info = asset_info(&wav);
+        assert_eq!(info.duration, Some(2.5));
+        assert_eq!(info.track_count, Some(1));
+        assert_eq!((info.width, info.height), (None, None));
+
+        // Version 0 header, with a timescale of 600 and a duration of 10.5 seconds
+        let mut mvhd = vec![0; 12];
+        mvhd.extend_from_slice(&600u32.to_be_bytes());
+        mvhd.extend_from_slice(&6300u32.to_be_bytes());
+        mvhd.extend_from_slice(&[0; 80]);
+        let audio_track = bmff_box(b"trak", &[&tkhd(0, 0)]);
+        let video_track = bmff_box(b"trak", &[&tkhd(1920, 1080)]);
+        let mp4 = [
+            bmff_box(b"ftyp", &[b"isom", &[0, 0, 2, 0], b"isommp41"]),
+            bmff_box(
+                b"moov",
+                &[&bmff_box(b"mvhd", &[&mvhd]), &audio_track, &video_track],
+            ),
+            bmff_box(b"mdat", &[]),
+        ]
+        .concat();
+        let info = asset_info(&mp4);
+        assert_eq!(info.duration, Some(10.5));
+        assert_eq!(info.track_count, Some(2));
+        assert_eq!((info.width, info.height), (Some(1920), Some(1080)));
+
+        // Headers cut short give no values instead of failing
+        let info = asset_info(&mp4[..40]);
+        assert_eq!(info.duration, None);
+        assert_eq!(asset_info(&wav[..30]).duration, None);
+        assert_eq!(asset_info(b"not an asset"), AssetInfo::default());
+    }
+}
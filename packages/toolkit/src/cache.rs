@@ -0,0 +1,382 @@
+// Copyright 2024 Adobe
+// All Rights Reserved.
+//
+// NOTICE: Adobe permits you to use, modify, and distribute this file in
+// accordance with the terms of the Adobe license agreement accompanying
+// it.
+
+//! An opt-in cache of manifest store reads.
+//!
+//! Reads are keyed by an XXH3 hash of the asset along with its mime type and the read options. A
+//! read of an asset that is already being read waits for that read instead of starting another
+//! one. Failed reads are cached as well, but only for `errorTtlMs`, since the cause may be
+//! temporary. Clearing the cache starts a new generation: reads in progress still finish, but
+//! their results, computed under the previous settings, aren't cached.
+use crate::options::ReadOptions;
+use js_sys::{Function, Promise};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+
+type ReadResult = std::result::Result<JsValue, JsValue>;
+
+/// Limits of the read cache
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ReadCacheOptions {
+    pub max_entries: usize,
+    /// The combined size of the manifest stores the cached results were read from
+    pub max_bytes: usize,
+    /// How long a failed read is cached for, in milliseconds
+    pub error_ttl_ms: f64,
+}
+
+impl Default for ReadCacheOptions {
+    fn default() -> Self {
+        Self {
+            max_entries: 64,
+            max_bytes: 64 * 1024 * 1024,
+            error_ttl_ms: 10_000.0,
+        }
+    }
+}
+
+/// Counters for tuning the read cache
+#[derive(Debug, Default, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadCacheStats {
+    pub hits: u32,
+    pub misses: u32,
+    /// Reads that waited for an identical read already in progress
+    pub coalesced: u32,
+    pub entries: usize,
+    pub bytes: usize,
+}
+
+enum Slot {
+    Ready {
+        result: ReadResult,
+        size: usize,
+        /// When a cached failure expires, as a JavaScript timestamp
+        expires_at: Option<f64>,
+    },
+    /// The ID of a read in progress
+    Pending(u64),
+}
+
+struct ReadCache {
+    options: ReadCacheOptions,
+    slots: HashMap<String, Slot>,
+    /// The `resolve` and `reject` functions of the reads waiting for each read in progress, by
+    /// read ID, including the reads whose slots were cleared
+    waiters: HashMap<u64, Vec<(Function, Function)>>,
+    next_read: u64,
+    /// Bumped each time the cache is cleared
+    generation: u64,
+    /// The keys of the ready slots, least recently used first
+    recency: VecDeque<String>,
+    stats: ReadCacheStats,
+}
+
+impl ReadCache {
+    fn touch(&mut self, key: &str) {
+        if let Some(position) = self.recency.iter().position(|used| used == key) {
+            if let Some(key) = self.recency.remove(position) {
+                self.recency.push_back(key);
+            }
+        }
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some(Slot::Ready { size, .. }) = self.slots.remove(key) {
+            self.stats.bytes -= size;
+        }
+        self.recency.retain(|used| used != key);
+    }
+
+    fn evict(&mut self) {
+        while self.recency.len() > self.options.max_entries
+            || self.stats.bytes > self.options.max_bytes
+        {
+            match self.recency.front().cloned() {
+                Some(key) => self.remove(&key),
+                None => break,
+            }
+        }
+        self.stats.entries = self.recency.len();
+    }
+}
+
+thread_local! {
+    static CACHE: RefCell<Option<ReadCache>> = const { RefCell::new(None) };
+}
+
+/// Turns the cache on, or changes its limits if it is already on
+pub fn enable(options: ReadCacheOptions) {
+    CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        let cache = cache.get_or_insert_with(|| ReadCache {
+            options: options.clone(),
+            slots: HashMap::new(),
+            waiters: HashMap::new(),
+            next_read: 1,
+            generation: 0,
+            recency: VecDeque::new(),
+            stats: ReadCacheStats::default(),
+        });
+        cache.options = options;
+        cache.evict();
+    });
+}
+
+/// Drops every cached result
+///
+/// The reads waiting for reads in progress still get their results, but the results aren't
+/// cached, and later reads of the same asset start a new read.
+pub fn clear() {
+    CACHE.with(|cache| {
+        if let Some(cache) = cache.borrow_mut().as_mut() {
+            cache.generation += 1;
+            cache.slots.clear();
+            cache.recency.clear();
+            cache.stats.bytes = 0;
+            cache.stats.entries = 0;
+        }
+    });
+}
+
+/// Frees the spare capacity left by results that were evicted or cleared
+pub fn shrink() {
+    CACHE.with(|cache| {
+        if let Some(cache) = cache.borrow_mut().as_mut() {
+            cache.slots.shrink_to_fit();
+            cache.waiters.shrink_to_fit();
+            cache.recency.shrink_to_fit();
+        }
+    });
+}
+
+/// The cache's counters, or `None` if it isn't on
+pub fn stats() -> Option<ReadCacheStats> {
+    CACHE.with(|cache| cache.borrow().as_ref().map(|cache| cache.stats.clone()))
+}
+
+/// The key of a read, or `None` if the read can't be cached
+///
+/// Reads with a `resourceResolver` aren't cached, since the resolver may return something
+/// different each time, and neither are reads with `ingredientAssets`, which would have to be
+/// hashed into the key as well, or traced reads, whose timings are only true of one read.
+pub fn key(asset: &[u8], mime_type: &str, options: &ReadOptions) -> Option<String> {
+    let enabled = CACHE.with(|cache| cache.borrow().is_some());
+    if !enabled
+        || options.resource_resolver.is_some()
+        || !options.ingredient_assets.is_empty()
+        || options.trace
+    {
+        return None;
+    }
+
+    let options = serde_json::to_string(options).ok()?;
+    let hash = xxhash_rust::xxh3::xxh3_128(asset);
+    Some(format!("{:032x}|{}|{}", hash, mime_type, options))
+}
+
+enum Lookup {
+    Hit(ReadResult),
+    Wait(Promise),
+    /// The read to start, with its ID and the generation it belongs to
+    Miss {
+        read: u64,
+        generation: u64,
+    },
+}
+
+/// A read that isn't cached, since the cache is off. Read IDs start at 1.
+const UNCACHED: Lookup = Lookup::Miss {
+    read: 0,
+    generation: 0,
+};
+
+fn lookup(key: &str) -> Lookup {
+    CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        let cache = match cache.as_mut() {
+            Some(cache) => cache,
+            None => return UNCACHED,
+        };
+        let now = js_sys::Date::now();
+
+        match cache.slots.get_mut(key) {
+            Some(Slot::Ready {
+                result, expires_at, ..
+            }) if !expires_at.is_some_and(|expires_at| now >= expires_at) => {
+                let result = result.clone();
+                cache.stats.hits += 1;
+                cache.touch(key);
+                return Lookup::Hit(result);
+            }
+            Some(Slot::Pending(read)) => {
+                let waiters = cache.waiters.entry(*read).or_default();
+                let promise = Promise::new(&mut |resolve, reject| waiters.push((resolve, reject)));
+                cache.stats.coalesced += 1;
+                return Lookup::Wait(promise);
+            }
+            _ => {}
+        }
+
+        cache.remove(key);
+        cache.stats.misses += 1;
+        let read = cache.next_read;
+        cache.next_read += 1;
+        cache.slots.insert(key.to_owned(), Slot::Pending(read));
+        cache.waiters.insert(read, Vec::new());
+        Lookup::Miss {
+            read,
+            generation: cache.generation,
+        }
+    })
+}
+
+/// Caches the result of a finished read and hands it to the reads waiting for it
+///
+/// Results of reads started before the cache was last cleared are only handed on.
+fn complete(key: String, read: u64, generation: u64, result: &ReadResult, size: usize) {
+    let waiters = CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        let cache = match cache.as_mut() {
+            Some(cache) if read != 0 => cache,
+            _ => return Vec::new(),
+        };
+        let waiters = cache.waiters.remove(&read).unwrap_or_default();
+        if generation != cache.generation {
+            return waiters;
+        }
+
+        let (size, expires_at) = match result {
+            Ok(_) => (size, None),
+            Err(_) => (0, Some(js_sys::Date::now() + cache.options.error_ttl_ms)),
+        };
+        let slot = Slot::Ready {
+            result: result.clone(),
+            size,
+            expires_at,
+        };
+        cache.slots.insert(key.clone(), slot);
+        cache.recency.push_back(key);
+        cache.stats.bytes += size;
+        cache.evict();
+
+        waiters
+    });
+
+    for (resolve, reject) in waiters {
+        let _ = match result {
+            Ok(value) => resolve.call1(&JsValue::NULL, value),
+            Err(err) => reject.call1(&JsValue::NULL, err),
+        };
+    }
+}
+
+/// Returns the cached result of the read with `key`, or else runs `read` and caches its result
+///
+/// `read` resolves to the result along with the size it counts for against `maxBytes`. Every
+/// read of the same asset gets the same object, so callers mustn't modify it, as the
+/// `ReadCacheOptions` type warns.
+pub async fn get_or_read<F>(key: String, read: F) -> ReadResult
+where
+    F: Future<Output = std::result::Result<(JsValue, usize), JsValue>>,
+{
+    match lookup(&key) {
+        Lookup::Hit(result) => result,
+        Lookup::Wait(promise) => JsFuture::from(promise).await,
+        Lookup::Miss {
+            read: id,
+            generation,
+        } => {
+            let (result, size) = match read.await {
+                Ok((value, size)) => (Ok(value), size),
+                Err(err) => (Err(err), 0),
+            };
+            complete(key, id, generation, &result, size);
+            result
+        }
+    }
+}
+
+// The cache holds JavaScript values, so it can only be exercised in a JavaScript environment
+#[cfg(all(test, target_arch = "wasm32"))]
+pub mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
+
+    fn read_caicai() -> Promise {
+        let test_asset = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+        let buf = js_sys::Uint8Array::from(&test_asset[..]).buffer();
+        crate::get_manifest_store_from_array_buffer(
+            buf.into(),
+            "image/jpeg".to_owned(),
+            JsValue::UNDEFINED,
+        )
+    }
+
+    #[wasm_bindgen_test]
+    pub async fn test_read_cache() {
+        enable(ReadCacheOptions::default());
+        clear();
+        let before = stats().unwrap();
+
+        // Concurrent reads share one computation
+        let concurrent = js_sys::Array::of2(&read_caicai(), &read_caicai());
+        let results = JsFuture::from(Promise::all(&concurrent)).await.unwrap();
+        let results = js_sys::Array::from(&results);
+        assert_eq!(results.get(0), results.get(1));
+
+        // Later reads are served from the cache
+        let cached = JsFuture::from(read_caicai()).await.unwrap();
+        assert_eq!(cached, results.get(0));
+
+        let after = stats().unwrap();
+        assert_eq!(after.misses - before.misses, 1);
+        assert_eq!(after.coalesced - before.coalesced, 1);
+        assert_eq!(after.hits - before.hits, 1);
+        assert_eq!(after.entries, 1);
+
+        clear();
+        assert_eq!(stats().unwrap().entries, 0);
+    }
+
+    #[wasm_bindgen_test]
+    pub async fn test_clear_during_read() {
+        enable(ReadCacheOptions::default());
+        clear();
+
+        // A read, and one waiting for it, in progress when the cache is cleared
+        let (read, generation) = match lookup("asset") {
+            Lookup::Miss { read, generation } => (read, generation),
+            _ => panic!("expected a miss"),
+        };
+        let waiting = match lookup("asset") {
+            Lookup::Wait(promise) => promise,
+            _ => panic!("expected to wait"),
+        };
+        clear();
+
+        // The waiting read still gets the result, but it isn't cached
+        complete(
+            "asset".to_owned(),
+            read,
+            generation,
+            &Ok("stale".into()),
+            10,
+        );
+        assert_eq!(JsFuture::from(waiting).await.unwrap(), "stale");
+        assert_eq!(stats().unwrap().entries, 0);
+        assert!(matches!(lookup("asset"), Lookup::Miss { .. }));
+        clear();
+    }
+}
@@ -0,0 +1,110 @@
+// Copyright 2024 Adobe
+// All Rights Reserved.
+//
+// NOTICE: Adobe permits you to use, modify, and distribute this file in
+// accordance with the terms of the Adobe license agreement accompanying
+// it.
+
+//! Serialization for JavaScript engines without `BigInt` support (the `compat` feature).
+//!
+//! Values are converted to JSON first and then emitted with plain JavaScript types only:
+//! - Numbers become JavaScript numbers, including the ones `arbitrary_precision` would otherwise
+//!   wrap in a `$serde_json::private::Number` object.
+//! - Integers outside the safe integer range (±(2^53 - 1)) become decimal strings, since they
+//!   can't be represented exactly without `BigInt`.
+//! - Byte buffers become arrays of numbers.
+use serde::ser::{Serialize, Serializer};
+use serde_json::{Number, Value};
+
+const MAX_SAFE_INTEGER: u64 = (1 << 53) - 1;
+
+/// Wraps a JSON value so that it serializes following the rules above
+pub struct CompatValue<'a>(pub &'a Value);
+
+impl<'a> Serialize for CompatValue<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.0 {
+            Value::Null => serializer.serialize_unit(),
+            Value::Bool(b) => serializer.serialize_bool(*b),
+            Value::Number(n) => serialize_number(n, serializer),
+            Value::String(s) => serializer.serialize_str(s),
+            Value::Array(values) => serializer.collect_seq(values.iter().map(CompatValue)),
+            Value::Object(map) => {
+                serializer.collect_map(map.iter().map(|(k, v)| (k, CompatValue(v))))
+            }
+        }
+    }
+}
+
+fn serialize_number<S: Serializer>(n: &Number, serializer: S) -> Result<S::Ok, S::Error> {
+    if let Some(i) = n.as_i64() {
+        if i.unsigned_abs() <= MAX_SAFE_INTEGER {
+            return serializer.serialize_f64(i as f64);
+        }
+    } else if let Some(u) = n.as_u64() {
+        if u <= MAX_SAFE_INTEGER {
+            return serializer.serialize_f64(u as f64);
+        }
+    } else if let Some(f) = n.as_f64().filter(|f| f.is_finite()) {
+        // `as_f64` also succeeds for integers beyond 64 bits, which would lose precision
+        let repr = n.to_string();
+        if repr.contains(['.', 'e', 'E']) {
+            return serializer.serialize_f64(f);
+        }
+    }
+
+    serializer.serialize_str(&n.to_string())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
+
+    fn compat(json: &str) -> String {
+        let value: Value = serde_json::from_str(json).unwrap();
+        serde_json::to_string(&CompatValue(&value)).unwrap()
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_safe_numbers_stay_numbers() {
+        assert_eq!(
+            compat("[1,-2,9007199254740991,1.5]"),
+            "[1.0,-2.0,9007199254740991.0,1.5]"
+        );
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_large_integers_become_strings() {
+        assert_eq!(
+            compat(
+                r#"{"a":9007199254740992,"b":-9007199254740992,"c":123456789012345678901234567890}"#
+            ),
+            r#"{"a":"9007199254740992","b":"-9007199254740992","c":"123456789012345678901234567890"}"#
+        );
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test]
+    pub async fn test_reads_without_bigint() {
+        use js_sys::Reflect;
+        use wasm_bindgen::JsValue;
+
+        // Simulate an engine without `BigInt` for the rest of this test run
+        let global = js_sys::global();
+        Reflect::set(&global, &"BigInt".into(), &JsValue::UNDEFINED).unwrap();
+
+        let test_asset = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+        let buf = js_sys::Uint8Array::from(&test_asset[..]).buffer();
+        let result =
+            wasm_bindgen_futures::JsFuture::from(crate::get_manifest_store_from_array_buffer(
+                buf.into(),
+                "image/jpeg".to_owned(),
+                JsValue::UNDEFINED,
+            ))
+            .await;
+        assert!(result.is_ok());
+    }
+}
@@ -0,0 +1,433 @@
+// Copyright 2024 Adobe
+// All Rights Reserved.
+//
+// NOTICE: Adobe permits you to use, modify, and distribute this file in
+// accordance with the terms of the Adobe license agreement accompanying
+// it.
+
+//! Finds the media packaged in ZIP-based containers, such as EPUB, OpenDocument and Office Open
+//! XML documents, so that each can be read like a standalone asset.
+//!
+//! Entries are picked out by their extension, and their format is then taken from their leading
+//! bytes where we recognize them. Only stored and deflated entries can be read; other compression
+//! methods are reported for the entry alone, while an encrypted entry or a central directory that
+//! doesn't hold together fails the whole container. ZIP64 containers aren't supported.
+use crate::error::{Error, Result};
+use crate::mime::sniff_mime_type;
+use serde::Deserialize;
+use std::convert::TryFrom;
+
+/// Mime types of the containers that can be read
+pub const CONTAINER_MIME_TYPES: &[&str] = &[
+    "application/zip",
+    "application/epub+zip",
+    "application/vnd.oasis.opendocument.text",
+    "application/vnd.oasis.opendocument.spreadsheet",
+    "application/vnd.oasis.opendocument.presentation",
+    "application/vnd.oasis.opendocument.graphics",
+    "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+    "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+    "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+];
+
+/// Extensions of the entries that are read, and the mime type each one maps to
+const MEDIA_EXTENSIONS: &[(&str, &str)] = &[
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("png", "image/png"),
+    ("gif", "image/gif"),
+    ("tif", "image/tiff"),
+    ("tiff", "image/tiff"),
+    ("dng", "image/x-adobe-dng"),
+    ("webp", "image/webp"),
+    ("heic", "image/heic"),
+    ("heif", "image/heif"),
+    ("avif", "image/avif"),
+    ("svg", "image/svg+xml"),
+    ("mp4", "video/mp4"),
+    ("m4v", "video/mp4"),
+    ("mov", "video/quicktime"),
+    ("avi", "video/x-msvideo"),
+    ("mp3", "audio/mpeg"),
+    ("m4a", "audio/mp4"),
+    ("wav", "audio/x-wav"),
+    ("pdf", "application/pdf"),
+];
+
+/// Entries larger than this are skipped unless the options say otherwise
+pub const DEFAULT_MAX_ENTRY_SIZE: u64 = 100 * 1024 * 1024;
+
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0605_4b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x0201_4b50;
+const LOCAL_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+const END_OF_CENTRAL_DIRECTORY_LEN: usize = 22;
+const CENTRAL_HEADER_LEN: usize = 46;
+const LOCAL_HEADER_LEN: usize = 30;
+/// The end of central directory record is followed by a comment of up to this many bytes
+const MAX_COMMENT_LEN: usize = 0xffff;
+
+const FLAG_ENCRYPTED: u16 = 1;
+const METHOD_STORED: u16 = 0;
+const METHOD_DEFLATED: u16 = 8;
+
+/// Options of `getManifestStoresFromContainer`, read alongside the read options
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ContainerOptions {
+    /// Skip entries that are larger than this many bytes once uncompressed
+    pub max_entry_size: Option<u64>,
+}
+
+impl ContainerOptions {
+    pub fn max_entry_size(&self) -> u64 {
+        self.max_entry_size.unwrap_or(DEFAULT_MAX_ENTRY_SIZE)
+    }
+}
+
+/// A media entry of a container
+#[derive(Debug)]
+pub struct Entry<'a> {
+    pub path: String,
+    /// The uncompressed size the central directory declares
+    pub size: u64,
+    method: u16,
+    data: &'a [u8],
+    extension_mime_type: &'static str,
+}
+
+impl Entry<'_> {
+    /// Decompresses the entry
+    pub fn read(&self) -> Result<Vec<u8>> {
+        let size = usize::try_from(self.size)
+            .map_err(|_err| Error::InvalidContainer("entry is too large"))?;
+        let bytes = match self.method {
+            METHOD_STORED => self.data.to_vec(),
+            METHOD_DEFLATED => miniz_oxide::inflate::decompress_to_vec_with_limit(self.data, size)
+                .map_err(|_err| Error::InvalidContainer("entry can't be inflated"))?,
+            _ => return Err(Error::InvalidContainer("unsupported compression method")),
+        };
+
+        if bytes.len() == size {
+            Ok(bytes)
+        } else {
+            Err(Error::InvalidContainer(
+                "entry size doesn't match the central directory",
+            ))
+        }
+    }
+
+    /// The mime type of the entry's contents, or the one its extension maps to
+    pub fn mime_type(&self, bytes: &[u8]) -> &'static str {
+        sniff_mime_type(bytes).unwrap_or(self.extension_mime_type)
+    }
+}
+
+/// Returns the media entries of a container, in central directory order
+pub fn media_entries<'a>(data: &'a [u8], container_mime: &str) -> Result<Vec<Entry<'a>>> {
+    if !CONTAINER_MIME_TYPES.contains(&container_mime) {
+        return Err(c2pa::Error::UnsupportedType.into());
+    }
+
+    let end = find_end_of_central_directory(data)?;
+    let count = read_u16(data, end + 10)?;
+    let directory_len = read_u32(data, end + 12)?;
+    let directory_offset = read_u32(data, end + 16)?;
+    if count == u16::MAX || directory_len == u32::MAX || directory_offset == u32::MAX {
+        return Err(Error::InvalidContainer("ZIP64 is not supported"));
+    }
+    // The central directory comes right before its end record
+    if (directory_offset as usize)
+        .checked_add(directory_len as usize)
+        .is_none_or(|directory_end| directory_end > end)
+    {
+        return Err(Error::InvalidContainer("central directory out of bounds"));
+    }
+
+    let mut entries = Vec::new();
+    let mut offset = directory_offset as usize;
+    for _ in 0..count {
+        if read_u32(data, offset)? != CENTRAL_HEADER_SIGNATURE {
+            return Err(Error::InvalidContainer("invalid central directory header"));
+        }
+        let flags = read_u16(data, offset + 8)?;
+        let method = read_u16(data, offset + 10)?;
+        let compressed_size = read_u32(data, offset + 20)?;
+        let size = read_u32(data, offset + 24)?;
+        let name_len = read_u16(data, offset + 28)? as usize;
+        let extra_len = read_u16(data, offset + 30)? as usize;
+        let comment_len = read_u16(data, offset + 32)? as usize;
+        let local_offset = read_u32(data, offset + 42)?;
+        let name = slice(data, offset + CENTRAL_HEADER_LEN, name_len)?;
+        offset += CENTRAL_HEADER_LEN + name_len + extra_len + comment_len;
+
+        if flags & FLAG_ENCRYPTED != 0 {
+            return Err(Error::EncryptedContainer);
+        }
+        if compressed_size == u32::MAX || size == u32::MAX || local_offset == u32::MAX {
+            return Err(Error::InvalidContainer("ZIP64 is not supported"));
+        }
+
+        let path = String::from_utf8_lossy(name).into_owned();
+        let extension_mime_type = match media_mime_type(&path) {
+            Some(mime_type) => mime_type,
+            None => continue,
+        };
+        entries.push(Entry {
+            path,
+            size: size as u64,
+            method,
+            data: entry_data(data, local_offset as usize, compressed_size as usize)?,
+            extension_mime_type,
+        });
+    }
+
+    Ok(entries)
+}
+
+/// Returns the mime type an entry's extension maps to, if it is media we read
+fn media_mime_type(path: &str) -> Option<&'static str> {
+    let (_, extension) = path.rsplit_once('.')?;
+    if extension.contains('/') {
+        return None;
+    }
+    MEDIA_EXTENSIONS
+        .iter()
+        .find(|(media_extension, _)| media_extension.eq_ignore_ascii_case(extension))
+        .map(|(_, mime_type)| *mime_type)
+}
+
+/// Finds the end of central directory record, searching back from the end past any comment
+fn find_end_of_central_directory(data: &[u8]) -> Result<usize> {
+    let last = data
+        .len()
+        .checked_sub(END_OF_CENTRAL_DIRECTORY_LEN)
+        .ok_or(Error::InvalidContainer("not a ZIP container"))?;
+    let first = last.saturating_sub(MAX_COMMENT_LEN);
+
+    (first..=last)
+        .rev()
+        .find(|offset| read_u32(data, *offset).ok() == Some(END_OF_CENTRAL_DIRECTORY_SIGNATURE))
+        .ok_or(Error::InvalidContainer("not a ZIP container"))
+}
+
+/// Returns the compressed data following an entry's local header
+fn entry_data(data: &[u8], local_offset: usize, compressed_size: usize) -> Result<&[u8]> {
+    slice(data, local_offset, LOCAL_HEADER_LEN)?;
+    if read_u32(data, local_offset)? != LOCAL_HEADER_SIGNATURE {
+        return Err(Error::InvalidContainer("invalid local header"));
+    }
+    // The local header's own lengths apply, as its extra field can differ from the central one
+    let name_len = read_u16(data, local_offset + 26)? as usize;
+    let extra_len = read_u16(data, local_offset + 28)? as usize;
+    slice(
+        data,
+        local_offset + LOCAL_HEADER_LEN + name_len + extra_len,
+        compressed_size,
+    )
+}
+
+fn slice(data: &[u8], offset: usize, len: usize) -> Result<&[u8]> {
+    offset
+        .checked_add(len)
+        .and_then(|end| data.get(offset..end))
+        .ok_or(Error::InvalidContainer("truncated container"))
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16> {
+    let bytes = slice(data, offset, 2)?;
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32> {
+    let bytes = slice(data, offset, 4)?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
+
+    const CAICAI: &[u8] = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+    const I: &[u8] = include_bytes!("../../../tools/testing/fixtures/images/I.jpg");
+
+    /// Builds a ZIP container of `(path, contents, method)` entries
+    fn zip(entries: &[(&str, &[u8], u16)]) -> Vec<u8> {
+        let mut data = Vec::new();
+        let mut directory = Vec::new();
+        for (path, contents, method) in entries.iter() {
+            let compressed = match *method {
+                METHOD_DEFLATED => miniz_oxide::deflate::compress_to_vec(contents, 1),
+                _ => contents.to_vec(),
+            };
+            let local_offset = data.len() as u32;
+            // Version, flags, method, time, date and CRC, which isn't checked, then the sizes and
+            // the name length
+            let mut fields = vec![20, 0, 0, 0];
+            fields.extend_from_slice(&method.to_le_bytes());
+            fields.extend_from_slice(&[0; 8]);
+            fields.extend_from_slice(&(compressed.len() as u32).to_le_bytes());
+            fields.extend_from_slice(&(contents.len() as u32).to_le_bytes());
+            fields.extend_from_slice(&(path.len() as u16).to_le_bytes());
+
+            data.extend_from_slice(&LOCAL_HEADER_SIGNATURE.to_le_bytes());
+            data.extend_from_slice(&fields);
+            // No extra field
+            data.extend_from_slice(&[0; 2]);
+            data.extend_from_slice(path.as_bytes());
+            data.extend_from_slice(&compressed);
+
+            directory.extend_from_slice(&CENTRAL_HEADER_SIGNATURE.to_le_bytes());
+            // Version made by
+            directory.extend_from_slice(&[20, 0]);
+            directory.extend_from_slice(&fields);
+            // No extra field or comment, then the disk number and attributes
+            directory.extend_from_slice(&[0; 12]);
+            directory.extend_from_slice(&local_offset.to_le_bytes());
+            directory.extend_from_slice(path.as_bytes());
+        }
+
+        let directory_offset = data.len() as u32;
+        data.extend_from_slice(&directory);
+        data.extend_from_slice(&END_OF_CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes());
+        // Disk numbers
+        data.extend_from_slice(&[0; 4]);
+        data.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        data.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        data.extend_from_slice(&(directory.len() as u32).to_le_bytes());
+        data.extend_from_slice(&directory_offset.to_le_bytes());
+        // No comment
+        data.extend_from_slice(&[0; 2]);
+        data
+    }
+
+    /// An EPUB with a signed image stored, an unsigned one deflated and a PNG that can't be read,
+    /// among entries that aren't media
+    fn epub() -> Vec<u8> {
+        let mut corrupt = b"\x89PNG\r\n\x1a\n".to_vec();
+        corrupt.resize(72, 0);
+        zip(&[
+            ("mimetype", b"application/epub+zip", METHOD_STORED),
+            ("META-INF/container.xml", b"<container/>", METHOD_DEFLATED),
+            ("OEBPS/images/", b"", METHOD_STORED),
+            ("OEBPS/images/CAICAI.jpg", CAICAI, METHOD_STORED),
+            ("OEBPS/images/I.jpg", I, METHOD_DEFLATED),
+            ("OEBPS/images/corrupt.png", &corrupt, METHOD_DEFLATED),
+        ])
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_media_entries() {
+        let epub = epub();
+        let entries = media_entries(&epub, "application/epub+zip").unwrap();
+        let paths: Vec<&str> = entries.iter().map(|entry| entry.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "OEBPS/images/CAICAI.jpg",
+                "OEBPS/images/I.jpg",
+                "OEBPS/images/corrupt.png"
+            ]
+        );
+
+        // Stored and deflated entries read back as the original files
+        let signed = entries[0].read().unwrap();
+        assert_eq!(signed.as_slice(), CAICAI);
+        assert_eq!(entries[0].mime_type(&signed), "image/jpeg");
+        let unsigned = entries[1].read().unwrap();
+        assert_eq!(unsigned.as_slice(), I);
+        assert_eq!(entries[1].size, unsigned.len() as u64);
+
+        assert!(matches!(
+            media_entries(&epub, "image/jpeg"),
+            Err(Error::C2pa(c2pa::Error::UnsupportedType))
+        ));
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_invalid_containers() {
+        assert!(matches!(
+            media_entries(b"not a zip", "application/zip"),
+            Err(Error::InvalidContainer("not a ZIP container"))
+        ));
+
+        // Central directories that point past the end of the file
+        let epub = epub();
+        let end = epub.len() - END_OF_CENTRAL_DIRECTORY_LEN;
+        let mut corrupt = epub.to_vec();
+        corrupt[end + 16..end + 20].copy_from_slice(&u32::MAX.to_le_bytes()[..]);
+        corrupt[end + 16] = 0xfe;
+        assert!(matches!(
+            media_entries(&corrupt, "application/epub+zip"),
+            Err(Error::InvalidContainer("central directory out of bounds"))
+        ));
+        let mut truncated = epub[..epub.len() - 1000].to_vec();
+        truncated.extend_from_slice(&epub[end..]);
+        assert!(media_entries(&truncated, "application/epub+zip").is_err());
+
+        // An encrypted entry fails the whole container, whichever entry it is
+        let directory_offset = read_u32(&epub, end + 16).unwrap() as usize;
+        let mut encrypted = epub.to_vec();
+        encrypted[directory_offset + 8] |= FLAG_ENCRYPTED as u8;
+        assert!(matches!(
+            media_entries(&encrypted, "application/epub+zip"),
+            Err(Error::EncryptedContainer)
+        ));
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test]
+    pub async fn test_manifest_stores_from_container() {
+        use crate::get_path;
+        use wasm_bindgen::{JsCast, JsValue};
+
+        let read = |container: &[u8], options: JsValue| {
+            let buf = js_sys::Uint8Array::from(container).buffer().into();
+            wasm_bindgen_futures::JsFuture::from(crate::get_manifest_stores_from_container(
+                buf,
+                "application/epub+zip".to_owned(),
+                options,
+            ))
+        };
+
+        let epub = epub();
+        let result = read(&epub, JsValue::UNDEFINED).await.unwrap();
+        let signed = get_path(&result, &["OEBPS/images/CAICAI.jpg"]);
+        assert_eq!(get_path(&signed, &["mimeType"]), "image/jpeg");
+        assert!(get_path(&signed, &["manifestStore", "active_manifest"]).is_string());
+        let unsigned = get_path(&result, &["OEBPS/images/I.jpg"]);
+        assert!(get_path(&unsigned, &["manifestStore"]).is_null());
+        // An entry that can't be read doesn't fail the others
+        let corrupt = get_path(&result, &["OEBPS/images/corrupt.png"]);
+        assert_eq!(get_path(&corrupt, &["mimeType"]), "image/png");
+        assert!(get_path(&corrupt, &["error"]).is_instance_of::<js_sys::Error>());
+
+        let options = js_sys::JSON::parse(r#"{ "maxEntrySize": 2000000 }"#).unwrap();
+        let result = read(&epub, options).await.unwrap();
+        assert!(get_path(&result, &["OEBPS/images/CAICAI.jpg", "manifestStore"]).is_object());
+        let skipped = get_path(&result, &["OEBPS/images/I.jpg"]);
+        assert_eq!(get_path(&skipped, &["skipped"]), "tooLarge");
+        assert_eq!(get_path(&skipped, &["size"]), 2134567);
+
+        let end = epub.len() - END_OF_CENTRAL_DIRECTORY_LEN;
+        let directory_offset = read_u32(&epub, end + 16).unwrap() as usize;
+        let mut encrypted = epub.to_vec();
+        encrypted[directory_offset + 8] |= FLAG_ENCRYPTED as u8;
+        let err: JsValue = read(&encrypted, JsValue::UNDEFINED)
+            .await
+            .unwrap_err()
+            .into();
+        assert_eq!(get_path(&err, &["name"]), "EncryptedContainer");
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_media_mime_type() {
+        assert_eq!(media_mime_type("images/photo.JPG"), Some("image/jpeg"));
+        assert_eq!(media_mime_type("word/media/image1.png"), Some("image/png"));
+        assert_eq!(media_mime_type("content.opf"), None);
+        assert_eq!(media_mime_type("images.jpg/cover"), None);
+        assert_eq!(media_mime_type("mimetype"), None);
+    }
+}
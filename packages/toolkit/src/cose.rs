@@ -0,0 +1,167 @@
+// Copyright 2024 Adobe
+// All Rights Reserved.
+//
+// NOTICE: Adobe permits you to use, modify, and distribute this file in
+// accordance with the terms of the Adobe license agreement accompanying
+// it.
+
+//! Access to the headers of a manifest's `COSE_Sign1` signature.
+use crate::error::{Error, Result};
+use c2pa::SigningAlg;
+use serde_cbor::Value;
+use std::collections::BTreeMap;
+
+/// COSE header label of the signature algorithm
+const ALG: i128 = 1;
+/// COSE header label of the X.509 certificate chain
+const X5CHAIN: i128 = 33;
+
+/// A parsed `COSE_Sign1` structure
+#[derive(Debug)]
+pub struct CoseSign1 {
+    /// The serialized protected header, exactly as signed
+    pub protected_bytes: Vec<u8>,
+    pub protected: BTreeMap<Value, Value>,
+    pub unprotected: BTreeMap<Value, Value>,
+    /// The payload, unless it is detached (as it is for C2PA claims)
+    pub payload: Option<Vec<u8>>,
+    pub signature: Vec<u8>,
+}
+
+impl CoseSign1 {
+    pub fn from_slice(data: &[u8]) -> Result<Self> {
+        let value: Value =
+            serde_cbor::from_slice(data).map_err(|_err| Error::InvalidCose("not CBOR"))?;
+
+        let mut items = match untag(value) {
+            Value::Array(items) if items.len() == 4 => items.into_iter(),
+            _ => return Err(Error::InvalidCose("expected a COSE_Sign1 array")),
+        };
+
+        let protected_bytes = match items.next() {
+            Some(Value::Bytes(bytes)) => bytes,
+            _ => return Err(Error::InvalidCose("invalid protected header")),
+        };
+        let protected = if protected_bytes.is_empty() {
+            BTreeMap::new()
+        } else {
+            match serde_cbor::from_slice(&protected_bytes) {
+                Ok(Value::Map(map)) => map,
+                _ => return Err(Error::InvalidCose("invalid protected header")),
+            }
+        };
+
+        let unprotected = match items.next() {
+            Some(Value::Map(map)) => map,
+            _ => return Err(Error::InvalidCose("invalid unprotected header")),
+        };
+
+        let payload = match items.next() {
+            Some(Value::Bytes(bytes)) => Some(bytes),
+            Some(Value::Null) => None,
+            _ => return Err(Error::InvalidCose("invalid payload")),
+        };
+
+        let signature = match items.next() {
+            Some(Value::Bytes(bytes)) => bytes,
+            _ => return Err(Error::InvalidCose("invalid signature")),
+        };
+
+        Ok(Self {
+            protected_bytes,
+            protected,
+            unprotected,
+            payload,
+            signature,
+        })
+    }
+
+    /// The COSE identifier of the signature algorithm from the protected header
+    pub fn alg_id(&self) -> Option<i128> {
+        match self.protected.get(&Value::Integer(ALG))? {
+            Value::Integer(alg) => Some(*alg),
+            _ => None,
+        }
+    }
+
+    /// The signature algorithm from the protected header, if it is one C2PA allows
+    pub fn alg(&self) -> Option<SigningAlg> {
+        // IANA COSE algorithm identifiers
+        Some(match self.alg_id()? {
+            -7 => SigningAlg::Es256,
+            -35 => SigningAlg::Es384,
+            -36 => SigningAlg::Es512,
+            -37 => SigningAlg::Ps256,
+            -38 => SigningAlg::Ps384,
+            -39 => SigningAlg::Ps512,
+            -8 => SigningAlg::Ed25519,
+            _ => return None,
+        })
+    }
+
+    /// Looks up a header, preferring the protected header over the unprotected one
+    pub fn header(&self, label: &Value) -> Option<&Value> {
+        self.protected
+            .get(label)
+            .or_else(|| self.unprotected.get(label))
+    }
+
+    /// The DER encoded signing certificate chain, starting with the signer's certificate
+    pub fn certificate_chain(&self) -> Vec<&[u8]> {
+        let chain = self
+            .header(&Value::Integer(X5CHAIN))
+            .or_else(|| self.header(&Value::Text("x5chain".to_owned())));
+
+        match chain {
+            Some(Value::Bytes(cert)) => vec![cert.as_slice()],
+            Some(Value::Array(certs)) => certs
+                .iter()
+                .filter_map(|cert| match cert {
+                    Value::Bytes(cert) => Some(cert.as_slice()),
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// The DER encoded OCSP responses stapled to the signature (`rVals.ocspVals`)
+    pub fn ocsp_responses(&self) -> Vec<&[u8]> {
+        let ocsp_vals = match self.unprotected.get(&Value::Text("rVals".to_owned())) {
+            Some(Value::Map(r_vals)) => r_vals.get(&Value::Text("ocspVals".to_owned())),
+            _ => None,
+        };
+
+        match ocsp_vals {
+            Some(Value::Array(responses)) => responses
+                .iter()
+                .filter_map(|response| match response {
+                    Value::Bytes(response) => Some(response.as_slice()),
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Builds the `Sig_structure` (RFC 8152 section 4.4) that a `COSE_Sign1` signature is computed over
+pub fn sig_structure(protected_bytes: &[u8], payload: &[u8]) -> Vec<u8> {
+    let sig_structure = Value::Array(vec![
+        Value::Text("Signature1".to_owned()),
+        Value::Bytes(protected_bytes.to_vec()),
+        // C2PA doesn't use external additional authenticated data
+        Value::Bytes(Vec::new()),
+        Value::Bytes(payload.to_vec()),
+    ]);
+
+    // Serializing a CBOR value to memory can't fail
+    serde_cbor::to_vec(&sig_structure).unwrap_or_default()
+}
+
+fn untag(value: Value) -> Value {
+    match value {
+        Value::Tag(_, inner) => untag(*inner),
+        value => value,
+    }
+}
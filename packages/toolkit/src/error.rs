@@ -4,6 +4,7 @@
 // NOTICE: Adobe permits you to use, modify, and distribute this file in
 // accordance with the terms of the Adobe license agreement accompanying
 // it.
+use crate::util::UnknownOption;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -16,6 +17,391 @@ pub enum Error {
 
     #[error(transparent)]
     C2pa(#[from] c2pa::Error),
+
+    #[error("invalid JUMBF: {0}")]
+    InvalidJumbf(&'static str),
+
+    #[error("invalid COSE signature: {0}")]
+    InvalidCose(&'static str),
+
+    #[error("invalid XMP: {0}")]
+    InvalidXmp(&'static str),
+
+    #[error("invalid ZIP container: {0}")]
+    InvalidContainer(&'static str),
+
+    #[error("ZIP container is password protected")]
+    EncryptedContainer,
+
+    #[error("manifest not found")]
+    ManifestNotFound,
+
+    #[error("assertion not found: {0}")]
+    AssertionNotFound(String),
+
+    #[error("ingredient not found")]
+    IngredientNotFound,
+
+    #[error("ingredient does not carry manifest data")]
+    IngredientManifestNotPresent,
+
+    #[error("manifest has no collection hash")]
+    CollectionHashNotFound,
+
+    #[error("collection has no entry named {0}")]
+    CollectionEntryNotFound(String),
+
+    #[error("collection has more than one entry named {0}")]
+    CollectionEntryAmbiguous(String),
+
+    #[error("manifest recovery failed at {0}: {1}")]
+    RecoveryFailed(&'static str, String),
+
+    #[error("{0} is a detached ArrayBuffer")]
+    DetachedBuffer(&'static str),
+
+    #[error("{0} is empty")]
+    EmptyInput(&'static str),
+
+    #[error("{parameter} must be {expected}")]
+    InvalidArgument {
+        parameter: &'static str,
+        expected: &'static str,
+    },
+
+    #[error("\"{0}\" can't be registered as a custom action")]
+    ReservedAction(String),
+
+    #[error("no manifest {label} to make active, the store has {}", available.join(", "))]
+    ActiveManifestNotFound {
+        label: String,
+        available: Vec<String>,
+    },
+
+    #[error("{api} doesn't accept the options {}", UnknownOption::keys(unknown))]
+    UnknownOptions {
+        api: &'static str,
+        unknown: Vec<UnknownOption>,
+    },
+
+    #[error("manifest store exceeds the {limit} limit: {observed} is more than {max}")]
+    LimitExceeded {
+        limit: &'static str,
+        observed: u64,
+        max: u64,
+    },
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
+
+/// The code of c2pa errors that this toolkit doesn't know about yet
+pub const UNKNOWN_UPSTREAM: &str = "unknownUpstream";
+
+/// What kind of problem an error is, attached to errors thrown to JavaScript
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCategory {
+    /// The caller passed something that can't be read, such as an unsupported or empty asset
+    Input,
+    /// The asset or manifest store is malformed
+    Format,
+    /// A signature or hash doesn't verify
+    Crypto,
+    /// A certificate or time stamp isn't trusted
+    Trust,
+    /// The toolkit or its environment failed
+    Internal,
+}
+
+impl ErrorCategory {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCategory::Input => "input",
+            ErrorCategory::Format => "format",
+            ErrorCategory::Crypto => "crypto",
+            ErrorCategory::Trust => "trust",
+            ErrorCategory::Internal => "internal",
+        }
+    }
+}
+
+/// A stable, machine-readable description of an error
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorCode {
+    pub code: &'static str,
+    pub category: ErrorCategory,
+    /// Whether the same call may succeed if tried again
+    pub retriable: bool,
+    /// The debug representation of a c2pa error with no code of its own
+    pub upstream: Option<String>,
+}
+
+impl ErrorCode {
+    fn new(code: &'static str, category: ErrorCategory, retriable: bool) -> Self {
+        Self {
+            code,
+            category,
+            retriable,
+            upstream: None,
+        }
+    }
+}
+
+impl Error {
+    pub fn code(&self) -> ErrorCode {
+        use ErrorCategory::*;
+
+        match self {
+            Error::SerdeInput(_) => ErrorCode::new("invalidInput", Input, false),
+            Error::JavaScriptConversion => ErrorCode::new("javascriptConversion", Internal, false),
+            Error::C2pa(err) => c2pa_error_code(err),
+            Error::InvalidJumbf(_) => ErrorCode::new("invalidJumbf", Format, false),
+            Error::InvalidCose(_) => ErrorCode::new("invalidCose", Format, false),
+            Error::InvalidXmp(_) => ErrorCode::new("invalidXmp", Format, false),
+            Error::InvalidContainer(_) => ErrorCode::new("invalidContainer", Format, false),
+            Error::EncryptedContainer => ErrorCode::new("encryptedContainer", Input, false),
+            Error::ManifestNotFound => ErrorCode::new("manifestNotFound", Input, false),
+            Error::AssertionNotFound(_) => ErrorCode::new("assertionNotFound", Input, false),
+            Error::IngredientNotFound => ErrorCode::new("ingredientNotFound", Input, false),
+            Error::IngredientManifestNotPresent => {
+                ErrorCode::new("ingredientManifestNotPresent", Input, false)
+            }
+            Error::CollectionHashNotFound => ErrorCode::new("collectionHashNotFound", Input, false),
+            Error::CollectionEntryNotFound(_) => {
+                ErrorCode::new("collectionEntryNotFound", Input, false)
+            }
+            Error::CollectionEntryAmbiguous(_) => {
+                ErrorCode::new("collectionEntryAmbiguous", Input, false)
+            }
+            // The application's callbacks may fail on a flaky network
+            Error::RecoveryFailed(..) => ErrorCode::new("recoveryFailed", Internal, true),
+            Error::DetachedBuffer(_) => ErrorCode::new("detachedBuffer", Input, false),
+            Error::EmptyInput(_) => ErrorCode::new("emptyInput", Input, false),
+            Error::InvalidArgument { .. } => ErrorCode::new("invalidArgument", Input, false),
+            Error::ReservedAction(_) => ErrorCode::new("reservedAction", Input, false),
+            Error::LimitExceeded { .. } => ErrorCode::new("limitExceeded", Format, false),
+            Error::UnknownOptions { .. } => ErrorCode::new("unknownOptions", Input, false),
+            Error::ActiveManifestNotFound { .. } => {
+                ErrorCode::new("activeManifestNotFound", Input, false)
+            }
+        }
+    }
+}
+
+/// Maps each c2pa error to a code of its own
+///
+/// Every variant of the c2pa version we build against is listed. `c2pa::Error` is non-exhaustive,
+/// so variants added by an upgrade fall through to `unknownUpstream` until they are listed here;
+/// the test of this mapping lists the variants too. `ImageError` and `OpenSslError` only exist with
+/// c2pa features this toolkit doesn't enable.
+pub fn c2pa_error_code(err: &c2pa::Error) -> ErrorCode {
+    use c2pa::Error as C2pa;
+    use ErrorCategory::*;
+
+    let (code, category, retriable) = match err {
+        C2pa::ClaimMissing { .. } => ("claimMissing", Format, false),
+        C2pa::AssertionUnsupportedVersion => ("assertionUnsupportedVersion", Format, false),
+        C2pa::AssertionMissing { .. } => ("assertionMissing", Format, false),
+        C2pa::AssertionEncoding => ("assertionEncoding", Internal, false),
+        C2pa::AssertionDecoding(_) => ("assertionDecoding", Format, false),
+        C2pa::AssertionInvalidRedaction => ("assertionInvalidRedaction", Format, false),
+        C2pa::AssertionRedactionNotFound => ("assertionRedactionNotFound", Format, false),
+        C2pa::BadParam(_) => ("badParam", Input, false),
+        C2pa::MissingFeature(_) => ("missingFeature", Internal, false),
+        C2pa::NotImplemented(_) => ("notImplemented", Internal, false),
+        C2pa::ClaimEncoding => ("claimEncoding", Internal, false),
+        C2pa::ClaimDecoding => ("claimDecoding", Format, false),
+        C2pa::ClaimAlreadySigned => ("claimAlreadySigned", Internal, false),
+        C2pa::ClaimUnsigned => ("claimUnsigned", Internal, false),
+        C2pa::ClaimMissingSignatureBox => ("claimMissingSignatureBox", Format, false),
+        C2pa::ClaimMissingIdentity => ("claimMissingIdentity", Format, false),
+        C2pa::ClaimVersion => ("claimVersion", Format, false),
+        C2pa::ClaimInvalidContent => ("claimInvalidContent", Format, false),
+        C2pa::ClaimMissingHardBinding => ("claimMissingHardBinding", Format, false),
+        C2pa::ClaimSelfRedact => ("claimSelfRedact", Format, false),
+        C2pa::ClaimDisallowedRedaction => ("claimDisallowedRedaction", Format, false),
+        C2pa::UpdateManifestInvalid => ("updateManifestInvalid", Format, false),
+        C2pa::TooManyManifestStores => ("tooManyManifestStores", Format, false),
+        C2pa::InvalidCoseSignature { .. } => ("invalidCoseSignature", Format, false),
+        C2pa::CoseSignatureAlgorithmNotSupported => {
+            ("coseSignatureAlgorithmNotSupported", Crypto, false)
+        }
+        C2pa::CoseMissingKey => ("coseMissingKey", Crypto, false),
+        C2pa::CoseX5ChainMissing => ("coseX5ChainMissing", Format, false),
+        C2pa::CoseInvalidCert => ("coseInvalidCert", Format, false),
+        C2pa::CoseSignature => ("coseSignature", Crypto, false),
+        C2pa::CoseVerifier => ("coseVerifier", Crypto, false),
+        C2pa::CoseCertExpiration => ("coseCertExpiration", Trust, false),
+        C2pa::CoseCertRevoked => ("coseCertRevoked", Trust, false),
+        C2pa::CoseInvalidTimeStamp => ("coseInvalidTimeStamp", Format, false),
+        C2pa::CoseTimeStampValidity => ("coseTimeStampValidity", Trust, false),
+        C2pa::CoseTimeStampMismatch => ("coseTimeStampMismatch", Crypto, false),
+        C2pa::CoseTimeStampGeneration => ("coseTimeStampGeneration", Internal, true),
+        C2pa::CoseTimeStampAuthority => ("coseTimeStampAuthority", Trust, true),
+        C2pa::CoseSigboxTooSmall => ("coseSigboxTooSmall", Internal, false),
+        C2pa::WasmVerifier => ("wasmVerifier", Crypto, false),
+        C2pa::WasmRsaKeyImport(_) => ("wasmRsaKeyImport", Crypto, false),
+        C2pa::WasmRsaVerification => ("wasmRsaVerification", Crypto, false),
+        C2pa::WasmKey => ("wasmKey", Crypto, false),
+        C2pa::WasmInvalidContext => ("wasmInvalidContext", Internal, false),
+        C2pa::WasmNoCrypto => ("wasmNoCrypto", Internal, false),
+        C2pa::JumbfCreationError => ("jumbfCreationError", Internal, false),
+        C2pa::ThreadReceiveError => ("threadReceiveError", Internal, true),
+        C2pa::JumbfNotFound => ("jumbfNotFound", Input, false),
+        C2pa::JumbfBoxNotFound => ("jumbfBoxNotFound", Format, false),
+        C2pa::RemoteManifestFetch(_) => ("remoteManifestFetch", Internal, true),
+        C2pa::RemoteManifestUrl(_) => ("remoteManifestUrl", Input, false),
+        C2pa::LogStop => ("logStop", Internal, false),
+        C2pa::NotFound => ("notFound", Input, false),
+        C2pa::UnsupportedType => ("unsupportedType", Input, false),
+        C2pa::EmbeddingError => ("embeddingError", Internal, false),
+        C2pa::IngredientNotFound => ("ingredientNotFound", Input, false),
+        C2pa::FileNotFound(_) => ("fileNotFound", Input, false),
+        C2pa::ResourceNotFound(_) => ("resourceNotFound", Input, false),
+        C2pa::XmpReadError => ("xmpReadError", Format, false),
+        C2pa::XmpWriteError => ("xmpWriteError", Internal, false),
+        C2pa::XmpNotSupported => ("xmpNotSupported", Input, false),
+        C2pa::ProvenanceMissing => ("provenanceMissing", Input, false),
+        C2pa::HashMismatch(_) => ("hashMismatch", Crypto, false),
+        C2pa::ClaimVerification(_) => ("claimVerification", Crypto, false),
+        C2pa::PdfReadError => ("pdfReadError", Format, false),
+        C2pa::InvalidClaim(_) => ("invalidClaim", Format, false),
+        C2pa::InvalidAsset(_) => ("invalidAsset", Format, false),
+        C2pa::JumbfParseError(_) => ("jumbfParseError", Format, false),
+        C2pa::VerifiableCredentialInvalid => ("verifiableCredentialInvalid", Format, false),
+        C2pa::InvalidEcdsaSignature => ("invalidEcdsaSignature", Crypto, false),
+        C2pa::MissingDataBox => ("missingDataBox", Format, false),
+        C2pa::XmlWriteError => ("xmlWriteError", Internal, false),
+        C2pa::IoError(_) => ("ioError", Internal, false),
+        C2pa::JsonError(_) => ("jsonError", Format, false),
+        C2pa::CborError(_) => ("cborError", Format, false),
+        C2pa::OtherError(_) => ("otherError", Internal, false),
+        C2pa::PrereleaseError => ("prereleaseError", Format, false),
+        _ => {
+            return ErrorCode {
+                upstream: Some(format!("{:?}", err)),
+                ..ErrorCode::new(UNKNOWN_UPSTREAM, Internal, false)
+            }
+        }
+    };
+
+    ErrorCode::new(code, category, retriable)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use c2pa::Error as C2pa;
+    use std::collections::HashSet;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
+
+    /// One error of each c2pa variant that can be built outside of the c2pa crate, which is every
+    /// variant listed in `c2pa_error_code` except `AssertionDecoding`, `InvalidCoseSignature`,
+    /// `InvalidClaim` and `JumbfParseError`, whose payloads are private to c2pa
+    fn c2pa_errors() -> Vec<C2pa> {
+        let text = || "x".to_owned();
+        vec![
+            C2pa::ClaimMissing { label: text() },
+            C2pa::AssertionUnsupportedVersion,
+            C2pa::AssertionMissing { url: text() },
+            C2pa::AssertionEncoding,
+            C2pa::AssertionInvalidRedaction,
+            C2pa::AssertionRedactionNotFound,
+            C2pa::BadParam(text()),
+            C2pa::MissingFeature(text()),
+            C2pa::NotImplemented(text()),
+            C2pa::ClaimEncoding,
+            C2pa::ClaimDecoding,
+            C2pa::ClaimAlreadySigned,
+            C2pa::ClaimUnsigned,
+            C2pa::ClaimMissingSignatureBox,
+            C2pa::ClaimMissingIdentity,
+            C2pa::ClaimVersion,
+            C2pa::ClaimInvalidContent,
+            C2pa::ClaimMissingHardBinding,
+            C2pa::ClaimSelfRedact,
+            C2pa::ClaimDisallowedRedaction,
+            C2pa::UpdateManifestInvalid,
+            C2pa::TooManyManifestStores,
+            C2pa::CoseSignatureAlgorithmNotSupported,
+            C2pa::CoseMissingKey,
+            C2pa::CoseX5ChainMissing,
+            C2pa::CoseInvalidCert,
+            C2pa::CoseSignature,
+            C2pa::CoseVerifier,
+            C2pa::CoseCertExpiration,
+            C2pa::CoseCertRevoked,
+            C2pa::CoseInvalidTimeStamp,
+            C2pa::CoseTimeStampValidity,
+            C2pa::CoseTimeStampMismatch,
+            C2pa::CoseTimeStampGeneration,
+            C2pa::CoseTimeStampAuthority,
+            C2pa::CoseSigboxTooSmall,
+            C2pa::WasmVerifier,
+            C2pa::WasmRsaKeyImport(text()),
+            C2pa::WasmRsaVerification,
+            C2pa::WasmKey,
+            C2pa::WasmInvalidContext,
+            C2pa::WasmNoCrypto,
+            C2pa::JumbfCreationError,
+            C2pa::ThreadReceiveError,
+            C2pa::JumbfNotFound,
+            C2pa::JumbfBoxNotFound,
+            C2pa::RemoteManifestFetch(text()),
+            C2pa::RemoteManifestUrl(text()),
+            C2pa::LogStop,
+            C2pa::NotFound,
+            C2pa::UnsupportedType,
+            C2pa::EmbeddingError,
+            C2pa::IngredientNotFound,
+            C2pa::FileNotFound(text()),
+            C2pa::ResourceNotFound(text()),
+            C2pa::XmpReadError,
+            C2pa::XmpWriteError,
+            C2pa::XmpNotSupported,
+            C2pa::ProvenanceMissing,
+            C2pa::HashMismatch(text()),
+            C2pa::ClaimVerification(text()),
+            C2pa::PdfReadError,
+            C2pa::InvalidAsset(text()),
+            C2pa::VerifiableCredentialInvalid,
+            C2pa::InvalidEcdsaSignature,
+            C2pa::MissingDataBox,
+            C2pa::XmlWriteError,
+            std::io::Error::other("x").into(),
+            serde_json::from_str::<u8>("x").unwrap_err().into(),
+            serde_cbor::from_slice::<u8>(b"").unwrap_err().into(),
+            C2pa::OtherError("x".into()),
+            C2pa::PrereleaseError,
+        ]
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_c2pa_error_codes() {
+        let errors = c2pa_errors();
+        let codes: HashSet<&str> = errors
+            .iter()
+            .map(|err| {
+                let code = c2pa_error_code(err);
+                assert_ne!(code.code, UNKNOWN_UPSTREAM, "{:?} has no code", err);
+                assert_eq!(code.upstream, None);
+                code.code
+            })
+            .collect();
+        // Each variant has a code of its own
+        assert_eq!(codes.len(), errors.len());
+
+        let code = Error::from(C2pa::RemoteManifestFetch("https://example.com".to_owned())).code();
+        assert_eq!(code.code, "remoteManifestFetch");
+        assert_eq!(code.category.as_str(), "internal");
+        assert!(code.retriable);
+        let code = Error::from(C2pa::CoseCertRevoked).code();
+        assert_eq!(code.category, ErrorCategory::Trust);
+        assert!(!code.retriable);
+        assert_eq!(
+            Error::EmptyInput("buf").code(),
+            ErrorCode::new("emptyInput", ErrorCategory::Input, false)
+        );
+    }
+}
@@ -0,0 +1,250 @@
+// Copyright 2024 Adobe
+// All Rights Reserved.
+//
+// NOTICE: Adobe permits you to use, modify, and distribute this file in
+// accordance with the terms of the Adobe license agreement accompanying
+// it.
+
+//! Compares a manifest's claim generator with who signed it, using hints set by the application.
+//!
+//! The claim generator is whatever the signer chose to write, so a manifest can name a well-known
+//! product while being signed by an unrelated certificate. Each hint maps a generator prefix to
+//! the organizations or domains expected to sign for it. A manifest whose generator matches a
+//! hint but whose signing certificate doesn't gets a `generatorCertMismatch` status. The check is
+//! advisory: the status is a warning, and nothing is checked until hints are set.
+use crate::manifest_store::UpdateValidationStatus;
+use crate::ocsp::Subject;
+use serde::Deserialize;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+pub const GENERATOR_CERT_MISMATCH: &str = "generatorCertMismatch";
+
+/// The organizations or domains expected to sign for a generator, as passed from JavaScript
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum ExpectedSigners {
+    One(String),
+    Many(Vec<String>),
+}
+
+struct Hint {
+    prefix: String,
+    expected: Vec<String>,
+}
+
+thread_local! {
+    static HINTS: RefCell<Vec<Hint>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Replaces the trust hints, keyed by generator prefix. An empty map turns the check off.
+pub fn set_hints(hints: HashMap<String, ExpectedSigners>) {
+    let mut hints: Vec<Hint> = hints
+        .into_iter()
+        .map(|(prefix, expected)| Hint {
+            prefix: normalize_generator(&prefix),
+            expected: match expected {
+                ExpectedSigners::One(expected) => vec![expected],
+                ExpectedSigners::Many(expected) => expected,
+            },
+        })
+        .filter(|hint| !hint.prefix.is_empty())
+        .collect();
+    // The most specific prefix wins, e.g. "Adobe Photoshop Express" over "Adobe Photoshop"
+    hints.sort_by_key(|hint| std::cmp::Reverse(hint.prefix.len()));
+    HINTS.with(|cell| *cell.borrow_mut() = hints);
+}
+
+pub fn has_hints() -> bool {
+    HINTS.with(|cell| !cell.borrow().is_empty())
+}
+
+/// Checks the generators a manifest names, its `claim_generator` and the names in
+/// `claim_generator_info`, against the subject of its signing certificate
+pub fn check(
+    label: &str,
+    generators: &[&str],
+    subject: &Subject,
+) -> Option<UpdateValidationStatus> {
+    HINTS.with(|cell| {
+        let hints = cell.borrow();
+        generators.iter().find_map(|generator| {
+            let normalized = normalize_generator(generator);
+            let hint = hints
+                .iter()
+                .find(|hint| has_word_prefix(&normalized, &hint.prefix))?;
+            if hint
+                .expected
+                .iter()
+                .any(|expected| signed_by(subject, expected))
+            {
+                return None;
+            }
+
+            let signer = subject
+                .organizations
+                .first()
+                .or_else(|| subject.common_names.first())
+                .map_or("an unnamed subject".to_owned(), |name| {
+                    format!("\"{}\"", name)
+                });
+            Some(UpdateValidationStatus {
+                code: GENERATOR_CERT_MISMATCH,
+                url: format!("self#jumbf=/c2pa/{}/c2pa.signature", label),
+                explanation: format!(
+                    "claim generator \"{}\" is expected to be signed by {}, but the signing \
+                     certificate was issued to {}",
+                    generator,
+                    hint.expected.join(" or "),
+                    signer
+                ),
+            })
+        })
+    })
+}
+
+/// Lowercases a generator, treating underscores and hyphens as spaces, as in `Adobe_Photoshop`
+fn normalize_generator(generator: &str) -> String {
+    generator
+        .to_lowercase()
+        .replace(['_', '-'], " ")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Lowercases an organization name, keeping only its words, so "Adobe, Inc." is "adobe inc"
+fn normalize_organization(organization: &str) -> String {
+    organization
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Whether `value` starts with `prefix` followed by the end or a character that ends a word
+fn has_word_prefix(value: &str, prefix: &str) -> bool {
+    value.starts_with(prefix)
+        && value[prefix.len()..]
+            .chars()
+            .next()
+            .is_none_or(|c| !c.is_alphanumeric())
+}
+
+/// Whether the certificate subject matches an expected signer
+///
+/// An expected signer with a dot and no spaces is a domain, which matches a common name for the
+/// domain or any of its subdomains. Anything else is an organization, which matches an
+/// organization name starting with the same words, so "Adobe" matches "Adobe, Inc.".
+fn signed_by(subject: &Subject, expected: &str) -> bool {
+    let expected = expected.trim();
+    if expected.contains('.') && !expected.contains(' ') {
+        let domain = expected.trim_start_matches("*.").to_lowercase();
+        subject.common_names.iter().any(|name| {
+            let name = name.to_lowercase();
+            name == domain || name.ends_with(&format!(".{}", domain))
+        })
+    } else {
+        let expected = normalize_organization(expected);
+        !expected.is_empty()
+            && subject.organizations.iter().any(|organization| {
+                has_word_prefix(&normalize_organization(organization), &expected)
+            })
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
+
+    fn hints(hints: &[(&str, &[&str])]) {
+        set_hints(
+            hints
+                .iter()
+                .map(|(prefix, expected)| {
+                    let expected = expected.iter().map(|s| s.to_string()).collect();
+                    (prefix.to_string(), ExpectedSigners::Many(expected))
+                })
+                .collect(),
+        );
+    }
+
+    fn adobe() -> Subject {
+        Subject {
+            organizations: vec!["Adobe, Inc.".to_owned()],
+            common_names: vec!["contentauthenticity.org".to_owned()],
+        }
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_generator_matching() {
+        hints(&[
+            ("Adobe Photoshop", &["Adobe"]),
+            (
+                "Adobe Photoshop Express",
+                &["Example Mobile", "express.example.com"],
+            ),
+            ("Acme-Camera", &["Acme Imaging", "Acme Corp"]),
+        ]);
+        assert!(has_hints());
+
+        // Generators are matched by whole words, ignoring case and separators
+        assert!(check("a", &["Adobe_Photoshop/23.0 adobe_c2pa/0.7.6"], &adobe()).is_none());
+        assert!(check("a", &["adobe photoshop"], &adobe()).is_none());
+        assert!(check("a", &["Adobe Photoshopper/1.0"], &Subject::default()).is_none());
+        assert!(check(
+            "a",
+            &["Paint/1.0 Adobe_Photoshop/23.0"],
+            &Subject::default()
+        )
+        .is_none());
+
+        // The most specific prefix applies
+        let express = Subject {
+            organizations: vec![],
+            common_names: vec!["signing.express.example.com".to_owned()],
+        };
+        assert!(check("a", &["Adobe_Photoshop_Express/1.0"], &express).is_none());
+        let status = check("label", &["Adobe_Photoshop_Express/1.0"], &adobe()).unwrap();
+        assert_eq!(status.code, GENERATOR_CERT_MISMATCH);
+        assert_eq!(status.url, "self#jumbf=/c2pa/label/c2pa.signature");
+        assert_eq!(
+            status.explanation,
+            "claim generator \"Adobe_Photoshop_Express/1.0\" is expected to be signed by Example \
+             Mobile or express.example.com, but the signing certificate was issued to \"Adobe, \
+             Inc.\""
+        );
+
+        // Any of the expected organizations will do
+        let acme = Subject {
+            organizations: vec!["ACME Corp.".to_owned()],
+            common_names: vec![],
+        };
+        assert!(check("a", &["acme_camera/2"], &acme).is_none());
+        assert!(check("a", &["Adobe Photoshop/23.0"], &acme).is_some());
+
+        // Every generator the manifest names is checked
+        assert!(check("a", &["make_test_images/0.16", "Acme Camera"], &adobe()).is_some());
+
+        set_hints(HashMap::new());
+        assert!(!has_hints());
+        assert!(check("a", &["Adobe Photoshop/23.0"], &Subject::default()).is_none());
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_signed_by() {
+        let subject = adobe();
+        assert!(signed_by(&subject, "Adobe"));
+        assert!(signed_by(&subject, "adobe inc"));
+        assert!(signed_by(&subject, "contentauthenticity.org"));
+        assert!(signed_by(&subject, "*.contentauthenticity.org"));
+        assert!(!signed_by(&subject, "Adob"));
+        assert!(!signed_by(&subject, "Adobe Systems"));
+        assert!(!signed_by(&subject, "authenticity.org"));
+        assert!(!signed_by(&subject, ""));
+    }
+}
@@ -0,0 +1,369 @@
+// Copyright 2024 Adobe
+// All Rights Reserved.
+//
+// NOTICE: Adobe permits you to use, modify, and distribute this file in
+// accordance with the terms of the Adobe license agreement accompanying
+// it.
+
+//! Flattens the provenance chain of a manifest store into nodes and edges.
+//!
+//! Each manifest is one node however many ingredients reference it, and each ingredient without a
+//! manifest is a node of its own. Edges run from a manifest to its ingredients. The traversal is
+//! a depth-first search with an explicit stack, so a chain can't exhaust the call stack, and an
+//! ingredient referencing a manifest that is still being visited is reported as a back edge
+//! instead of being followed.
+use crate::validation::{self, Severity};
+use c2pa::validation_status::ValidationStatus;
+use c2pa::{Ingredient, Manifest, ManifestStore};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// How a manifest or ingredient validated
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidationSummary {
+    pub worst_severity: Severity,
+    /// The codes of the statuses that aren't informational
+    pub codes: Vec<String>,
+}
+
+impl ValidationSummary {
+    fn new<'a>(statuses: impl IntoIterator<Item = &'a ValidationStatus>) -> Self {
+        let codes: Vec<String> = statuses
+            .into_iter()
+            .map(ValidationStatus::code)
+            .filter(|code| validation::severity(code) != Severity::Info)
+            .map(str::to_owned)
+            .collect();
+
+        Self {
+            worst_severity: validation::worst_severity(
+                codes.iter().map(|code| validation::severity(code)),
+                false,
+            ),
+            codes,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphNode {
+    /// The manifest label, or the manifest label and index of an ingredient without a manifest
+    pub id: String,
+    /// The manifest label, or the label of the missing manifest an ingredient references
+    pub label: Option<String>,
+    pub title: Option<String>,
+    pub generator: Option<String>,
+    /// The issuer of the signing certificate
+    pub signer: Option<String>,
+    pub validation: ValidationSummary,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GraphEdge {
+    pub from: String,
+    pub to: String,
+    /// `parentOf`, `componentOf` or `inputTo`
+    pub relationship: String,
+}
+
+/// Result of `getProvenanceGraph`
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProvenanceGraph {
+    pub active_manifest: Option<String>,
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+    /// Edges to a manifest that is its own ingredient, directly or further down the chain, which
+    /// are left out of `edges`
+    pub back_edges: Vec<GraphEdge>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Visit {
+    /// The manifest's ingredients are being visited
+    InProgress,
+    Done,
+}
+
+/// What the traversal found, in the order it found it
+#[derive(Debug, PartialEq, Eq)]
+enum Step<'a> {
+    /// A manifest reached for the first time
+    Manifest(&'a str),
+    /// The ingredient at `index` of manifest `from`, along with the label of its manifest if that
+    /// manifest is in the store
+    Ingredient {
+        from: &'a str,
+        index: usize,
+        to: Option<&'a str>,
+        /// Whether `to` is still being visited, so that following it would loop
+        back_edge: bool,
+    },
+}
+
+/// Walks the provenance chain depth first from each of `roots` in turn
+///
+/// `ingredients` returns, for each ingredient of a manifest, the label of the ingredient's
+/// manifest if it is in the store.
+fn walk<'a>(
+    roots: impl IntoIterator<Item = &'a str>,
+    ingredients: impl Fn(&'a str) -> Vec<Option<&'a str>>,
+) -> Vec<Step<'a>> {
+    let mut steps = Vec::new();
+    let mut visits: HashMap<&str, Visit> = HashMap::new();
+    for root in roots {
+        if visits.contains_key(root) {
+            continue;
+        }
+        visits.insert(root, Visit::InProgress);
+        steps.push(Step::Manifest(root));
+
+        // Each entry is a manifest along with its ingredients and the index of the next one
+        let mut stack = vec![(root, ingredients(root), 0)];
+        while let Some((label, targets, index)) = stack.pop() {
+            let to = match targets.get(index) {
+                Some(to) => *to,
+                None => {
+                    visits.insert(label, Visit::Done);
+                    continue;
+                }
+            };
+            stack.push((label, targets, index + 1));
+
+            let visit = to.and_then(|to| visits.get(to).copied());
+            steps.push(Step::Ingredient {
+                from: label,
+                index,
+                to,
+                back_edge: visit == Some(Visit::InProgress),
+            });
+            if let (Some(to), None) = (to, visit) {
+                visits.insert(to, Visit::InProgress);
+                steps.push(Step::Manifest(to));
+                stack.push((to, ingredients(to), 0));
+            }
+        }
+    }
+
+    steps
+}
+
+fn manifest_node(label: &str, manifest: &Manifest, statuses: &[&ValidationStatus]) -> GraphNode {
+    GraphNode {
+        id: label.to_owned(),
+        label: Some(label.to_owned()),
+        title: manifest.title().map(str::to_owned),
+        generator: Some(manifest.claim_generator().to_owned()),
+        signer: manifest.issuer(),
+        validation: ValidationSummary::new(statuses.iter().copied()),
+    }
+}
+
+fn ingredient_node(id: String, ingredient: &Ingredient) -> GraphNode {
+    GraphNode {
+        id,
+        label: ingredient.active_manifest().map(str::to_owned),
+        title: Some(ingredient.title().to_owned()),
+        generator: None,
+        signer: None,
+        validation: ValidationSummary::new(ingredient.validation_status().unwrap_or_default()),
+    }
+}
+
+/// The relationship of an ingredient, as it is named in the ingredient assertion
+///
+/// The c2pa crate doesn't export the relationship's type, so it is read back from its serialized
+/// form.
+fn relationship(ingredient: &Ingredient) -> String {
+    serde_json::to_value(ingredient.relationship())
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_owned))
+        .unwrap_or_default()
+}
+
+/// Builds the provenance graph of a manifest store
+///
+/// Nodes are listed in the order they are first reached from the active manifest, followed by
+/// the manifests the chain doesn't reach.
+pub fn provenance_graph(manifest_store: &ManifestStore) -> ProvenanceGraph {
+    let active_label = manifest_store.active_label();
+
+    // Store statuses are attributed to the manifest they refer to, or else to the active manifest
+    let mut statuses: HashMap<&str, Vec<&ValidationStatus>> = HashMap::new();
+    for status in manifest_store.validation_status().unwrap_or_default() {
+        let label = status.url().and_then(validation::manifest_label);
+        if let Some(label) = label.or(active_label) {
+            statuses.entry(label).or_default().push(status);
+        }
+    }
+
+    let mut labels: Vec<&str> = manifest_store
+        .manifests()
+        .keys()
+        .map(String::as_str)
+        .collect();
+    labels.sort_unstable();
+    let roots = active_label.into_iter().chain(
+        labels
+            .into_iter()
+            .filter(|label| Some(*label) != active_label),
+    );
+
+    let steps = walk(roots, |label| {
+        manifest_store
+            .get(label)
+            .map(|manifest| {
+                manifest
+                    .ingredients()
+                    .iter()
+                    .map(|ingredient| {
+                        let to = ingredient.active_manifest()?;
+                        manifest_store
+                            .manifests()
+                            .get_key_value(to)
+                            .map(|(to, _)| to.as_str())
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    });
+
+    let mut graph = ProvenanceGraph {
+        active_manifest: active_label.map(str::to_owned),
+        ..ProvenanceGraph::default()
+    };
+    for step in steps {
+        match step {
+            Step::Manifest(label) => {
+                if let Some(manifest) = manifest_store.get(label) {
+                    let node_statuses = statuses.get(label).map(Vec::as_slice).unwrap_or_default();
+                    graph
+                        .nodes
+                        .push(manifest_node(label, manifest, node_statuses));
+                }
+            }
+            Step::Ingredient {
+                from,
+                index,
+                to,
+                back_edge,
+            } => {
+                let ingredient = match manifest_store
+                    .get(from)
+                    .and_then(|manifest| manifest.ingredients().get(index))
+                {
+                    Some(ingredient) => ingredient,
+                    None => continue,
+                };
+                let to = match to {
+                    Some(to) => to.to_owned(),
+                    None => {
+                        let id = format!("{}/ingredients/{}", from, index);
+                        graph.nodes.push(ingredient_node(id.clone(), ingredient));
+                        id
+                    }
+                };
+                let edge = GraphEdge {
+                    from: from.to_owned(),
+                    to,
+                    relationship: relationship(ingredient),
+                };
+                if back_edge {
+                    graph.back_edges.push(edge);
+                } else {
+                    graph.edges.push(edge);
+                }
+            }
+        }
+    }
+
+    graph
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::manifest_store::get_manifest_store_data;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    pub async fn test_provenance_graph() {
+        let test_asset = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+        let manifest_store = get_manifest_store_data(test_asset, "image/jpeg")
+            .await
+            .unwrap();
+
+        let graph = provenance_graph(&manifest_store);
+        let active_label = "adobetest:urn:uuid:825cf3cf-0127-4af3-b65c-c11d0f961e67";
+        assert_eq!(graph.active_manifest.as_deref(), Some(active_label));
+        assert_eq!(graph.nodes[0].id, active_label);
+        assert!(graph.back_edges.is_empty());
+
+        // Every manifest is a single node, and every edge joins two nodes
+        let manifest_nodes = graph.nodes.iter().filter(|node| {
+            node.label
+                .as_deref()
+                .is_some_and(|label| manifest_store.get(label).is_some())
+        });
+        assert_eq!(manifest_nodes.count(), manifest_store.manifests().len());
+        for edge in &graph.edges {
+            assert!(graph.nodes.iter().any(|node| node.id == edge.from));
+            assert!(graph.nodes.iter().any(|node| node.id == edge.to));
+        }
+        let ingredient_count: usize = manifest_store
+            .manifests()
+            .values()
+            .map(|manifest| manifest.ingredients().len())
+            .sum();
+        assert_eq!(graph.edges.len(), ingredient_count);
+        assert!(graph
+            .edges
+            .iter()
+            .any(|edge| edge.from == active_label && edge.relationship == "parentOf"));
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_walk() {
+        // a has ingredients b, c and a leaf; b has c; c has a, which loops back; d is unreferenced
+        let ingredients = |label| match label {
+            "a" => vec![Some("b"), Some("c"), None],
+            "b" => vec![Some("c")],
+            "c" => vec![Some("a")],
+            _ => vec![],
+        };
+        let ingredient = |from, index, to, back_edge| Step::Ingredient {
+            from,
+            index,
+            to,
+            back_edge,
+        };
+
+        let steps = walk(vec!["a", "d", "b"], ingredients);
+        assert_eq!(
+            steps,
+            vec![
+                Step::Manifest("a"),
+                ingredient("a", 0, Some("b"), false),
+                Step::Manifest("b"),
+                ingredient("b", 0, Some("c"), false),
+                Step::Manifest("c"),
+                ingredient("c", 0, Some("a"), true),
+                // c is visited once however many manifests reference it
+                ingredient("a", 1, Some("c"), false),
+                ingredient("a", 2, None, false),
+                Step::Manifest("d"),
+            ]
+        );
+
+        // A manifest that is its own ingredient
+        let steps = walk(vec!["a"], |_| vec![Some("a")]);
+        assert_eq!(
+            steps,
+            vec![Step::Manifest("a"), ingredient("a", 0, Some("a"), true)]
+        );
+    }
+}
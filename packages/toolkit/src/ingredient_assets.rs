@@ -0,0 +1,340 @@
+// Copyright 2024 Adobe
+// All Rights Reserved.
+//
+// NOTICE: Adobe permits you to use, modify, and distribute this file in
+// accordance with the terms of the Adobe license agreement accompanying
+// it.
+
+//! Checks ingredient assets supplied alongside the main asset against the ingredients they claim
+//! to be.
+//!
+//! Ingredient assertions don't record a hash of the ingredient's bytes, so what can be checked
+//! depends on the ingredient. When it references a manifest, the supplied asset must carry that
+//! manifest as its active one, unchanged since the ingredient was recorded, and must still match
+//! the manifest's hard binding. Otherwise the ingredient thumbnail is compared with the supplied
+//! asset, which is only a perceptual match.
+use crate::error::Result;
+use crate::manifest_store::{
+    check_ingredient_manifest_reference, get_manifest_store_data, HashedUriCheck,
+};
+use crate::options::IngredientAsset;
+use crate::thumbnail::{self, ThumbnailConsistency};
+use crate::validation;
+use c2pa::{Ingredient, ManifestStore};
+use serde::Serialize;
+
+/// How a supplied asset was checked against its ingredient
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Method {
+    /// Against the manifest the ingredient references
+    Manifest,
+    /// Against the ingredient thumbnail, for ingredients without a manifest
+    Thumbnail,
+}
+
+/// How a supplied asset was checked, added to its ingredient as `details`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Details {
+    pub method: Method,
+    /// The check of the ingredient's manifest reference against the supplied asset's active
+    /// manifest, which fails if that isn't the manifest the reference names
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub manifest_reference: Option<HashedUriCheck>,
+    /// Whether the supplied asset still matches the hard binding of its active manifest
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hard_binding_valid: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thumbnail: Option<ThumbnailConsistency>,
+    /// Why the supplied asset couldn't be read
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// The result of checking a supplied asset against one ingredient
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SuppliedAssetCheck {
+    pub supplied_asset_matches: bool,
+    pub details: Details,
+}
+
+/// A supplied asset that no ingredient has the identifiers of
+#[derive(Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnmatchedAsset {
+    pub instance_id: Option<String>,
+    pub document_id: Option<String>,
+}
+
+/// The checks of the supplied assets, keyed by manifest label and ingredient index
+#[derive(Debug, Default)]
+pub struct IngredientAssetChecks {
+    pub checks: Vec<(String, usize, SuppliedAssetCheck)>,
+    pub unmatched: Vec<UnmatchedAsset>,
+}
+
+/// Whether `asset` was supplied for `ingredient`
+fn matches(asset: &IngredientAsset, ingredient: &Ingredient) -> bool {
+    asset.instance_id.as_deref() == Some(ingredient.instance_id())
+        || asset.document_id.is_some() && asset.document_id.as_deref() == ingredient.document_id()
+}
+
+/// Checks each supplied asset against every ingredient with its instance or document ID
+///
+/// `store_bytes` is the JUMBF of the main asset. Without it manifest references can't be checked,
+/// so assets supplied for ingredients with a manifest don't match.
+pub async fn check_ingredient_assets(
+    manifest_store: &ManifestStore,
+    store_bytes: Option<&[u8]>,
+    assets: &[IngredientAsset],
+) -> Result<IngredientAssetChecks> {
+    let mut manifests: Vec<_> = manifest_store.manifests().iter().collect();
+    manifests.sort_by_key(|(label, _)| *label);
+
+    let mut result = IngredientAssetChecks::default();
+    for asset in assets {
+        let mut matched = false;
+        for (label, manifest) in &manifests {
+            for (index, ingredient) in manifest.ingredients().iter().enumerate() {
+                if !matches(asset, ingredient) {
+                    continue;
+                }
+                matched = true;
+                let check = match ingredient.active_manifest() {
+                    Some(_) => check_manifest(store_bytes, label, index, asset).await?,
+                    None => check_thumbnail(ingredient, asset),
+                };
+                result.checks.push(((*label).clone(), index, check));
+            }
+        }
+
+        if !matched {
+            result.unmatched.push(UnmatchedAsset {
+                instance_id: asset.instance_id.clone(),
+                document_id: asset.document_id.clone(),
+            });
+        }
+    }
+
+    Ok(result)
+}
+
+/// Checks a supplied asset against the manifest its ingredient references
+async fn check_manifest(
+    store_bytes: Option<&[u8]>,
+    label: &str,
+    index: usize,
+    asset: &IngredientAsset,
+) -> Result<SuppliedAssetCheck> {
+    let mut details = Details {
+        method: Method::Manifest,
+        manifest_reference: None,
+        hard_binding_valid: None,
+        thumbnail: None,
+        error: None,
+    };
+
+    let asset_store_bytes = c2pa::jumbf_io::load_jumbf_from_memory(&asset.mime_type, &asset.buffer);
+    if let Err(err) = &asset_store_bytes {
+        details.error = Some(err.to_string());
+    }
+    if let Some(store_bytes) = store_bytes {
+        details.manifest_reference = check_ingredient_manifest_reference(
+            store_bytes,
+            label,
+            index,
+            asset_store_bytes.as_deref().ok(),
+        )?;
+    }
+
+    if asset_store_bytes.is_ok() {
+        match get_manifest_store_data(&asset.buffer, &asset.mime_type).await {
+            Ok(asset_store) => {
+                details.hard_binding_valid = Some(
+                    !asset_store
+                        .validation_status()
+                        .unwrap_or_default()
+                        .iter()
+                        .any(|status| validation::is_hard_binding_mismatch(status.code())),
+                );
+            }
+            Err(err) => details.error = Some(err.to_string()),
+        }
+    }
+
+    Ok(SuppliedAssetCheck {
+        supplied_asset_matches: details
+            .manifest_reference
+            .as_ref()
+            .is_some_and(|check| check.hashed_uri_valid)
+            && details.hard_binding_valid == Some(true),
+        details,
+    })
+}
+
+/// Compares a supplied asset with the thumbnail of its ingredient
+fn check_thumbnail(ingredient: &Ingredient, asset: &IngredientAsset) -> SuppliedAssetCheck {
+    let consistency = match ingredient.thumbnail() {
+        Some((_, thumbnail)) => {
+            thumbnail::compare(&thumbnail, &asset.buffer, thumbnail::DEFAULT_THRESHOLD)
+        }
+        None => ThumbnailConsistency::not_evaluable(
+            thumbnail::DEFAULT_THRESHOLD,
+            thumbnail::NotEvaluable::NoThumbnail,
+        ),
+    };
+
+    SuppliedAssetCheck {
+        supplied_asset_matches: consistency.consistent == Some(true),
+        details: Details {
+            method: Method::Thumbnail,
+            manifest_reference: None,
+            hard_binding_valid: None,
+            thumbnail: Some(consistency),
+            error: None,
+        },
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
+
+    const XCI_INSTANCE_ID: &str = "xmp:iid:d344ce1e-72b4-441e-80bd-35bc3223d0e4";
+    const I_INSTANCE_ID: &str = "xmp.iid:eb00056d-7202-4f8e-b919-11b7fdab5898";
+
+    fn ingredient_asset(instance_id: &str, buffer: &[u8]) -> IngredientAsset {
+        IngredientAsset {
+            instance_id: Some(instance_id.to_owned()),
+            document_id: None,
+            buffer: serde_bytes::ByteBuf::from(buffer),
+            mime_type: "image/jpeg".to_owned(),
+        }
+    }
+
+    async fn check(assets: &[IngredientAsset]) -> IngredientAssetChecks {
+        let test_asset = include_bytes!("../../../tools/testing/fixtures/images/CAIXCI.jpg");
+        let store_bytes = c2pa::jumbf_io::load_jumbf_from_memory("image/jpeg", test_asset).unwrap();
+        let manifest_store = get_manifest_store_data(test_asset, "image/jpeg")
+            .await
+            .unwrap();
+        check_ingredient_assets(&manifest_store, Some(&store_bytes), assets)
+            .await
+            .unwrap()
+    }
+
+    #[wasm_bindgen_test]
+    pub async fn test_check_ingredient_assets() {
+        let parent = include_bytes!("../../../tools/testing/fixtures/images/XCI.jpg");
+        let grandparent = include_bytes!("../../../tools/testing/fixtures/images/I.jpg");
+        let unrelated = include_bytes!("../../../tools/testing/fixtures/images/firefly-1.jpg");
+
+        let result = check(&[
+            ingredient_asset(XCI_INSTANCE_ID, parent),
+            ingredient_asset(I_INSTANCE_ID, grandparent),
+            ingredient_asset("xmp:iid:unknown", unrelated),
+        ])
+        .await;
+        assert_eq!(
+            result.unmatched,
+            vec![UnmatchedAsset {
+                instance_id: Some("xmp:iid:unknown".to_owned()),
+                document_id: None,
+            }]
+        );
+        assert_eq!(result.checks.len(), 2);
+
+        // XCI carries the referenced manifest unchanged, but its pixels were altered after signing
+        let (_, _, parent_check) = &result.checks[0];
+        assert_eq!(parent_check.details.method, Method::Manifest);
+        assert!(
+            parent_check
+                .details
+                .manifest_reference
+                .as_ref()
+                .unwrap()
+                .hashed_uri_valid
+        );
+        assert_eq!(parent_check.details.hard_binding_valid, Some(false));
+        assert!(!parent_check.supplied_asset_matches);
+
+        // I has no manifest, so only its thumbnail can be compared
+        let (_, _, grandparent_check) = &result.checks[1];
+        assert_eq!(grandparent_check.details.method, Method::Thumbnail);
+        assert!(grandparent_check.supplied_asset_matches);
+
+        // An unrelated file supplied for either ingredient doesn't match it
+        let result = check(&[
+            ingredient_asset(XCI_INSTANCE_ID, unrelated),
+            ingredient_asset(I_INSTANCE_ID, unrelated),
+        ])
+        .await;
+        assert!(result.unmatched.is_empty());
+        for (_, _, check) in &result.checks {
+            assert!(!check.supplied_asset_matches);
+        }
+        let (_, _, parent_check) = &result.checks[0];
+        assert!(
+            !parent_check
+                .details
+                .manifest_reference
+                .as_ref()
+                .unwrap()
+                .hashed_uri_valid
+        );
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test]
+    pub async fn test_ingredient_assets_option() {
+        use crate::get_path;
+        use js_sys::{Array, Object, Reflect, Uint8Array};
+        use wasm_bindgen::JsValue;
+
+        let test_asset = include_bytes!("../../../tools/testing/fixtures/images/CAIXCI.jpg");
+        let grandparent = include_bytes!("../../../tools/testing/fixtures/images/I.jpg");
+        let asset = Object::new();
+        Reflect::set(&asset, &"instanceId".into(), &I_INSTANCE_ID.into()).unwrap();
+        Reflect::set(
+            &asset,
+            &"buffer".into(),
+            &Uint8Array::from(&grandparent[..]).buffer(),
+        )
+        .unwrap();
+        Reflect::set(&asset, &"mimeType".into(), &"image/jpeg".into()).unwrap();
+        let options = Object::new();
+        Reflect::set(&options, &"ingredientAssets".into(), &Array::of1(&asset)).unwrap();
+
+        let result =
+            wasm_bindgen_futures::JsFuture::from(crate::get_manifest_store_from_array_buffer(
+                Uint8Array::from(&test_asset[..]).buffer().into(),
+                "image/jpeg".to_owned(),
+                options.into(),
+            ))
+            .await
+            .unwrap();
+        let ingredient = get_path(
+            &result,
+            &[
+                "manifests",
+                "adobetest:urn:uuid:ac39fe0b-7b99-49b8-9760-dfa4ca0053e3",
+                "ingredients",
+                "0",
+            ],
+        );
+        assert_eq!(get_path(&ingredient, &["suppliedAssetMatches"]), true);
+        assert_eq!(
+            get_path(&ingredient, &["details", "method"]),
+            JsValue::from("thumbnail")
+        );
+        assert_eq!(
+            Array::from(&get_path(&result, &["unmatchedIngredientAssets"])).length(),
+            0
+        );
+    }
+}
@@ -0,0 +1,137 @@
+// Copyright 2024 Adobe
+// All Rights Reserved.
+//
+// NOTICE: Adobe permits you to use, modify, and distribute this file in
+// accordance with the terms of the Adobe license agreement accompanying
+// it.
+
+//! Sets up logging and panic reporting.
+//!
+//! This used to happen when the module started, which hosts such as Cloudflare Workers don't
+//! always allow. It now happens when `initToolkit` is called, or with the defaults on the first
+//! call to any other entry point.
+use log::LevelFilter;
+use serde::Deserialize;
+use std::cell::Cell;
+use std::panic;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PanicHook {
+    /// Panics are logged with `console.error` before the module traps
+    Console,
+    /// The module traps without logging, as it does without a hook
+    None,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LogLevel {
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl From<LogLevel> for LevelFilter {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Off => LevelFilter::Off,
+            LogLevel::Error => LevelFilter::Error,
+            LogLevel::Warn => LevelFilter::Warn,
+            LogLevel::Info => LevelFilter::Info,
+            LogLevel::Debug => LevelFilter::Debug,
+            LogLevel::Trace => LevelFilter::Trace,
+        }
+    }
+}
+
+/// Options of `initToolkit`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct InitOptions {
+    /// Whether to log to the console
+    pub install_logger: bool,
+    pub log_level: LogLevel,
+    pub panic_hook: PanicHook,
+}
+
+impl Default for InitOptions {
+    fn default() -> Self {
+        Self {
+            install_logger: true,
+            log_level: LogLevel::Info,
+            panic_hook: PanicHook::Console,
+        }
+    }
+}
+
+thread_local! {
+    static INITIALIZED: Cell<bool> = const { Cell::new(false) };
+    static LOGGER_INSTALLED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Applies `options`, replacing the ones applied before
+///
+/// A logger can't be removed once it is installed, so turning logging off after the first call
+/// silences it instead.
+pub fn init(options: &InitOptions) {
+    INITIALIZED.with(|initialized| initialized.set(true));
+
+    match options.panic_hook {
+        PanicHook::Console => panic::set_hook(Box::new(console_error_panic_hook::hook)),
+        PanicHook::None => drop(panic::take_hook()),
+    }
+
+    if options.install_logger && !LOGGER_INSTALLED.with(Cell::get) {
+        // This only fails if the embedding application installed a logger of its own, which we
+        // then log to instead
+        let _ = console_log::init();
+        LOGGER_INSTALLED.with(|installed| installed.set(true));
+    }
+    log::set_max_level(if options.install_logger {
+        options.log_level.into()
+    } else {
+        LevelFilter::Off
+    });
+}
+
+/// Initializes the toolkit with the default options, unless it has been initialized already
+pub fn ensure() {
+    if !INITIALIZED.with(Cell::get) {
+        init(&InitOptions::default());
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    pub fn test_init_without_logger() {
+        init(&InitOptions {
+            install_logger: false,
+            log_level: LogLevel::Debug,
+            panic_hook: PanicHook::None,
+        });
+        assert_eq!(log::max_level(), LevelFilter::Off);
+
+        // Entry points keep the options chosen explicitly
+        ensure();
+        assert_eq!(log::max_level(), LevelFilter::Off);
+        assert!(!LOGGER_INSTALLED.with(Cell::get));
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_options_defaults() {
+        let options: InitOptions = serde_json::from_str(r#"{ "panicHook": "none" }"#).unwrap();
+        assert!(options.install_logger);
+        assert_eq!(options.log_level, LogLevel::Info);
+        assert_eq!(options.panic_hook, PanicHook::None);
+    }
+}
@@ -0,0 +1,295 @@
+// Copyright 2024 Adobe
+// All Rights Reserved.
+//
+// NOTICE: Adobe permits you to use, modify, and distribute this file in
+// accordance with the terms of the Adobe license agreement accompanying
+// it.
+
+//! Checks the buffers passed to the toolkit before reading them.
+//!
+//! A buffer transferred to another worker is detached: it reads as zero bytes, so without these
+//! checks the asset would be reported as having no manifest rather than as missing. Empty buffers
+//! and views are rejected too, since there is nothing in them to read.
+//!
+//! The exported functions copy their buffers before returning their promise, so the caller can
+//! reuse or change a buffer as soon as the call returns without the read seeing a mix of old and
+//! new bytes. An `async` export would only copy them when its body first runs, after the caller
+//! has had a chance to change them. The copy costs as much memory as the buffer until the read
+//! finishes.
+use crate::error::{Error, Result};
+use js_sys::{Array, ArrayBuffer, Error as JsSysError, Promise, Reflect, Uint8Array};
+use std::future::Future;
+use wasm_bindgen::{JsCast, JsValue};
+
+/// Checks that `value`, if it is an `ArrayBuffer` or `Uint8Array`, is neither detached nor empty
+///
+/// Other values are left for deserialization to reject.
+pub fn check_buffer(value: &JsValue, parameter: &'static str) -> Result<()> {
+    let (buffer, len) = if let Some(buffer) = value.dyn_ref::<ArrayBuffer>() {
+        (buffer.clone(), buffer.byte_length())
+    } else if let Some(view) = value.dyn_ref::<Uint8Array>() {
+        (view.buffer(), view.byte_length())
+    } else {
+        return Ok(());
+    };
+
+    if is_detached(&buffer) {
+        Err(Error::DetachedBuffer(parameter))
+    } else if len == 0 {
+        Err(Error::EmptyInput(parameter))
+    } else {
+        Ok(())
+    }
+}
+
+/// Checks a buffer passed as `parameter` and copies it into WebAssembly memory
+pub fn read_buffer(value: JsValue, parameter: &'static str) -> Result<serde_bytes::ByteBuf> {
+    check_buffer(&value, parameter)?;
+    serde_wasm_bindgen::from_value(value).map_err(Error::SerdeInput)
+}
+
+/// Checks an array of buffers passed as `parameter` and copies each into WebAssembly memory
+pub fn read_buffers(value: JsValue, parameter: &'static str) -> Result<Vec<serde_bytes::ByteBuf>> {
+    let array = value
+        .dyn_into::<Array>()
+        .map_err(|_value| Error::InvalidArgument {
+            parameter,
+            expected: "an array of buffers",
+        })?;
+    array
+        .iter()
+        .map(|buffer| read_buffer(buffer, parameter))
+        .collect()
+}
+
+/// Checks that a number passed as `parameter` is an index, a whole number that isn't negative
+pub fn read_index(value: f64, parameter: &'static str) -> Result<usize> {
+    if value.is_finite() && value >= 0.0 && value.fract() == 0.0 && value <= usize::MAX as f64 {
+        Ok(value as usize)
+    } else {
+        Err(Error::InvalidArgument {
+            parameter,
+            expected: "a non-negative integer",
+        })
+    }
+}
+
+/// Runs the rest of an exported function as a promise, once it has read its buffers
+pub fn promise<F>(rest: F) -> Promise
+where
+    F: Future<Output = std::result::Result<JsValue, JsSysError>> + 'static,
+{
+    wasm_bindgen_futures::future_to_promise(async move { rest.await.map_err(JsValue::from) })
+}
+
+/// Whether `buffer` has been detached, such as by being transferred to another worker
+fn is_detached(buffer: &ArrayBuffer) -> bool {
+    match Reflect::get(buffer, &"detached".into())
+        .ok()
+        .and_then(|detached| detached.as_bool())
+    {
+        Some(detached) => detached,
+        // Engines without `ArrayBuffer.prototype.detached` refuse to create a view of a detached
+        // buffer, which is the only empty buffer they refuse
+        None => {
+            buffer.byte_length() == 0
+                && Reflect::construct(
+                    &Uint8Array::new_with_length(0).constructor(),
+                    &Array::of1(buffer),
+                )
+                .is_err()
+        }
+    }
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+pub mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
+
+    /// Returns a copy of CAICAI, transferred away by `transfer` if given
+    fn test_buffer(transfer: Option<&str>) -> JsValue {
+        let test_asset = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+        let buffer: JsValue = Uint8Array::from(&test_asset[..]).buffer().into();
+        if let Some(transfer) = transfer {
+            js_sys::Function::new_with_args("buf", transfer)
+                .call1(&JsValue::NULL, &buffer)
+                .unwrap();
+        }
+        buffer
+    }
+
+    fn assert_rejected(err: JsValue, name: &str, parameter: &str) {
+        use crate::get_path;
+
+        assert_eq!(get_path(&err, &["name"]), name);
+        assert_eq!(get_path(&err, &["parameter"]), parameter);
+        assert_eq!(get_path(&err, &["category"]), "input");
+        assert_eq!(get_path(&err, &["retriable"]), false);
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_check_buffer() {
+        // A genuine transfer, and one through a message port that is never read
+        let transfers = [
+            "structuredClone(buf, { transfer: [buf] })",
+            "const channel = new MessageChannel(); channel.port1.postMessage(buf, [buf]); \
+             channel.port1.close(); channel.port2.close()",
+        ];
+        for transfer in transfers.iter() {
+            // A view made before the transfer is left without bytes as well
+            let buffer = test_buffer(None);
+            let view = Uint8Array::new(&buffer);
+            js_sys::Function::new_with_args("buf", transfer)
+                .call1(&JsValue::NULL, &buffer)
+                .unwrap();
+            assert!(matches!(
+                check_buffer(&buffer, "buf"),
+                Err(Error::DetachedBuffer("buf"))
+            ));
+            assert!(matches!(
+                check_buffer(&view, "buf"),
+                Err(Error::DetachedBuffer("buf"))
+            ));
+        }
+
+        let buffer = test_buffer(None);
+        assert!(check_buffer(&buffer, "buf").is_ok());
+        assert!(check_buffer(&Uint8Array::new(&buffer), "buf").is_ok());
+        assert!(matches!(
+            check_buffer(&ArrayBuffer::new(0), "buf"),
+            Err(Error::EmptyInput("buf"))
+        ));
+        let empty_view = Uint8Array::new_with_byte_offset_and_length(&buffer, 10, 0);
+        assert!(matches!(
+            check_buffer(&empty_view, "buf"),
+            Err(Error::EmptyInput("buf"))
+        ));
+        assert!(check_buffer(&JsValue::NULL, "buf").is_ok());
+    }
+
+    #[wasm_bindgen_test]
+    pub async fn test_rejected_buffers() {
+        let transferred = test_buffer(Some("structuredClone(buf, { transfer: [buf] })"));
+        let err =
+            wasm_bindgen_futures::JsFuture::from(crate::get_manifest_store_from_array_buffer(
+                transferred.clone(),
+                "image/jpeg".to_owned(),
+                JsValue::UNDEFINED,
+            ))
+            .await
+            .unwrap_err();
+        assert_rejected(err, "Toolkit(DetachedBuffer)", "buf");
+
+        let err = wasm_bindgen_futures::JsFuture::from(
+            crate::get_manifest_store_from_manifest_and_asset(
+                test_buffer(None),
+                transferred.clone(),
+                "image/jpeg".to_owned(),
+                JsValue::UNDEFINED,
+            ),
+        )
+        .await
+        .unwrap_err();
+        assert_rejected(err, "Toolkit(DetachedBuffer)", "assetBuffer");
+
+        let err = crate::has_manifest(transferred, "image/jpeg".to_owned()).unwrap_err();
+        assert_rejected(err.into(), "Toolkit(DetachedBuffer)", "buf");
+
+        let err =
+            wasm_bindgen_futures::JsFuture::from(crate::get_manifest_store_from_array_buffer(
+                ArrayBuffer::new(0).into(),
+                "image/jpeg".to_owned(),
+                JsValue::UNDEFINED,
+            ))
+            .await
+            .unwrap_err();
+        assert_rejected(err, "Toolkit(EmptyInput)", "buf");
+    }
+
+    #[wasm_bindgen_test]
+    pub async fn test_rejected_indexes() {
+        assert_eq!(read_index(0.0, "ingredient").unwrap(), 0);
+        assert_eq!(read_index(2.0, "ingredient").unwrap(), 2);
+        for index in [1.5, -1.0, -0.5, f64::NAN, f64::INFINITY, 1e300].iter() {
+            assert!(matches!(
+                read_index(*index, "ingredient"),
+                Err(Error::InvalidArgument {
+                    parameter: "ingredient",
+                    ..
+                })
+            ));
+        }
+
+        for ingredient in [JsValue::from(1.5), JsValue::from(-1), JsValue::TRUE].iter() {
+            let err = wasm_bindgen_futures::JsFuture::from(crate::get_ingredient_manifest_bytes(
+                test_buffer(None),
+                "image/jpeg".to_owned(),
+                "contentauth:urn:uuid:7d0d1b69-9d1e-4e4b-8b5e-0f0f3f0d2a3b".to_owned(),
+                ingredient.clone(),
+            ))
+            .await
+            .unwrap_err();
+            assert_rejected(err, "Toolkit(InvalidArgument)", "ingredient");
+        }
+    }
+
+    #[wasm_bindgen_test]
+    pub async fn test_rejected_fragments() {
+        let fragments: JsValue = Array::of1(&test_buffer(None)).into();
+        assert_eq!(read_buffers(fragments, "fragments").unwrap().len(), 1);
+        assert!(read_buffers(Array::new().into(), "fragments")
+            .unwrap()
+            .is_empty());
+
+        for fragments in [
+            test_buffer(None),
+            JsValue::UNDEFINED,
+            JsValue::from("fragments"),
+        ]
+        .iter()
+        {
+            let err = wasm_bindgen_futures::JsFuture::from(crate::get_coverage_timeline(
+                test_buffer(None),
+                fragments.clone(),
+                "video/mp4".to_owned(),
+            ))
+            .await
+            .unwrap_err();
+            assert_rejected(err, "Toolkit(InvalidArgument)", "fragments");
+        }
+    }
+
+    #[wasm_bindgen_test]
+    pub async fn test_buffers_copied_on_call() {
+        use crate::get_path;
+
+        // The caller reuses its buffers as soon as the calls return
+        let buffer = test_buffer(None);
+        let read = crate::get_manifest_store_from_array_buffer(
+            buffer.clone(),
+            "image/jpeg".to_owned(),
+            JsValue::UNDEFINED,
+        );
+        Uint8Array::new(&buffer).fill(0, 0, u32::MAX);
+        let result = wasm_bindgen_futures::JsFuture::from(read).await.unwrap();
+        assert!(get_path(&result, &["active_manifest"]).is_string());
+
+        let test_asset = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+        let store = c2pa::jumbf_io::load_jumbf_from_memory("image/jpeg", test_asset).unwrap();
+        let manifest: JsValue = Uint8Array::from(store.as_slice()).buffer().into();
+        let asset = test_buffer(None);
+        let read = crate::get_manifest_store_from_manifest_and_asset(
+            manifest.clone(),
+            asset.clone(),
+            "image/jpeg".to_owned(),
+            JsValue::UNDEFINED,
+        );
+        Uint8Array::new(&manifest).fill(0, 0, u32::MAX);
+        Uint8Array::new(&asset).fill(0, 0, u32::MAX);
+        let result = wasm_bindgen_futures::JsFuture::from(read).await.unwrap();
+        assert!(get_path(&result, &["active_manifest"]).is_string());
+    }
+}
@@ -0,0 +1,421 @@
+// Copyright 2024 Adobe
+// All Rights Reserved.
+//
+// NOTICE: Adobe permits you to use, modify, and distribute this file in
+// accordance with the terms of the Adobe license agreement accompanying
+// it.
+
+//! A minimal reader for the JUMBF (ISO/IEC 19566-5) boxes that make up a C2PA manifest store.
+//!
+//! The c2pa crate keeps its box model private, so this gives the toolkit access to the raw bytes
+//! of individual manifests, claims, and assertions without re-serializing them.
+use crate::error::{Error, Result};
+
+pub const JUMB: &[u8; 4] = b"jumb";
+const JUMD: &[u8; 4] = b"jumd";
+
+/// Description box toggle indicating that the superbox is requestable, which C2PA requires
+const TOGGLE_REQUESTABLE: u8 = 0x01;
+/// Description box toggle indicating that a label is present
+const TOGGLE_LABEL: u8 = 0x02;
+/// Description box toggles of the optional fields that follow the label (ID, signature and
+/// private box)
+const TOGGLE_OPTIONAL_FIELDS: u8 = 0x04 | 0x08 | 0x10;
+
+/// Box type of the manifest store (`c2pa`)
+pub const STORE_UUID: [u8; 16] = c2pa_uuid(*b"c2pa");
+/// Box type of a standard manifest (`c2ma`)
+pub const MANIFEST_UUID: [u8; 16] = c2pa_uuid(*b"c2ma");
+/// Box type of an update manifest (`c2um`)
+pub const UPDATE_MANIFEST_UUID: [u8; 16] = c2pa_uuid(*b"c2um");
+
+/// Label of the claim superbox within a manifest
+pub const CLAIM_LABEL: &str = "c2pa.claim";
+/// Label of the signature superbox within a manifest
+pub const SIGNATURE_LABEL: &str = "c2pa.signature";
+/// Label of the assertion store superbox within a manifest
+pub const ASSERTIONS_LABEL: &str = "c2pa.assertions";
+
+/// Builds a C2PA box type UUID from its four character code
+const fn c2pa_uuid(fourcc: [u8; 4]) -> [u8; 16] {
+    [
+        fourcc[0], fourcc[1], fourcc[2], fourcc[3], 0x00, 0x11, 0x00, 0x10, 0x80, 0x00, 0x00, 0xAA,
+        0x00, 0x38, 0x9B, 0x71,
+    ]
+}
+
+/// A single box
+#[derive(Debug)]
+pub struct JumbfBox<'a> {
+    pub box_type: [u8; 4],
+    /// The complete box, including its header
+    pub bytes: &'a [u8],
+    /// The box payload, excluding its header
+    pub payload: &'a [u8],
+}
+
+/// A JUMBF superbox (`jumb`) along with its parsed description box
+#[derive(Debug)]
+pub struct SuperBox<'a> {
+    /// The type UUID from the description box
+    pub uuid: [u8; 16],
+    pub label: Option<String>,
+    /// The complete superbox, including its header
+    pub bytes: &'a [u8],
+    /// The superbox payload, excluding its header, which is what a hashed URI to the box covers
+    pub contents: &'a [u8],
+    /// The complete description box, including its header
+    pub description: &'a [u8],
+    /// The child superboxes, in order
+    pub children: Vec<SuperBox<'a>>,
+    /// The child content boxes, in order
+    pub boxes: Vec<JumbfBox<'a>>,
+}
+
+impl<'a> SuperBox<'a> {
+    /// Parses a superbox that spans the start of `data`
+    ///
+    /// Nested superboxes are parsed with a worklist rather than recursively, so deeply nested
+    /// boxes in a malformed store can't exhaust the stack.
+    pub fn parse(data: &'a [u8]) -> Result<Self> {
+        let (jumbf_box, _) = read_box(data)?;
+        let mut pending = vec![Self::open(jumbf_box)?];
+
+        while let Some((super_box, rest)) = pending.last_mut() {
+            if rest.is_empty() {
+                let (super_box, _) = pending.pop().ok_or(Error::InvalidJumbf("empty worklist"))?;
+                match pending.last_mut() {
+                    Some((parent, _)) => parent.children.push(super_box),
+                    None => return Ok(super_box),
+                }
+                continue;
+            }
+
+            let (child, remaining) = read_box(rest)?;
+            *rest = remaining;
+            if &child.box_type == JUMB {
+                pending.push(Self::open(child)?);
+            } else {
+                super_box.boxes.push(child);
+            }
+        }
+
+        Err(Error::InvalidJumbf("expected a superbox"))
+    }
+
+    /// Reads the description box of a superbox, returning the superbox without any of its
+    /// children along with the bytes that hold them
+    fn open(jumbf_box: JumbfBox<'a>) -> Result<(Self, &'a [u8])> {
+        if &jumbf_box.box_type != JUMB {
+            return Err(Error::InvalidJumbf("expected a superbox"));
+        }
+
+        let (description, rest) = read_box(jumbf_box.payload)?;
+        if &description.box_type != JUMD || description.payload.len() < 17 {
+            return Err(Error::InvalidJumbf("missing description box"));
+        }
+
+        let mut uuid = [0u8; 16];
+        uuid.copy_from_slice(&description.payload[..16]);
+        let toggles = description.payload[16];
+        let label = if toggles & TOGGLE_LABEL != 0 {
+            let label_bytes = &description.payload[17..];
+            let end = label_bytes
+                .iter()
+                .position(|b| *b == 0)
+                .unwrap_or(label_bytes.len());
+            Some(String::from_utf8_lossy(&label_bytes[..end]).into_owned())
+        } else {
+            None
+        };
+
+        Ok((
+            Self {
+                uuid,
+                label,
+                bytes: jumbf_box.bytes,
+                contents: jumbf_box.payload,
+                description: description.bytes,
+                children: Vec::new(),
+                boxes: Vec::new(),
+            },
+            rest,
+        ))
+    }
+
+    /// Finds the child superbox with the given label
+    pub fn find(&self, label: &str) -> Option<&SuperBox<'a>> {
+        self.children
+            .iter()
+            .find(|super_box| super_box.label.as_deref() == Some(label))
+    }
+
+    /// The payload of the first content box, which holds the data of most C2PA superboxes
+    pub fn payload(&self) -> Option<&'a [u8]> {
+        self.boxes.first().map(|content| content.payload)
+    }
+
+    /// Finds the claim superbox of a manifest, whichever claim version it is
+    pub fn claim(&self) -> Option<&SuperBox<'a>> {
+        self.children.iter().find(|super_box| {
+            super_box
+                .label
+                .as_deref()
+                .is_some_and(|label| claim_version_of(label).is_some())
+        })
+    }
+
+    /// Returns `true` if this superbox is a (standard or update) manifest
+    pub fn is_manifest(&self) -> bool {
+        self.uuid == MANIFEST_UUID || self.uuid == UPDATE_MANIFEST_UUID
+    }
+
+    /// Returns a copy of the description box with its repairable defects fixed, along with the
+    /// defects that were found
+    pub fn repair_description(&self) -> (Vec<u8>, DescriptionDefects) {
+        let mut defects = DescriptionDefects::default();
+        // The description box was validated when the superbox was parsed
+        let mut payload = match read_box(self.description) {
+            Ok((description, _)) => description.payload.to_vec(),
+            Err(_) => return (self.description.to_vec(), defects),
+        };
+
+        let toggles = payload[16];
+        if toggles & TOGGLE_REQUESTABLE == 0 {
+            payload[16] |= TOGGLE_REQUESTABLE;
+            defects.not_requestable = true;
+        }
+
+        // Without optional fields, nothing may follow the label
+        if toggles & TOGGLE_OPTIONAL_FIELDS == 0 {
+            let end = if toggles & TOGGLE_LABEL != 0 {
+                payload[17..]
+                    .iter()
+                    .position(|b| *b == 0)
+                    .map(|end| 17 + end + 1)
+            } else {
+                Some(17)
+            };
+            if let Some(end) = end.filter(|end| *end < payload.len()) {
+                payload.truncate(end);
+                defects.trailing_data = true;
+            }
+        }
+
+        (build_box(JUMD, &[&payload]), defects)
+    }
+}
+
+/// The repairable defects of a description box
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct DescriptionDefects {
+    /// The requestable toggle isn't set
+    pub not_requestable: bool,
+    /// There is data after the label that none of the toggles account for
+    pub trailing_data: bool,
+}
+
+/// The CDDL version of a claim superbox (`c2pa.claim` is version 1, `c2pa.claim.v2` version 2)
+pub fn claim_version(claim: &SuperBox) -> u32 {
+    claim
+        .label
+        .as_deref()
+        .and_then(claim_version_of)
+        .unwrap_or(1)
+}
+
+fn claim_version_of(label: &str) -> Option<u32> {
+    match label.strip_prefix(CLAIM_LABEL)? {
+        "" => Some(1),
+        version => version.strip_prefix(".v")?.parse().ok(),
+    }
+}
+
+/// The manifests of a parsed manifest store, in store order
+pub fn manifests<'a, 'b>(store: &'b SuperBox<'a>) -> impl Iterator<Item = &'b SuperBox<'a>> {
+    store
+        .children
+        .iter()
+        .filter(|super_box| super_box.is_manifest())
+}
+
+/// Builds a new manifest store from `store`'s description box and the given manifest boxes
+///
+/// The last manifest in `manifests` becomes the active manifest of the new store.
+pub fn build_store(store: &SuperBox, manifests: &[&SuperBox]) -> Vec<u8> {
+    let mut contents = vec![store.description];
+    contents.extend(manifests.iter().map(|manifest| manifest.bytes));
+    build_box(JUMB, &contents)
+}
+
+/// Builds a box of type `box_type` whose payload is the concatenation of `contents`
+pub fn build_box(box_type: &[u8; 4], contents: &[&[u8]]) -> Vec<u8> {
+    let len = 8 + contents.iter().map(|content| content.len()).sum::<usize>();
+    let mut out = Vec::with_capacity(len);
+    out.extend_from_slice(&(len as u32).to_be_bytes());
+    out.extend_from_slice(box_type);
+    for content in contents {
+        out.extend_from_slice(content);
+    }
+    out
+}
+
+/// Reads the box at the start of `data`, returning it along with the remaining bytes
+fn read_box(data: &[u8]) -> Result<(JumbfBox<'_>, &[u8])> {
+    if data.len() < 8 {
+        return Err(Error::InvalidJumbf("truncated box header"));
+    }
+
+    let lbox = u32::from_be_bytes([data[0], data[1], data[2], data[3]]) as u64;
+    let mut box_type = [0u8; 4];
+    box_type.copy_from_slice(&data[4..8]);
+
+    let (len, header_len) = match lbox {
+        0 => (data.len() as u64, 8),
+        1 => {
+            if data.len() < 16 {
+                return Err(Error::InvalidJumbf("truncated box header"));
+            }
+            let mut xlbox = [0u8; 8];
+            xlbox.copy_from_slice(&data[8..16]);
+            (u64::from_be_bytes(xlbox), 16)
+        }
+        len => (len, 8),
+    };
+
+    if len < header_len as u64 || len > data.len() as u64 {
+        return Err(Error::InvalidJumbf("box length out of range"));
+    }
+
+    let len = len as usize;
+    Ok((
+        JumbfBox {
+            box_type,
+            bytes: &data[..len],
+            payload: &data[header_len..len],
+        },
+        &data[len..],
+    ))
+}
+
+#[cfg(test)]
+pub mod tests {
+    //! Builders for hand-made manifest stores
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
+
+    /// Builds a C2PA box type UUID from its four character code
+    pub fn uuid_of(fourcc: [u8; 4]) -> [u8; 16] {
+        c2pa_uuid(fourcc)
+    }
+
+    /// Builds a superbox with the given type, label and child boxes
+    pub fn super_box(uuid: [u8; 16], label: &str, children: &[Vec<u8>]) -> Vec<u8> {
+        let mut description = uuid.to_vec();
+        description.push(0x03);
+        description.extend_from_slice(label.as_bytes());
+        description.push(0);
+
+        let mut payload = content_box(*JUMD, &description);
+        for child in children {
+            payload.extend_from_slice(child);
+        }
+        content_box(*JUMB, &payload)
+    }
+
+    /// Builds a content box with the given type and payload
+    pub fn content_box(box_type: [u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut out = ((payload.len() + 8) as u32).to_be_bytes().to_vec();
+        out.extend_from_slice(&box_type);
+        out.extend_from_slice(payload);
+        out
+    }
+
+    /// Builds a CBOR content box holding `value`
+    pub fn cbor_box(value: &serde_cbor::Value) -> Vec<u8> {
+        content_box(*b"cbor", &serde_cbor::to_vec(value).unwrap())
+    }
+
+    /// Builds a CBOR text value
+    pub fn text(s: &str) -> serde_cbor::Value {
+        serde_cbor::Value::Text(s.to_owned())
+    }
+
+    /// Builds a CBOR map with text keys
+    pub fn map(entries: Vec<(&str, serde_cbor::Value)>) -> serde_cbor::Value {
+        serde_cbor::Value::Map(entries.into_iter().map(|(k, v)| (text(k), v)).collect())
+    }
+
+    /// Builds a manifest whose claim lists the given CBOR assertions in order, along with their
+    /// SHA-256 hashes
+    pub fn manifest(
+        uuid: [u8; 16],
+        label: &str,
+        assertions: &[(&str, serde_cbor::Value)],
+    ) -> Vec<u8> {
+        use sha2::{Digest, Sha256};
+
+        let assertion_boxes: Vec<_> = assertions
+            .iter()
+            .map(|(label, value)| super_box(uuid_of(*b"cbor"), label, &[cbor_box(value)]))
+            .collect();
+        let claim = map(vec![(
+            "assertions",
+            serde_cbor::Value::Array(
+                assertions
+                    .iter()
+                    .zip(&assertion_boxes)
+                    .map(|((label, _), assertion_box)| {
+                        map(vec![
+                            (
+                                "url",
+                                text(&format!("self#jumbf=c2pa.assertions/{}", label)),
+                            ),
+                            (
+                                "hash",
+                                serde_cbor::Value::Bytes(
+                                    Sha256::digest(&assertion_box[8..]).to_vec(),
+                                ),
+                            ),
+                        ])
+                    })
+                    .collect(),
+            ),
+        )]);
+
+        super_box(
+            uuid,
+            label,
+            &[
+                super_box(uuid_of(*b"c2as"), ASSERTIONS_LABEL, &assertion_boxes),
+                super_box(uuid_of(*b"c2cl"), CLAIM_LABEL, &[cbor_box(&claim)]),
+            ],
+        )
+    }
+
+    /// Builds a manifest store holding the given manifests
+    pub fn store(manifests: &[Vec<u8>]) -> Vec<u8> {
+        super_box(STORE_UUID, "c2pa", manifests)
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_parse_deeply_nested_boxes() {
+        let depth = 1_000;
+        let mut data = super_box(STORE_UUID, "innermost", &[content_box(*b"json", b"{}")]);
+        for _ in 1..depth {
+            data = super_box(STORE_UUID, "nested", &[data]);
+        }
+
+        let mut super_box = &SuperBox::parse(&data).unwrap();
+        let mut levels = 1;
+        while let Some(child) = super_box.children.first() {
+            super_box = child;
+            levels += 1;
+        }
+        assert_eq!(levels, depth);
+        assert_eq!(super_box.label.as_deref(), Some("innermost"));
+        assert_eq!(super_box.payload(), Some(&b"{}"[..]));
+    }
+}
@@ -7,46 +7,258 @@
 
 // See https://github.com/rustwasm/wasm-bindgen/issues/2774
 #![allow(clippy::unused_unit)]
-use log::Level;
+use c2pa::ManifestStore;
 use serde::Serialize;
 use serde_wasm_bindgen::Serializer;
-use std::panic;
 use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
 
+mod action_vocabulary;
+mod asset_info;
+mod cache;
+#[cfg(feature = "compat")]
+mod compat;
+mod container;
+mod cose;
 mod error;
+mod generator_trust;
+mod graph;
+mod ingredient_assets;
+mod init;
+mod input;
+mod jumbf;
+mod limits;
+mod locale;
 mod manifest_store;
+mod memory;
+mod metadata;
+mod mime;
+mod ocsp;
+mod options;
+mod ordering;
+mod output_schema;
+pub mod presence;
+mod provenance_loss;
+mod recovery;
+mod report;
+#[cfg(all(test, feature = "json_schema"))]
+mod schema;
+mod segments;
+mod self_test;
+mod serialization;
+mod thumbnail;
+mod timeline;
+mod timestamp;
+mod trace;
+mod utf8;
 mod util;
+mod validation;
+mod xmp;
 
+use container::ContainerOptions;
 use error::Error;
 use js_sys::Error as JsSysError;
+use js_sys::Object;
+use js_sys::Promise;
 use js_sys::Reflect;
+use js_sys::Uint8Array;
+use log::warn;
 use manifest_store::{
-    get_manifest_store_data, get_manifest_store_data_from_manifest_and_asset_bytes,
+    bound_assertions, contain_manifest_errors, get_assertion as get_assertion_data,
+    get_assertions as get_assertions_data, get_asset_identity as get_asset_identity_data,
+    get_claim_versions, get_collection_hashes, get_display_strings, get_external_resources,
+    get_hard_binding_algs, get_hashed_uri_checks,
+    get_ingredient_manifest_bytes as get_ingredient_manifest_bytes_data, get_manifest_order,
+    get_manifest_store_data, get_manifest_store_data_from_manifest_and_asset_bytes, get_raw_claims,
+    get_revocation_info, get_signature_material as get_signature_material_data,
+    get_signing_subjects, get_timestamp_checks, get_unsupported_algs, get_update_manifests,
+    legacy_png_store, normalize_sidecar, repair_store, trailing_app11_warning, trailing_jpeg_store,
+    truncate_provenance, verify_asset_in_collection as verify_asset_in_collection_data,
+    with_active_manifest, EmbeddingFormat, ExternalResourceRef, IngredientRef, LabelQuery,
+    ManifestError, ParseWarning, Truncation, UpdateValidationStatus,
 };
-use util::log_time;
+use options::{OnUnsupportedAlg, Parsing, ReadOptions};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use timestamp::SkewClassification;
+use utf8::InvalidUtf8;
+use util::{log_time, OptionKeys};
+use validation::Severity;
 
 #[wasm_bindgen(typescript_custom_section)]
 pub const TS_APPEND_CONTENT: &'static str = r#"
-import { ManifestStore } from './types'
+import {
+    ActionVocabularyEntry,
+    AssertionMatch,
+    AssetIdentity,
+    CollectionEntryCheck,
+    ContainerEntry,
+    ContainerOptions,
+    CoverageTimelineEntry,
+    GeneratorTrustHints,
+    InitOptions,
+    MetadataSummary,
+    ManifestPresence,
+    ManifestStore,
+    MemoryStats,
+    ParserLimits,
+    ProvenanceGraph,
+    ProvenanceLossProbe,
+    ReadCacheOptions,
+    ReadCacheStats,
+    ReadOptions,
+    RecoveredManifestStore,
+    RecoveryOptions,
+    SelfTestOptions,
+    SelfTestResult,
+    SignatureMaterial,
+    ThumbnailConsistency,
+    ThumbnailOptions,
+} from './types'
 
 export * from './types';
 
+export function initToolkit(options?: InitOptions): void;
+
 export function getManifestStoreFromArrayBuffer(
     buf: ArrayBuffer,
-    mimeType: string
+    mimeType: string,
+    options?: ReadOptions
 ): Promise<ManifestStore>;
 
+export function getMetadataSummary(
+    buf: ArrayBuffer,
+    mimeType: string,
+    options?: ReadOptions
+): Promise<MetadataSummary>;
+
 export function getManifestStoreFromManifestAndAsset(
     manifestBuffer: ArrayBuffer,
     assetBuffer: ArrayBuffer,
-    mimeType: string
+    mimeType: string,
+    options?: ReadOptions
 ): Promise<ManifestStore>;
+
+export function recoverManifest(
+    buf: ArrayBuffer,
+    mimeType: string,
+    options: RecoveryOptions
+): Promise<RecoveredManifestStore | null>;
+
+export function verifyAssetInCollection(
+    manifestBuffer: ArrayBuffer,
+    assetBuffer: ArrayBuffer,
+    name: string
+): Promise<CollectionEntryCheck>;
+
+export function getIngredientManifestBytes(
+    buf: ArrayBuffer,
+    mimeType: string,
+    manifestLabel: string,
+    ingredient: number | string
+): Promise<ArrayBuffer>;
+
+export function getAssertions(
+    buf: ArrayBuffer,
+    mimeType: string,
+    label: string | string[]
+): Promise<AssertionMatch[]>;
+
+export function getAssertion(
+    buf: ArrayBuffer,
+    mimeType: string,
+    manifestLabel: string,
+    assertionLabel: string
+): Promise<unknown>;
+
+export function getProvenanceGraph(
+    buf: ArrayBuffer,
+    mimeType: string
+): Promise<ProvenanceGraph>;
+
+export function verifyThumbnailConsistency(
+    buf: ArrayBuffer,
+    mimeType: string,
+    options?: ThumbnailOptions
+): Promise<ThumbnailConsistency>;
+
+export function probeProvenanceLoss(
+    buf: ArrayBuffer,
+    mimeType: string
+): Promise<ProvenanceLossProbe>;
+
+export function getSignatureMaterial(
+    buf: ArrayBuffer,
+    mimeType: string
+): Promise<Record<string, SignatureMaterial>>;
+
+export function getManifestStoresFromContainer(
+    buf: ArrayBuffer,
+    containerMime: string,
+    options?: ContainerOptions
+): Promise<Record<string, ContainerEntry>>;
+
+export function getAssetIdentity(
+    buf: ArrayBuffer,
+    mimeType: string
+): Promise<AssetIdentity>;
+
+export function getDetailedReport(
+    buf: ArrayBuffer,
+    mimeType: string
+): Promise<string>;
+
+export function selfTest(options?: SelfTestOptions): Promise<SelfTestResult>;
+
+export function getCoverageTimeline(
+    initSegment: ArrayBuffer,
+    fragments: ArrayBuffer[],
+    mimeType: string
+): Promise<CoverageTimelineEntry[]>;
+
+export function hasManifest(
+    buf: ArrayBuffer,
+    mimeType: string
+): ManifestPresence;
+
+export function enableReadCache(options?: ReadCacheOptions): void;
+
+export function clearReadCache(): void;
+
+export function setGeneratorTrustHints(hints: GeneratorTrustHints | null): void;
+
+export function setParserLimits(limits: ParserLimits | null): void;
+
+export function strictOptions(strict: boolean): void;
+
+export function registerActionVocabulary(entries: ActionVocabularyEntry[]): void;
+
+export function clearActionVocabulary(): void;
+
+export function getReadCacheStats(): ReadCacheStats | null;
+
+export function getMemoryStats(): MemoryStats;
+
+export function releaseInternalBuffers(): void;
 "#;
 
-#[wasm_bindgen(start)]
-pub fn run() {
-    panic::set_hook(Box::new(console_error_panic_hook::hook));
-    console_log::init_with_level(Level::Info).unwrap();
+/// Sets up logging and panic reporting
+///
+/// This is optional: the other entry points initialize the toolkit with the default options if
+/// this hasn't been called.
+#[wasm_bindgen(js_name = initToolkit, skip_typescript)]
+pub fn init_toolkit(options: JsValue) -> Result<(), JsSysError> {
+    let options = if options.is_undefined() || options.is_null() {
+        init::InitOptions::default()
+    } else {
+        let accepted = OptionKeys::of::<init::InitOptions>();
+        util::check_options(&options, "initToolkit", &accepted).map_err(as_js_error)?;
+        serde_wasm_bindgen::from_value(options)
+            .map_err(Error::SerdeInput)
+            .map_err(as_js_error)?
+    };
+    init::init(&options);
+
+    Ok(())
 }
 
 /// Creates a JavaScript Error with additional error info
@@ -56,66 +268,2159 @@ pub fn run() {
 fn as_js_error(err: Error) -> JsSysError {
     let js_err = JsSysError::new(&err.to_string());
     js_err.set_name(&format!("{:?}", err));
+    let code = err.code();
+    Reflect::set(&js_err, &"code".into(), &code.code.into());
+    Reflect::set(&js_err, &"category".into(), &code.category.as_str().into());
+    Reflect::set(&js_err, &"retriable".into(), &code.retriable.into());
+    if let Some(upstream) = code.upstream {
+        Reflect::set(&js_err, &"upstream".into(), &upstream.into());
+    }
 
     if let Error::C2pa(c2pa::Error::RemoteManifestUrl(url)) = err {
         js_err.set_name("Toolkit(RemoteManifestUrl)");
         Reflect::set(&js_err, &"url".into(), &url.into());
+    } else if let Error::RecoveryFailed(stage, _) = err {
+        js_err.set_name("Toolkit(RecoveryFailed)");
+        Reflect::set(&js_err, &"stage".into(), &stage.into());
+    } else if let Error::DetachedBuffer(parameter) = err {
+        js_err.set_name("Toolkit(DetachedBuffer)");
+        Reflect::set(&js_err, &"parameter".into(), &parameter.into());
+    } else if let Error::EmptyInput(parameter) = err {
+        js_err.set_name("Toolkit(EmptyInput)");
+        Reflect::set(&js_err, &"parameter".into(), &parameter.into());
+    } else if let Error::InvalidArgument {
+        parameter,
+        expected,
+    } = err
+    {
+        js_err.set_name("Toolkit(InvalidArgument)");
+        Reflect::set(&js_err, &"parameter".into(), &parameter.into());
+        Reflect::set(&js_err, &"expected".into(), &expected.into());
+    } else if let Error::LimitExceeded {
+        limit,
+        observed,
+        max,
+    } = err
+    {
+        js_err.set_name("Toolkit(LimitExceeded)");
+        Reflect::set(&js_err, &"limit".into(), &limit.into());
+        Reflect::set(&js_err, &"observed".into(), &(observed as f64).into());
+        Reflect::set(&js_err, &"max".into(), &(max as f64).into());
+    } else if let Error::UnknownOptions { api, unknown } = err {
+        js_err.set_name("Toolkit(UnknownOptions)");
+        Reflect::set(&js_err, &"api".into(), &api.into());
+        if let Ok(unknown) = to_js_value(&unknown) {
+            Reflect::set(&js_err, &"unknown".into(), &unknown);
+        }
+    } else if let Error::ActiveManifestNotFound { label, available } = err {
+        js_err.set_name("Toolkit(ActiveManifestNotFound)");
+        Reflect::set(&js_err, &"label".into(), &label.into());
+        let available: js_sys::Array = available.iter().map(JsValue::from).collect();
+        Reflect::set(&js_err, &"available".into(), &available);
     }
 
     js_err
 }
 
+/// Converts a result to a JavaScript value
+///
+/// The `compat` build avoids emitting `BigInt`s, see the `compat` module for the rules it follows.
+fn to_js_value<T: Serialize>(value: &T) -> Result<JsValue, Error> {
+    #[cfg(feature = "compat")]
+    let json = serde_json::to_value(value).map_err(|_err| Error::JavaScriptConversion)?;
+    #[cfg(feature = "compat")]
+    let value = &compat::CompatValue(&json);
+
+    let serializer = Serializer::new().serialize_maps_as_objects(true);
+    value
+        .serialize(&serializer)
+        .map_err(|_err| Error::JavaScriptConversion)
+}
+
+/// Returns the value found at `path` within `target`, or `undefined` if there is none
+fn get_path(target: &JsValue, path: &[&str]) -> JsValue {
+    let mut value = target.clone();
+    for key in path {
+        value = Reflect::get(&value, &(*key).into()).unwrap_or(JsValue::UNDEFINED);
+    }
+    value
+}
+
+/// Copies the properties of `source` onto the object found at `path` within `target`, if any
+fn merge_into(target: &JsValue, path: &[&str], source: &JsValue) {
+    let object = get_path(target, path);
+    if let (Some(object), Some(source)) = (object.dyn_ref::<Object>(), source.dyn_ref::<Object>()) {
+        Object::assign(object, source);
+    }
+}
+
+/// Replaces the byte arrays at `keys` of `object` with `ArrayBuffer`s
+///
+/// Arrays of byte arrays are converted element by element.
+fn bytes_to_array_buffers(object: &JsValue, keys: &[&str]) -> Result<(), JsValue> {
+    let to_array_buffer = |bytes: &JsValue| JsValue::from(Uint8Array::new(bytes).buffer());
+
+    for key in keys {
+        let key = JsValue::from(*key);
+        let value = Reflect::get(object, &key)?;
+        let converted = if value.is_instance_of::<Uint8Array>() {
+            to_array_buffer(&value)
+        } else if let Some(array) = value.dyn_ref::<js_sys::Array>() {
+            array
+                .iter()
+                .map(|bytes| to_array_buffer(&bytes))
+                .collect::<js_sys::Array>()
+                .into()
+        } else {
+            continue;
+        };
+        Reflect::set(object, &key, &converted)?;
+    }
+
+    Ok(())
+}
+
+/// The keys the read options accept, with those read outside of deserialization
+fn read_option_keys() -> OptionKeys {
+    OptionKeys::of::<ReadOptions>()
+        .with(&["resourceResolver"])
+        .nest("serialization", OptionKeys::of::<options::Serialization>())
+        .nest("timestampSkew", OptionKeys::of::<options::TimestampSkew>())
+        .nest(
+            "ingredientAssets",
+            OptionKeys::of::<options::IngredientAsset>(),
+        )
+}
+
+/// Parses the options passed to the read APIs, which may be omitted
+///
+/// `accepted` are the keys `api` accepts: the read options, along with any of its own.
+fn read_options(
+    options: JsValue,
+    api: &'static str,
+    accepted: &OptionKeys,
+) -> Result<ReadOptions, Error> {
+    if options.is_undefined() || options.is_null() {
+        return Ok(ReadOptions::default());
+    }
+    util::check_options(&options, api, accepted)?;
+
+    let resource_resolver = Reflect::get(&options, &"resourceResolver".into())
+        .ok()
+        .and_then(|resolver| resolver.dyn_into::<js_sys::Function>().ok());
+    for asset in array_items(&get_path(&options, &["ingredientAssets"])) {
+        input::check_buffer(&get_path(&asset, &["buffer"]), "ingredientAssets")?;
+    }
+    let mut read_options: ReadOptions =
+        serde_wasm_bindgen::from_value(options).map_err(Error::SerdeInput)?;
+    read_options.resource_resolver = resource_resolver;
+
+    Ok(read_options)
+}
+
+/// Merges the revocation status of each manifest's signing certificate into its `signature_info`
+///
+/// Revocation checking is best effort, so failures are logged rather than failing the read.
+fn add_revocation_info(js_value: &JsValue, store_bytes: &[u8]) {
+    let result = get_revocation_info(store_bytes).and_then(|revocation_info| {
+        for (label, info) in revocation_info {
+            let info = to_js_value(&info)?;
+            merge_into(js_value, &["manifests", &label, "signature_info"], &info);
+        }
+        Ok(())
+    });
+
+    if let Err(err) = result {
+        warn!(
+            "Could not check the revocation status of the signatures: {}",
+            err
+        );
+    }
+}
+
+/// Merges how the timestamp of each manifest's signature compares with the clock into its
+/// `signature_info` as `timestampSkew`
+///
+/// A timestamp outside the allowed skew adds a warning, or under the `untrusted` policy a
+/// `timeStamp.untrusted` status against the manifest. The signing certificate's validity is then
+/// checked at the time of validation instead, adding a `signingCredential.expired` status if it
+/// isn't valid then.
+fn add_timestamp_checks(
+    js_value: &JsValue,
+    store_bytes: &[u8],
+    options: &options::TimestampSkew,
+) -> Result<(), Error> {
+    for (label, check) in get_timestamp_checks(store_bytes, options, js_sys::Date::now())? {
+        merge_into(
+            js_value,
+            &["manifests", &label, "signature_info"],
+            &to_js_value(&check)?,
+        );
+
+        let skew = &check.timestamp_skew;
+        let description = match (skew.classification, skew.delta_ms) {
+            (SkewClassification::FutureDated, Some(delta_ms)) => format!(
+                "is dated {}, {} ms ahead of the time of validation",
+                skew.timestamp_time, delta_ms
+            ),
+            (SkewClassification::StaleRelativeToSignature, Some(delta_ms)) => format!(
+                "is dated {}, {} ms before its signing certificate was valid",
+                skew.timestamp_time, -delta_ms
+            ),
+            _ => continue,
+        };
+        if !skew.downgraded {
+            add_warning(
+                js_value,
+                format!("The signature timestamp of {} {}", label, description),
+            );
+            continue;
+        }
+
+        let url = format!("self#jumbf=/c2pa/{}/c2pa.signature", label);
+        push_validation_status(
+            js_value,
+            &UpdateValidationStatus {
+                code: c2pa::validation_status::TIMESTAMP_UNTRUSTED,
+                url: url.clone(),
+                explanation: format!("signature timestamp {}", description),
+            },
+        )?;
+        let expired_reported = array_items(&get_path(js_value, &["validation_status"]))
+            .iter()
+            .any(|status| {
+                get_path(status, &["code"]) == c2pa::validation_status::SIGNING_CREDENTIAL_EXPIRED
+                    && get_path(status, &["url"])
+                        .as_string()
+                        .is_some_and(|url| validation::manifest_label(&url) == Some(label.as_str()))
+            });
+        if skew.certificate_valid_at_validation == Some(false) && !expired_reported {
+            push_validation_status(
+                js_value,
+                &UpdateValidationStatus {
+                    code: c2pa::validation_status::SIGNING_CREDENTIAL_EXPIRED,
+                    url,
+                    explanation: "signing certificate is not valid at the time of validation"
+                        .to_owned(),
+                },
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Marks the update manifests with `isUpdateManifest` and the manifest they update
+///
+/// The c2pa crate validates the active manifest itself, so only the problems found with other
+/// update manifests are added to the store's `validation_status`.
+fn add_update_manifests(
+    js_value: &JsValue,
+    store_bytes: &[u8],
+    active_label: Option<&str>,
+) -> Result<(), Error> {
+    for (label, update_manifest) in get_update_manifests(store_bytes)? {
+        merge_into(
+            js_value,
+            &["manifests", &label],
+            &to_js_value(&update_manifest)?,
+        );
+
+        if Some(label.as_str()) == active_label {
+            continue;
+        }
+        for status in &update_manifest.validation_status {
+            push_validation_status(js_value, status)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Adds a status to the store's `validation_status`, creating it if the store has none
+fn push_validation_status(
+    js_value: &JsValue,
+    status: &UpdateValidationStatus,
+) -> Result<(), Error> {
+    let validation_status = match get_path(js_value, &["validation_status"]).dyn_into() {
+        Ok(validation_status) => validation_status,
+        Err(_) => {
+            let validation_status = js_sys::Array::new();
+            Reflect::set(js_value, &"validation_status".into(), &validation_status)
+                .map_err(|_err| Error::JavaScriptConversion)?;
+            validation_status
+        }
+    };
+    validation_status.push(&to_js_value(status)?);
+
+    Ok(())
+}
+
+/// Adds a `generatorCertMismatch` status for each manifest whose claim generator isn't signed by
+/// who the generator trust hints expect
+fn add_generator_checks(
+    js_value: &JsValue,
+    manifest_store: &ManifestStore,
+    store_bytes: &[u8],
+) -> Result<(), Error> {
+    if !generator_trust::has_hints() {
+        return Ok(());
+    }
+
+    let subjects = get_signing_subjects(store_bytes)?;
+    for (label, manifest) in manifest_store.manifests() {
+        let subject = match subjects.get(label) {
+            Some(subject) => subject,
+            None => continue,
+        };
+        let generators: Vec<&str> = std::iter::once(manifest.claim_generator())
+            .chain(
+                manifest
+                    .claim_generator_info
+                    .iter()
+                    .flatten()
+                    .map(|info| info.name.as_str()),
+            )
+            .collect();
+        if let Some(status) = generator_trust::check(label, &generators, subject) {
+            push_validation_status(js_value, &status)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Marks the manifests signed with an algorithm this build can't verify, for the `degrade`
+/// handling of unsupported algorithms
+///
+/// Each gets `signatureVerification: 'unsupported-algorithm'` with the COSE identifier of the
+/// algorithm as `signatureAlgorithmId`. The c2pa crate reports these signatures as mismatched,
+/// once against the manifest and once against no manifest in particular, which would otherwise
+/// be put down to the active manifest. Those statuses are replaced by an `algorithm.unsupported`
+/// status against the manifest, so that the store still doesn't read as valid.
+fn degrade_unsupported_algs(
+    js_value: &JsValue,
+    unsupported_algs: &BTreeMap<String, i64>,
+) -> Result<(), Error> {
+    let mut unattributed_mismatches = unsupported_algs.len();
+    let statuses: js_sys::Array = array_items(&get_path(js_value, &["validation_status"]))
+        .into_iter()
+        .filter(|status| {
+            let code = get_path(status, &["code"]).as_string().unwrap_or_default();
+            let url = get_path(status, &["url"]).as_string().unwrap_or_default();
+            match validation::manifest_label(&url) {
+                Some(label) => {
+                    !(unsupported_algs.contains_key(label)
+                        && (code.starts_with("claimSignature.")
+                            || code.starts_with("signingCredential.")))
+                }
+                None if code == c2pa::validation_status::CLAIM_SIGNATURE_MISMATCH
+                    && unattributed_mismatches > 0 =>
+                {
+                    unattributed_mismatches -= 1;
+                    false
+                }
+                None => true,
+            }
+        })
+        .collect();
+    Reflect::set(js_value, &"validation_status".into(), &statuses)
+        .map_err(|_err| Error::JavaScriptConversion)?;
+
+    for (label, alg) in unsupported_algs {
+        let signature_verification = Object::new();
+        Reflect::set(
+            &signature_verification,
+            &"signatureVerification".into(),
+            &"unsupported-algorithm".into(),
+        )
+        .and_then(|_| {
+            Reflect::set(
+                &signature_verification,
+                &"signatureAlgorithmId".into(),
+                &(*alg as f64).into(),
+            )
+        })
+        .map_err(|_err| Error::JavaScriptConversion)?;
+        merge_into(js_value, &["manifests", label], &signature_verification);
+
+        push_validation_status(
+            js_value,
+            &UpdateValidationStatus {
+                code: c2pa::validation_status::ALGORITHM_UNSUPPORTED,
+                url: format!("self#jumbf=/c2pa/{}/c2pa.signature", label),
+                explanation: format!(
+                    "signature algorithm {} is not supported, so the signature was not verified",
+                    alg
+                ),
+            },
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Merges the result of checking each hashed URI into the assertion or ingredient it references
+///
+/// Ingredients also get the check of their reference to their own manifest as
+/// `manifestReference`.
+fn add_hashed_uri_checks(
+    js_value: &JsValue,
+    store_bytes: &[u8],
+    include_digests: bool,
+) -> Result<(), Error> {
+    for (label, checks) in get_hashed_uri_checks(store_bytes, include_digests)? {
+        let manifest = get_path(js_value, &["manifests", &label]);
+
+        for assertion in array_items(&get_path(&manifest, &["assertions"])) {
+            let assertion_label = match get_path(&assertion, &["label"]).as_string() {
+                Some(assertion_label) => assertion_label,
+                None => continue,
+            };
+            // The assertion store labels every instance after the first with its number
+            let assertion_label = match get_path(&assertion, &["instance"]).as_f64() {
+                Some(instance) if instance > 0.0 => {
+                    format!("{}__{}", assertion_label, instance as u64)
+                }
+                _ => assertion_label,
+            };
+            if let Some(check) = checks.assertions.get(&assertion_label) {
+                merge_into(&assertion, &[], &to_js_value(check)?);
+            }
+        }
+
+        let ingredients = array_items(&get_path(&manifest, &["ingredients"]));
+        for (ingredient, hashed_uris) in ingredients.iter().zip(&checks.ingredients) {
+            if let Some(check) = checks.assertions.get(&hashed_uris.assertion_label) {
+                merge_into(ingredient, &[], &to_js_value(check)?);
+            }
+            if let Some(check) = &hashed_uris.manifest_reference {
+                Reflect::set(
+                    ingredient,
+                    &"manifestReference".into(),
+                    &to_js_value(check)?,
+                )
+                .map_err(|_err| Error::JavaScriptConversion)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Attaches the signed claim bytes to each manifest
+fn add_raw_claims(
+    js_value: &JsValue,
+    store_bytes: &[u8],
+    options: &ReadOptions,
+) -> Result<(), Error> {
+    for (label, raw_claim) in get_raw_claims(store_bytes, options.raw_claim_encoding)? {
+        let raw_claim = to_js_value(&raw_claim)?;
+        bytes_to_array_buffers(&raw_claim, &["rawClaimBytes"])
+            .map_err(|_err| Error::JavaScriptConversion)?;
+        merge_into(js_value, &["manifests", &label], &raw_claim);
+    }
+
+    Ok(())
+}
+
+/// Fetches the ingredient thumbnails that are stored outside of the manifest store with `resolver`
+///
+/// The resolver is called for every reference before any of the results are awaited, so the
+/// fetches run concurrently. Resolved thumbnails are added to the ingredient's resources and
+/// marked with `resolvedExternally`, while failures and `null` results are marked as unresolved.
+async fn resolve_external_resources(
+    js_value: &JsValue,
+    store_bytes: &[u8],
+    resolver: &js_sys::Function,
+) -> Result<(), Error> {
+    let pending: Vec<_> = get_external_resources(store_bytes)?
+        .into_iter()
+        .filter_map(|resource| {
+            let ingredient = get_path(
+                js_value,
+                &[
+                    "manifests",
+                    &resource.manifest_label,
+                    "ingredients",
+                    &resource.ingredient_index.to_string(),
+                ],
+            );
+            // The manifest may have been left out of the output
+            if !ingredient.is_object() {
+                return None;
+            }
+
+            let result = resolver
+                .call1(&JsValue::NULL, &resource.uri.as_str().into())
+                .map(|result| JsFuture::from(js_sys::Promise::resolve(&result)));
+            Some((resource, ingredient, result))
+        })
+        .collect();
+
+    for (resource, ingredient, result) in pending {
+        let bytes = match result {
+            Ok(future) => future.await.ok(),
+            Err(_err) => None,
+        }
+        .filter(|bytes| bytes.is_instance_of::<js_sys::ArrayBuffer>())
+        .map(|bytes| Uint8Array::new(&bytes).to_vec());
+
+        if bytes.is_none() {
+            warn!("Could not resolve the external resource {}", resource.uri);
+        }
+
+        let thumbnail = ExternalResourceRef::new(&resource.uri, bytes.as_deref());
+        Reflect::set(&ingredient, &"thumbnail".into(), &to_js_value(&thumbnail)?)
+            .map_err(|_err| Error::JavaScriptConversion)?;
+
+        if let Some(bytes) = bytes {
+            let resources = get_path(&ingredient, &["resources", "resources"]);
+            // Resources are returned as arrays of numbers, like the ones the c2pa crate serializes
+            let data: js_sys::Array = bytes.iter().map(|b| JsValue::from(*b)).collect();
+            if resources.is_object() {
+                Reflect::set(&resources, &resource.uri.as_str().into(), &data)
+                    .map_err(|_err| Error::JavaScriptConversion)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Leaves the manifests beyond the depth limit out of the output
+///
+/// The ingredients that reference them are marked with `truncatedAtDepth` and a warning is added
+/// to the store.
+fn apply_truncation(js_value: &JsValue, truncation: &Truncation, max_depth: usize) {
+    let manifests = get_path(js_value, &["manifests"]);
+    if let Some(manifests) = manifests.dyn_ref::<Object>() {
+        for label in &truncation.omitted {
+            let _ = Reflect::delete_property(manifests, &label.into());
+        }
+    }
+
+    let marker = Object::new();
+    let _ = Reflect::set(
+        &marker,
+        &"truncatedAtDepth".into(),
+        &(max_depth as u32).into(),
+    );
+    for (label, index) in &truncation.truncated {
+        merge_into(
+            js_value,
+            &["manifests", label, "ingredients", &index.to_string()],
+            &marker,
+        );
+    }
+
+    let warning = format!(
+        "The provenance chain is deeper than {} manifests and was truncated",
+        max_depth
+    );
+    add_warning(js_value, warning);
+}
+
+/// Logs a warning and adds it to the store's `warnings`
+fn add_warning(js_value: &JsValue, warning: String) {
+    warn!("{}", warning);
+    let warnings = get_path(js_value, &["warnings"]);
+    match warnings.dyn_ref::<js_sys::Array>() {
+        Some(warnings) => {
+            warnings.push(&warning.into());
+        }
+        None => {
+            let warnings = js_sys::Array::of1(&warning.into());
+            let _ = Reflect::set(js_value, &"warnings".into(), &warnings);
+        }
+    }
+}
+
+/// Reads the manifest store again without the manifests that fail to parse if `result` is an
+/// error, unless the failure lies with the active manifest
+///
+/// Returns the manifest store along with the store bytes it was read from, if they differ from
+/// `store_bytes`, and the error of each manifest left out.
+async fn contain_errors(
+    result: Result<ManifestStore, Error>,
+    store_bytes: Option<&[u8]>,
+    mime_type: &str,
+    asset: &[u8],
+) -> Result<
+    (
+        ManifestStore,
+        Option<Vec<u8>>,
+        HashMap<String, ManifestError>,
+    ),
+    Error,
+> {
+    let (err, store_bytes) = match (result, store_bytes) {
+        (Ok(manifest_store), _) => return Ok((manifest_store, None, HashMap::new())),
+        // Leaving out the manifests that exceed a limit would let them hide from the read
+        (Err(err @ Error::LimitExceeded { .. }), _) => return Err(err),
+        (Err(err), Some(store_bytes)) => (err, store_bytes),
+        (Err(err), None) => return Err(err),
+    };
+
+    match contain_manifest_errors(store_bytes, mime_type, asset).await {
+        Ok(Some((contained, errors))) => {
+            let manifest_store =
+                get_manifest_store_data_from_manifest_and_asset_bytes(&contained, mime_type, asset)
+                    .await?;
+            Ok((manifest_store, Some(contained), errors))
+        }
+        _ => Err(err),
+    }
+}
+
+/// Marks the ingredients whose manifest was left out for failing to parse with its `error`, and
+/// adds a warning for each such manifest
+fn add_manifest_errors(
+    js_value: &JsValue,
+    errors: &HashMap<String, ManifestError>,
+) -> Result<(), Error> {
+    if errors.is_empty() {
+        return Ok(());
+    }
+
+    let manifests = get_path(js_value, &["manifests"]);
+    let manifests = manifests
+        .dyn_ref::<Object>()
+        .map(Object::values)
+        .unwrap_or_default();
+    for manifest in manifests.iter() {
+        for ingredient in array_items(&get_path(&manifest, &["ingredients"])) {
+            let label = get_path(&ingredient, &["active_manifest"]).as_string();
+            if let Some(error) = label.and_then(|label| errors.get(&label)) {
+                Reflect::set(&ingredient, &"error".into(), &to_js_value(error)?)
+                    .map_err(|_err| Error::JavaScriptConversion)?;
+            }
+        }
+    }
+
+    let mut labels: Vec<&String> = errors.keys().collect();
+    labels.sort_unstable();
+    for label in labels {
+        add_warning(
+            js_value,
+            format!(
+                "The manifest {} could not be read and was left out: {}",
+                label, errors[label].message
+            ),
+        );
+    }
+
+    Ok(())
+}
+
+/// Returns the elements of `value` if it is an array
+fn array_items(value: &JsValue) -> Vec<JsValue> {
+    value
+        .dyn_ref::<js_sys::Array>()
+        .map(|array| array.iter().collect())
+        .unwrap_or_default()
+}
+
+/// Sets the `severity` of a validation status, returning it
+///
+/// With `tolerate_hard_binding_mismatch`, hard binding mismatches are warnings.
+fn add_severity(status: &JsValue, tolerate_hard_binding_mismatch: bool) -> Result<Severity, Error> {
+    let code = get_path(status, &["code"]).as_string().unwrap_or_default();
+    let severity = if tolerate_hard_binding_mismatch && validation::is_hard_binding_mismatch(&code)
+    {
+        Severity::Warning
+    } else {
+        validation::severity(&code)
+    };
+    Reflect::set(status, &"severity".into(), &to_js_value(&severity)?)
+        .map_err(|_err| Error::JavaScriptConversion)?;
+    Ok(severity)
+}
+
+/// Adds the version of each actions assertion to its data as `version`, so that v1 and v2 actions
+/// can be read as one type
+fn add_action_versions(js_value: &JsValue) -> Result<(), Error> {
+    let manifests = get_path(js_value, &["manifests"]);
+    let manifests = match manifests.dyn_ref::<Object>() {
+        Some(manifests) => manifests,
+        None => return Ok(()),
+    };
+
+    for manifest in Object::values(manifests).iter() {
+        for assertion in array_items(&get_path(&manifest, &["assertions"])) {
+            let version = match get_path(&assertion, &["label"]).as_string().as_deref() {
+                Some("c2pa.actions") => 1,
+                Some("c2pa.actions.v2") => 2,
+                _ => continue,
+            };
+            let data = get_path(&assertion, &["data"]);
+            if data.is_object() {
+                Reflect::set(&data, &"version".into(), &version.into())
+                    .map_err(|_err| Error::JavaScriptConversion)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Annotates the actions that use a registered custom action with `registered: true`, the
+/// registered description as `registeredDescription` and the required parameters they lack as
+/// `missingParameters`
+fn add_action_vocabulary(js_value: &JsValue) -> Result<(), Error> {
+    let manifests = get_path(js_value, &["manifests"]);
+    let manifests = match manifests.dyn_ref::<Object>() {
+        Some(manifests) if !action_vocabulary::is_empty() => manifests,
+        _ => return Ok(()),
+    };
+
+    for manifest in Object::values(manifests).iter() {
+        for assertion in array_items(&get_path(&manifest, &["assertions"])) {
+            for action in array_items(&get_path(&assertion, &["data", "actions"])) {
+                let label = match get_path(&action, &["action"]).as_string() {
+                    Some(label) => label,
+                    None => continue,
+                };
+                let parameters: Vec<String> = get_path(&action, &["parameters"])
+                    .dyn_ref::<Object>()
+                    .map(|parameters| {
+                        Object::keys(parameters)
+                            .iter()
+                            .filter_map(|key| key.as_string())
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                if let Some(annotation) = action_vocabulary::annotate(&label, &parameters) {
+                    merge_into(&action, &[], &to_js_value(&annotation)?);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Adds the `severity` of every validation status along with the `worstSeverity` of each
+/// manifest and of the store
+///
+/// Store statuses that don't refer to a particular manifest are attributed to the active
+/// manifest. `treatWarningsAsErrors` only affects `worstSeverity`. Hard binding mismatches are
+/// warnings with `tolerate_hard_binding_mismatch`, which a recovered manifest may call for.
+fn add_severities(
+    js_value: &JsValue,
+    active_label: Option<&str>,
+    treat_warnings_as_errors: bool,
+    tolerate_hard_binding_mismatch: bool,
+) -> Result<(), Error> {
+    let mut store_severities = Vec::new();
+    let mut manifest_severities: HashMap<String, Vec<Severity>> = HashMap::new();
+    for status in array_items(&get_path(js_value, &["validation_status"])) {
+        let severity = add_severity(&status, tolerate_hard_binding_mismatch)?;
+        store_severities.push(severity);
+
+        let url = get_path(&status, &["url"]).as_string().unwrap_or_default();
+        if let Some(label) = validation::manifest_label(&url).or(active_label) {
+            manifest_severities
+                .entry(label.to_owned())
+                .or_default()
+                .push(severity);
+        }
+    }
+
+    let manifests = get_path(js_value, &["manifests"]);
+    if let Some(manifests) = manifests.dyn_ref::<Object>() {
+        for label in Object::keys(manifests).iter() {
+            let manifest = Reflect::get(manifests, &label).unwrap_or(JsValue::UNDEFINED);
+            for ingredient in array_items(&get_path(&manifest, &["ingredients"])) {
+                for status in array_items(&get_path(&ingredient, &["validation_status"])) {
+                    add_severity(&status, false)?;
+                }
+            }
+
+            let severities = label
+                .as_string()
+                .and_then(|label| manifest_severities.remove(&label))
+                .unwrap_or_default();
+            let worst_severity = validation::worst_severity(severities, treat_warnings_as_errors);
+            Reflect::set(
+                &manifest,
+                &"worstSeverity".into(),
+                &to_js_value(&worst_severity)?,
+            )
+            .map_err(|_err| Error::JavaScriptConversion)?;
+        }
+    }
+
+    let worst_severity = validation::worst_severity(store_severities, treat_warnings_as_errors);
+    Reflect::set(
+        js_value,
+        &"worstSeverity".into(),
+        &to_js_value(&worst_severity)?,
+    )
+    .map_err(|_err| Error::JavaScriptConversion)?;
+
+    Ok(())
+}
+
+/// Repairs the store's structural defects with the `lenient` parsing option, returning the
+/// repaired store and its parse warnings, or `None` if there was nothing to repair
+///
+/// Stores our own parser can't read either are left for the c2pa crate to report.
+fn lenient_repair(
+    store_bytes: Option<&[u8]>,
+    options: &ReadOptions,
+) -> Option<(Vec<u8>, Vec<ParseWarning>)> {
+    match (options.parsing, store_bytes) {
+        (Parsing::Lenient, Some(store_bytes)) => repair_store(store_bytes).ok().flatten(),
+        _ => None,
+    }
+}
+
+/// Rebuilds the manifest store with the manifest the `activeManifestOverride` read option names as
+/// its active manifest, if the option is given
+///
+/// An asset without a manifest store has no manifest to make active.
+fn override_active_manifest(
+    store_bytes: Option<&[u8]>,
+    options: &ReadOptions,
+) -> Result<Option<Vec<u8>>, Error> {
+    let label = match &options.active_manifest_override {
+        Some(label) => label,
+        None => return Ok(None),
+    };
+    match store_bytes {
+        Some(store_bytes) => with_active_manifest(store_bytes, label).map(Some),
+        None => Err(Error::ActiveManifestNotFound {
+            label: label.clone(),
+            available: Vec::new(),
+        }),
+    }
+}
+
+/// Marks output read with the `activeManifestOverride` read option, so that it can't be taken for
+/// the asset's own validation result
+fn mark_override(js_value: &JsValue, options: &ReadOptions) -> Result<(), Error> {
+    if let Some(label) = &options.active_manifest_override {
+        Reflect::set(js_value, &"overrideApplied".into(), &true.into())
+            .map_err(|_err| Error::JavaScriptConversion)?;
+        add_warning(
+            js_value,
+            format!(
+                "Validated as if manifest {} were the active manifest, leaving out the manifests \
+                 after it",
+                label
+            ),
+        );
+    }
+
+    Ok(())
+}
+
+/// Repairs the text of a manifest store that isn't valid UTF-8, returning the store to read along
+/// with the fields that were repaired
+fn repair_utf8(store_bytes: Option<&[u8]>) -> (Option<Vec<u8>>, Vec<InvalidUtf8>) {
+    match store_bytes.map(utf8::repair_store) {
+        Some(Ok(Some((repaired, invalid_utf8)))) => (Some(repaired), invalid_utf8),
+        _ => (None, Vec::new()),
+    }
+}
+
+/// Marks the manifests whose text had invalid UTF-8 with `hadInvalidUtf8` and the fields that were
+/// repaired, and replaces the validation statuses that only report the repair
+///
+/// The c2pa crate hashed the repaired assertions, so its hashed URI mismatches for them are dropped
+/// when the toolkit's check of the bytes as they were signed passes. It also verified the signature
+/// of repaired claims over the repaired bytes, so their signature statuses are replaced with
+/// `utf8.claimNotVerified`.
+fn add_invalid_utf8(
+    js_value: &JsValue,
+    invalid_utf8: &[InvalidUtf8],
+    store_bytes: Option<&[u8]>,
+) -> Result<(), Error> {
+    if invalid_utf8.is_empty() {
+        return Ok(());
+    }
+
+    let hashed_uri_checks = match store_bytes {
+        Some(store_bytes) => get_hashed_uri_checks(store_bytes, false)?,
+        None => HashMap::new(),
+    };
+    let hashes_verified: HashSet<(&str, &str)> = invalid_utf8
+        .iter()
+        .filter_map(|invalid| {
+            let assertion_label = invalid.url.rsplit('/').next().unwrap_or_default();
+            let verified = !invalid.in_claim
+                && hashed_uri_checks
+                    .get(&invalid.manifest)
+                    .and_then(|checks| checks.assertions.get(assertion_label))
+                    .is_some_and(|check| check.hashed_uri_valid);
+            verified.then_some((invalid.manifest.as_str(), assertion_label))
+        })
+        .collect();
+    let repaired_claims: BTreeSet<&str> = invalid_utf8
+        .iter()
+        .filter(|invalid| invalid.in_claim)
+        .map(|invalid| invalid.manifest.as_str())
+        .collect();
+
+    let mut unattributed_mismatches = repaired_claims.len();
+    let statuses: js_sys::Array = array_items(&get_path(js_value, &["validation_status"]))
+        .into_iter()
+        .filter(|status| {
+            let code = get_path(status, &["code"]).as_string().unwrap_or_default();
+            let url = get_path(status, &["url"]).as_string().unwrap_or_default();
+            if code == c2pa::validation_status::ASSERTION_HASHEDURI_MISMATCH {
+                // The c2pa crate reports these with URLs relative to the manifest
+                let assertion_label = url.rsplit('/').next().unwrap_or_default();
+                return !hashes_verified.iter().any(|(manifest, label)| {
+                    *label == assertion_label
+                        && validation::manifest_label(&url).is_none_or(|url| url == *manifest)
+                });
+            }
+            match validation::manifest_label(&url) {
+                Some(label) => {
+                    !(repaired_claims.contains(label)
+                        && (code.starts_with("claimSignature.")
+                            || code.starts_with("signingCredential.")))
+                }
+                None if code == c2pa::validation_status::CLAIM_SIGNATURE_MISMATCH
+                    && unattributed_mismatches > 0 =>
+                {
+                    unattributed_mismatches -= 1;
+                    false
+                }
+                None => true,
+            }
+        })
+        .collect();
+    Reflect::set(js_value, &"validation_status".into(), &statuses)
+        .map_err(|_err| Error::JavaScriptConversion)?;
+
+    for label in &repaired_claims {
+        push_validation_status(
+            js_value,
+            &UpdateValidationStatus {
+                code: utf8::CLAIM_NOT_VERIFIED,
+                url: format!("self#jumbf=/c2pa/{}/c2pa.signature", label),
+                explanation: "the claim has text that isn't valid UTF-8, so the signature was not \
+                              verified"
+                    .to_owned(),
+            },
+        )?;
+    }
+
+    let mut by_manifest: BTreeMap<&str, Vec<&InvalidUtf8>> = BTreeMap::new();
+    for invalid in invalid_utf8 {
+        by_manifest
+            .entry(invalid.manifest.as_str())
+            .or_default()
+            .push(invalid);
+        add_warning(
+            js_value,
+            format!(
+                "Replaced text that isn't valid UTF-8 at {} in {} of manifest {}",
+                invalid.path, invalid.url, invalid.manifest
+            ),
+        );
+    }
+    for (label, fields) in by_manifest {
+        let marker = Object::new();
+        let fields = to_js_value(&fields)?;
+        Reflect::set(&marker, &"hadInvalidUtf8".into(), &true.into())
+            .and_then(|_| Reflect::set(&marker, &"invalidUtf8".into(), &fields))
+            .map_err(|_err| Error::JavaScriptConversion)?;
+        if repaired_claims.contains(label) {
+            Reflect::set(
+                &marker,
+                &"signatureVerification".into(),
+                &"invalid-utf8".into(),
+            )
+            .map_err(|_err| Error::JavaScriptConversion)?;
+        }
+        merge_into(js_value, &["manifests", label], &marker);
+    }
+
+    Ok(())
+}
+
+/// Lists the defects tolerated by lenient parsing as `parseWarnings`, which is empty if there were
+/// none
+fn add_parse_warnings(
+    js_value: &JsValue,
+    options: &ReadOptions,
+    parse_warnings: Option<Vec<ParseWarning>>,
+) -> Result<(), Error> {
+    if options.parsing != Parsing::Lenient {
+        return Ok(());
+    }
+
+    let parse_warnings = to_js_value(&parse_warnings.unwrap_or_default())?;
+    Reflect::set(js_value, &"parseWarnings".into(), &parse_warnings)
+        .map_err(|_err| Error::JavaScriptConversion)?;
+
+    Ok(())
+}
+
+/// Attaches the dimensions and duration of the asset as `assetInfo`
+fn add_asset_info(js_value: &JsValue, asset: &[u8], options: &ReadOptions) -> Result<(), Error> {
+    if !options.include_asset_info {
+        return Ok(());
+    }
+
+    let asset_info = to_js_value(&asset_info::asset_info(asset))?;
+    Reflect::set(js_value, &"assetInfo".into(), &asset_info)
+        .map_err(|_err| Error::JavaScriptConversion)?;
+
+    Ok(())
+}
+
+/// Checks the `ingredientAssets` supplied with the read against their ingredients
+///
+/// Each matching ingredient gets `suppliedAssetMatches` and the `details` of the check, and the
+/// assets no ingredient has the identifiers of are listed as `unmatchedIngredientAssets`.
+async fn add_ingredient_asset_checks(
+    js_value: &JsValue,
+    manifest_store: &ManifestStore,
+    store_bytes: Option<&[u8]>,
+    options: &ReadOptions,
+) -> Result<(), Error> {
+    if options.ingredient_assets.is_empty() {
+        return Ok(());
+    }
+
+    let result = ingredient_assets::check_ingredient_assets(
+        manifest_store,
+        store_bytes,
+        &options.ingredient_assets,
+    )
+    .await?;
+    for (label, index, check) in result.checks {
+        let ingredients = array_items(&get_path(js_value, &["manifests", &label, "ingredients"]));
+        if let Some(ingredient) = ingredients.get(index) {
+            merge_into(ingredient, &[], &to_js_value(&check)?);
+        }
+    }
+    Reflect::set(
+        js_value,
+        &"unmatchedIngredientAssets".into(),
+        &to_js_value(&result.unmatched)?,
+    )
+    .map_err(|_err| Error::JavaScriptConversion)?;
+
+    Ok(())
+}
+
+/// Attaches a trace of the validation walk as `trace`
+///
+/// Without the store bytes there is nothing to walk, so the trace has no steps.
+fn add_trace(
+    js_value: &JsValue,
+    manifest_store: &ManifestStore,
+    store_bytes: Option<&[u8]>,
+) -> Result<(), Error> {
+    let mut tracer = trace::Tracer::new(trace::MAX_TRACE_STEPS);
+    if let Some(store_bytes) = store_bytes {
+        manifest_store::trace_validation(
+            store_bytes,
+            manifest_store.validation_status().unwrap_or_default(),
+            manifest_store.active_label(),
+            &mut tracer,
+        );
+    }
+    Reflect::set(js_value, &"trace".into(), &to_js_value(&tracer.finish())?)
+        .map_err(|_err| Error::JavaScriptConversion)?;
+
+    Ok(())
+}
+
+/// Converts a manifest store to JavaScript along with the extra data the toolkit reports
+///
+/// `store_bytes` is the JUMBF the store was read from, if it is available.
+async fn manifest_store_to_js(
+    manifest_store: &ManifestStore,
+    store_bytes: Option<&[u8]>,
+    options: &ReadOptions,
+) -> Result<JsValue, Error> {
+    let unsupported_algs = match store_bytes {
+        Some(store_bytes) => get_unsupported_algs(store_bytes)?,
+        None => BTreeMap::new(),
+    };
+    if let (OnUnsupportedAlg::Error, Some((label, alg))) =
+        (options.on_unsupported_alg, unsupported_algs.iter().next())
+    {
+        warn!(
+            "The signature of {} uses COSE algorithm {}, which this build can't verify",
+            label, alg
+        );
+        return Err(c2pa::Error::CoseSignatureAlgorithmNotSupported.into());
+    }
+
+    let js_value = match options.max_assertion_bytes {
+        Some(max_bytes) => to_js_value(&bound_assertions(manifest_store, max_bytes)?)?,
+        None => to_js_value(manifest_store)?,
+    };
+    output_schema::stamp(&js_value)?;
+    if !unsupported_algs.is_empty() {
+        degrade_unsupported_algs(&js_value, &unsupported_algs)?;
+    }
+
+    let truncation = truncate_provenance(manifest_store, options.max_depth);
+    if !truncation.is_empty() {
+        apply_truncation(&js_value, &truncation, options.max_depth);
+    }
+    if let Some(store_bytes) = store_bytes {
+        add_revocation_info(&js_value, store_bytes);
+        add_timestamp_checks(&js_value, store_bytes, &options.timestamp_skew)?;
+        add_update_manifests(&js_value, store_bytes, manifest_store.active_label())?;
+        add_hashed_uri_checks(&js_value, store_bytes, options.include_hash_digests)?;
+        add_generator_checks(&js_value, manifest_store, store_bytes)?;
+        for (label, claim_version) in
+            get_claim_versions(store_bytes, options.max_supported_claim_version)?
+        {
+            merge_into(
+                &js_value,
+                &["manifests", &label],
+                &to_js_value(&claim_version)?,
+            );
+        }
+        for (label, hard_binding_alg) in get_hard_binding_algs(store_bytes)? {
+            merge_into(
+                &js_value,
+                &["manifests", &label],
+                &to_js_value(&hard_binding_alg)?,
+            );
+        }
+        for (label, collection_hash) in get_collection_hashes(store_bytes)? {
+            merge_into(
+                &js_value,
+                &["manifests", &label],
+                &to_js_value(&collection_hash)?,
+            );
+        }
+    }
+    if options.include_raw_claim {
+        let store_bytes = store_bytes.ok_or(Error::InvalidJumbf("manifest store not found"))?;
+        add_raw_claims(&js_value, store_bytes, options)?;
+    }
+    if let Some(locale) = &options.locale {
+        for (label, display_strings) in get_display_strings(manifest_store, locale)? {
+            merge_into(
+                &js_value,
+                &["manifests", &label],
+                &to_js_value(&display_strings)?,
+            );
+        }
+    }
+    for (label, iptc_metadata) in metadata::get_iptc_metadata(manifest_store) {
+        merge_into(
+            &js_value,
+            &["manifests", &label],
+            &to_js_value(&iptc_metadata)?,
+        );
+    }
+    add_action_versions(&js_value)?;
+    add_action_vocabulary(&js_value)?;
+    add_severities(
+        &js_value,
+        manifest_store.active_label(),
+        options.treat_warnings_as_errors,
+        false,
+    )?;
+    if let (Some(store_bytes), Some(resolver)) = (store_bytes, &options.resource_resolver) {
+        resolve_external_resources(&js_value, store_bytes, resolver).await?;
+    }
+    if options.trace {
+        add_trace(&js_value, manifest_store, store_bytes)?;
+    }
+
+    // Without the store bytes, manifests can only be ordered by label
+    let manifest_order = match store_bytes {
+        Some(store_bytes) => get_manifest_order(store_bytes)?,
+        None => Vec::new(),
+    };
+    ordering::order_manifest_store(&js_value, &manifest_order)?;
+
+    Ok(js_value)
+}
+
 #[wasm_bindgen(js_name = getManifestStoreFromArrayBuffer, skip_typescript)]
-pub async fn get_manifest_store_from_array_buffer(
+pub fn get_manifest_store_from_array_buffer(
     buf: JsValue,
     mime_type: String,
-) -> Result<JsValue, JsSysError> {
+    options: JsValue,
+) -> Promise {
+    init::ensure();
     log_time("get_manifest_store_from_array_buffer::start");
-    let asset: serde_bytes::ByteBuf = serde_wasm_bindgen::from_value(buf)
-        .map_err(Error::SerdeInput)
+    let asset = input::read_buffer(buf, "buf");
+
+    input::promise(async move {
+        let asset: serde_bytes::ByteBuf = asset.map_err(as_js_error)?;
+        let options: ReadOptions = read_options(
+            options,
+            "getManifestStoreFromArrayBuffer",
+            &read_option_keys(),
+        )
         .map_err(as_js_error)?;
+
+        match cache::key(&asset, &mime_type, &options) {
+            Some(key) => {
+                let read = read_manifest_store(&asset, &mime_type, &options);
+                cache::get_or_read(key, async {
+                    read.await.map_err(|err| JsValue::from(as_js_error(err)))
+                })
+                .await
+                .map_err(JsCast::unchecked_into)
+            }
+            None => Ok(read_manifest_store(&asset, &mime_type, &options)
+                .await
+                .map_err(as_js_error)?
+                .0),
+        }
+    })
+}
+
+/// Reads the manifest store of an asset, returning it along with the size of the store
+async fn read_manifest_store(
+    asset: &[u8],
+    mime_type: &str,
+    options: &ReadOptions,
+) -> Result<(JsValue, usize), Error> {
     log_time("get_manifest_store_from_array_buffer::from_bytes");
-    let result = get_manifest_store_data(&asset, &mime_type)
-        .await
-        .map_err(as_js_error)?;
+    // Remote manifests aren't embedded, in which case there are no store bytes to inspect
+    let mut store_bytes = c2pa::jumbf_io::load_jumbf_from_memory(mime_type, asset).ok();
+    let mut embedding_format = None;
+    if store_bytes.is_none() && options.legacy_embeddings {
+        store_bytes = legacy_png_store(asset);
+        embedding_format = store_bytes.as_ref().map(|_| EmbeddingFormat::LegacyItxt);
+    }
+    if store_bytes.is_none() && options.parsing == Parsing::Lenient {
+        store_bytes = trailing_jpeg_store(asset);
+        embedding_format = store_bytes.as_ref().map(|_| EmbeddingFormat::TrailingApp11);
+    }
+    // The repairs below parse the store themselves, before the c2pa crate would check it
+    if let Some(store_bytes) = &store_bytes {
+        limits::check(store_bytes)?;
+    }
+    let (repaired, mut parse_warnings) = match lenient_repair(store_bytes.as_deref(), options) {
+        Some((repaired, parse_warnings)) => (Some(repaired), Some(parse_warnings)),
+        None => (None, None),
+    };
+    // The c2pa crate only finds stores where the specification puts them
+    let read_from_store = repaired.is_some() || embedding_format.is_some();
+    let store_bytes = repaired.or(store_bytes);
+    if let (Some(EmbeddingFormat::TrailingApp11), Some(store_bytes)) =
+        (embedding_format, &store_bytes)
+    {
+        parse_warnings
+            .get_or_insert_with(Vec::new)
+            .push(trailing_app11_warning(store_bytes));
+    }
+    let overridden = override_active_manifest(store_bytes.as_deref(), options)?;
+    let read_from_store = read_from_store || overridden.is_some();
+    let store_bytes = overridden.or(store_bytes);
+    // ...and only reads text that is valid UTF-8
+    let (utf8_repaired, invalid_utf8) = repair_utf8(store_bytes.as_deref());
+    let read_bytes = utf8_repaired
+        .as_deref()
+        .or_else(|| store_bytes.as_deref().filter(|_| read_from_store));
+    let result = match read_bytes {
+        Some(read_bytes) => {
+            get_manifest_store_data_from_manifest_and_asset_bytes(read_bytes, mime_type, asset)
+                .await
+        }
+        None => get_manifest_store_data(asset, mime_type).await,
+    };
+    let (result, contained, manifest_errors) = contain_errors(
+        result,
+        utf8_repaired.as_deref().or(store_bytes.as_deref()),
+        mime_type,
+        asset,
+    )
+    .await?;
+    // The toolkit's own checks read the store as it was signed
+    let store_bytes = contained.or(store_bytes);
     log_time("get_manifest_store_from_array_buffer::get_result");
-    let serializer = Serializer::new().serialize_maps_as_objects(true);
-    let js_value = result
-        .serialize(&serializer)
-        .map_err(|_err| Error::JavaScriptConversion)
-        .map_err(as_js_error)?;
+    let js_value = manifest_store_to_js(&result, store_bytes.as_deref(), options).await?;
+    add_manifest_errors(&js_value, &manifest_errors)?;
+    add_invalid_utf8(&js_value, &invalid_utf8, store_bytes.as_deref())?;
+    add_parse_warnings(&js_value, options, parse_warnings)?;
+    add_asset_info(&js_value, asset, options)?;
+    add_ingredient_asset_checks(&js_value, &result, store_bytes.as_deref(), options).await?;
+    if let Some(embedding_format) = embedding_format {
+        Reflect::set(
+            &js_value,
+            &"embeddingFormat".into(),
+            &to_js_value(&embedding_format)?,
+        )
+        .map_err(|_err| Error::JavaScriptConversion)?;
+        if embedding_format == EmbeddingFormat::TrailingApp11 {
+            Reflect::set(&js_value, &"nonStandardPlacement".into(), &true.into())
+                .map_err(|_err| Error::JavaScriptConversion)?;
+        }
+    }
+    mark_override(&js_value, options)?;
+    output_schema::apply(&js_value, options.schema_version)?;
+    serialization::apply(&js_value, &options.serialization)?;
     log_time("get_manifest_store_from_array_buffer::javascript_conversion");
 
+    Ok((
+        js_value,
+        store_bytes.map_or(0, |store_bytes| store_bytes.len()),
+    ))
+}
+
+/// Returns the manifest store of an asset along with the attribution in its XMP, either of which
+/// may be `null`
+///
+/// The XMP is read separately from the manifest store, so XMP that can't be parsed leaves
+/// `legacyMetadata` out without affecting `c2pa`.
+#[wasm_bindgen(js_name = getMetadataSummary, skip_typescript)]
+pub fn get_metadata_summary(buf: JsValue, mime_type: String, options: JsValue) -> Promise {
+    init::ensure();
+    log_time("get_metadata_summary::start");
+    let asset = input::read_buffer(buf, "buf");
+
+    input::promise(async move {
+        let asset: serde_bytes::ByteBuf = asset.map_err(as_js_error)?;
+        let options: ReadOptions = read_options(options, "getMetadataSummary", &read_option_keys())
+            .map_err(as_js_error)?;
+
+        let c2pa = match read_manifest_store(&asset, &mime_type, &options).await {
+            Ok((c2pa, _)) => c2pa,
+            Err(Error::C2pa(c2pa::Error::ProvenanceMissing | c2pa::Error::JumbfNotFound)) => {
+                JsValue::NULL
+            }
+            Err(err) => return Err(as_js_error(err)),
+        };
+        log_time("get_metadata_summary::get_result");
+
+        let legacy_metadata = match xmp::extract_xmp(&asset).map(xmp::parse_xmp) {
+            Some(Ok(metadata)) if !metadata.is_empty() => {
+                to_js_value(&metadata).map_err(as_js_error)?
+            }
+            Some(Err(err)) => {
+                warn!("Could not read the XMP metadata: {}", err);
+                JsValue::NULL
+            }
+            _ => JsValue::NULL,
+        };
+
+        let summary = Object::new();
+        Reflect::set(&summary, &"c2pa".into(), &c2pa)
+            .and_then(|_| Reflect::set(&summary, &"legacyMetadata".into(), &legacy_metadata))
+            .map_err(|_err| as_js_error(Error::JavaScriptConversion))?;
+
+        Ok(summary.into())
+    })
+}
+
+/// Turns on caching of `getManifestStoreFromArrayBuffer` results, or changes the cache's limits
+#[wasm_bindgen(js_name = enableReadCache, skip_typescript)]
+pub fn enable_read_cache(options: JsValue) -> Result<(), JsSysError> {
+    init::ensure();
+    let options = if options.is_undefined() || options.is_null() {
+        cache::ReadCacheOptions::default()
+    } else {
+        let accepted = OptionKeys::of::<cache::ReadCacheOptions>();
+        util::check_options(&options, "enableReadCache", &accepted).map_err(as_js_error)?;
+        serde_wasm_bindgen::from_value(options)
+            .map_err(Error::SerdeInput)
+            .map_err(as_js_error)?
+    };
+    cache::enable(options);
+
+    Ok(())
+}
+
+/// Drops every cached result
+#[wasm_bindgen(js_name = clearReadCache, skip_typescript)]
+pub fn clear_read_cache() {
+    init::ensure();
+    cache::clear();
+}
+
+/// Sets the organizations or domains expected to sign for each claim generator, keyed by generator
+/// prefix, or turns the check off with `null`
+///
+/// Cached read results were checked against the previous hints, so they are dropped.
+#[wasm_bindgen(js_name = setGeneratorTrustHints, skip_typescript)]
+pub fn set_generator_trust_hints(hints: JsValue) -> Result<(), JsSysError> {
+    init::ensure();
+    let hints = if hints.is_undefined() || hints.is_null() {
+        HashMap::new()
+    } else {
+        serde_wasm_bindgen::from_value(hints)
+            .map_err(Error::SerdeInput)
+            .map_err(as_js_error)?
+    };
+    generator_trust::set_hints(hints);
+    cache::clear();
+
+    Ok(())
+}
+
+/// Sets the limits manifest stores are checked against before they are parsed, or restores the
+/// defaults with `null`
+///
+/// Limits that aren't given keep their defaults. Cached read results were checked against the
+/// previous limits, so they are dropped.
+#[wasm_bindgen(js_name = setParserLimits, skip_typescript)]
+pub fn set_parser_limits(limits: JsValue) -> Result<(), JsSysError> {
+    init::ensure();
+    let limits = if limits.is_undefined() || limits.is_null() {
+        limits::ParserLimits::default()
+    } else {
+        let accepted = OptionKeys::of::<limits::ParserLimits>();
+        util::check_options(&limits, "setParserLimits", &accepted).map_err(as_js_error)?;
+        serde_wasm_bindgen::from_value(limits)
+            .map_err(Error::SerdeInput)
+            .map_err(as_js_error)?
+    };
+    limits::set_limits(limits);
+    cache::clear();
+
+    Ok(())
+}
+
+/// Sets whether options objects with keys an API doesn't accept fail the call, rather than having
+/// the unknown keys logged
+///
+/// Nested options objects are checked too. Unknown keys are logged by default.
+#[wasm_bindgen(js_name = strictOptions, skip_typescript)]
+pub fn strict_options(strict: bool) {
+    init::ensure();
+    util::set_strict_options(strict);
+}
+
+/// Registers custom actions, such as `com.example.reviewed`, so that the actions of a manifest
+/// that use them are annotated with what was registered
+///
+/// Cached read results were annotated with the previous vocabulary, so they are dropped.
+#[wasm_bindgen(js_name = registerActionVocabulary, skip_typescript)]
+pub fn register_action_vocabulary(entries: JsValue) -> Result<(), JsSysError> {
+    init::ensure();
+    let entries = serde_wasm_bindgen::from_value(entries)
+        .map_err(Error::SerdeInput)
+        .map_err(as_js_error)?;
+    action_vocabulary::register(entries).map_err(as_js_error)?;
+    cache::clear();
+
+    Ok(())
+}
+
+/// Removes every registered custom action
+#[wasm_bindgen(js_name = clearActionVocabulary, skip_typescript)]
+pub fn clear_action_vocabulary() {
+    init::ensure();
+    action_vocabulary::clear();
+    cache::clear();
+}
+
+/// Returns the read cache's counters, or `null` if it isn't on
+#[wasm_bindgen(js_name = getReadCacheStats, skip_typescript)]
+pub fn get_read_cache_stats() -> Result<JsValue, JsSysError> {
+    init::ensure();
+    match cache::stats() {
+        Some(stats) => to_js_value(&stats).map_err(as_js_error),
+        None => Ok(JsValue::NULL),
+    }
+}
+
+/// Returns the size of the WebAssembly memory and how much of it the toolkit is using
+#[wasm_bindgen(js_name = getMemoryStats, skip_typescript)]
+pub fn get_memory_stats() -> Result<JsValue, JsSysError> {
+    init::ensure();
+    to_js_value(&memory::stats()).map_err(as_js_error)
+}
+
+/// Drops the read cache's results and resets the peak memory usage
+///
+/// The WebAssembly memory itself can't shrink, but the freed bytes are reused by later reads.
+#[wasm_bindgen(js_name = releaseInternalBuffers, skip_typescript)]
+pub fn release_internal_buffers() {
+    init::ensure();
+    memory::release();
+}
+
+/// Validates a manifest store supplied separately from its asset, converting it to JavaScript
+/// along with the extra data the toolkit reports, except for the `serialization` option
+async fn manifest_and_asset_to_js(
+    manifest: &[u8],
+    asset: &[u8],
+    mime_type: &str,
+    options: &ReadOptions,
+) -> Result<JsValue, Error> {
+    // Manifests from other tools may be wrapped or carry a header
+    let (store_bytes, sidecar_format) = normalize_sidecar(manifest);
+    limits::check(store_bytes)?;
+    let (repaired, parse_warnings) = match lenient_repair(Some(store_bytes), options) {
+        Some((repaired, parse_warnings)) => (Some(repaired), Some(parse_warnings)),
+        None => (None, None),
+    };
+    let store_bytes = repaired.as_deref().unwrap_or(store_bytes);
+    let overridden = override_active_manifest(Some(store_bytes), options)?;
+    let store_bytes = overridden.as_deref().unwrap_or(store_bytes);
+    let (utf8_repaired, invalid_utf8) = repair_utf8(Some(store_bytes));
+    let read_bytes = utf8_repaired.as_deref().unwrap_or(store_bytes);
+
+    log_time("get_manifest_store_data_from_manifest_and_asset::from_bytes");
+    let result =
+        get_manifest_store_data_from_manifest_and_asset_bytes(read_bytes, mime_type, asset).await;
+    let (result, contained, manifest_errors) =
+        contain_errors(result, Some(read_bytes), mime_type, asset).await?;
+    let store_bytes = contained.as_deref().unwrap_or(store_bytes);
+
+    let js_value = manifest_store_to_js(&result, Some(store_bytes), options).await?;
+    add_manifest_errors(&js_value, &manifest_errors)?;
+    add_invalid_utf8(&js_value, &invalid_utf8, Some(store_bytes))?;
+    add_parse_warnings(&js_value, options, parse_warnings)?;
+    add_asset_info(&js_value, asset, options)?;
+    add_ingredient_asset_checks(&js_value, &result, Some(store_bytes), options).await?;
+    if let Some(sidecar_format) = sidecar_format {
+        Reflect::set(
+            &js_value,
+            &"sidecarFormat".into(),
+            &to_js_value(&sidecar_format)?,
+        )
+        .map_err(|_err| Error::JavaScriptConversion)?;
+    }
+    mark_override(&js_value, options)?;
+
     Ok(js_value)
 }
 
 #[wasm_bindgen(js_name = getManifestStoreFromManifestAndAsset, skip_typescript)]
-pub async fn get_manifest_store_from_manifest_and_asset(
+pub fn get_manifest_store_from_manifest_and_asset(
     manifest_buffer: JsValue,
     asset_buffer: JsValue,
     mime_type: String,
-) -> Result<JsValue, JsSysError> {
+    options: JsValue,
+) -> Promise {
+    init::ensure();
     log_time("get_manifest_store_data_from_manifest_and_asset::start");
-    let manifest: serde_bytes::ByteBuf = serde_wasm_bindgen::from_value(manifest_buffer)
-        .map_err(Error::SerdeInput)
+    let manifest = input::read_buffer(manifest_buffer, "manifestBuffer");
+    let asset = input::read_buffer(asset_buffer, "assetBuffer");
+
+    input::promise(async move {
+        let manifest: serde_bytes::ByteBuf = manifest.map_err(as_js_error)?;
+
+        let asset: serde_bytes::ByteBuf = asset.map_err(as_js_error)?;
+        let options: ReadOptions = read_options(
+            options,
+            "getManifestStoreFromManifestAndAsset",
+            &read_option_keys(),
+        )
         .map_err(as_js_error)?;
 
-    let asset: serde_bytes::ByteBuf = serde_wasm_bindgen::from_value(asset_buffer)
-        .map_err(Error::SerdeInput)
+        let js_value = manifest_and_asset_to_js(&manifest, &asset, &mime_type, &options)
+            .await
+            .map_err(as_js_error)?;
+        output_schema::apply(&js_value, options.schema_version).map_err(as_js_error)?;
+        serialization::apply(&js_value, &options.serialization).map_err(as_js_error)?;
+        log_time("get_manifest_store_data_from_manifest_and_asset::javascript_conversion");
+
+        Ok(js_value)
+    })
+}
+
+/// Recovers the manifest of an asset that carries a watermark instead of an embedded manifest
+///
+/// `options.detectWatermark` returns the recovery identifier the asset's watermark encodes, or
+/// `null` if it has none, in which case `null` is returned. `options.fetchManifest` returns the
+/// manifest store for that identifier, which is validated against the asset like a sidecar.
+/// The other options are the read options.
+#[wasm_bindgen(js_name = recoverManifest, skip_typescript)]
+pub fn recover_manifest(buf: JsValue, mime_type: String, options: JsValue) -> Promise {
+    init::ensure();
+    log_time("recover_manifest::start");
+    let asset = input::read_buffer(buf, "buf");
+
+    input::promise(async move {
+        let asset: serde_bytes::ByteBuf = asset.map_err(as_js_error)?;
+        let callback = |stage: &'static str| {
+            Reflect::get(&options, &stage.into())
+                .ok()
+                .and_then(|callback| callback.dyn_into::<js_sys::Function>().ok())
+                .ok_or_else(|| {
+                    as_js_error(Error::RecoveryFailed(
+                        stage,
+                        "no callback was given".to_owned(),
+                    ))
+                })
+        };
+        let detect_watermark = callback(recovery::STAGE_DETECT_WATERMARK)?;
+        let fetch_manifest = callback(recovery::STAGE_FETCH_MANIFEST)?;
+        let detector_alg = get_path(&options, &["detectorAlg"]).as_string();
+        let read_options = read_options(
+            options,
+            "recoverManifest",
+            &read_option_keys().with(&[
+                recovery::STAGE_DETECT_WATERMARK,
+                recovery::STAGE_FETCH_MANIFEST,
+                "detectorAlg",
+            ]),
+        )
         .map_err(as_js_error)?;
 
-    log_time("get_manifest_store_data_from_manifest_and_asset::from_bytes");
-    let result =
-        get_manifest_store_data_from_manifest_and_asset_bytes(&manifest, &mime_type, &asset)
+        let recovery_id = match recovery::detect_watermark(&detect_watermark, &asset)
+            .await
+            .map_err(as_js_error)?
+        {
+            Some(recovery_id) => recovery_id,
+            None => return Ok(JsValue::NULL),
+        };
+        let manifest = recovery::fetch_manifest(&fetch_manifest, &recovery_id)
             .await
             .map_err(as_js_error)?;
+        log_time("recover_manifest::fetch_manifest");
 
-    let serializer = Serializer::new().serialize_maps_as_objects(true);
-    let js_value = result
-        .serialize(&serializer)
-        .map_err(|_err| Error::JavaScriptConversion)
+        let js_value = manifest_and_asset_to_js(&manifest, &asset, &mime_type, &read_options)
+            .await
+            .map_err(as_js_error)?;
+
+        let codes: Vec<String> = array_items(&get_path(&js_value, &["validation_status"]))
+            .iter()
+            .filter_map(|status| get_path(status, &["code"]).as_string())
+            .collect();
+        let active_label = get_path(&js_value, &["active_manifest"]).as_string();
+        let soft_binding_algs: Vec<String> = active_label
+            .as_deref()
+            .map(|label| array_items(&get_path(&js_value, &["manifests", label, "assertions"])))
+            .unwrap_or_default()
+            .iter()
+            .filter(|assertion| {
+                get_path(assertion, &["label"])
+                    .as_string()
+                    .is_some_and(|label| recovery::is_soft_binding_label(&label))
+            })
+            .filter_map(|assertion| get_path(assertion, &["data", "alg"]).as_string())
+            .collect();
+        let recovery = recovery::Recovery::new(
+            recovery_id,
+            &codes,
+            &soft_binding_algs,
+            detector_alg.as_deref(),
+        );
+        if recovery.tolerates_hard_binding_mismatch() {
+            add_severities(
+                &js_value,
+                active_label.as_deref(),
+                read_options.treat_warnings_as_errors,
+                true,
+            )
+            .map_err(as_js_error)?;
+        }
+        merge_into(
+            &js_value,
+            &[],
+            &to_js_value(&recovery).map_err(as_js_error)?,
+        );
+        output_schema::apply(&js_value, read_options.schema_version).map_err(as_js_error)?;
+        serialization::apply(&js_value, &read_options.serialization).map_err(as_js_error)?;
+        log_time("recover_manifest::javascript_conversion");
+
+        Ok(js_value)
+    })
+}
+
+/// Checks a file against its entry in the collection hash of a separately supplied manifest
+///
+/// Only the file's hash is checked, so the manifest should also be read to validate it.
+#[wasm_bindgen(js_name = verifyAssetInCollection, skip_typescript)]
+pub fn verify_asset_in_collection(
+    manifest_buffer: JsValue,
+    asset_buffer: JsValue,
+    name: String,
+) -> Promise {
+    init::ensure();
+    log_time("verify_asset_in_collection::start");
+    let manifest = input::read_buffer(manifest_buffer, "manifestBuffer");
+    let asset = input::read_buffer(asset_buffer, "assetBuffer");
+
+    input::promise(async move {
+        let manifest: serde_bytes::ByteBuf = manifest.map_err(as_js_error)?;
+        let asset: serde_bytes::ByteBuf = asset.map_err(as_js_error)?;
+
+        let (store_bytes, _) = normalize_sidecar(&manifest);
+        let result =
+            verify_asset_in_collection_data(store_bytes, &asset, &name).map_err(as_js_error)?;
+        log_time("verify_asset_in_collection::get_result");
+
+        to_js_value(&result).map_err(as_js_error)
+    })
+}
+
+/// Returns the manifest of an ingredient as a standalone `.c2pa` manifest store
+///
+/// `ingredient` is either the index of the ingredient in the manifest or its instance ID. Any other
+/// value, or an index that isn't a non-negative integer, fails with an `InvalidArgument` error.
+#[wasm_bindgen(js_name = getIngredientManifestBytes, skip_typescript)]
+pub fn get_ingredient_manifest_bytes(
+    buf: JsValue,
+    mime_type: String,
+    manifest_label: String,
+    ingredient: JsValue,
+) -> Promise {
+    init::ensure();
+    log_time("get_ingredient_manifest_bytes::start");
+    let asset = input::read_buffer(buf, "buf");
+
+    input::promise(async move {
+        let asset: serde_bytes::ByteBuf = asset.map_err(as_js_error)?;
+
+        let ingredient = if let Some(index) = ingredient.as_f64() {
+            IngredientRef::Index(input::read_index(index, "ingredient").map_err(as_js_error)?)
+        } else if let Some(instance_id) = ingredient.as_string() {
+            IngredientRef::InstanceId(instance_id)
+        } else {
+            return Err(as_js_error(Error::InvalidArgument {
+                parameter: "ingredient",
+                expected: "an index or an instance ID",
+            }));
+        };
+
+        let result =
+            get_ingredient_manifest_bytes_data(&asset, &mime_type, &manifest_label, &ingredient)
+                .await
+                .map_err(as_js_error)?;
+        log_time("get_ingredient_manifest_bytes::get_result");
+
+        Ok(Uint8Array::from(result.as_slice()).buffer().into())
+    })
+}
+
+/// Checks whether an asset has an embedded manifest store or a remote manifest reference
+///
+/// Only the container structure is scanned, so this is much cheaper than reading the manifest
+/// store. The buffer is read through a view rather than copied into WebAssembly memory.
+#[wasm_bindgen(js_name = hasManifest, skip_typescript)]
+pub fn has_manifest(buf: JsValue, mime_type: String) -> Result<JsValue, JsSysError> {
+    init::ensure();
+    input::check_buffer(&buf, "buf").map_err(as_js_error)?;
+    let view = Uint8Array::new(&buf);
+    let result = presence::has_manifest(&view, &mime_type);
+
+    to_js_value(&result).map_err(as_js_error)
+}
+
+/// Returns the assertions matching `label` from every manifest in the asset's provenance chain
+///
+/// `label` is an assertion label, a prefix ending in `*`, or an array of either.
+#[wasm_bindgen(js_name = getAssertions, skip_typescript)]
+pub fn get_assertions(buf: JsValue, mime_type: String, label: JsValue) -> Promise {
+    init::ensure();
+    log_time("get_assertions::start");
+    let asset = input::read_buffer(buf, "buf");
+
+    input::promise(async move {
+        let asset: serde_bytes::ByteBuf = asset.map_err(as_js_error)?;
+        let query: LabelQuery = serde_wasm_bindgen::from_value(label)
+            .map_err(Error::SerdeInput)
+            .map_err(as_js_error)?;
+
+        let result = get_assertions_data(&asset, &mime_type, &query)
+            .await
+            .map_err(as_js_error)?;
+        log_time("get_assertions::get_result");
+        let js_value = to_js_value(&result).map_err(as_js_error)?;
+        log_time("get_assertions::javascript_conversion");
+
+        Ok(js_value)
+    })
+}
+
+/// Returns the data of one assertion of one manifest
+///
+/// This fetches the assertions left out of a manifest store read with `maxAssertionBytes`.
+/// `assertionLabel` includes the instance suffix (e.g. `c2pa.actions__1`).
+#[wasm_bindgen(js_name = getAssertion, skip_typescript)]
+pub fn get_assertion(
+    buf: JsValue,
+    mime_type: String,
+    manifest_label: String,
+    assertion_label: String,
+) -> Promise {
+    init::ensure();
+    log_time("get_assertion::start");
+    let asset = input::read_buffer(buf, "buf");
+
+    input::promise(async move {
+        let asset: serde_bytes::ByteBuf = asset.map_err(as_js_error)?;
+
+        let result = get_assertion_data(&asset, &mime_type, &manifest_label, &assertion_label)
+            .await
+            .map_err(as_js_error)?;
+        log_time("get_assertion::get_result");
+        let js_value = to_js_value(&result).map_err(as_js_error)?;
+        log_time("get_assertion::javascript_conversion");
+
+        Ok(js_value)
+    })
+}
+
+/// Returns the provenance chain of the asset as a flat list of nodes and edges
+#[wasm_bindgen(js_name = getProvenanceGraph, skip_typescript)]
+pub fn get_provenance_graph(buf: JsValue, mime_type: String) -> Promise {
+    init::ensure();
+    log_time("get_provenance_graph::start");
+    let asset = input::read_buffer(buf, "buf");
+
+    input::promise(async move {
+        let asset: serde_bytes::ByteBuf = asset.map_err(as_js_error)?;
+
+        let manifest_store = get_manifest_store_data(&asset, &mime_type)
+            .await
+            .map_err(as_js_error)?;
+        let result = graph::provenance_graph(&manifest_store);
+        log_time("get_provenance_graph::get_result");
+
+        to_js_value(&result).map_err(as_js_error)
+    })
+}
+
+/// Compares the claim thumbnail of the active manifest with the asset
+///
+/// This is advisory: it only reports how alike they look, and doesn't affect validation.
+#[wasm_bindgen(js_name = verifyThumbnailConsistency, skip_typescript)]
+pub fn verify_thumbnail_consistency(buf: JsValue, mime_type: String, options: JsValue) -> Promise {
+    init::ensure();
+    log_time("verify_thumbnail_consistency::start");
+    let asset = input::read_buffer(buf, "buf");
+
+    input::promise(async move {
+        let asset: serde_bytes::ByteBuf = asset.map_err(as_js_error)?;
+        let options: thumbnail::ThumbnailOptions = if options.is_undefined() || options.is_null() {
+            Default::default()
+        } else {
+            let accepted = OptionKeys::of::<thumbnail::ThumbnailOptions>();
+            util::check_options(&options, "verifyThumbnailConsistency", &accepted)
+                .map_err(as_js_error)?;
+            serde_wasm_bindgen::from_value(options)
+                .map_err(Error::SerdeInput)
+                .map_err(as_js_error)?
+        };
+
+        let result = thumbnail::verify_thumbnail_consistency(&asset, &mime_type, &options)
+            .await
+            .map_err(as_js_error)?;
+        log_time("verify_thumbnail_consistency::get_result");
+
+        to_js_value(&result).map_err(as_js_error)
+    })
+}
+
+/// Fields of `SignatureMaterial` that are returned as `ArrayBuffer`s
+const SIGNATURE_MATERIAL_BYTES: &[&str] = &[
+    "protectedHeader",
+    "payload",
+    "signature",
+    "toBeSigned",
+    "certificates",
+];
+
+/// Returns the protected header, payload, signature and certificates of each manifest's signature
+///
+/// This lets the signatures be verified independently of the toolkit's own validation.
+#[wasm_bindgen(js_name = getSignatureMaterial, skip_typescript)]
+pub fn get_signature_material(buf: JsValue, mime_type: String) -> Promise {
+    init::ensure();
+    log_time("get_signature_material::start");
+    let asset = input::read_buffer(buf, "buf");
+
+    input::promise(async move {
+        let asset: serde_bytes::ByteBuf = asset.map_err(as_js_error)?;
+
+        let result = get_signature_material_data(&asset, &mime_type).map_err(as_js_error)?;
+        log_time("get_signature_material::get_result");
+        let js_value = to_js_value(&result).map_err(as_js_error)?;
+        for label in result.keys() {
+            Reflect::get(&js_value, &label.into())
+                .and_then(|material| bytes_to_array_buffers(&material, SIGNATURE_MATERIAL_BYTES))
+                .map_err(|_err| as_js_error(Error::JavaScriptConversion))?;
+        }
+        log_time("get_signature_material::javascript_conversion");
+
+        Ok(js_value)
+    })
+}
+
+/// Reads the manifest store of each image or other media file packaged in a ZIP-based container,
+/// such as an EPUB or Office document, keyed by its path within the container
+///
+/// Each entry has either its manifest store, which is `null` if it has none, or the error reading
+/// it raised. Entries larger than `options.maxEntrySize` are skipped. The other options are the
+/// read options, applied to each entry.
+#[wasm_bindgen(js_name = getManifestStoresFromContainer, skip_typescript)]
+pub fn get_manifest_stores_from_container(
+    buf: JsValue,
+    container_mime: String,
+    options: JsValue,
+) -> Promise {
+    init::ensure();
+    log_time("get_manifest_stores_from_container::start");
+    let container = input::read_buffer(buf, "buf");
+
+    input::promise(async move {
+        let container: serde_bytes::ByteBuf = container.map_err(as_js_error)?;
+        let container_options: ContainerOptions = if options.is_undefined() || options.is_null() {
+            ContainerOptions::default()
+        } else {
+            serde_wasm_bindgen::from_value(options.clone())
+                .map_err(Error::SerdeInput)
+                .map_err(as_js_error)?
+        };
+        let options: ReadOptions = read_options(
+            options,
+            "getManifestStoresFromContainer",
+            &read_option_keys().and(OptionKeys::of::<ContainerOptions>()),
+        )
         .map_err(as_js_error)?;
-    log_time("get_manifest_store_data_from_manifest_and_asset::javascript_conversion");
 
-    Ok(js_value)
+        let entries = container::media_entries(&container, &container_mime).map_err(as_js_error)?;
+        log_time("get_manifest_stores_from_container::media_entries");
+        let result = Object::new();
+        for entry in entries {
+            let value = Object::new();
+            let set = |key: &str, field: &JsValue| {
+                Reflect::set(&value, &key.into(), field)
+                    .map(|_| ())
+                    .map_err(|_err| as_js_error(Error::JavaScriptConversion))
+            };
+            if entry.size > container_options.max_entry_size() {
+                set("skipped", &"tooLarge".into())?;
+                set("size", &(entry.size as f64).into())?;
+            } else {
+                match entry.read() {
+                    Ok(asset) => {
+                        let mime_type = entry.mime_type(&asset);
+                        set("mimeType", &mime_type.into())?;
+                        match read_manifest_store(&asset, mime_type, &options).await {
+                            Ok((manifest_store, _)) => set("manifestStore", &manifest_store)?,
+                            Err(Error::C2pa(
+                                c2pa::Error::ProvenanceMissing | c2pa::Error::JumbfNotFound,
+                            )) => set("manifestStore", &JsValue::NULL)?,
+                            Err(err) => set("error", &as_js_error(err).into())?,
+                        }
+                    }
+                    Err(err) => set("error", &as_js_error(err).into())?,
+                }
+            }
+            Reflect::set(&result, &entry.path.as_str().into(), &value)
+                .map_err(|_err| as_js_error(Error::JavaScriptConversion))?;
+        }
+        log_time("get_manifest_stores_from_container::get_result");
+
+        Ok(result.into())
+    })
+}
+
+/// Returns the time ranges of the fragments of a fragmented MP4 in playback order, along with
+/// whether each validates against the manifest in the init segment
+///
+/// Ranges aren't merged: time no fragment covers is listed as a gap, and overlapping fragments
+/// list each other. `fragments` that isn't an array fails with an `InvalidArgument` error.
+#[wasm_bindgen(js_name = getCoverageTimeline, skip_typescript)]
+pub fn get_coverage_timeline(
+    init_segment: JsValue,
+    fragments: JsValue,
+    mime_type: String,
+) -> Promise {
+    init::ensure();
+    log_time("get_coverage_timeline::start");
+    let init_segment = input::read_buffer(init_segment, "initSegment");
+    let fragments = input::read_buffers(fragments, "fragments");
+
+    input::promise(async move {
+        let init_segment: serde_bytes::ByteBuf = init_segment.map_err(as_js_error)?;
+        let fragments = fragments.map_err(as_js_error)?;
+
+        let timeline = timeline::coverage_timeline(&init_segment, &fragments, &mime_type).await;
+        log_time("get_coverage_timeline::get_result");
+
+        to_js_value(&timeline).map_err(as_js_error)
+    })
+}
+
+/// Returns the hashes that identify an asset, for keying cached verification results
+///
+/// This doesn't validate the asset: the hard binding hashes are the ones its active manifest
+/// declares. An asset without a manifest store gets a non-cryptographic fingerprint instead.
+#[wasm_bindgen(js_name = getAssetIdentity, skip_typescript)]
+pub fn get_asset_identity(buf: JsValue, mime_type: String) -> Promise {
+    init::ensure();
+    log_time("get_asset_identity::start");
+    let asset = input::read_buffer(buf, "buf");
+
+    input::promise(async move {
+        let asset: serde_bytes::ByteBuf = asset.map_err(as_js_error)?;
+
+        let result = get_asset_identity_data(&asset, &mime_type).map_err(as_js_error)?;
+        log_time("get_asset_identity::get_result");
+        let js_value = to_js_value(&result).map_err(as_js_error)?;
+        log_time("get_asset_identity::javascript_conversion");
+
+        Ok(js_value)
+    })
+}
+
+/// Returns the manifest store as the detailed report that `c2patool --detailed` prints, as
+/// canonical JSON
+///
+/// This is separate from `getManifestStoreFromArrayBuffer`, whose output it doesn't change.
+#[wasm_bindgen(js_name = getDetailedReport, skip_typescript)]
+pub fn get_detailed_report(buf: JsValue, mime_type: String) -> Promise {
+    init::ensure();
+    log_time("get_detailed_report::start");
+    let asset = input::read_buffer(buf, "buf");
+
+    input::promise(async move {
+        let asset: serde_bytes::ByteBuf = asset.map_err(as_js_error)?;
+
+        let manifest_store = get_manifest_store_data(&asset, &mime_type)
+            .await
+            .map_err(as_js_error)?;
+        let store_bytes = limits::load(&asset, &mime_type).map_err(as_js_error)?;
+        let result = report::DetailedReport::new(&manifest_store, &store_bytes)
+            .and_then(|report| report.to_json())
+            .map_err(as_js_error)?;
+        log_time("get_detailed_report::get_result");
+
+        Ok(result.into())
+    })
+}
+
+/// Checks that a toolkit error reaches JavaScript with the name and message it is given
+fn check_error_conversion() -> Result<(), String> {
+    let js_err = as_js_error(Error::AssertionNotFound("c2pa.self-test".to_owned()));
+    let name: String = js_err.name().into();
+    let message: String = js_err.message().into();
+    if name == "AssertionNotFound(\"c2pa.self-test\")"
+        && message == "assertion not found: c2pa.self-test"
+    {
+        Ok(())
+    } else {
+        Err(format!("unexpected error {}: {}", name, message))
+    }
+}
+
+/// Checks that bytes keep their values when passed to and from JavaScript
+fn check_buffer_round_trip() -> Result<(), String> {
+    let bytes: Vec<u8> = (0..=255).collect();
+    let buffer = Uint8Array::from(bytes.as_slice()).buffer();
+    let from_js: serde_bytes::ByteBuf =
+        serde_wasm_bindgen::from_value(buffer.into()).map_err(|err| err.to_string())?;
+    let to_js = to_js_value(&serde_bytes::ByteBuf::from(bytes.clone()))
+        .map(|value| Uint8Array::new(&value).to_vec())
+        .map_err(|err| err.to_string())?;
+
+    if from_js.as_slice() == bytes.as_slice() && to_js == bytes {
+        Ok(())
+    } else {
+        Err("bytes changed crossing the JavaScript boundary".to_owned())
+    }
+}
+
+/// Digests the known-answer input with a SHA-256 function supplied from JavaScript
+async fn check_js_digest(digest: &js_sys::Function) -> Result<(), String> {
+    let input = Uint8Array::from(self_test::DIGEST_INPUT);
+    let mut result = digest
+        .call1(&JsValue::NULL, &input)
+        .map_err(|err| format!("{:?}", err))?;
+    if let Some(promise) = result.dyn_ref::<js_sys::Promise>() {
+        result = JsFuture::from(promise.clone())
+            .await
+            .map_err(|err| format!("{:?}", err))?;
+    }
+    input::check_buffer(&result, "digest").map_err(|err| err.to_string())?;
+
+    self_test::check_digest(&Uint8Array::new(&result).to_vec())
+}
+
+/// Runs known-answer tests of the toolkit in the current JavaScript engine
+///
+/// No network access or signer is needed. `options.digest` is an optional SHA-256 function whose
+/// output is compared with the toolkit's own.
+#[wasm_bindgen(js_name = selfTest, skip_typescript)]
+pub async fn run_self_test(options: JsValue) -> Result<JsValue, JsSysError> {
+    init::ensure();
+    log_time("self_test::start");
+    let digest = if options.is_undefined() || options.is_null() {
+        None
+    } else {
+        let accepted = OptionKeys::default().with(&["digest"]);
+        util::check_options(&options, "selfTest", &accepted).map_err(as_js_error)?;
+        Reflect::get(&options, &"digest".into())
+            .map_err(|_err| as_js_error(Error::JavaScriptConversion))?
+            .dyn_into::<js_sys::Function>()
+            .ok()
+    };
+
+    let mut self_test = self_test::SelfTest::new(js_sys::Date::now);
+    let started = self_test.start();
+    self_test.record("bufferRoundTrip", started, check_buffer_round_trip());
+    let started = self_test.start();
+    self_test.record("digest", started, self_test::check_rust_digest());
+    match digest {
+        Some(digest) => {
+            let started = self_test.start();
+            let outcome = check_js_digest(&digest).await;
+            self_test.record("jsDigest", started, outcome);
+        }
+        None => self_test.skip("jsDigest", "no digest function was given"),
+    }
+
+    let started = self_test.start();
+    match self_test::read_fixture().await {
+        Ok(manifest_store) => {
+            self_test.record(
+                "manifest",
+                started,
+                self_test::check_manifest(&manifest_store),
+            );
+            let started = self_test.start();
+            self_test.record(
+                "signatureVerification",
+                started,
+                self_test::check_signature(&manifest_store),
+            );
+        }
+        Err(message) => {
+            self_test.record("manifest", started, Err(message));
+            self_test.skip("signatureVerification", "the manifest could not be read");
+        }
+    }
+    self_test.skip("signing", "this build cannot sign");
+    self_test.skip(
+        "timestampRequest",
+        "this build does not encode timestamp requests",
+    );
+
+    let started = self_test.start();
+    self_test.record("errorConversion", started, check_error_conversion());
+    log_time("self_test::get_result");
+
+    to_js_value(&self_test.finish()).map_err(as_js_error)
+}
+
+/// Suggests why an asset has no valid manifest, for instance because it was modified or had its
+/// manifest stripped
+///
+/// This is a heuristic over the asset's container and metadata, separate from validation.
+#[wasm_bindgen(js_name = probeProvenanceLoss, skip_typescript)]
+pub fn probe_provenance_loss(buf: JsValue, mime_type: String) -> Promise {
+    init::ensure();
+    log_time("probe_provenance_loss::start");
+    let asset = input::read_buffer(buf, "buf");
+
+    input::promise(async move {
+        let asset: serde_bytes::ByteBuf = asset.map_err(as_js_error)?;
+
+        let embedded = presence::has_manifest(asset.as_slice(), &mime_type)
+            == presence::ManifestPresence::Embedded;
+        let read_result = get_manifest_store_data(&asset, &mime_type).await;
+        let result = provenance_loss::probe_provenance_loss(&asset, embedded, &read_result);
+        log_time("probe_provenance_loss::get_result");
+
+        to_js_value(&result).map_err(as_js_error)
+    })
 }
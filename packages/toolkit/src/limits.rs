@@ -0,0 +1,285 @@
+// Copyright 2024 Adobe
+// All Rights Reserved.
+//
+// NOTICE: Adobe permits you to use, modify, and distribute this file in
+// accordance with the terms of the Adobe license agreement accompanying
+// it.
+
+//! Quotas on the JUMBF structure of a manifest store, checked before the c2pa crate parses it.
+//!
+//! A crafted store can declare many thousands of tiny boxes, nest superboxes deeply, or have a box
+//! claim far more bytes than there are. The store is walked once without parsing any box
+//! contents, and the read fails with `LimitExceeded` as soon as one of the limits is passed. The
+//! limits are set by the application with `setParserLimits`; the defaults leave real stores a
+//! lot of headroom.
+use crate::error::{Error, Result};
+use crate::jumbf::{SuperBox, JUMB};
+use serde::Deserialize;
+use std::cell::RefCell;
+use std::convert::TryInto;
+
+/// The limits a manifest store is checked against
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ParserLimits {
+    /// The most boxes the store may hold, counting superboxes, description boxes and content boxes
+    pub max_boxes: u64,
+    /// How deeply superboxes may be nested, counting the store itself
+    pub max_box_depth: u64,
+    /// How many times the bytes actually left for it a box may declare
+    pub max_declared_size_ratio: f64,
+    pub max_store_bytes: u64,
+}
+
+impl Default for ParserLimits {
+    fn default() -> Self {
+        Self {
+            max_boxes: 10_000,
+            max_box_depth: 32,
+            max_declared_size_ratio: 1.0,
+            max_store_bytes: 64 * 1024 * 1024,
+        }
+    }
+}
+
+thread_local! {
+    static LIMITS: RefCell<ParserLimits> = RefCell::new(ParserLimits::default());
+}
+
+pub fn set_limits(limits: ParserLimits) {
+    LIMITS.with(|cell| *cell.borrow_mut() = limits);
+}
+
+/// Checks a manifest store against the limits set by the application
+pub fn check(store_bytes: &[u8]) -> Result<()> {
+    check_with(store_bytes, &LIMITS.with(|cell| *cell.borrow()))
+}
+
+/// Checks the manifest store of an asset, if it has one that can be found
+pub fn check_asset(data: &[u8], mime_type: &str) -> Result<()> {
+    match c2pa::jumbf_io::load_jumbf_from_memory(mime_type, data) {
+        Ok(store_bytes) => check(&store_bytes),
+        // The c2pa crate reports stores it can't find itself
+        Err(_) => Ok(()),
+    }
+}
+
+/// Loads the manifest store of an asset and checks it against the limits
+pub fn load(data: &[u8], mime_type: &str) -> Result<Vec<u8>> {
+    let store_bytes = c2pa::jumbf_io::load_jumbf_from_memory(mime_type, data)?;
+    check(&store_bytes)?;
+    Ok(store_bytes)
+}
+
+/// Checks a manifest store against the limits before parsing it, for the toolkit's own readers of
+/// the stores that the c2pa crate doesn't expose
+pub fn parse(store_bytes: &[u8]) -> Result<SuperBox<'_>> {
+    check(store_bytes)?;
+    SuperBox::parse(store_bytes)
+}
+
+fn exceeds(limit: &'static str, observed: u64, max: u64) -> Result<()> {
+    if observed > max {
+        Err(Error::LimitExceeded {
+            limit,
+            observed,
+            max,
+        })
+    } else {
+        Ok(())
+    }
+}
+
+/// Walks the boxes of a store, descending into superboxes
+///
+/// Malformed headers end the walk of the superbox they are in, since the c2pa crate reports them.
+fn check_with(store_bytes: &[u8], limits: &ParserLimits) -> Result<()> {
+    exceeds(
+        "maxStoreBytes",
+        store_bytes.len() as u64,
+        limits.max_store_bytes,
+    )?;
+
+    let mut boxes: u64 = 0;
+    // The contents of each superbox yet to be walked, along with its depth
+    let mut pending = vec![(store_bytes, 0)];
+    while let Some((mut data, depth)) = pending.pop() {
+        while let Some((declared, header_len)) = declared_len(data) {
+            boxes += 1;
+            exceeds("maxBoxes", boxes, limits.max_boxes)?;
+
+            let available = data.len() as u64;
+            if declared > available {
+                let allowed = (available as f64 * limits.max_declared_size_ratio) as u64;
+                exceeds("maxDeclaredSizeRatio", declared, allowed)?;
+                break;
+            }
+            if declared < header_len as u64 {
+                break;
+            }
+
+            let (jumbf_box, rest) = data.split_at(declared as usize);
+            if &jumbf_box[4..8] == JUMB {
+                exceeds("maxBoxDepth", depth + 1, limits.max_box_depth)?;
+                pending.push((&jumbf_box[header_len..], depth + 1));
+            }
+            data = rest;
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads the length a box header declares, along with the length of the header
+fn declared_len(data: &[u8]) -> Option<(u64, usize)> {
+    let lbox = u32::from_be_bytes(data.get(0..4)?.try_into().ok()?) as u64;
+    data.get(4..8)?;
+    match lbox {
+        0 => Some((data.len() as u64, 8)),
+        1 => Some((u64::from_be_bytes(data.get(8..16)?.try_into().ok()?), 16)),
+        len => Some((len, 8)),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::jumbf::tests::{content_box, store, super_box, uuid_of};
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
+
+    fn limit_exceeded(result: Result<()>) -> (&'static str, u64, u64) {
+        match result {
+            Err(Error::LimitExceeded {
+                limit,
+                observed,
+                max,
+            }) => (limit, observed, max),
+            result => panic!("expected LimitExceeded, got {:?}", result),
+        }
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_adversarial_stores() {
+        let limits = ParserLimits::default();
+
+        // Thousands of tiny boxes
+        let boxes: Vec<Vec<u8>> = (0..limits.max_boxes)
+            .map(|_| content_box(*b"free", b""))
+            .collect();
+        let crowded = store(&[super_box(uuid_of(*b"c2ma"), "crowded", &boxes)]);
+        assert_eq!(
+            limit_exceeded(check_with(&crowded, &limits)),
+            ("maxBoxes", limits.max_boxes + 1, limits.max_boxes)
+        );
+
+        // Superboxes nested one level deeper than allowed
+        let mut nested = content_box(*b"json", b"{}");
+        for _ in 0..limits.max_box_depth + 1 {
+            nested = super_box(uuid_of(*b"c2as"), "nested", &[nested]);
+        }
+        assert_eq!(
+            limit_exceeded(check_with(&nested, &limits)),
+            (
+                "maxBoxDepth",
+                limits.max_box_depth + 1,
+                limits.max_box_depth
+            )
+        );
+
+        // A box claiming 4 GiB, with a 64 bit length
+        let mut oversized = store(&[content_box(*b"json", b"{}")]);
+        let declared: u64 = 4 * 1024 * 1024 * 1024;
+        let mut huge = 1u32.to_be_bytes().to_vec();
+        huge.extend_from_slice(b"json");
+        huge.extend_from_slice(&declared.to_be_bytes());
+        oversized.extend_from_slice(&huge);
+        let len = oversized.len() as u32;
+        oversized[..4].copy_from_slice(&len.to_be_bytes());
+        let (limit, observed, max) = limit_exceeded(check_with(&oversized, &limits));
+        assert_eq!((limit, observed), ("maxDeclaredSizeRatio", declared));
+        assert!(max < 100);
+
+        // A larger ratio tolerates truncated boxes, which are left for the c2pa crate to report
+        let mut truncated = store(&[content_box(*b"json", b"{}")]);
+        truncated.truncate(truncated.len() - 1);
+        assert!(check_with(&truncated, &limits).is_err());
+        let lenient = ParserLimits {
+            max_declared_size_ratio: 2.0,
+            ..limits
+        };
+        assert!(check_with(&truncated, &lenient).is_ok());
+
+        let small = ParserLimits {
+            max_store_bytes: 16,
+            ..limits
+        };
+        assert_eq!(
+            limit_exceeded(check_with(&store(&[]), &small)).0,
+            "maxStoreBytes"
+        );
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_toolkit_parsers_checked() {
+        use crate::manifest_store::{get_manifest_order, get_unsupported_algs};
+
+        let limits = ParserLimits::default();
+        let boxes: Vec<Vec<u8>> = (0..limits.max_boxes)
+            .map(|_| content_box(*b"free", b""))
+            .collect();
+        let crowded = store(&[super_box(uuid_of(*b"c2ma"), "crowded", &boxes)]);
+
+        // Readers of the store that the c2pa crate never sees
+        assert_eq!(
+            limit_exceeded(get_manifest_order(&crowded).map(drop)).0,
+            "maxBoxes"
+        );
+        assert_eq!(
+            limit_exceeded(get_unsupported_algs(&crowded).map(drop)).0,
+            "maxBoxes"
+        );
+        assert_eq!(
+            limit_exceeded(crate::utf8::repair_store(&crowded).map(drop)).0,
+            "maxBoxes"
+        );
+        assert_eq!(limit_exceeded(parse(&crowded).map(drop)).0, "maxBoxes");
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_real_stores_have_headroom() {
+        // A tenth of each default still passes
+        let defaults = ParserLimits::default();
+        let tight = ParserLimits {
+            max_boxes: defaults.max_boxes / 10,
+            max_box_depth: defaults.max_box_depth / 4,
+            max_declared_size_ratio: 1.0,
+            max_store_bytes: defaults.max_store_bytes / 10,
+        };
+
+        let fixtures: &[(&str, &[u8])] = &[
+            (
+                "image/jpeg",
+                include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg"),
+            ),
+            (
+                "image/jpeg",
+                include_bytes!("../../../tools/testing/fixtures/images/CAIXCI.jpg"),
+            ),
+            (
+                "image/jpeg",
+                include_bytes!("../../../tools/testing/fixtures/images/E-dat-CA.jpg"),
+            ),
+            (
+                "image/jpeg",
+                include_bytes!("../../../tools/testing/fixtures/images/firefly-1.jpg"),
+            ),
+        ];
+        for (mime_type, asset) in fixtures {
+            let store_bytes = c2pa::jumbf_io::load_jumbf_from_memory(mime_type, asset).unwrap();
+            check_with(&store_bytes, &tight).unwrap();
+            check_with(&store_bytes, &defaults).unwrap();
+        }
+    }
+}
@@ -0,0 +1,178 @@
+// Copyright 2024 Adobe
+// All Rights Reserved.
+//
+// NOTICE: Adobe permits you to use, modify, and distribute this file in
+// accordance with the terms of the Adobe license agreement accompanying
+// it.
+
+//! Picks display strings out of localized values for a requested locale.
+//!
+//! A localized value is an array of `{ "lang": "<BCP 47 tag>", "value": "..." }` objects. The best
+//! match for a locale is, in order:
+//! 1. the entry whose tag matches the locale exactly (ignoring case),
+//! 2. the entry tagged with just the locale's language (`fr` for `fr-CA`),
+//! 3. the first entry in the same language (`fr-FR` for `fr-CA`),
+//! 4. the default entry, tagged `x-default`, or else the first entry.
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+const DEFAULT_LANG: &str = "x-default";
+
+/// Finds every localized value in `value` and picks the best match for `locale` from each
+///
+/// The results are keyed by the JSON pointer of the localized value within `value`.
+pub fn display_strings(value: &Value, locale: &str) -> BTreeMap<String, String> {
+    let mut result = BTreeMap::new();
+    let mut pending = vec![(String::new(), value)];
+
+    while let Some((pointer, value)) = pending.pop() {
+        if let Some(entries) = localized_entries(value) {
+            if let Some(display_string) = best_match(&entries, locale) {
+                result.insert(pointer, display_string.to_owned());
+            }
+            continue;
+        }
+
+        match value {
+            Value::Array(values) => pending.extend(
+                values
+                    .iter()
+                    .enumerate()
+                    .map(|(index, value)| (format!("{}/{}", pointer, index), value)),
+            ),
+            Value::Object(map) => pending.extend(
+                map.iter()
+                    .map(|(key, value)| (format!("{}/{}", pointer, escape(key)), value)),
+            ),
+            _ => {}
+        }
+    }
+
+    result
+}
+
+/// Picks the value that best matches `locale` from `(lang, value)` pairs
+pub fn best_match<'a>(entries: &[(&str, &'a str)], locale: &str) -> Option<&'a str> {
+    let language = primary_language(locale);
+
+    let exact = || {
+        entries
+            .iter()
+            .find(|(lang, _)| lang.eq_ignore_ascii_case(locale))
+    };
+    let language_only = || {
+        entries
+            .iter()
+            .find(|(lang, _)| lang.eq_ignore_ascii_case(language))
+    };
+    let same_language = || {
+        entries
+            .iter()
+            .find(|(lang, _)| primary_language(lang).eq_ignore_ascii_case(language))
+    };
+    let default = || {
+        entries
+            .iter()
+            .find(|(lang, _)| lang.eq_ignore_ascii_case(DEFAULT_LANG))
+            .or_else(|| entries.first())
+    };
+
+    exact()
+        .or_else(language_only)
+        .or_else(same_language)
+        .or_else(default)
+        .map(|(_, value)| *value)
+}
+
+/// Returns the `(lang, value)` pairs of a localized value, or `None` if it isn't one
+fn localized_entries(value: &Value) -> Option<Vec<(&str, &str)>> {
+    let values = match value {
+        Value::Array(values) if !values.is_empty() => values,
+        _ => return None,
+    };
+
+    values
+        .iter()
+        .map(|entry| match (entry.get("lang"), entry.get("value")) {
+            (Some(Value::String(lang)), Some(Value::String(value))) => {
+                Some((lang.as_str(), value.as_str()))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+fn primary_language(tag: &str) -> &str {
+    tag.split(['-', '_']).next().unwrap_or(tag)
+}
+
+/// Escapes a key for use in a JSON pointer (RFC 6901)
+fn escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
+
+    const ENTRIES: &[(&str, &str)] = &[
+        ("en-US", "Color adjustments"),
+        ("fr-FR", "Réglages des couleurs (France)"),
+        ("fr", "Réglages des couleurs"),
+        ("pt-BR", "Ajustes de cor (Brasil)"),
+        ("pt-PT", "Ajustes de cor (Portugal)"),
+        ("x-default", "Colour adjustments"),
+    ];
+
+    #[wasm_bindgen_test]
+    pub fn test_best_match_order() {
+        // Exact matches win, regardless of case
+        assert_eq!(best_match(ENTRIES, "fr-FR"), Some(ENTRIES[1].1));
+        assert_eq!(best_match(ENTRIES, "PT-pt"), Some(ENTRIES[4].1));
+        // The language-only tag wins over other regions of the same language
+        assert_eq!(best_match(ENTRIES, "fr-CA"), Some(ENTRIES[2].1));
+        // Otherwise the first entry in the same language is used
+        assert_eq!(best_match(ENTRIES, "pt-AO"), Some(ENTRIES[3].1));
+        assert_eq!(best_match(ENTRIES, "en-GB"), Some(ENTRIES[0].1));
+        // Other languages get the default
+        assert_eq!(best_match(ENTRIES, "de-DE"), Some(ENTRIES[5].1));
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_best_match_fallbacks() {
+        let without_default = &ENTRIES[..5];
+        assert_eq!(best_match(without_default, "ja"), Some(ENTRIES[0].1));
+        assert_eq!(best_match(&[], "en"), None);
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_display_strings() {
+        let value = serde_json::json!({
+            "assertions": [{
+                "label": "c2pa.actions",
+                "data": {
+                    "actions": [{
+                        "action": "c2pa.color_adjustments",
+                        "description": [
+                            { "lang": "en", "value": "Color adjustments" },
+                            { "lang": "de", "value": "Farbanpassungen" },
+                        ],
+                    }],
+                },
+            }],
+            "claim_generator_info": [{ "name": "app/1.0" }],
+            "a/b": [{ "lang": "en", "value": "escaped" }],
+        });
+
+        let result = display_strings(&value, "de-AT");
+        assert_eq!(result.len(), 2);
+        assert_eq!(
+            result["/assertions/0/data/actions/0/description"],
+            "Farbanpassungen"
+        );
+        assert_eq!(result["/a~1b"], "escaped");
+    }
+}
@@ -4,37 +4,3411 @@
 // NOTICE: Adobe permits you to use, modify, and distribute this file in
 // accordance with the terms of the Adobe license agreement accompanying
 // it.
+use crate::cose::{self, CoseSign1};
 use crate::error::{Error, Result};
+use crate::jumbf::{self, SuperBox};
+use crate::limits;
+use crate::locale;
+use crate::mime::{is_unsupported_format, sniff_mime_type};
+use crate::ocsp::{self, RevocationInfo, RevocationStatus};
+use crate::options::{BinaryEncoding, TimestampSkew};
+use crate::segments::{JpegSegments, PngChunks, JPEG_APP11, JPEG_EOI, JPEG_SOS};
+use crate::timestamp::{self, TimestampCheck};
+use crate::trace::{Outcome, StepKind, TraceStep, Tracer};
+use crate::utf8;
+use crate::validation::{self, Severity};
+use base64::Engine;
+use c2pa::validation_status::ValidationStatus;
 use c2pa::ManifestStore;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use serde_bytes::ByteBuf;
+use serde_json::Value;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::convert::TryFrom;
+
+/// Identifies an ingredient of a manifest, either by position or by instance ID
+#[derive(Debug)]
+pub enum IngredientRef {
+    Index(usize),
+    InstanceId(String),
+}
 
 pub async fn get_manifest_store_data(data: &[u8], mime_type: &str) -> Result<ManifestStore> {
+    if is_unsupported_format(data) {
+        return Err(c2pa::Error::UnsupportedType.into());
+    }
+    limits::check_asset(data, mime_type)?;
+
     ManifestStore::from_bytes_async(mime_type, data, true)
         .await
         .map_err(Error::from)
 }
 
+/// How a manifest store was embedded in the asset, when it isn't the way the C2PA specification
+/// describes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EmbeddingFormat {
+    /// Base64 text in a PNG `iTXt` chunk, as early CAI experiments wrote it before `caBX`
+    LegacyItxt,
+    /// JPEG `APP11` segments after the end of image marker, as some camera firmware writes them
+    TrailingApp11,
+}
+
+/// Finds a manifest store embedded in a PNG `iTXt` chunk as base64 text
+///
+/// Chunks are recognized by their content, a manifest store, rather than by keyword. A PNG with a
+/// `caBX` chunk has a current-format store, so `None` is returned even if it has a legacy one too.
+pub fn legacy_png_store(data: &[u8]) -> Option<Vec<u8>> {
+    let mut legacy = None;
+    for chunk in PngChunks::new(data) {
+        match &chunk.chunk_type {
+            b"caBX" => return None,
+            b"iTXt" if legacy.is_none() => legacy = itxt_store(chunk.data(data)),
+            _ => {}
+        }
+    }
+
+    legacy
+}
+
+/// Decodes the text of an uncompressed `iTXt` chunk, if it is a base64 manifest store
+fn itxt_store(chunk: &[u8]) -> Option<Vec<u8>> {
+    // Keyword, then the compression flag and method, then the language tag and translated keyword
+    let keyword_end = chunk.iter().position(|b| *b == 0)?;
+    if *chunk.get(keyword_end + 1)? != 0 {
+        return None;
+    }
+    let mut text = chunk.get(keyword_end + 3..)?;
+    for _ in 0..2 {
+        text = &text[text.iter().position(|b| *b == 0)? + 1..];
+    }
+
+    let text: Vec<u8> = text
+        .iter()
+        .copied()
+        .filter(|b| !b.is_ascii_whitespace())
+        .collect();
+    let store = base64::engine::general_purpose::STANDARD
+        .decode(text)
+        .ok()?;
+    let is_store =
+        SuperBox::parse(&store).is_ok_and(|super_box| super_box.uuid == jumbf::STORE_UUID);
+    is_store.then_some(store)
+}
+
+/// Finds a manifest store in `APP11` segments that follow the end of image marker of a JPEG
+///
+/// The segments are put together the way the c2pa crate puts together those before the image
+/// data: the first carries the store's box header, and the following ones with the same box
+/// instance number repeat it, so it is skipped. Only segments directly after the marker are read.
+pub fn trailing_jpeg_store(data: &[u8]) -> Option<Vec<u8>> {
+    let segments = JpegSegments::at(data, jpeg_image_end(data)?)
+        .take_while(|segment| segment.marker == JPEG_APP11);
+    let mut store = Vec::new();
+    let mut instance = None;
+    let mut sequence = 0;
+    for segment in segments {
+        let segment = segment.data(data);
+        // The common identifier `JP`, the box instance number, the packet sequence number, and
+        // then the box header
+        let (en, z) = match (segment.get(2..4), segment.get(4..8)) {
+            (Some(en), Some(z)) => (en, z),
+            _ => continue,
+        };
+        let z = u32::from_be_bytes([z[0], z[1], z[2], z[3]]);
+        if instance == Some(en) && z > sequence {
+            store.extend_from_slice(segment.get(16..)?);
+            sequence = z;
+        } else if instance.is_none() && segment.get(24..28) == Some(b"c2pa") {
+            store.extend_from_slice(&segment[8..]);
+            instance = Some(en);
+            sequence = z;
+        }
+    }
+
+    let is_store =
+        SuperBox::parse(&store).is_ok_and(|super_box| super_box.uuid == jumbf::STORE_UUID);
+    is_store.then_some(store)
+}
+
+/// Returns the offset just past the end of image marker of a JPEG
+fn jpeg_image_end(data: &[u8]) -> Option<usize> {
+    let mut segments = JpegSegments::new(data);
+    loop {
+        let last = segments.last()?;
+        match last.marker {
+            JPEG_EOI => return Some(last.end()),
+            // The entropy-coded data runs to the next marker other than a stuffed zero or a
+            // restart marker
+            JPEG_SOS => {
+                let scan = data.get(last.end()..)?.windows(2).position(|pair| {
+                    pair[0] == 0xFF && pair[1] != 0 && !(0xD0..=0xD7).contains(&pair[1])
+                })?;
+                segments = JpegSegments::at(data, last.end() + scan);
+            }
+            // The walk stopped at data that isn't a marker, or a segment that doesn't fit
+            _ => return None,
+        }
+    }
+}
+
+/// The form a separately supplied manifest store came in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SidecarFormat {
+    /// A bare manifest store, as this toolkit and c2patool write `.c2pa` files
+    Store,
+    /// A manifest store nested in another JUMBF superbox
+    Wrapped,
+    /// A manifest store preceded by other data, such as a metadata header
+    Prefixed,
+}
+
+/// Finds the manifest store in a separately supplied manifest, returning it along with the form
+/// it came in
+///
+/// Data that doesn't hold a recognizable manifest store is returned unchanged, without a form, so
+/// that the c2pa crate reports the problem.
+pub fn normalize_sidecar(manifest_bytes: &[u8]) -> (&[u8], Option<SidecarFormat>) {
+    if let Ok(super_box) = SuperBox::parse(manifest_bytes) {
+        if super_box.uuid == jumbf::STORE_UUID {
+            return (super_box.bytes, Some(SidecarFormat::Store));
+        }
+
+        let mut pending: VecDeque<&SuperBox> = super_box.children.iter().collect();
+        while let Some(child) = pending.pop_front() {
+            if child.uuid == jumbf::STORE_UUID {
+                return (child.bytes, Some(SidecarFormat::Wrapped));
+            }
+            pending.extend(child.children.iter());
+        }
+    }
+
+    // A store starts with its own size, `jumb`, and then its description box, whose size is
+    // followed by `jumd` and the store's type UUID
+    let store_start = manifest_bytes.windows(32).position(|window| {
+        &window[4..8] == b"jumb" && &window[12..16] == b"jumd" && window[16..] == jumbf::STORE_UUID
+    });
+    if let Some(store) =
+        store_start.and_then(|start| SuperBox::parse(&manifest_bytes[start..]).ok())
+    {
+        if store.uuid == jumbf::STORE_UUID {
+            return (store.bytes, Some(SidecarFormat::Prefixed));
+        }
+    }
+
+    (manifest_bytes, None)
+}
+
 pub async fn get_manifest_store_data_from_manifest_and_asset_bytes(
     manifest_bytes: &[u8],
     format: &str,
     asset_bytes: &[u8],
 ) -> Result<ManifestStore> {
+    if is_unsupported_format(asset_bytes) {
+        return Err(c2pa::Error::UnsupportedType.into());
+    }
+    limits::check(manifest_bytes)?;
+
     ManifestStore::from_manifest_and_asset_bytes_async(manifest_bytes, format, asset_bytes)
         .await
         .map_err(Error::from)
 }
 
-#[cfg(test)]
-pub mod tests {
-    use super::*;
-    use wasm_bindgen_test::*;
+/// A structural defect of a manifest store that was tolerated by lenient parsing
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ParseWarning {
+    pub code: &'static str,
+    /// The JUMBF URI of the box with the defect
+    pub location: String,
+}
 
-    wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
+/// The description box of the store doesn't mark it as requestable
+pub const DESCRIPTION_NOT_REQUESTABLE: &str = "jumbf.description.notRequestable";
+/// The description box of the store has data after its label that none of its toggles account for
+pub const DESCRIPTION_TRAILING_DATA: &str = "jumbf.description.trailingData";
+/// The store holds a box other than a superbox, such as padding
+pub const UNEXPECTED_BOX: &str = "jumbf.unexpectedBox";
+/// The store is in `APP11` segments after the end of a JPEG's image data
+pub const TRAILING_APP11: &str = "jpeg.trailingApp11";
 
-    #[wasm_bindgen_test]
-    pub async fn test_manifest_store_data() {
-        let test_asset = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+/// Repairs the defects of a manifest store that the c2pa crate rejects but lenient parsing
+/// tolerates, returning the repaired store along with a warning for each defect
+///
+/// Returns `None` if the store has none of these defects. Only the store's own superbox is
+/// repaired: no hash or signature covers it, so the manifests are validated exactly as written.
+pub fn repair_store(store_bytes: &[u8]) -> Result<Option<(Vec<u8>, Vec<ParseWarning>)>> {
+    let store = limits::parse(store_bytes)?;
+    if store.uuid != jumbf::STORE_UUID {
+        return Ok(None);
+    }
 
-        let result = get_manifest_store_data(test_asset, "image/jpeg").await;
-        assert!(result.is_ok());
+    let location = format!("self#jumbf=/{}", store.label.as_deref().unwrap_or("c2pa"));
+    let (description, defects) = store.repair_description();
+    let codes = [
+        (defects.not_requestable, DESCRIPTION_NOT_REQUESTABLE),
+        (defects.trailing_data, DESCRIPTION_TRAILING_DATA),
+    ];
+    let warnings: Vec<_> = codes
+        .iter()
+        .filter(|(found, _)| *found)
+        .map(|(_, code)| *code)
+        .chain(store.boxes.iter().map(|_| UNEXPECTED_BOX))
+        .map(|code| ParseWarning {
+            code,
+            location: location.clone(),
+        })
+        .collect();
+    if warnings.is_empty() {
+        return Ok(None);
+    }
+
+    // Boxes other than superboxes are dropped, keeping the order of the rest
+    let mut contents = vec![description.as_slice()];
+    contents.extend(store.children.iter().map(|child| child.bytes));
+
+    Ok(Some((jumbf::build_box(jumbf::JUMB, &contents), warnings)))
+}
+
+/// The warning for a store found with `trailing_jpeg_store`
+pub fn trailing_app11_warning(store_bytes: &[u8]) -> ParseWarning {
+    let label = limits::parse(store_bytes)
+        .ok()
+        .and_then(|store| store.label)
+        .unwrap_or_else(|| "c2pa".to_owned());
+    ParseWarning {
+        code: TRAILING_APP11,
+        location: format!("self#jumbf=/{}", label),
+    }
+}
+
+/// Why a manifest of the store couldn't be read
+#[derive(Debug, Clone, Serialize)]
+pub struct ManifestError {
+    /// The name of the error, as JavaScript errors thrown by the toolkit have it
+    pub code: String,
+    pub message: String,
+}
+
+/// Finds the manifests other than the active one that fail to parse, returning the store without
+/// them along with the error of each, keyed by label
+///
+/// Each manifest is read on its own to tell which ones fail. Returns `None` if none of them do, in
+/// which case the failure lies with the active manifest or the store itself.
+pub async fn contain_manifest_errors(
+    store_bytes: &[u8],
+    mime_type: &str,
+    asset: &[u8],
+) -> Result<Option<(Vec<u8>, HashMap<String, ManifestError>)>> {
+    let store = limits::parse(store_bytes)?;
+    let manifests: Vec<&SuperBox> = jumbf::manifests(&store).collect();
+    let (active, others) = match manifests.split_last() {
+        Some(split) => split,
+        None => return Ok(None),
+    };
+
+    let mut errors = HashMap::new();
+    let mut readable = Vec::new();
+    for manifest in others {
+        let alone = jumbf::build_store(&store, &[manifest]);
+        match get_manifest_store_data_from_manifest_and_asset_bytes(&alone, mime_type, asset).await
+        {
+            Ok(_) => readable.push(*manifest),
+            Err(err) => {
+                let label = manifest.label.clone().unwrap_or_default();
+                warn!(
+                    "Leaving out manifest {}, which can't be read: {}",
+                    label, err
+                );
+                errors.insert(
+                    label,
+                    ManifestError {
+                        code: format!("{:?}", err),
+                        message: err.to_string(),
+                    },
+                );
+            }
+        }
+    }
+    if errors.is_empty() {
+        return Ok(None);
+    }
+    readable.push(active);
+
+    Ok(Some((jumbf::build_store(&store, &readable), errors)))
+}
+
+/// Rebuilds a manifest store with the manifest labeled `label` as its active manifest, leaving out
+/// the manifests that come after it
+///
+/// Earlier manifests are kept, since only they can be its ingredients.
+pub fn with_active_manifest(store_bytes: &[u8], label: &str) -> Result<Vec<u8>> {
+    let store = limits::parse(store_bytes)?;
+    let manifests: Vec<&SuperBox> = jumbf::manifests(&store).collect();
+    match manifests
+        .iter()
+        .position(|manifest| manifest.label.as_deref() == Some(label))
+    {
+        Some(index) => Ok(jumbf::build_store(&store, &manifests[..=index])),
+        None => Err(Error::ActiveManifestNotFound {
+            label: label.to_owned(),
+            available: manifests
+                .iter()
+                .filter_map(|manifest| manifest.label.clone())
+                .collect(),
+        }),
+    }
+}
+
+/// How deep the provenance chain is followed unless a limit is given
+pub const DEFAULT_MAX_DEPTH: usize = 32;
+
+/// The parts of the provenance chain that lie beyond the depth limit
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Truncation {
+    /// The manifests that are only reachable beyond the limit
+    pub omitted: BTreeSet<String>,
+    /// The `(manifest label, ingredient index)` of each ingredient whose manifest was omitted
+    pub truncated: Vec<(String, usize)>,
+}
+
+impl Truncation {
+    pub fn is_empty(&self) -> bool {
+        self.omitted.is_empty() && self.truncated.is_empty()
+    }
+}
+
+/// Finds the manifests that are more than `max_depth` ingredients away from the active manifest
+pub fn truncate_provenance(manifest_store: &ManifestStore, max_depth: usize) -> Truncation {
+    match manifest_store.active_label() {
+        Some(active_label) => truncate(active_label, max_depth, |label| {
+            manifest_store
+                .get(label)
+                .map(|manifest| {
+                    manifest
+                        .ingredients()
+                        .iter()
+                        .map(|ingredient| ingredient.active_manifest())
+                        .collect()
+                })
+                .unwrap_or_default()
+        }),
+        None => Truncation::default(),
+    }
+}
+
+/// Walks the provenance chain breadth first with a worklist, so its depth is bounded by
+/// `max_depth` rather than by the stack
+///
+/// `ingredients` returns the manifest label (if any) of each ingredient of a manifest.
+fn truncate<'a>(
+    active_label: &'a str,
+    max_depth: usize,
+    ingredients: impl Fn(&'a str) -> Vec<Option<&'a str>>,
+) -> Truncation {
+    let mut included = HashSet::new();
+    let mut boundary = Vec::new();
+    let mut pending = VecDeque::from([(active_label, 0)]);
+    while let Some((label, depth)) = pending.pop_front() {
+        if !included.insert(label) {
+            continue;
+        }
+        for (index, ingredient_label) in ingredients(label).into_iter().enumerate() {
+            match ingredient_label {
+                Some(ingredient_label) if depth < max_depth => {
+                    pending.push_back((ingredient_label, depth + 1))
+                }
+                Some(ingredient_label) => boundary.push((label, index, ingredient_label)),
+                None => {}
+            }
+        }
+    }
+
+    // Everything reachable past the boundary that isn't included by a shorter path is omitted
+    let mut truncation = Truncation::default();
+    let mut pending = Vec::new();
+    for (label, index, ingredient_label) in boundary {
+        if !included.contains(ingredient_label) {
+            truncation.truncated.push((label.to_owned(), index));
+            pending.push(ingredient_label);
+        }
+    }
+    while let Some(label) = pending.pop() {
+        if included.contains(label) || !truncation.omitted.insert(label.to_owned()) {
+            continue;
+        }
+        pending.extend(ingredients(label).into_iter().flatten());
+    }
+
+    truncation
+}
+
+/// One or more assertion labels to match
+///
+/// A label ending in `*` matches every label starting with the part before it.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum LabelQuery {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl LabelQuery {
+    fn matches(&self, label: &str) -> bool {
+        let matches_one = |pattern: &String| match pattern.strip_suffix('*') {
+            Some(prefix) => label.starts_with(prefix),
+            None => label == pattern,
+        };
+
+        match self {
+            Self::One(pattern) => matches_one(pattern),
+            Self::Many(patterns) => patterns.iter().any(matches_one),
+        }
+    }
+}
+
+/// An assertion matched by [`get_assertions`]
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssertionMatch {
+    pub manifest_label: String,
+    pub assertion_label: String,
+    pub data: Value,
+    pub is_from_active_manifest: bool,
+}
+
+/// Finds the assertions matching `query` across all manifests of the store
+///
+/// Manifests are visited along the provenance chain, starting with the active manifest and
+/// following each ingredient's manifest, followed by any manifests the chain doesn't reference.
+pub async fn get_assertions(
+    data: &[u8],
+    mime_type: &str,
+    query: &LabelQuery,
+) -> Result<Vec<AssertionMatch>> {
+    limits::check_asset(data, mime_type)?;
+    let manifest_store = ManifestStore::from_bytes_async(mime_type, data, false).await?;
+    let active_label = manifest_store.active_label();
+
+    let mut visited = HashSet::new();
+    let mut pending: VecDeque<&str> = active_label.into_iter().collect();
+    let mut unreferenced: Vec<&str> = manifest_store
+        .manifests()
+        .keys()
+        .map(String::as_str)
+        .collect();
+    unreferenced.sort_unstable();
+
+    let mut matches = Vec::new();
+    while let Some(label) = pending.pop_front().or_else(|| unreferenced.pop()) {
+        if !visited.insert(label) {
+            continue;
+        }
+        let manifest = match manifest_store.get(label) {
+            Some(manifest) => manifest,
+            None => continue,
+        };
+
+        for assertion in manifest.assertions() {
+            if !query.matches(assertion.label()) {
+                continue;
+            }
+            // Binary assertions have no JSON data to return
+            if let Ok(data) = assertion.value() {
+                matches.push(AssertionMatch {
+                    manifest_label: label.to_owned(),
+                    assertion_label: assertion.label_with_instance(),
+                    data: data.clone(),
+                    is_from_active_manifest: Some(label) == active_label,
+                });
+            }
+        }
+
+        pending.extend(
+            manifest
+                .ingredients()
+                .iter()
+                .filter_map(|ingredient| ingredient.active_manifest()),
+        );
+    }
+
+    Ok(matches)
+}
+
+/// Returns the data of the assertion labeled `assertion_label` in the manifest labeled
+/// `manifest_label`
+///
+/// `assertion_label` includes the instance suffix, as in [`AssertionMatch::assertion_label`].
+pub async fn get_assertion(
+    data: &[u8],
+    mime_type: &str,
+    manifest_label: &str,
+    assertion_label: &str,
+) -> Result<Value> {
+    limits::check_asset(data, mime_type)?;
+    let manifest_store = ManifestStore::from_bytes_async(mime_type, data, false).await?;
+    let manifest = manifest_store
+        .get(manifest_label)
+        .ok_or(Error::ManifestNotFound)?;
+    let assertion = manifest
+        .assertions()
+        .iter()
+        .find(|assertion| assertion.label_with_instance() == assertion_label)
+        .ok_or_else(|| Error::AssertionNotFound(assertion_label.to_owned()))?;
+
+    Ok(assertion.value()?.clone())
+}
+
+/// Converts a manifest store to JSON, leaving out the data of every assertion larger than
+/// `max_bytes`
+///
+/// The size of an assertion is the length of its data as JSON. Assertions that are too large keep
+/// their `label`, `instance` and `kind`, and get `truncated` and `size` instead of `data`. They can
+/// be read in full with [`get_assertion`].
+pub fn bound_assertions(manifest_store: &ManifestStore, max_bytes: usize) -> Result<Value> {
+    let mut json =
+        serde_json::to_value(manifest_store).map_err(|_err| Error::JavaScriptConversion)?;
+
+    let manifests = json
+        .get_mut("manifests")
+        .and_then(Value::as_object_mut)
+        .into_iter()
+        .flat_map(|manifests| manifests.values_mut());
+    for manifest in manifests {
+        let assertions = manifest
+            .get_mut("assertions")
+            .and_then(Value::as_array_mut)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_object_mut);
+        for assertion in assertions {
+            let size = match assertion.get("data") {
+                Some(data) => serde_json::to_vec(data)
+                    .map_err(|_err| Error::JavaScriptConversion)?
+                    .len(),
+                None => continue,
+            };
+            if size > max_bytes {
+                assertion.remove("data");
+                assertion.insert("truncated".to_owned(), Value::Bool(true));
+                assertion.insert("size".to_owned(), size.into());
+            }
+        }
+    }
+
+    Ok(json)
+}
+
+/// The parts of a manifest's `COSE_Sign1` signature needed to verify it independently
+///
+/// `signature` is computed over `to_be_signed`, the `Sig_structure` built from `protected_header`
+/// and `payload` (the claim), using the first of `certificates`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignatureMaterial {
+    #[serde(with = "serde_bytes")]
+    pub protected_header: Vec<u8>,
+    #[serde(with = "serde_bytes")]
+    pub payload: Vec<u8>,
+    #[serde(with = "serde_bytes")]
+    pub signature: Vec<u8>,
+    #[serde(with = "serde_bytes")]
+    pub to_be_signed: Vec<u8>,
+    pub certificates: Vec<ByteBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alg: Option<String>,
+}
+
+/// Extracts the signature material of every manifest in the store, keyed by manifest label
+pub fn get_signature_material(
+    data: &[u8],
+    mime_type: &str,
+) -> Result<BTreeMap<String, SignatureMaterial>> {
+    let store_bytes = limits::load(data, mime_type)?;
+    let store = SuperBox::parse(&store_bytes)?;
+    let mut result = BTreeMap::new();
+
+    for manifest in jumbf::manifests(&store) {
+        let (label, signature) = match (
+            &manifest.label,
+            manifest
+                .find(jumbf::SIGNATURE_LABEL)
+                .and_then(SuperBox::payload),
+        ) {
+            (Some(label), Some(signature)) => (label, signature),
+            _ => continue,
+        };
+
+        let signature = CoseSign1::from_slice(signature)?;
+        // C2PA signatures are detached from the claim they sign
+        let payload = match signature.payload.clone() {
+            Some(payload) => payload,
+            None => manifest
+                .claim()
+                .and_then(SuperBox::payload)
+                .ok_or(Error::InvalidJumbf("missing claim"))?
+                .to_vec(),
+        };
+
+        result.insert(
+            label.clone(),
+            SignatureMaterial {
+                certificates: signature
+                    .certificate_chain()
+                    .into_iter()
+                    .map(|cert| ByteBuf::from(cert.to_vec()))
+                    .collect(),
+                alg: signature.alg().map(|alg| alg.to_string()),
+                to_be_signed: cose::sig_structure(&signature.protected_bytes, &payload),
+                protected_header: signature.protected_bytes,
+                payload,
+                signature: signature.signature,
+            },
+        );
+    }
+
+    Ok(result)
+}
+
+/// The labels of the manifests in the order they appear in the store
+pub fn get_manifest_order(store_bytes: &[u8]) -> Result<Vec<String>> {
+    let store = limits::parse(store_bytes)?;
+
+    Ok(jumbf::manifests(&store)
+        .filter_map(|manifest| manifest.label.clone())
+        .collect())
+}
+
+/// The signed claim of a manifest
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RawClaim {
+    pub raw_claim_bytes: RawBytes,
+    pub claim_cddl_version: u32,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum RawBytes {
+    Bytes(ByteBuf),
+    Base64(String),
+}
+
+impl RawBytes {
+    pub fn new(bytes: &[u8], encoding: BinaryEncoding) -> Self {
+        match encoding {
+            BinaryEncoding::ArrayBuffer => Self::Bytes(ByteBuf::from(bytes)),
+            BinaryEncoding::Base64 => {
+                Self::Base64(base64::engine::general_purpose::STANDARD.encode(bytes))
+            }
+        }
+    }
+}
+
+/// Extracts the claim of every manifest in the store exactly as it was signed, keyed by manifest label
+pub fn get_raw_claims(
+    store_bytes: &[u8],
+    encoding: BinaryEncoding,
+) -> Result<HashMap<String, RawClaim>> {
+    let store = limits::parse(store_bytes)?;
+    let mut result = HashMap::new();
+
+    for manifest in jumbf::manifests(&store) {
+        let (label, claim) = match (&manifest.label, manifest.claim()) {
+            (Some(label), Some(claim)) => (label, claim),
+            _ => continue,
+        };
+        let claim_bytes = claim
+            .payload()
+            .ok_or(Error::InvalidJumbf("missing claim"))?;
+
+        result.insert(
+            label.clone(),
+            RawClaim {
+                raw_claim_bytes: RawBytes::new(claim_bytes, encoding),
+                claim_cddl_version: jumbf::claim_version(claim),
+            },
+        );
+    }
+
+    Ok(result)
+}
+
+/// The hash algorithm of a manifest's hard binding
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HardBindingAlg {
+    pub hard_binding_alg: String,
+}
+
+/// Finds the hash algorithm each manifest's hard binding uses, keyed by manifest label
+///
+/// Manifests without a hard binding, such as update manifests, are left out.
+pub fn get_hard_binding_algs(store_bytes: &[u8]) -> Result<HashMap<String, HardBindingAlg>> {
+    let store = limits::parse(store_bytes)?;
+    let mut result = HashMap::new();
+
+    for manifest in jumbf::manifests(&store) {
+        let (label, assertions) = match (&manifest.label, manifest.find(jumbf::ASSERTIONS_LABEL)) {
+            (Some(label), Some(assertions)) => (label, assertions),
+            _ => continue,
+        };
+        let hard_binding = assertions.children.iter().find(|assertion| {
+            assertion
+                .label
+                .as_deref()
+                .is_some_and(is_hard_binding_label)
+        });
+        let hard_binding = match hard_binding.and_then(SuperBox::payload) {
+            Some(hard_binding) => hard_binding,
+            None => continue,
+        };
+
+        // The binding's own algorithm takes precedence over the claim's
+        let parse = |payload: &[u8]| utf8::from_cbor::<serde_cbor::Value>(payload).ok();
+        let alg = parse(hard_binding)
+            .and_then(|hard_binding| cbor_text(&hard_binding, "alg"))
+            .or_else(|| {
+                manifest
+                    .claim()
+                    .and_then(SuperBox::payload)
+                    .and_then(parse)
+                    .and_then(|claim| cbor_text(&claim, "alg"))
+            });
+
+        if let Some(hard_binding_alg) = alg {
+            result.insert(label.clone(), HardBindingAlg { hard_binding_alg });
+        }
+    }
+
+    Ok(result)
+}
+
+/// Returns `true` for the labels of hard binding assertions (`c2pa.hash.data`, `c2pa.hash.boxes`,
+/// and the versions of `c2pa.hash.bmff`)
+fn is_hard_binding_label(label: &str) -> bool {
+    let label = label.split("__").next().unwrap_or(label);
+    matches!(label, "c2pa.hash.data" | "c2pa.hash.boxes") || label.starts_with("c2pa.hash.bmff")
+}
+
+/// A hash that identifies an asset, as hex
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct IdentityHash {
+    pub alg: String,
+    pub hash: String,
+}
+
+/// A hard binding of the active manifest along with the hashes it declares, as hex
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct BindingHash {
+    pub label: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alg: Option<String>,
+    /// A single hash for data and BMFF hashes, and one per box for box hashes
+    pub hashes: Vec<String>,
+}
+
+/// What identifies an asset, for keying cached verification results
+#[derive(Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum AssetIdentity {
+    /// The hashes the active manifest's hard bindings declare, and the hash of the manifest store
+    #[serde(rename_all = "camelCase")]
+    Manifest {
+        active_manifest: String,
+        bindings: Vec<BindingHash>,
+        manifest_store: IdentityHash,
+    },
+    /// A fast hash of the bytes of an asset without a manifest store, which is not cryptographic
+    #[serde(rename_all = "camelCase")]
+    Fingerprint {
+        fingerprint: IdentityHash,
+        cryptographic: bool,
+    },
+}
+
+/// Identifies an asset by its manifest store, without validating it
+///
+/// The hard binding hashes are the ones the active manifest declares, not hashes of the asset, so
+/// the identity only stands for the asset once a verification has found them to match. The active
+/// manifest is the last one in the store. Assets without a manifest store are identified by the
+/// XXH3 hash of their bytes instead.
+pub fn get_asset_identity(data: &[u8], mime_type: &str) -> Result<AssetIdentity> {
+    if is_unsupported_format(data) {
+        return Err(c2pa::Error::UnsupportedType.into());
+    }
+
+    let store_bytes = match c2pa::jumbf_io::load_jumbf_from_memory(mime_type, data) {
+        Ok(store_bytes) => store_bytes,
+        Err(c2pa::Error::JumbfNotFound | c2pa::Error::ProvenanceMissing) => {
+            return Ok(AssetIdentity::Fingerprint {
+                fingerprint: IdentityHash {
+                    alg: "xxh3-64".to_owned(),
+                    hash: format!("{:016x}", xxhash_rust::xxh3::xxh3_64(data)),
+                },
+                cryptographic: false,
+            });
+        }
+        Err(err) => return Err(err.into()),
+    };
+
+    let store = limits::parse(&store_bytes)?;
+    let manifest = jumbf::manifests(&store)
+        .last()
+        .ok_or(Error::ManifestNotFound)?;
+    let active_manifest = manifest.label.clone().ok_or(Error::ManifestNotFound)?;
+
+    let parse = |payload: &[u8]| utf8::from_cbor::<serde_cbor::Value>(payload).ok();
+    let claim_alg = manifest
+        .claim()
+        .and_then(SuperBox::payload)
+        .and_then(parse)
+        .and_then(|claim| cbor_text(&claim, "alg"));
+    let bindings = manifest
+        .find(jumbf::ASSERTIONS_LABEL)
+        .map(|assertions| assertions.children.iter().collect::<Vec<_>>())
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|assertion| {
+            let label = assertion.label.clone()?;
+            if !is_hard_binding_label(&label) {
+                return None;
+            }
+            let binding = assertion.payload().and_then(parse)?;
+            let hash_of = |value: &serde_cbor::Value| match cbor_get(value, "hash") {
+                Some(serde_cbor::Value::Bytes(hash)) => Some(to_hex(hash)),
+                _ => None,
+            };
+            let hashes = match (hash_of(&binding), cbor_get(&binding, "boxes")) {
+                (Some(hash), _) => vec![hash],
+                (None, Some(serde_cbor::Value::Array(boxes))) => {
+                    boxes.iter().filter_map(hash_of).collect()
+                }
+                _ => Vec::new(),
+            };
+            Some(BindingHash {
+                label,
+                alg: cbor_text(&binding, "alg").or_else(|| claim_alg.clone()),
+                hashes,
+            })
+        })
+        .collect();
+
+    Ok(AssetIdentity::Manifest {
+        active_manifest,
+        bindings,
+        manifest_store: IdentityHash {
+            alg: "sha256".to_owned(),
+            hash: to_hex(&Sha256::digest(&store_bytes)),
+        },
+    })
+}
+
+/// The highest claim version this toolkit understands
+pub const MAX_CLAIM_VERSION: u32 = 2;
+
+/// The fields each claim version requires, from the claim CDDL of the C2PA specification
+const REQUIRED_CLAIM_FIELDS: &[(u32, &[&str])] = &[
+    (
+        1,
+        &[
+            "claim_generator",
+            "signature",
+            "assertions",
+            "dc:format",
+            "instanceID",
+        ],
+    ),
+    (
+        2,
+        &[
+            "claim_generator_info",
+            "signature",
+            "created_assertions",
+            "instanceID",
+        ],
+    ),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ComplianceLevel {
+    /// The claim has every field its version requires
+    Full,
+    /// The claim is missing fields its version requires
+    Partial,
+    /// The claim version is above the `maxSupportedClaimVersion` read option
+    UnsupportedVersion,
+}
+
+/// The version of a manifest's claim and the version of the specification it was produced against
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClaimVersion {
+    pub claim_version: u32,
+    /// The major version of the C2PA specification
+    pub spec_version: u32,
+    pub compliance_level: ComplianceLevel,
+}
+
+/// Determines the claim version of each manifest, keyed by manifest label
+///
+/// Claims above `max_supported_version` are reported as [`ComplianceLevel::UnsupportedVersion`].
+pub fn get_claim_versions(
+    store_bytes: &[u8],
+    max_supported_version: u32,
+) -> Result<HashMap<String, ClaimVersion>> {
+    let store = limits::parse(store_bytes)?;
+    let mut result = HashMap::new();
+
+    for manifest in jumbf::manifests(&store) {
+        let (label, claim) = match (&manifest.label, manifest.claim()) {
+            (Some(label), Some(claim)) => (label, claim),
+            _ => continue,
+        };
+        let claim_version = jumbf::claim_version(claim);
+        let claim_value = claim
+            .payload()
+            .and_then(|claim| utf8::from_cbor::<serde_cbor::Value>(claim).ok());
+
+        // Version 3 ingredients only exist in version 2 of the specification
+        let spec_version = if claim_version >= 2
+            || claim_value
+                .as_ref()
+                .is_some_and(|claim| claim_assertion_urls(claim).any(|url| url.contains(".v3")))
+        {
+            2
+        } else {
+            1
+        };
+
+        let required = REQUIRED_CLAIM_FIELDS
+            .iter()
+            .find(|(version, _)| *version == claim_version)
+            .map(|(_, fields)| *fields);
+        let compliance_level = match (&claim_value, required) {
+            _ if claim_version > max_supported_version => ComplianceLevel::UnsupportedVersion,
+            (Some(claim), Some(required))
+                if required
+                    .iter()
+                    .all(|field| cbor_get(claim, field).is_some()) =>
+            {
+                ComplianceLevel::Full
+            }
+            _ => ComplianceLevel::Partial,
+        };
+
+        result.insert(
+            label.clone(),
+            ClaimVersion {
+                claim_version,
+                spec_version,
+                compliance_level,
+            },
+        );
+    }
+
+    Ok(result)
+}
+
+/// Whether the hash in a hashed URI matches the box it references
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HashedUriCheck {
+    pub hashed_uri_valid: bool,
+    /// The hash recorded in the hashed URI, as hex
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected_hash: Option<String>,
+    /// The hash of the referenced box as read, as hex, unless the box is missing or the hash
+    /// algorithm isn't supported
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actual_hash: Option<String>,
+}
+
+/// The hashed URI checks of a manifest's ingredient
+#[derive(Debug)]
+pub struct IngredientHashedUris {
+    /// The label of the ingredient assertion, whose own check is in [`HashedUriChecks::assertions`]
+    pub assertion_label: String,
+    /// The check of the ingredient's reference to its manifest, if it has one
+    pub manifest_reference: Option<HashedUriCheck>,
+}
+
+/// The hashed URI checks of a manifest
+#[derive(Debug, Default)]
+pub struct HashedUriChecks {
+    /// The check of each assertion the claim references, keyed by its label in the assertion store
+    pub assertions: HashMap<String, HashedUriCheck>,
+    /// The ingredients, in the order of the manifest's `ingredients`
+    pub ingredients: Vec<IngredientHashedUris>,
+}
+
+/// Checks every hashed URI in each manifest's claim and ingredients against the box it references,
+/// keyed by manifest label
+///
+/// The digests behind each check are only included with `include_digests`.
+pub fn get_hashed_uri_checks(
+    store_bytes: &[u8],
+    include_digests: bool,
+) -> Result<HashMap<String, HashedUriChecks>> {
+    let store = limits::parse(store_bytes)?;
+    let mut result = HashMap::new();
+
+    for manifest in jumbf::manifests(&store) {
+        let (label, claim) = match (
+            &manifest.label,
+            manifest.claim().and_then(SuperBox::payload),
+        ) {
+            (Some(label), Some(claim)) => (label, claim),
+            _ => continue,
+        };
+        let claim: serde_cbor::Value =
+            utf8::from_cbor(claim).map_err(|_err| Error::InvalidJumbf("invalid claim"))?;
+        let claim_alg = cbor_text(&claim, "alg").unwrap_or_else(|| DEFAULT_HASH_ALG.to_owned());
+        let assertion_store = manifest.find(jumbf::ASSERTIONS_LABEL);
+
+        let mut checks = HashedUriChecks::default();
+        for hashed_uri in claim_hashed_uris(&claim) {
+            let assertion_label = match cbor_text(hashed_uri, "url") {
+                Some(url) => url.rsplit('/').next().unwrap_or_default().to_owned(),
+                None => continue,
+            };
+            let candidates: Vec<_> = assertion_store
+                .and_then(|assertions| assertions.find(&assertion_label))
+                .map(|assertion| assertion.contents)
+                .into_iter()
+                .collect();
+            let check = check_hashed_uri(hashed_uri, &candidates, &claim_alg, include_digests);
+            checks.assertions.insert(assertion_label, check);
+        }
+
+        for (assertion_label, ingredient) in ingredient_assertions(manifest)? {
+            let manifest_reference = ingredient.as_ref().and_then(|ingredient| {
+                // Version 3 ingredients renamed `c2pa_manifest` to `activeManifest`
+                let hashed_uri = cbor_get(ingredient, "c2pa_manifest")
+                    .or_else(|| cbor_get(ingredient, "activeManifest"))?;
+                let ingredient_manifest = ingredient_manifest_label(ingredient)
+                    .and_then(|label| store.find(&label))
+                    .filter(|ingredient_manifest| ingredient_manifest.is_manifest());
+                Some(check_manifest_reference(
+                    hashed_uri,
+                    ingredient_manifest,
+                    include_digests,
+                ))
+            });
+            checks.ingredients.push(IngredientHashedUris {
+                assertion_label,
+                manifest_reference,
+            });
+        }
+
+        result.insert(label.clone(), checks);
+    }
+
+    Ok(result)
+}
+
+/// Checks the manifest reference of the `index`th ingredient of the manifest labelled `label`
+/// against the active manifest of another asset's store, which must be the manifest it names
+///
+/// Returns `None` if the ingredient has no manifest reference.
+pub fn check_ingredient_manifest_reference(
+    store_bytes: &[u8],
+    label: &str,
+    index: usize,
+    asset_store_bytes: Option<&[u8]>,
+) -> Result<Option<HashedUriCheck>> {
+    let store = limits::parse(store_bytes)?;
+    let manifest = store
+        .find(label)
+        .filter(|manifest| manifest.is_manifest())
+        .ok_or(Error::ManifestNotFound)?;
+    let ingredient = match ingredient_assertions(manifest)?.into_iter().nth(index) {
+        Some((_, Some(ingredient))) => ingredient,
+        _ => return Ok(None),
+    };
+    let hashed_uri = match cbor_get(&ingredient, "c2pa_manifest")
+        .or_else(|| cbor_get(&ingredient, "activeManifest"))
+    {
+        Some(hashed_uri) => hashed_uri,
+        None => return Ok(None),
+    };
+
+    // A supplied store that can't be parsed just doesn't carry the manifest
+    let asset_store = asset_store_bytes.and_then(|bytes| limits::parse(bytes).ok());
+    let ingredient_label = ingredient_manifest_label(&ingredient);
+    let active_manifest = asset_store
+        .as_ref()
+        .and_then(|asset_store| jumbf::manifests(asset_store).last())
+        .filter(|active_manifest| {
+            active_manifest.label.is_some() && active_manifest.label == ingredient_label
+        });
+
+    Ok(Some(check_manifest_reference(
+        hashed_uri,
+        active_manifest,
+        true,
+    )))
+}
+
+/// Compares the hash in an ingredient's manifest reference with the manifest it names, if found
+fn check_manifest_reference(
+    hashed_uri: &serde_cbor::Value,
+    ingredient_manifest: Option<&SuperBox>,
+    include_digests: bool,
+) -> HashedUriCheck {
+    let ingredient_claim = ingredient_manifest
+        .and_then(SuperBox::claim)
+        .and_then(SuperBox::payload);
+
+    // The reference covers the manifest box, or just the claim before C2PA 1.1
+    let candidates: Vec<_> = ingredient_manifest
+        .map(|ingredient_manifest| ingredient_manifest.contents)
+        .into_iter()
+        .chain(ingredient_claim)
+        .collect();
+    let ingredient_alg = ingredient_claim
+        .and_then(|claim| utf8::from_cbor(claim).ok())
+        .and_then(|claim| cbor_text(&claim, "alg"))
+        .unwrap_or_else(|| DEFAULT_HASH_ALG.to_owned());
+    check_hashed_uri(hashed_uri, &candidates, &ingredient_alg, include_digests)
+}
+
+/// The hash algorithm used when neither a hashed URI nor its claim names one
+const DEFAULT_HASH_ALG: &str = "sha256";
+
+/// Compares the hash in `hashed_uri` with the hashes of the data it may cover
+///
+/// The hashed URI's own algorithm takes precedence over `default_alg`. The actual hash is the
+/// first one that matches, or else the hash of the first of `candidates`.
+fn check_hashed_uri(
+    hashed_uri: &serde_cbor::Value,
+    candidates: &[&[u8]],
+    default_alg: &str,
+    include_digests: bool,
+) -> HashedUriCheck {
+    let expected = match cbor_get(hashed_uri, "hash") {
+        Some(serde_cbor::Value::Bytes(hash)) => Some(hash.as_slice()),
+        _ => None,
+    };
+    let alg = cbor_text(hashed_uri, "alg").unwrap_or_else(|| default_alg.to_owned());
+    let hashes: Vec<_> = candidates
+        .iter()
+        .filter_map(|candidate| hash_by_alg(&alg, candidate))
+        .collect();
+    let matching = hashes.iter().find(|hash| Some(hash.as_slice()) == expected);
+
+    HashedUriCheck {
+        hashed_uri_valid: matching.is_some(),
+        expected_hash: expected.filter(|_| include_digests).map(to_hex),
+        actual_hash: matching
+            .or_else(|| hashes.first())
+            .filter(|_| include_digests)
+            .map(|hash| to_hex(hash)),
+    }
+}
+
+/// Hashes `data` with one of the hash algorithms C2PA allows, or returns `None` for any other
+fn hash_by_alg(alg: &str, data: &[u8]) -> Option<Vec<u8>> {
+    match alg {
+        "sha256" => Some(Sha256::digest(data).to_vec()),
+        "sha384" => Some(Sha384::digest(data).to_vec()),
+        "sha512" => Some(Sha512::digest(data).to_vec()),
+        _ => None,
+    }
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    let mut hex = Vec::with_capacity(bytes.len() * 2);
+    for b in bytes {
+        hex.push(DIGITS[usize::from(b >> 4)]);
+        hex.push(DIGITS[usize::from(b & 0xf)]);
+    }
+    // Only ASCII digits were written
+    String::from_utf8(hex).unwrap_or_default()
+}
+
+/// Decodes lowercase or uppercase hex, returning `None` if it isn't hex
+pub fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    hex.as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            let digit = |d: u8| char::from(d).to_digit(16);
+            Some((digit(pair[0])? * 16 + digit(pair[1])?) as u8)
+        })
+        .collect()
+}
+
+/// The label of the assertion that binds a manifest to a collection of files
+const COLLECTION_HASH_LABEL: &str = "c2pa.hash.collection.data";
+
+/// A file listed in a manifest's collection hash
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionEntry {
+    /// The file's URI relative to the collection
+    pub name: String,
+    /// The recorded hash as hex
+    pub hash: String,
+    pub alg: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    /// `true` if another entry has the same name, in which case neither can be verified
+    pub duplicate: bool,
+}
+
+/// The files a manifest's collection hash binds it to
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionHash {
+    pub collection_hash: Vec<CollectionEntry>,
+}
+
+/// Lists the entries of each manifest's collection hash, keyed by manifest label
+///
+/// Manifests without a collection hash are left out.
+pub fn get_collection_hashes(store_bytes: &[u8]) -> Result<HashMap<String, CollectionHash>> {
+    let store = limits::parse(store_bytes)?;
+    let mut result = HashMap::new();
+
+    for manifest in jumbf::manifests(&store) {
+        if let (Some(label), Some(entries)) = (&manifest.label, collection_entries(manifest)) {
+            result.insert(
+                label.clone(),
+                CollectionHash {
+                    collection_hash: entries,
+                },
+            );
+        }
+    }
+
+    Ok(result)
+}
+
+/// The entries of a manifest's collection hash, or `None` if it doesn't have one
+fn collection_entries(manifest: &SuperBox) -> Option<Vec<CollectionEntry>> {
+    let collection_hash = manifest
+        .find(jumbf::ASSERTIONS_LABEL)?
+        .children
+        .iter()
+        .find(|assertion| {
+            assertion
+                .label
+                .as_deref()
+                .is_some_and(|label| label.split("__").next() == Some(COLLECTION_HASH_LABEL))
+        })?
+        .payload()?;
+    let collection_hash: serde_cbor::Value = utf8::from_cbor(collection_hash).ok()?;
+
+    // The assertion's own algorithm takes precedence over the claim's
+    let alg = cbor_text(&collection_hash, "alg")
+        .or_else(|| {
+            manifest
+                .claim()
+                .and_then(SuperBox::payload)
+                .and_then(|claim| utf8::from_cbor(claim).ok())
+                .and_then(|claim| cbor_text(&claim, "alg"))
+        })
+        .unwrap_or_else(|| DEFAULT_HASH_ALG.to_owned());
+
+    let uris = match cbor_get(&collection_hash, "uris") {
+        Some(serde_cbor::Value::Array(uris)) => uris,
+        _ => return Some(Vec::new()),
+    };
+    let mut entries: Vec<_> = uris
+        .iter()
+        .filter_map(|uri| {
+            let hash = match cbor_get(uri, "hash") {
+                Some(serde_cbor::Value::Bytes(hash)) => to_hex(hash),
+                _ => return None,
+            };
+            let size = match cbor_get(uri, "size") {
+                Some(serde_cbor::Value::Integer(size)) => u64::try_from(*size).ok(),
+                _ => None,
+            };
+            Some(CollectionEntry {
+                name: cbor_text(uri, "uri")?,
+                hash,
+                alg: alg.clone(),
+                size,
+                format: cbor_text(uri, "dc:format"),
+                duplicate: false,
+            })
+        })
+        .collect();
+
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for entry in &entries {
+        *counts.entry(entry.name.clone()).or_default() += 1;
+    }
+    for entry in &mut entries {
+        entry.duplicate = counts[&entry.name] > 1;
+    }
+
+    Some(entries)
+}
+
+/// The result of checking a file against the collection hash of a manifest
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionEntryCheck {
+    pub manifest_label: String,
+    pub name: String,
+    pub hash_valid: bool,
+    pub expected_hash: String,
+    /// The hash of the file as hex, unless the hash algorithm isn't supported
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actual_hash: Option<String>,
+}
+
+/// Checks `asset` against the entry called `name` in the active manifest's collection hash
+///
+/// Only the entry's hash is checked. The manifest itself is validated by reading it.
+pub fn verify_asset_in_collection(
+    store_bytes: &[u8],
+    asset: &[u8],
+    name: &str,
+) -> Result<CollectionEntryCheck> {
+    let store = limits::parse(store_bytes)?;
+    if store.uuid != jumbf::STORE_UUID {
+        return Err(Error::InvalidJumbf("not a manifest store"));
+    }
+    // The active manifest is the last one in the store
+    let manifest = jumbf::manifests(&store)
+        .last()
+        .ok_or(Error::ManifestNotFound)?;
+    let entries = collection_entries(manifest).ok_or(Error::CollectionHashNotFound)?;
+
+    let entry = match entries.iter().find(|entry| entry.name == name) {
+        Some(entry) if entry.duplicate => {
+            return Err(Error::CollectionEntryAmbiguous(name.to_owned()))
+        }
+        Some(entry) => entry,
+        None => return Err(Error::CollectionEntryNotFound(name.to_owned())),
+    };
+    let actual_hash = hash_by_alg(&entry.alg, asset).map(|hash| to_hex(&hash));
+
+    Ok(CollectionEntryCheck {
+        manifest_label: manifest.label.clone().unwrap_or_default(),
+        name: entry.name.clone(),
+        hash_valid: actual_hash.as_ref() == Some(&entry.hash),
+        expected_hash: entry.hash.clone(),
+        actual_hash,
+    })
+}
+
+/// Checks the stapled OCSP response of each manifest's signature, keyed by manifest label
+pub fn get_revocation_info(store_bytes: &[u8]) -> Result<HashMap<String, RevocationInfo>> {
+    let store = limits::parse(store_bytes)?;
+    let mut result = HashMap::new();
+
+    for (label, signature) in signatures(&store) {
+        let cert_chain = signature.certificate_chain();
+        let info = ocsp::revocation_info(&signature.ocsp_responses(), cert_chain.first().copied());
+        result.insert(label.clone(), info);
+    }
+
+    Ok(result)
+}
+
+/// Checks the timestamp of each manifest's signature against `now_ms`, keyed by manifest label
+///
+/// Manifests whose signature has no timestamp that can be read are left out.
+pub fn get_timestamp_checks(
+    store_bytes: &[u8],
+    options: &TimestampSkew,
+    now_ms: f64,
+) -> Result<HashMap<String, TimestampCheck>> {
+    let store = limits::parse(store_bytes)?;
+
+    Ok(signatures(&store)
+        .filter_map(|(label, signature)| {
+            Some((
+                label.clone(),
+                timestamp::check(&signature, options, now_ms)?,
+            ))
+        })
+        .collect())
+}
+
+/// Reads the subject of each manifest's signing certificate, keyed by manifest label
+pub fn get_signing_subjects(store_bytes: &[u8]) -> Result<HashMap<String, ocsp::Subject>> {
+    let store = limits::parse(store_bytes)?;
+    let mut result = HashMap::new();
+
+    for (label, signature) in signatures(&store) {
+        let cert = match signature.certificate_chain().first() {
+            Some(cert) => *cert,
+            None => continue,
+        };
+        match ocsp::certificate_subject(cert) {
+            Ok(subject) => {
+                result.insert(label.clone(), subject);
+            }
+            Err(err) => warn!(
+                "Could not read the signing certificate of {}: {}",
+                label, err
+            ),
+        }
+    }
+
+    Ok(result)
+}
+
+/// Finds the manifests signed with an algorithm this build can't verify, keyed by manifest label
+/// with the COSE identifier of the algorithm
+pub fn get_unsupported_algs(store_bytes: &[u8]) -> Result<BTreeMap<String, i64>> {
+    let store = limits::parse(store_bytes)?;
+
+    Ok(signatures(&store)
+        .filter(|(_, signature)| signature.alg().is_none())
+        .filter_map(|(label, signature)| {
+            let alg = i64::try_from(signature.alg_id()?).ok()?;
+            Some((label.clone(), alg))
+        })
+        .collect())
+}
+
+/// The labelled manifests of a store along with their signatures, skipping the ones that can't
+/// be read
+fn signatures<'a, 'b>(
+    store: &'b SuperBox<'a>,
+) -> impl Iterator<Item = (&'b String, CoseSign1)> + 'b {
+    jumbf::manifests(store).filter_map(|manifest| {
+        let label = manifest.label.as_ref()?;
+        match manifest
+            .find(jumbf::SIGNATURE_LABEL)
+            .and_then(SuperBox::payload)
+            .map(CoseSign1::from_slice)?
+        {
+            Ok(signature) => Some((label, signature)),
+            Err(err) => {
+                warn!("Could not read the signature of {}: {}", label, err);
+                None
+            }
+        }
+    })
+}
+
+/// The prefixes of the validation status codes of the steps the c2pa crate performs
+const SIGNATURE_CODES: &[&str] = &["claimSignature."];
+const TRUST_CODES: &[&str] = &["signingCredential."];
+const TIMESTAMP_CODES: &[&str] = &["timeStamp."];
+const HARD_BINDING_CODES: &[&str] = &[
+    "assertion.dataHash.",
+    "assertion.bmffHash.",
+    "assertion.boxesHash.",
+];
+const REVOCATION_CODES: &[&str] = &["signingCredential.ocsp.", "signingCredential.revoked"];
+
+/// Walks a manifest store the way it is validated, recording each step with `tracer`
+///
+/// Manifests are walked in store order. `statuses` are the validation statuses the c2pa crate
+/// returned, which give the outcome of the signature, trust, timestamp and hard binding steps it
+/// performs itself. A store that can't be parsed ends the walk after its first step.
+pub fn trace_validation(
+    store_bytes: &[u8],
+    statuses: &[ValidationStatus],
+    active_label: Option<&str>,
+    tracer: &mut Tracer,
+) {
+    let store = tracer.timed(|| {
+        let store = limits::parse(store_bytes).ok();
+        let step = TraceStep::new(StepKind::StoreLocated, None, passed(store.is_some()));
+        (store, step.input(store_bytes))
+    });
+    let store = match store {
+        Some(store) => store,
+        None => return,
+    };
+
+    for manifest in jumbf::manifests(&store) {
+        let label = match &manifest.label {
+            Some(label) => label.as_str(),
+            None => continue,
+        };
+        tracer.timed(|| {
+            let step = TraceStep::new(StepKind::ManifestLocated, Some(label), Outcome::Passed);
+            ((), step.input(manifest.contents))
+        });
+
+        let claim = tracer.timed(|| {
+            let claim_bytes = manifest.claim().and_then(SuperBox::payload);
+            let claim: Option<serde_cbor::Value> =
+                claim_bytes.and_then(|claim| utf8::from_cbor(claim).ok());
+            let step = TraceStep::new(StepKind::ClaimDecoded, Some(label), passed(claim.is_some()));
+            (claim, with_input(step, claim_bytes))
+        });
+        if let Some(claim) = &claim {
+            let claim_alg = cbor_text(claim, "alg").unwrap_or_else(|| DEFAULT_HASH_ALG.to_owned());
+            let assertion_store = manifest.find(jumbf::ASSERTIONS_LABEL);
+            for hashed_uri in claim_hashed_uris(claim) {
+                let assertion_label = match cbor_text(hashed_uri, "url") {
+                    Some(url) => url.rsplit('/').next().unwrap_or_default().to_owned(),
+                    None => continue,
+                };
+                tracer.timed(|| {
+                    let assertion = assertion_store
+                        .and_then(|assertions| assertions.find(&assertion_label))
+                        .map(|assertion| assertion.contents);
+                    let candidates: Vec<_> = assertion.into_iter().collect();
+                    let check = check_hashed_uri(hashed_uri, &candidates, &claim_alg, false);
+                    let step = TraceStep::new(
+                        StepKind::AssertionHashChecked,
+                        Some(label),
+                        passed(check.hashed_uri_valid),
+                    );
+                    (
+                        (),
+                        with_input(step.target(assertion_label.as_str()), assertion),
+                    )
+                });
+            }
+        }
+
+        for (assertion_label, ingredient) in ingredient_assertions(manifest).unwrap_or_default() {
+            let hashed_uri = match ingredient.as_ref().and_then(|ingredient| {
+                cbor_get(ingredient, "c2pa_manifest")
+                    .or_else(|| cbor_get(ingredient, "activeManifest"))
+            }) {
+                Some(hashed_uri) => hashed_uri,
+                None => continue,
+            };
+            tracer.timed(|| {
+                let ingredient_manifest = ingredient
+                    .as_ref()
+                    .and_then(ingredient_manifest_label)
+                    .and_then(|label| store.find(&label))
+                    .filter(|ingredient_manifest| ingredient_manifest.is_manifest());
+                let check = check_manifest_reference(hashed_uri, ingredient_manifest, false);
+                let step = TraceStep::new(
+                    StepKind::IngredientReferenceChecked,
+                    Some(label),
+                    passed(check.hashed_uri_valid),
+                );
+                let input =
+                    ingredient_manifest.map(|ingredient_manifest| ingredient_manifest.contents);
+                ((), with_input(step.target(assertion_label), input))
+            });
+        }
+
+        let codes: Vec<&str> = statuses
+            .iter()
+            .filter(|status| {
+                status
+                    .url()
+                    .and_then(validation::manifest_label)
+                    .or(active_label)
+                    == Some(label)
+            })
+            .map(ValidationStatus::code)
+            .collect();
+        let signature = tracer.timed(|| {
+            let signature_bytes = manifest
+                .find(jumbf::SIGNATURE_LABEL)
+                .and_then(SuperBox::payload);
+            let signature =
+                signature_bytes.and_then(|signature| CoseSign1::from_slice(signature).ok());
+            let mut step = TraceStep::new(
+                StepKind::SignatureDecoded,
+                Some(label),
+                passed(signature.is_some()),
+            );
+            if let Some(alg) = signature.as_ref().and_then(CoseSign1::alg) {
+                step = step.target(alg.to_string());
+            }
+            (signature, with_input(step, signature_bytes))
+        });
+        if let Some(signature) = &signature {
+            let cert_chain = signature.certificate_chain();
+            for (index, cert) in cert_chain.iter().enumerate() {
+                tracer.timed(|| {
+                    // The last certificate only has to be readable
+                    let linked = match cert_chain.get(index + 1) {
+                        Some(issuer) => ocsp::is_issued_by(cert, issuer),
+                        None => ocsp::certificate_subject(cert).map(|_subject| true),
+                    };
+                    let step = TraceStep::new(
+                        StepKind::CertificateChainLinkChecked,
+                        Some(label),
+                        passed(linked == Ok(true)),
+                    );
+                    ((), step.target(index.to_string()).input(cert))
+                });
+            }
+
+            tracer.timed(|| {
+                let info =
+                    ocsp::revocation_info(&signature.ocsp_responses(), cert_chain.first().copied());
+                let outcome = match info.revocation_status {
+                    RevocationStatus::Good => Outcome::Passed,
+                    RevocationStatus::Revoked => Outcome::Failed,
+                    RevocationStatus::Unknown => Outcome::Inconclusive,
+                    RevocationStatus::NotPresent => Outcome::NotPresent,
+                };
+                let mut step = TraceStep::new(StepKind::RevocationChecked, Some(label), outcome);
+                step.codes = matching_codes(&codes, REVOCATION_CODES);
+                ((), step)
+            });
+        }
+
+        tracer.push(status_step(
+            StepKind::SignatureVerified,
+            label,
+            &codes,
+            SIGNATURE_CODES,
+        ));
+        // Revocation statuses are reported with the revocation step instead
+        let trust_codes: Vec<&str> = codes
+            .iter()
+            .copied()
+            .filter(|code| !has_prefix(code, REVOCATION_CODES))
+            .collect();
+        tracer.push(status_step(
+            StepKind::CredentialTrustChecked,
+            label,
+            &trust_codes,
+            TRUST_CODES,
+        ));
+        let mut timestamp = status_step(StepKind::TimestampChecked, label, &codes, TIMESTAMP_CODES);
+        let stamped = signature
+            .as_ref()
+            .and_then(|signature| signature.header(&serde_cbor::Value::Text("sigTst".to_owned())))
+            .is_some();
+        if timestamp.codes.is_empty() && !stamped {
+            timestamp.outcome = Outcome::NotPresent;
+        }
+        tracer.push(timestamp);
+        // The c2pa crate only checks the hard binding of the active manifest
+        if active_label == Some(label) {
+            tracer.push(status_step(
+                StepKind::HardBindingChecked,
+                label,
+                &codes,
+                HARD_BINDING_CODES,
+            ));
+        }
+    }
+}
+
+fn passed(passed: bool) -> Outcome {
+    if passed {
+        Outcome::Passed
+    } else {
+        Outcome::Failed
+    }
+}
+
+fn with_input(step: TraceStep, input: Option<&[u8]>) -> TraceStep {
+    match input {
+        Some(input) => step.input(input),
+        None => step,
+    }
+}
+
+fn has_prefix(code: &str, prefixes: &[&str]) -> bool {
+    prefixes.iter().any(|prefix| code.starts_with(prefix))
+}
+
+/// The codes that start with one of `prefixes`
+fn matching_codes(codes: &[&str], prefixes: &[&str]) -> Vec<String> {
+    codes
+        .iter()
+        .filter(|code| has_prefix(code, prefixes))
+        .map(|code| code.to_string())
+        .collect()
+}
+
+/// A step performed by the c2pa crate, with the codes that start with one of `prefixes`, which
+/// failed if any of them is an error
+fn status_step(step: StepKind, label: &str, codes: &[&str], prefixes: &[&str]) -> TraceStep {
+    let codes = matching_codes(codes, prefixes);
+    let outcome = match codes.iter().map(|code| validation::severity(code)).max() {
+        Some(Severity::Error) => Outcome::Failed,
+        Some(Severity::Warning) => Outcome::Inconclusive,
+        _ => Outcome::Passed,
+    };
+    let mut step = TraceStep::new(step, Some(label), outcome);
+    step.codes = codes;
+    step
+}
+
+/// An ingredient thumbnail that is referenced by URI instead of being embedded in the store
+#[derive(Debug, PartialEq, Eq)]
+pub struct ExternalResource {
+    pub manifest_label: String,
+    /// The index of the ingredient within the manifest's `ingredients`
+    pub ingredient_index: usize,
+    pub uri: String,
+}
+
+/// The localized strings of a manifest resolved for a locale, see [`locale::display_strings`]
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DisplayStrings {
+    pub display_strings: BTreeMap<String, String>,
+}
+
+/// Picks the best match for `locale` from the localized strings of every manifest, keyed by
+/// manifest label
+pub fn get_display_strings(
+    manifest_store: &ManifestStore,
+    locale: &str,
+) -> Result<HashMap<String, DisplayStrings>> {
+    manifest_store
+        .manifests()
+        .iter()
+        .map(|(label, manifest)| {
+            let manifest =
+                serde_json::to_value(manifest).map_err(|_err| Error::JavaScriptConversion)?;
+            let display_strings = DisplayStrings {
+                display_strings: locale::display_strings(&manifest, locale),
+            };
+            Ok((label.clone(), display_strings))
+        })
+        .collect()
+}
+
+/// The reference to an externally stored thumbnail that replaces the ingredient's `thumbnail`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExternalResourceRef {
+    pub format: String,
+    pub identifier: String,
+    pub resolved: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub resolved_externally: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<usize>,
+}
+
+impl ExternalResourceRef {
+    /// Describes the resource at `uri`, given the bytes it resolved to, if any
+    pub fn new(uri: &str, bytes: Option<&[u8]>) -> Self {
+        Self {
+            format: bytes
+                .and_then(sniff_mime_type)
+                .unwrap_or("application/octet-stream")
+                .to_owned(),
+            identifier: uri.to_owned(),
+            resolved: bytes.is_some(),
+            resolved_externally: bytes.is_some(),
+            size: bytes.map(<[u8]>::len),
+        }
+    }
+}
+
+/// Finds the ingredient thumbnails of every manifest that point outside of the manifest store
+///
+/// The c2pa crate drops these references, so they are read from the ingredient assertions.
+pub fn get_external_resources(store_bytes: &[u8]) -> Result<Vec<ExternalResource>> {
+    let store = limits::parse(store_bytes)?;
+    let mut result = Vec::new();
+
+    for manifest in jumbf::manifests(&store) {
+        let label = match &manifest.label {
+            Some(label) => label,
+            None => continue,
+        };
+
+        for (ingredient_index, (_, ingredient)) in
+            ingredient_assertions(manifest)?.iter().enumerate()
+        {
+            match ingredient.as_ref().and_then(thumbnail_url) {
+                Some(uri) if !uri.starts_with("self#jumbf=") => result.push(ExternalResource {
+                    manifest_label: label.clone(),
+                    ingredient_index,
+                    uri,
+                }),
+                _ => continue,
+            }
+        }
+    }
+
+    Ok(result)
+}
+
+/// How an update manifest is reported in the manifest store output
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateManifest {
+    pub is_update_manifest: bool,
+    /// The label of the manifest this one updates, from its `parentOf` ingredient
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_manifest: Option<String>,
+    /// The problems found with the update manifest, which are reported with the store's
+    #[serde(skip)]
+    pub validation_status: Vec<UpdateValidationStatus>,
+}
+
+/// A validation status entry, in the shape the c2pa crate reports them
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub struct UpdateValidationStatus {
+    pub code: &'static str,
+    pub url: String,
+    pub explanation: String,
+}
+
+/// Finds the update manifests of the store, keyed by manifest label, and checks that each one
+/// updates exactly one manifest that is present in the store
+///
+/// Update manifests carry no hard binding of their own, so that isn't required of them.
+pub fn get_update_manifests(store_bytes: &[u8]) -> Result<HashMap<String, UpdateManifest>> {
+    let store = limits::parse(store_bytes)?;
+    let labels: HashSet<&str> = jumbf::manifests(&store)
+        .filter_map(|manifest| manifest.label.as_deref())
+        .collect();
+    let mut result = HashMap::new();
+
+    for manifest in jumbf::manifests(&store) {
+        let label = match &manifest.label {
+            Some(label) if manifest.uuid == jumbf::UPDATE_MANIFEST_UUID => label,
+            _ => continue,
+        };
+        let manifest_uri = format!("self#jumbf=/c2pa/{}", label);
+
+        let ingredients = ingredient_assertions(manifest)?;
+        let parents: Vec<_> = ingredients
+            .iter()
+            .filter_map(|(assertion_label, ingredient)| {
+                let ingredient = ingredient.as_ref()?;
+                match cbor_get(ingredient, "relationship") {
+                    Some(serde_cbor::Value::Text(relationship)) if relationship == "parentOf" => {
+                        Some((assertion_label, ingredient_manifest_label(ingredient)))
+                    }
+                    _ => None,
+                }
+            })
+            .collect();
+
+        let mut validation_status = Vec::new();
+        if parents.len() != 1 || ingredients.len() != 1 {
+            validation_status.push(UpdateValidationStatus {
+                code: c2pa::validation_status::MANIFEST_UPDATE_WRONG_PARENTS,
+                url: manifest_uri.clone(),
+                explanation: "update manifest must have one parent".to_owned(),
+            });
+        }
+
+        let updated_manifest = parents
+            .first()
+            .and_then(|(_, parent_label)| parent_label.clone());
+        if let Some((assertion_label, parent_label)) = parents.first() {
+            if !parent_label
+                .as_deref()
+                .is_some_and(|parent_label| labels.contains(parent_label))
+            {
+                validation_status.push(UpdateValidationStatus {
+                    code: c2pa::validation_status::CLAIM_MISSING,
+                    url: format!(
+                        "{}/{}/{}",
+                        manifest_uri,
+                        jumbf::ASSERTIONS_LABEL,
+                        assertion_label
+                    ),
+                    explanation: format!(
+                        "ingredient: {} is missing",
+                        parent_label.as_deref().unwrap_or_default()
+                    ),
+                });
+            }
+        }
+
+        result.insert(
+            label.clone(),
+            UpdateManifest {
+                is_update_manifest: true,
+                updated_manifest,
+                validation_status,
+            },
+        );
+    }
+
+    Ok(result)
+}
+
+/// The ingredient assertions of a manifest in the order its claim lists them, which is the order
+/// of the manifest's `ingredients`, along with their labels
+///
+/// Assertions that are missing or aren't valid CBOR are `None`.
+fn ingredient_assertions(manifest: &SuperBox) -> Result<Vec<(String, Option<serde_cbor::Value>)>> {
+    let (claim, assertions) = match (
+        manifest.claim().and_then(SuperBox::payload),
+        manifest.find(jumbf::ASSERTIONS_LABEL),
+    ) {
+        (Some(claim), Some(assertions)) => (claim, assertions),
+        _ => return Ok(Vec::new()),
+    };
+
+    let claim: serde_cbor::Value =
+        utf8::from_cbor(claim).map_err(|_err| Error::InvalidJumbf("invalid claim"))?;
+
+    Ok(claim_assertion_urls(&claim)
+        .filter_map(|url| url.rsplit('/').next())
+        .filter(|assertion_label| is_ingredient_label(assertion_label))
+        .map(|assertion_label| {
+            let ingredient = assertions
+                .find(assertion_label)
+                .and_then(SuperBox::payload)
+                .and_then(|payload| utf8::from_cbor(payload).ok());
+            (assertion_label.to_owned(), ingredient)
+        })
+        .collect())
+}
+
+/// The label of the manifest an ingredient assertion references, if any
+fn ingredient_manifest_label(ingredient: &serde_cbor::Value) -> Option<String> {
+    // Version 3 ingredients renamed `c2pa_manifest` to `activeManifest`
+    let url = match cbor_get(ingredient, "c2pa_manifest")
+        .or_else(|| cbor_get(ingredient, "activeManifest"))
+        .and_then(|manifest| cbor_get(manifest, "url"))?
+    {
+        serde_cbor::Value::Text(url) => url,
+        _ => return None,
+    };
+
+    let path = url.strip_prefix("self#jumbf=")?;
+    let path = path.strip_prefix("/c2pa/").unwrap_or(path);
+    path.split('/').next().map(str::to_owned)
+}
+
+/// The hashed URIs of the assertions a claim references, in order
+fn claim_hashed_uris(claim: &serde_cbor::Value) -> impl Iterator<Item = &serde_cbor::Value> {
+    // Version 2 claims split the assertions into created and gathered ones
+    ["assertions", "created_assertions", "gathered_assertions"]
+        .iter()
+        .filter_map(move |key| match cbor_get(claim, key) {
+            Some(serde_cbor::Value::Array(assertions)) => Some(assertions),
+            _ => None,
+        })
+        .flatten()
+}
+
+/// The URLs of the assertions a claim references, in order
+fn claim_assertion_urls(claim: &serde_cbor::Value) -> impl Iterator<Item = &str> {
+    claim_hashed_uris(claim).filter_map(|assertion| match cbor_get(assertion, "url") {
+        Some(serde_cbor::Value::Text(url)) => Some(url.as_str()),
+        _ => None,
+    })
+}
+
+/// Returns `true` for the labels of ingredient assertions (`c2pa.ingredient`, `c2pa.ingredient.v2`,
+/// `c2pa.ingredient__1`, ...)
+fn is_ingredient_label(label: &str) -> bool {
+    let label = label.split("__").next().unwrap_or(label);
+    match label.strip_prefix("c2pa.ingredient") {
+        Some(version) => version.is_empty() || version.starts_with(".v"),
+        None => false,
+    }
+}
+
+fn thumbnail_url(ingredient: &serde_cbor::Value) -> Option<String> {
+    cbor_text(cbor_get(ingredient, "thumbnail")?, "url")
+}
+
+fn cbor_text(value: &serde_cbor::Value, key: &str) -> Option<String> {
+    match cbor_get(value, key)? {
+        serde_cbor::Value::Text(text) => Some(text.clone()),
+        _ => None,
+    }
+}
+
+fn cbor_get<'a>(value: &'a serde_cbor::Value, key: &str) -> Option<&'a serde_cbor::Value> {
+    match value {
+        serde_cbor::Value::Map(map) => map.get(&serde_cbor::Value::Text(key.to_owned())),
+        _ => None,
+    }
+}
+
+/// Extracts the manifest of an ingredient as a standalone `.c2pa` manifest store
+///
+/// The returned store contains the ingredient's manifest as its active manifest, along with the
+/// manifests of that ingredient's own ingredients so that it can be validated on its own.
+pub async fn get_ingredient_manifest_bytes(
+    data: &[u8],
+    mime_type: &str,
+    manifest_label: &str,
+    ingredient: &IngredientRef,
+) -> Result<Vec<u8>> {
+    limits::check_asset(data, mime_type)?;
+    let manifest_store = ManifestStore::from_bytes_async(mime_type, data, false).await?;
+    let manifest = manifest_store
+        .get(manifest_label)
+        .ok_or(Error::ManifestNotFound)?;
+
+    let ingredient = match ingredient {
+        IngredientRef::Index(index) => manifest.ingredients().get(*index),
+        IngredientRef::InstanceId(instance_id) => manifest
+            .ingredients()
+            .iter()
+            .find(|ingredient| ingredient.instance_id() == instance_id),
+    }
+    .ok_or(Error::IngredientNotFound)?;
+
+    let ingredient_label = ingredient
+        .active_manifest()
+        .ok_or(Error::IngredientManifestNotPresent)?;
+
+    // Collect the ingredient's manifest along with everything it transitively references
+    let mut labels = HashSet::new();
+    let mut pending = vec![ingredient_label];
+    while let Some(label) = pending.pop() {
+        if !labels.insert(label) {
+            continue;
+        }
+        if let Some(manifest) = manifest_store.get(label) {
+            pending.extend(
+                manifest
+                    .ingredients()
+                    .iter()
+                    .filter_map(|ingredient| ingredient.active_manifest()),
+            );
+        }
+    }
+
+    let store_bytes = limits::load(data, mime_type)?;
+    let store = SuperBox::parse(&store_bytes)?;
+    if store.uuid != jumbf::STORE_UUID {
+        return Err(Error::InvalidJumbf("not a manifest store"));
+    }
+
+    let mut manifests: Vec<&SuperBox> = jumbf::manifests(&store)
+        .filter(|manifest| {
+            manifest
+                .label
+                .as_deref()
+                .is_some_and(|label| label != ingredient_label && labels.contains(label))
+        })
+        .collect();
+    manifests.push(
+        jumbf::manifests(&store)
+            .find(|manifest| manifest.label.as_deref() == Some(ingredient_label))
+            .ok_or(Error::IngredientManifestNotPresent)?,
+    );
+
+    Ok(jumbf::build_store(&store, &manifests))
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    pub async fn test_manifest_store_data() {
+        let test_asset = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+
+        let result = get_manifest_store_data(test_asset, "image/jpeg").await;
+        assert!(result.is_ok());
+    }
+
+    #[wasm_bindgen_test]
+    pub async fn test_assertions() {
+        let test_asset = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+
+        let actions = get_assertions(
+            test_asset,
+            "image/jpeg",
+            &LabelQuery::One("c2pa.actions".to_owned()),
+        )
+        .await
+        .unwrap();
+        assert!(!actions.is_empty());
+        assert!(actions[0].is_from_active_manifest);
+        assert!(actions.iter().all(|a| a.assertion_label == "c2pa.actions"));
+        assert_eq!(
+            actions.iter().filter(|a| a.is_from_active_manifest).count(),
+            1
+        );
+
+        let prefixed = get_assertions(
+            test_asset,
+            "image/jpeg",
+            &LabelQuery::Many(vec!["c2pa.act*".to_owned(), "missing".to_owned()]),
+        )
+        .await
+        .unwrap();
+        assert_eq!(prefixed.len(), actions.len());
+
+        let none = get_assertions(
+            test_asset,
+            "image/jpeg",
+            &LabelQuery::One("missing".to_owned()),
+        )
+        .await
+        .unwrap();
+        assert!(none.is_empty());
+    }
+
+    #[wasm_bindgen_test]
+    pub async fn test_bound_assertions() {
+        let test_asset = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+        let manifest_store = get_manifest_store_data(test_asset, "image/jpeg")
+            .await
+            .unwrap();
+        let active_label = manifest_store.active_label().unwrap().to_owned();
+
+        let bounded = bound_assertions(&manifest_store, 0).unwrap();
+        let assertions = bounded["manifests"][&active_label]["assertions"]
+            .as_array()
+            .unwrap();
+        assert!(!assertions.is_empty());
+        for assertion in assertions {
+            assert!(assertion.get("data").is_none());
+            assert_eq!(assertion["truncated"], true);
+            assert!(assertion["size"].as_u64().unwrap() > 0);
+        }
+
+        // The full data is still available on request
+        let stub = &assertions[0];
+        let label = stub["label"].as_str().unwrap();
+        let data = get_assertion(test_asset, "image/jpeg", &active_label, label)
+            .await
+            .unwrap();
+        assert_eq!(
+            serde_json::to_vec(&data).unwrap().len() as u64,
+            stub["size"].as_u64().unwrap()
+        );
+
+        let unbounded = bound_assertions(&manifest_store, usize::MAX).unwrap();
+        assert_eq!(unbounded, serde_json::to_value(&manifest_store).unwrap());
+
+        assert!(matches!(
+            get_assertion(test_asset, "image/jpeg", &active_label, "missing").await,
+            Err(Error::AssertionNotFound(_))
+        ));
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_signature_material_verifies() {
+        use ring::signature::{self, VerificationAlgorithm};
+        use x509_certificate::CapturedX509Certificate;
+
+        let test_asset = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+
+        let result = get_signature_material(test_asset, "image/jpeg").unwrap();
+        assert_eq!(result.len(), 3);
+
+        for material in result.values() {
+            let cert =
+                CapturedX509Certificate::from_der(material.certificates[0].to_vec()).unwrap();
+            let algorithm: &'static dyn VerificationAlgorithm = match material.alg.as_deref() {
+                Some("es256") => &signature::ECDSA_P256_SHA256_FIXED,
+                Some("es384") => &signature::ECDSA_P384_SHA384_FIXED,
+                Some("ps256") => &signature::RSA_PSS_2048_8192_SHA256,
+                Some("ps384") => &signature::RSA_PSS_2048_8192_SHA384,
+                Some("ps512") => &signature::RSA_PSS_2048_8192_SHA512,
+                Some("ed25519") => &signature::ED25519,
+                alg => panic!("unexpected algorithm {:?}", alg),
+            };
+
+            cert.verify_signed_data_with_algorithm(
+                &material.to_be_signed,
+                &material.signature,
+                algorithm,
+            )
+            .unwrap();
+        }
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_raw_claims_are_signed_payloads() {
+        let test_asset = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+        let store_bytes = c2pa::jumbf_io::load_jumbf_from_memory("image/jpeg", test_asset).unwrap();
+
+        let raw_claims = get_raw_claims(&store_bytes, BinaryEncoding::ArrayBuffer).unwrap();
+        let signature_material = get_signature_material(test_asset, "image/jpeg").unwrap();
+        assert_eq!(raw_claims.len(), signature_material.len());
+
+        for (label, raw_claim) in raw_claims {
+            assert_eq!(raw_claim.claim_cddl_version, 1);
+            match raw_claim.raw_claim_bytes {
+                RawBytes::Bytes(bytes) => {
+                    assert_eq!(bytes.as_slice(), signature_material[&label].payload)
+                }
+                RawBytes::Base64(_) => panic!("expected bytes"),
+            }
+        }
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_hex() {
+        let bytes: Vec<u8> = (0..=255).collect();
+        let hex = to_hex(&bytes);
+        assert_eq!(&hex[..8], "00010203");
+        assert_eq!(&hex[hex.len() - 4..], "feff");
+        assert_eq!(from_hex(&hex), Some(bytes));
+        assert_eq!(from_hex("0aFF"), Some(vec![0x0a, 0xff]));
+        assert_eq!(from_hex("0a0"), None);
+        assert_eq!(from_hex("0g"), None);
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_external_resources() {
+        use crate::jumbf::tests::{manifest, map, store, text};
+        use serde_cbor::Value;
+
+        let ingredient = |thumbnail: &str| {
+            map(vec![
+                ("title", text("ingredient.jpg")),
+                ("thumbnail", map(vec![("url", text(thumbnail))])),
+            ])
+        };
+        let store = store(&[manifest(
+            jumbf::MANIFEST_UUID,
+            "test:urn:uuid:1",
+            &[
+                (
+                    "c2pa.ingredient",
+                    ingredient("https://example.com/thumbnail.jpg"),
+                ),
+                ("c2pa.actions", map(vec![("actions", Value::Array(vec![]))])),
+                (
+                    "c2pa.ingredient__1",
+                    ingredient("self#jumbf=c2pa.assertions/c2pa.thumbnail.ingredient.jpeg"),
+                ),
+                (
+                    "c2pa.ingredient__2",
+                    ingredient("https://example.com/other.png"),
+                ),
+            ],
+        )]);
+
+        let result = get_external_resources(&store).unwrap();
+        assert_eq!(
+            result,
+            vec![
+                ExternalResource {
+                    manifest_label: "test:urn:uuid:1".to_owned(),
+                    ingredient_index: 0,
+                    uri: "https://example.com/thumbnail.jpg".to_owned(),
+                },
+                ExternalResource {
+                    manifest_label: "test:urn:uuid:1".to_owned(),
+                    ingredient_index: 2,
+                    uri: "https://example.com/other.png".to_owned(),
+                },
+            ]
+        );
+
+        let test_asset = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+        let store_bytes = c2pa::jumbf_io::load_jumbf_from_memory("image/jpeg", test_asset).unwrap();
+        assert!(get_external_resources(&store_bytes).unwrap().is_empty());
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_update_manifests() {
+        use crate::jumbf::tests::{manifest, map, store, text};
+
+        let parent_of = |label: &str| {
+            map(vec![
+                ("title", text("parent.jpg")),
+                ("relationship", text("parentOf")),
+                (
+                    "c2pa_manifest",
+                    map(vec![("url", text(&format!("self#jumbf=/c2pa/{}", label)))]),
+                ),
+            ])
+        };
+        let original = manifest(jumbf::MANIFEST_UUID, "urn:uuid:original", &[]);
+
+        // A valid update manifest, which has no hard binding
+        let valid = store(&[
+            original.clone(),
+            manifest(
+                jumbf::UPDATE_MANIFEST_UUID,
+                "urn:uuid:update",
+                &[("c2pa.ingredient", parent_of("urn:uuid:original"))],
+            ),
+        ]);
+        let result = get_update_manifests(&valid).unwrap();
+        assert_eq!(result.len(), 1);
+        let update = &result["urn:uuid:update"];
+        assert!(update.is_update_manifest);
+        assert_eq!(
+            update.updated_manifest.as_deref(),
+            Some("urn:uuid:original")
+        );
+        assert!(update.validation_status.is_empty());
+
+        // An update manifest whose predecessor isn't in the store
+        let missing = store(&[manifest(
+            jumbf::UPDATE_MANIFEST_UUID,
+            "urn:uuid:update",
+            &[("c2pa.ingredient", parent_of("urn:uuid:missing"))],
+        )]);
+        let result = get_update_manifests(&missing).unwrap();
+        let update = &result["urn:uuid:update"];
+        assert_eq!(update.updated_manifest.as_deref(), Some("urn:uuid:missing"));
+        assert_eq!(
+            update.validation_status,
+            vec![UpdateValidationStatus {
+                code: c2pa::validation_status::CLAIM_MISSING,
+                url: "self#jumbf=/c2pa/urn:uuid:update/c2pa.assertions/c2pa.ingredient".to_owned(),
+                explanation: "ingredient: urn:uuid:missing is missing".to_owned(),
+            }]
+        );
+
+        // Update manifests must have exactly one parent
+        let two_parents = store(&[
+            original,
+            manifest(
+                jumbf::UPDATE_MANIFEST_UUID,
+                "urn:uuid:update",
+                &[
+                    ("c2pa.ingredient", parent_of("urn:uuid:original")),
+                    ("c2pa.ingredient__1", parent_of("urn:uuid:original")),
+                ],
+            ),
+        ]);
+        let result = get_update_manifests(&two_parents).unwrap();
+        let codes: Vec<_> = result["urn:uuid:update"]
+            .validation_status
+            .iter()
+            .map(|status| status.code)
+            .collect();
+        assert_eq!(
+            codes,
+            vec![c2pa::validation_status::MANIFEST_UPDATE_WRONG_PARENTS]
+        );
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_truncate_deep_provenance() {
+        // A chain of 200 nested ingredient manifests, each referencing the next one
+        let labels: Vec<String> = (0..200).map(|i| format!("urn:uuid:{}", i)).collect();
+        let index = |label: &str| labels.iter().position(|l| l == label).unwrap();
+        let chain = |label| vec![labels.get(index(label) + 1).map(String::as_str)];
+
+        let truncation = truncate(&labels[0], DEFAULT_MAX_DEPTH, chain);
+        assert_eq!(truncation.truncated, vec![(labels[32].clone(), 0)]);
+        assert_eq!(
+            truncation.omitted,
+            labels[33..].iter().cloned().collect::<BTreeSet<_>>()
+        );
+        assert!(truncate(&labels[0], 200, chain).is_empty());
+
+        // Cycles are neither truncated nor walked forever
+        let cycle = |label| vec![None, Some(labels[(index(label) + 1) % 3].as_str())];
+        assert!(truncate(&labels[0], 5, cycle).is_empty());
+        let truncation = truncate(&labels[0], 1, cycle);
+        assert_eq!(truncation.truncated, vec![(labels[1].clone(), 1)]);
+        assert_eq!(truncation.omitted, BTreeSet::from([labels[2].clone()]));
+    }
+
+    #[wasm_bindgen_test]
+    pub async fn test_truncate_provenance() {
+        let test_asset = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+        let manifest_store = get_manifest_store_data(test_asset, "image/jpeg")
+            .await
+            .unwrap();
+
+        assert!(truncate_provenance(&manifest_store, DEFAULT_MAX_DEPTH).is_empty());
+
+        let truncation = truncate_provenance(&manifest_store, 0);
+        assert_eq!(
+            truncation.omitted.len(),
+            manifest_store.manifests().len() - 1
+        );
+        assert!(truncation
+            .omitted
+            .contains("adobetest:urn:uuid:120c2204-929d-4e97-a3b7-f5ecc9408b79"));
+        assert!(truncation.truncated.contains(&(
+            "adobetest:urn:uuid:825cf3cf-0127-4af3-b65c-c11d0f961e67".to_owned(),
+            1
+        )));
+    }
+
+    #[wasm_bindgen_test]
+    pub async fn test_misdeclared_gif() {
+        let result = get_manifest_store_data(b"GIF89a\x01\x00\x01\x00", "image/jpeg").await;
+        assert!(matches!(
+            result,
+            Err(Error::C2pa(c2pa::Error::UnsupportedType))
+        ));
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_hard_binding_algs() {
+        use crate::jumbf::tests::{manifest, map, store, text};
+
+        let test_asset = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+        let store_bytes = c2pa::jumbf_io::load_jumbf_from_memory("image/jpeg", test_asset).unwrap();
+        let result = get_hard_binding_algs(&store_bytes).unwrap();
+        assert_eq!(
+            result["adobetest:urn:uuid:825cf3cf-0127-4af3-b65c-c11d0f961e67"].hard_binding_alg,
+            "sha256"
+        );
+
+        let store_bytes = store(&[
+            manifest(
+                jumbf::MANIFEST_UUID,
+                "urn:uuid:sha384",
+                &[("c2pa.hash.data", map(vec![("alg", text("sha384"))]))],
+            ),
+            manifest(
+                jumbf::MANIFEST_UUID,
+                "urn:uuid:unbound",
+                &[("c2pa.actions", map(vec![]))],
+            ),
+        ]);
+        let result = get_hard_binding_algs(&store_bytes).unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result["urn:uuid:sha384"].hard_binding_alg, "sha384");
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_normalize_sidecar() {
+        use crate::jumbf::tests::{content_box, super_box};
+
+        let test_asset = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+        let store_bytes = c2pa::jumbf_io::load_jumbf_from_memory("image/jpeg", test_asset).unwrap();
+
+        assert_eq!(
+            normalize_sidecar(&store_bytes),
+            (&store_bytes[..], Some(SidecarFormat::Store))
+        );
+
+        let wrapped = super_box(
+            jumbf::tests::uuid_of(*b"wrap"),
+            "wrapper",
+            &[content_box(*b"json", b"{}"), store_bytes.clone()],
+        );
+        assert_eq!(
+            normalize_sidecar(&wrapped),
+            (&store_bytes[..], Some(SidecarFormat::Wrapped))
+        );
+
+        let mut prefixed = b"c2pa sidecar\n{\"generator\":\"c2patool\"}\n".to_vec();
+        prefixed.extend_from_slice(&store_bytes);
+        prefixed.extend_from_slice(b"trailing");
+        assert_eq!(
+            normalize_sidecar(&prefixed),
+            (&store_bytes[..], Some(SidecarFormat::Prefixed))
+        );
+
+        assert_eq!(
+            normalize_sidecar(b"not a manifest"),
+            (&b"not a manifest"[..], None)
+        );
+    }
+
+    #[wasm_bindgen_test]
+    pub async fn test_legacy_png_store() {
+        let current = include_bytes!("../../../tools/testing/fixtures/self-test/1px.png");
+        let store_bytes = c2pa::jumbf_io::load_jumbf_from_memory("image/png", current).unwrap();
+
+        // The same PNG with its store moved from the caBX chunk to a base64 iTXt chunk
+        let chunk = |kind: &[u8], data: &[u8]| {
+            let mut chunk = (data.len() as u32).to_be_bytes().to_vec();
+            chunk.extend_from_slice(kind);
+            chunk.extend_from_slice(data);
+            chunk.extend_from_slice(&[0; 4]);
+            chunk
+        };
+        let mut text = b"c2pa\0\0\0\0\0".to_vec();
+        text.extend_from_slice(
+            base64::engine::general_purpose::STANDARD
+                .encode(&store_bytes)
+                .as_bytes(),
+        );
+        let cabx_start = crate::presence::find(current, b"caBX").unwrap() - 4;
+        let cabx_end = cabx_start + store_bytes.len() + 12;
+        let mut legacy = current[..cabx_start].to_vec();
+        legacy.extend_from_slice(&chunk(b"iTXt", &text));
+        legacy.extend_from_slice(&current[cabx_end..]);
+
+        assert_eq!(legacy_png_store(&legacy), Some(store_bytes.clone()));
+        assert!(get_manifest_store_data(&legacy, "image/png").await.is_err());
+        let manifest_store = get_manifest_store_data_from_manifest_and_asset_bytes(
+            &store_bytes,
+            "image/png",
+            &legacy,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            manifest_store.active_label(),
+            Some("urn:uuid:a276e355-4273-4628-8956-5d8d4f9021b8")
+        );
+
+        // A current-format store takes precedence
+        let mut both = current[..cabx_end].to_vec();
+        both.extend_from_slice(&chunk(b"iTXt", &text));
+        both.extend_from_slice(&current[cabx_end..]);
+        assert_eq!(legacy_png_store(&both), None);
+
+        // Other text, and compressed text, isn't mistaken for a store
+        let mut other = current[..cabx_start].to_vec();
+        other.extend_from_slice(&chunk(b"iTXt", b"Comment\0\0\0\0\0aGVsbG8="));
+        other.extend_from_slice(&current[cabx_end..]);
+        assert_eq!(legacy_png_store(&other), None);
+        text[5] = 1;
+        let mut compressed = current[..cabx_start].to_vec();
+        compressed.extend_from_slice(&chunk(b"iTXt", &text));
+        compressed.extend_from_slice(&current[cabx_end..]);
+        assert_eq!(legacy_png_store(&compressed), None);
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_claim_versions() {
+        use crate::jumbf::tests::{cbor_box, manifest, map, store, super_box, text};
+
+        let test_asset = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+        let store_bytes = c2pa::jumbf_io::load_jumbf_from_memory("image/jpeg", test_asset).unwrap();
+        let result = get_claim_versions(&store_bytes, MAX_CLAIM_VERSION).unwrap();
+        assert_eq!(result.len(), 3);
+        for claim_version in result.values() {
+            assert_eq!(claim_version.claim_version, 1);
+            assert_eq!(claim_version.spec_version, 1);
+            assert_eq!(claim_version.compliance_level, ComplianceLevel::Full);
+        }
+
+        let v2_claim = map(vec![
+            ("instanceID", text("xmp:iid:1")),
+            ("claim_generator_info", map(vec![("name", text("app"))])),
+            ("signature", text("self#jumbf=c2pa.signature")),
+            ("created_assertions", serde_cbor::Value::Array(vec![])),
+        ]);
+        let store_bytes = store(&[
+            super_box(
+                jumbf::MANIFEST_UUID,
+                "urn:uuid:v2",
+                &[super_box(
+                    jumbf::tests::uuid_of(*b"c2cl"),
+                    "c2pa.claim.v2",
+                    &[cbor_box(&v2_claim)],
+                )],
+            ),
+            // The test builder leaves out most required fields
+            manifest(jumbf::MANIFEST_UUID, "urn:uuid:v1", &[]),
+        ]);
+
+        let result = get_claim_versions(&store_bytes, MAX_CLAIM_VERSION).unwrap();
+        assert_eq!(result["urn:uuid:v2"].claim_version, 2);
+        assert_eq!(result["urn:uuid:v2"].spec_version, 2);
+        assert_eq!(
+            result["urn:uuid:v2"].compliance_level,
+            ComplianceLevel::Full
+        );
+        assert_eq!(result["urn:uuid:v1"].claim_version, 1);
+        assert_eq!(
+            result["urn:uuid:v1"].compliance_level,
+            ComplianceLevel::Partial
+        );
+
+        let result = get_claim_versions(&store_bytes, 1).unwrap();
+        assert_eq!(
+            result["urn:uuid:v2"].compliance_level,
+            ComplianceLevel::UnsupportedVersion
+        );
+        assert_eq!(
+            result["urn:uuid:v1"].compliance_level,
+            ComplianceLevel::Partial
+        );
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_hashed_uri_checks() {
+        let test_asset = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+        let store_bytes = c2pa::jumbf_io::load_jumbf_from_memory("image/jpeg", test_asset).unwrap();
+
+        let result = get_hashed_uri_checks(&store_bytes, false).unwrap();
+        assert_eq!(result.len(), 3);
+        for checks in result.values() {
+            assert!(!checks.assertions.is_empty());
+            assert!(checks
+                .assertions
+                .values()
+                .all(|check| check.hashed_uri_valid
+                    && check.expected_hash.is_none()
+                    && check.actual_hash.is_none()));
+        }
+
+        let active = &result["adobetest:urn:uuid:825cf3cf-0127-4af3-b65c-c11d0f961e67"];
+        let references: Vec<_> = active
+            .ingredients
+            .iter()
+            .filter_map(|ingredient| ingredient.manifest_reference.as_ref())
+            .collect();
+        assert!(!references.is_empty());
+        assert!(references.iter().all(|check| check.hashed_uri_valid));
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_hashed_uri_checks_flag_altered_assertion() {
+        use crate::jumbf::tests::{manifest, map, store, text};
+
+        let mut store_bytes = store(&[manifest(
+            jumbf::MANIFEST_UUID,
+            "urn:uuid:altered",
+            &[
+                ("c2pa.actions", map(vec![("action", text("c2pa.opened"))])),
+                ("stds.exif", map(vec![("exif:Make", text("original"))])),
+                ("c2pa.hash.data", map(vec![("alg", text("sha256"))])),
+            ],
+        )]);
+
+        // Alter the EXIF assertion without updating the claim
+        let position = store_bytes
+            .windows(8)
+            .position(|window| window == b"original")
+            .unwrap();
+        store_bytes[position..position + 8].copy_from_slice(b"tampered");
+
+        let result = get_hashed_uri_checks(&store_bytes, true).unwrap();
+        let assertions = &result["urn:uuid:altered"].assertions;
+        assert_eq!(assertions.len(), 3);
+        assert!(assertions["c2pa.actions"].hashed_uri_valid);
+        assert!(assertions["c2pa.hash.data"].hashed_uri_valid);
+
+        let exif = &assertions["stds.exif"];
+        assert!(!exif.hashed_uri_valid);
+        assert!(exif.expected_hash.is_some());
+        assert_ne!(exif.expected_hash, exif.actual_hash);
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_collection_hash() {
+        use crate::jumbf::tests::{manifest, map, store, text};
+
+        let entry = |name: &str, data: &[u8]| {
+            map(vec![
+                ("uri", text(name)),
+                (
+                    "hash",
+                    serde_cbor::Value::Bytes(Sha256::digest(data).to_vec()),
+                ),
+                ("dc:format", text("image/jpeg")),
+            ])
+        };
+        let store_bytes = store(&[manifest(
+            jumbf::MANIFEST_UUID,
+            "urn:uuid:burst",
+            &[(
+                "c2pa.hash.collection.data",
+                map(vec![
+                    ("alg", text("sha256")),
+                    (
+                        "uris",
+                        serde_cbor::Value::Array(vec![
+                            entry("burst/1.jpg", b"first"),
+                            entry("burst/2.jpg", b"second"),
+                            entry("burst/3.jpg", b"third"),
+                            entry("burst/3.jpg", b"third again"),
+                        ]),
+                    ),
+                ]),
+            )],
+        )]);
+
+        let result = get_collection_hashes(&store_bytes).unwrap();
+        let entries = &result["urn:uuid:burst"].collection_hash;
+        assert_eq!(entries.len(), 4);
+        assert_eq!(entries[0].name, "burst/1.jpg");
+        assert_eq!(entries[0].format.as_deref(), Some("image/jpeg"));
+        assert!(!entries[1].duplicate);
+        assert!(entries[2].duplicate && entries[3].duplicate);
+
+        let check = verify_asset_in_collection(&store_bytes, b"first", "burst/1.jpg").unwrap();
+        assert!(check.hash_valid);
+        assert_eq!(check.manifest_label, "urn:uuid:burst");
+        let check = verify_asset_in_collection(&store_bytes, b"first", "burst/2.jpg").unwrap();
+        assert!(!check.hash_valid);
+        assert_ne!(check.actual_hash.as_ref(), Some(&check.expected_hash));
+
+        assert!(matches!(
+            verify_asset_in_collection(&store_bytes, b"third", "burst/3.jpg"),
+            Err(Error::CollectionEntryAmbiguous(_))
+        ));
+        assert!(matches!(
+            verify_asset_in_collection(&store_bytes, b"fourth", "burst/4.jpg"),
+            Err(Error::CollectionEntryNotFound(_))
+        ));
+
+        let test_asset = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+        let store_bytes = c2pa::jumbf_io::load_jumbf_from_memory("image/jpeg", test_asset).unwrap();
+        assert!(get_collection_hashes(&store_bytes).unwrap().is_empty());
+        assert!(matches!(
+            verify_asset_in_collection(&store_bytes, test_asset, "CAICAI.jpg"),
+            Err(Error::CollectionHashNotFound)
+        ));
+    }
+
+    /// Moves the `APP11` segments before the image data of a JPEG to after its end of image marker
+    pub fn with_trailing_app11(jpeg: &[u8]) -> Vec<u8> {
+        let mut header = jpeg[..2].to_vec();
+        let mut trailing = Vec::new();
+        let mut pos = 2;
+        for segment in JpegSegments::new(jpeg) {
+            if segment.marker == JPEG_SOS {
+                break;
+            }
+            match segment.marker {
+                JPEG_APP11 => trailing.extend_from_slice(&jpeg[pos..segment.end()]),
+                _ => header.extend_from_slice(&jpeg[pos..segment.end()]),
+            }
+            pos = segment.end();
+        }
+        header.extend_from_slice(&jpeg[pos..]);
+        header.extend_from_slice(&trailing);
+        header
+    }
+
+    #[wasm_bindgen_test]
+    pub async fn test_trailing_jpeg_store() {
+        let asset = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+        let store_bytes = c2pa::jumbf_io::load_jumbf_from_memory("image/jpeg", asset).unwrap();
+        assert_eq!(trailing_jpeg_store(asset), None);
+
+        let trailing = with_trailing_app11(asset);
+        assert_eq!(trailing.len(), asset.len());
+        assert_eq!(trailing_jpeg_store(&trailing), Some(store_bytes.clone()));
+        assert!(matches!(
+            get_manifest_store_data(&trailing, "image/jpeg").await,
+            Err(Error::C2pa(c2pa::Error::JumbfNotFound))
+        ));
+        let manifest_store = get_manifest_store_data_from_manifest_and_asset_bytes(
+            &store_bytes,
+            "image/jpeg",
+            &trailing,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            manifest_store.active_label(),
+            Some("adobetest:urn:uuid:825cf3cf-0127-4af3-b65c-c11d0f961e67")
+        );
+        assert_eq!(
+            trailing_app11_warning(&store_bytes),
+            ParseWarning {
+                code: TRAILING_APP11,
+                location: "self#jumbf=/c2pa".to_owned()
+            }
+        );
+
+        // Segments that aren't directly after the marker, or that don't hold a store, are ignored
+        let eoi = jpeg_image_end(&trailing).unwrap();
+        let mut separated = trailing[..eoi].to_vec();
+        separated.extend_from_slice(&[0xFF, 0xFE, 0x00, 0x02]);
+        separated.extend_from_slice(&trailing[eoi..]);
+        assert_eq!(trailing_jpeg_store(&separated), None);
+        let mut other = trailing[..eoi].to_vec();
+        other.extend_from_slice(&[0xFF, JPEG_APP11, 0x00, 0x06, b'X', b'M', b'P', 0]);
+        assert_eq!(trailing_jpeg_store(&other), None);
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test]
+    pub async fn test_read_trailing_app11() {
+        use crate::get_path;
+        use crate::options::{Parsing, ReadOptions};
+        use wasm_bindgen::JsValue;
+
+        let asset = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+        let trailing = with_trailing_app11(asset);
+
+        let strict =
+            crate::read_manifest_store(&trailing, "image/jpeg", &ReadOptions::default()).await;
+        assert!(matches!(
+            strict,
+            Err(Error::C2pa(c2pa::Error::JumbfNotFound))
+        ));
+
+        let options = ReadOptions {
+            parsing: Parsing::Lenient,
+            ..Default::default()
+        };
+        let (lenient, _) = crate::read_manifest_store(&trailing, "image/jpeg", &options)
+            .await
+            .unwrap();
+        assert_eq!(get_path(&lenient, &["embeddingFormat"]), "trailing-app11");
+        assert_eq!(get_path(&lenient, &["nonStandardPlacement"]), true);
+        assert_eq!(
+            get_path(&lenient, &["parseWarnings", "0", "code"]),
+            TRAILING_APP11
+        );
+        assert_eq!(
+            get_path(&lenient, &["active_manifest"]),
+            "adobetest:urn:uuid:825cf3cf-0127-4af3-b65c-c11d0f961e67"
+        );
+        // The hard binding is checked with the exclusions as they were signed, which here still
+        // cover where the segments were before they were moved
+        let codes: Vec<JsValue> = crate::array_items(&get_path(&lenient, &["validation_status"]))
+            .iter()
+            .map(|status| get_path(status, &["code"]))
+            .collect();
+        assert!(codes.contains(&"assertion.dataHash.mismatch".into()));
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_with_active_manifest() {
+        use crate::jumbf::tests::{manifest, store};
+
+        let manifests: Vec<Vec<u8>> = ["urn:uuid:1", "urn:uuid:2", "urn:uuid:3"]
+            .iter()
+            .map(|label| manifest(jumbf::MANIFEST_UUID, label, &[]))
+            .collect();
+        let store_bytes = store(&manifests);
+
+        let labels = |store_bytes: &[u8]| -> Vec<String> {
+            let store = SuperBox::parse(store_bytes).unwrap();
+            jumbf::manifests(&store)
+                .filter_map(|manifest| manifest.label.clone())
+                .collect()
+        };
+        let rebuilt = with_active_manifest(&store_bytes, "urn:uuid:2").unwrap();
+        assert_eq!(labels(&rebuilt), ["urn:uuid:1", "urn:uuid:2"]);
+        let rebuilt = with_active_manifest(&store_bytes, "urn:uuid:3").unwrap();
+        assert_eq!(rebuilt, store_bytes);
+
+        match with_active_manifest(&store_bytes, "urn:uuid:4") {
+            Err(Error::ActiveManifestNotFound { label, available }) => {
+                assert_eq!(label, "urn:uuid:4");
+                assert_eq!(available, ["urn:uuid:1", "urn:uuid:2", "urn:uuid:3"]);
+            }
+            result => panic!("expected ActiveManifestNotFound, got {:?}", result),
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test]
+    pub async fn test_active_manifest_override() {
+        use crate::get_path;
+        use crate::options::ReadOptions;
+        use wasm_bindgen::JsCast;
+
+        // CAICAI was made from CAI, which was made from CA
+        let asset = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+        let read = |label: Option<&str>| {
+            let options = ReadOptions {
+                active_manifest_override: label.map(str::to_owned),
+                ..Default::default()
+            };
+            async move { crate::read_manifest_store(asset, "image/jpeg", &options).await }
+        };
+        let (normal, _) = read(None).await.unwrap();
+        assert!(get_path(&normal, &["overrideApplied"]).is_undefined());
+        let manifests = get_path(&normal, &["manifests"]);
+        assert_eq!(
+            js_sys::Object::keys(manifests.unchecked_ref::<js_sys::Object>()).length(),
+            3
+        );
+
+        let active = get_path(&normal, &["active_manifest"]).as_string().unwrap();
+        let store_bytes = c2pa::jumbf_io::load_jumbf_from_memory("image/jpeg", asset).unwrap();
+        let store = SuperBox::parse(&store_bytes).unwrap();
+        let in_store_order: Vec<String> = jumbf::manifests(&store)
+            .filter_map(|manifest| manifest.label.clone())
+            .collect();
+        assert_eq!(in_store_order.last(), Some(&active));
+
+        for (generation, label) in in_store_order.iter().enumerate() {
+            let (result, _) = read(Some(label)).await.unwrap();
+            assert_eq!(get_path(&result, &["overrideApplied"]), true);
+            assert_eq!(get_path(&result, &["active_manifest"]), label.as_str());
+            let manifests = get_path(&result, &["manifests"]);
+            assert_eq!(
+                js_sys::Object::keys(manifests.unchecked_ref::<js_sys::Object>()).length() as usize,
+                generation + 1
+            );
+            // Only the current manifest's hard binding matches the current bytes
+            let codes: Vec<String> = crate::array_items(&get_path(&result, &["validation_status"]))
+                .iter()
+                .filter_map(|status| get_path(status, &["code"]).as_string())
+                .collect();
+            assert_eq!(
+                codes
+                    .iter()
+                    .any(|code| code == c2pa::validation_status::ASSERTION_DATAHASH_MISMATCH),
+                *label != active,
+                "{} {:?}",
+                label,
+                codes
+            );
+        }
+
+        let err = read(Some("urn:uuid:missing")).await.unwrap_err();
+        match err {
+            Error::ActiveManifestNotFound { available, .. } => {
+                assert_eq!(available, in_store_order)
+            }
+            err => panic!("expected ActiveManifestNotFound, got {:?}", err),
+        }
+    }
+
+    #[wasm_bindgen_test]
+    pub async fn test_repair_store() {
+        let asset = include_bytes!("../../../tools/testing/fixtures/images/sample.pdf");
+        // The manifest store of `sample.pdf`, each with a different defect in the store's superbox
+        let fixtures: [(&[u8], &str); 3] = [
+            (
+                include_bytes!("../../../tools/testing/fixtures/malformed/not-requestable.c2pa"),
+                DESCRIPTION_NOT_REQUESTABLE,
+            ),
+            (
+                include_bytes!(
+                    "../../../tools/testing/fixtures/malformed/description-trailing-data.c2pa"
+                ),
+                DESCRIPTION_TRAILING_DATA,
+            ),
+            (
+                include_bytes!("../../../tools/testing/fixtures/malformed/padding-box.c2pa"),
+                UNEXPECTED_BOX,
+            ),
+        ];
+
+        for &(store_bytes, code) in fixtures.iter() {
+            let strict = get_manifest_store_data_from_manifest_and_asset_bytes(
+                store_bytes,
+                "application/pdf",
+                asset,
+            )
+            .await;
+            assert!(strict.is_err());
+
+            let (repaired, warnings) = repair_store(store_bytes).unwrap().unwrap();
+            assert_eq!(
+                warnings,
+                vec![ParseWarning {
+                    code,
+                    location: "self#jumbf=/c2pa".to_owned()
+                }]
+            );
+            let lenient = get_manifest_store_data_from_manifest_and_asset_bytes(
+                &repaired,
+                "application/pdf",
+                asset,
+            )
+            .await
+            .unwrap();
+            assert!(lenient.active_label().is_some());
+        }
+
+        let store_bytes = c2pa::jumbf_io::load_jumbf_from_memory("application/pdf", asset).unwrap();
+        assert!(repair_store(&store_bytes).unwrap().is_none());
+    }
+
+    #[wasm_bindgen_test]
+    pub async fn test_contain_manifest_errors() {
+        let asset = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+        let ingredient_label = "adobetest:urn:uuid:120c2204-929d-4e97-a3b7-f5ecc9408b79";
+        // The manifest store of `CAICAI.jpg` with the claim of the active manifest's ingredient
+        // overwritten
+        let mut store_bytes = c2pa::jumbf_io::load_jumbf_from_memory("image/jpeg", asset).unwrap();
+        let claim = SuperBox::parse(&store_bytes)
+            .unwrap()
+            .find(ingredient_label)
+            .and_then(SuperBox::claim)
+            .and_then(SuperBox::payload)
+            .unwrap()
+            .to_vec();
+        let position = store_bytes
+            .windows(claim.len())
+            .position(|bytes| bytes == claim.as_slice())
+            .unwrap();
+        store_bytes[position..position + claim.len()].fill(0xff);
+        let store_bytes = store_bytes.as_slice();
+
+        let strict =
+            get_manifest_store_data_from_manifest_and_asset_bytes(store_bytes, "image/jpeg", asset)
+                .await;
+        assert!(matches!(
+            strict,
+            Err(Error::C2pa(c2pa::Error::ClaimDecoding))
+        ));
+
+        let (contained, errors) = contain_manifest_errors(store_bytes, "image/jpeg", asset)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[ingredient_label].code, "C2pa(ClaimDecoding)");
+
+        let manifest_store =
+            get_manifest_store_data_from_manifest_and_asset_bytes(&contained, "image/jpeg", asset)
+                .await
+                .unwrap();
+        assert_eq!(
+            manifest_store.active_label(),
+            Some("adobetest:urn:uuid:825cf3cf-0127-4af3-b65c-c11d0f961e67")
+        );
+        assert_eq!(manifest_store.manifests().len(), 2);
+        assert!(manifest_store.get(ingredient_label).is_none());
+
+        // Nothing is contained when every manifest can be read
+        let store_bytes = c2pa::jumbf_io::load_jumbf_from_memory("image/jpeg", asset).unwrap();
+        assert!(contain_manifest_errors(&store_bytes, "image/jpeg", asset)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_manifest_order() {
+        let test_asset = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+        let store_bytes = c2pa::jumbf_io::load_jumbf_from_memory("image/jpeg", test_asset).unwrap();
+
+        let order = get_manifest_order(&store_bytes).unwrap();
+        assert_eq!(order.len(), 3);
+        // The active manifest is the last one in the store
+        assert_eq!(
+            order.last().map(String::as_str),
+            Some("adobetest:urn:uuid:825cf3cf-0127-4af3-b65c-c11d0f961e67")
+        );
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_revocation_info_not_present() {
+        let test_asset = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+        let store_bytes = c2pa::jumbf_io::load_jumbf_from_memory("image/jpeg", test_asset).unwrap();
+
+        let result = get_revocation_info(&store_bytes).unwrap();
+        assert_eq!(result.len(), 3);
+        assert!(result
+            .values()
+            .all(|info| info.revocation_status == ocsp::RevocationStatus::NotPresent));
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_asset_identity() {
+        let test_asset = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+        assert_eq!(
+            get_asset_identity(test_asset, "image/jpeg").unwrap(),
+            AssetIdentity::Manifest {
+                active_manifest: "adobetest:urn:uuid:825cf3cf-0127-4af3-b65c-c11d0f961e67"
+                    .to_owned(),
+                bindings: vec![BindingHash {
+                    label: "c2pa.hash.data".to_owned(),
+                    alg: Some("sha256".to_owned()),
+                    hashes: vec![
+                        "605bd79ebae6db22a12634dd6d477fafa3b508ffe6729b9912401d3eef0912ac"
+                            .to_owned()
+                    ],
+                }],
+                manifest_store: IdentityHash {
+                    alg: "sha256".to_owned(),
+                    hash: "fb5472b8f42d670051b48064959a1a82850f5b65dfb99cadc533963a4a53e6d4"
+                        .to_owned(),
+                },
+            }
+        );
+
+        // An asset without a manifest store gets a fingerprint of its bytes
+        let test_asset = include_bytes!("../../../tools/testing/fixtures/images/I.jpg");
+        let identity = get_asset_identity(test_asset, "image/jpeg").unwrap();
+        assert_eq!(
+            identity,
+            AssetIdentity::Fingerprint {
+                fingerprint: IdentityHash {
+                    alg: "xxh3-64".to_owned(),
+                    hash: "48d2bfe8d3848b6f".to_owned(),
+                },
+                cryptographic: false,
+            }
+        );
+        assert_eq!(
+            serde_json::to_value(&identity).unwrap(),
+            serde_json::json!({
+                "kind": "fingerprint",
+                "fingerprint": { "alg": "xxh3-64", "hash": "48d2bfe8d3848b6f" },
+                "cryptographic": false,
+            })
+        );
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_signing_subjects() {
+        let test_asset = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+        let store_bytes = c2pa::jumbf_io::load_jumbf_from_memory("image/jpeg", test_asset).unwrap();
+
+        let result = get_signing_subjects(&store_bytes).unwrap();
+        assert_eq!(result.len(), 3);
+        assert!(result.values().all(|subject| subject
+            == &ocsp::Subject {
+                organizations: vec!["Adobe, Inc.".to_owned()],
+                common_names: vec!["contentauthenticity.org".to_owned()],
+            }));
+    }
+
+    #[wasm_bindgen_test]
+    pub async fn test_ingredient_manifest_bytes() {
+        let test_asset = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+        let active_label = "adobetest:urn:uuid:825cf3cf-0127-4af3-b65c-c11d0f961e67";
+
+        let by_index = get_ingredient_manifest_bytes(
+            test_asset,
+            "image/jpeg",
+            active_label,
+            &IngredientRef::Index(1),
+        )
+        .await
+        .unwrap();
+        let by_instance_id = get_ingredient_manifest_bytes(
+            test_asset,
+            "image/jpeg",
+            active_label,
+            &IngredientRef::InstanceId("xmp:iid:4f66b468-ec33-47bd-87aa-7faa279ab025".to_owned()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(by_index, by_instance_id);
+
+        let store = SuperBox::parse(&by_index).unwrap();
+        let labels: Vec<_> = jumbf::manifests(&store)
+            .map(|manifest| manifest.label.as_deref().unwrap())
+            .collect();
+        assert_eq!(
+            labels,
+            vec!["adobetest:urn:uuid:120c2204-929d-4e97-a3b7-f5ecc9408b79"]
+        );
+    }
+
+    #[wasm_bindgen_test]
+    pub async fn test_ingredient_manifest_not_present() {
+        let test_asset = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+
+        let result = get_ingredient_manifest_bytes(
+            test_asset,
+            "image/jpeg",
+            "adobetest:urn:uuid:120c2204-929d-4e97-a3b7-f5ecc9408b79",
+            &IngredientRef::Index(0),
+        )
+        .await;
+        assert!(matches!(result, Err(Error::IngredientManifestNotPresent)));
+
+        let result = get_ingredient_manifest_bytes(
+            test_asset,
+            "image/jpeg",
+            "adobetest:urn:uuid:120c2204-929d-4e97-a3b7-f5ecc9408b79",
+            &IngredientRef::Index(5),
+        )
+        .await;
+        assert!(matches!(result, Err(Error::IngredientNotFound)));
+    }
+
+    async fn trace_asset(test_asset: &[u8]) -> crate::trace::Trace {
+        let store_bytes = c2pa::jumbf_io::load_jumbf_from_memory("image/jpeg", test_asset).unwrap();
+        let manifest_store = get_manifest_store_data(test_asset, "image/jpeg")
+            .await
+            .unwrap();
+        let mut tracer = Tracer::new(crate::trace::MAX_TRACE_STEPS);
+        trace_validation(
+            &store_bytes,
+            manifest_store.validation_status().unwrap_or_default(),
+            manifest_store.active_label(),
+            &mut tracer,
+        );
+        tracer.finish()
+    }
+
+    #[wasm_bindgen_test]
+    pub async fn test_trace_validation() {
+        use StepKind::*;
+
+        let test_asset = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+        let trace = trace_asset(test_asset).await;
+        assert_eq!(trace.dropped_steps, 0);
+
+        // Consecutive steps of the same kind, such as each assertion's, are listed once
+        let mut kinds: Vec<StepKind> = trace.steps.iter().map(|step| step.step).collect();
+        kinds.dedup();
+        let manifest = [
+            ManifestLocated,
+            ClaimDecoded,
+            AssertionHashChecked,
+            SignatureDecoded,
+            CertificateChainLinkChecked,
+            RevocationChecked,
+            SignatureVerified,
+            CredentialTrustChecked,
+            TimestampChecked,
+        ];
+        let active_manifest = [
+            ManifestLocated,
+            ClaimDecoded,
+            AssertionHashChecked,
+            IngredientReferenceChecked,
+            SignatureDecoded,
+            CertificateChainLinkChecked,
+            RevocationChecked,
+            SignatureVerified,
+            CredentialTrustChecked,
+            TimestampChecked,
+            HardBindingChecked,
+        ];
+        // CAICAI's store holds two earlier manifests ahead of the active one
+        let expected: Vec<StepKind> = std::iter::once(StoreLocated)
+            .chain(manifest.iter().copied())
+            .chain(manifest.iter().copied())
+            .chain(active_manifest.iter().copied())
+            .collect();
+        assert_eq!(kinds, expected);
+
+        let active_label = "adobetest:urn:uuid:825cf3cf-0127-4af3-b65c-c11d0f961e67";
+        for step in &trace.steps[1..] {
+            let performed_by_c2pa = matches!(
+                step.step,
+                SignatureVerified | CredentialTrustChecked | TimestampChecked | HardBindingChecked
+            );
+            assert_eq!(step.duration_ms.is_none(), performed_by_c2pa);
+            assert!(step.manifest.is_some());
+            // Signatures are only verified in WebAssembly builds of the c2pa crate
+            if !performed_by_c2pa {
+                assert_ne!(step.outcome, Outcome::Failed, "{:?}", step);
+            }
+        }
+        let reference = trace
+            .steps
+            .iter()
+            .find(|step| step.step == IngredientReferenceChecked)
+            .unwrap();
+        assert_eq!(reference.manifest.as_deref(), Some(active_label));
+        assert_eq!(reference.target.as_deref(), Some("c2pa.ingredient"));
+        assert!(reference.input_hash.is_some());
+
+        // A changed asset fails the hard binding of the active manifest
+        let test_asset = include_bytes!("../../../tools/testing/fixtures/images/E-dat-CA.jpg");
+        let trace = trace_asset(test_asset).await;
+        let hard_binding = trace.steps.last().unwrap();
+        assert_eq!(hard_binding.step, HardBindingChecked);
+        assert_eq!(hard_binding.outcome, Outcome::Failed);
+        assert_eq!(hard_binding.codes, vec!["assertion.dataHash.mismatch"]);
+    }
+
+    /// CAICAI with the signature of its manifest at `index`, in store order, claiming COSE
+    /// algorithm -48, which isn't registered
+    ///
+    /// The protected headers of its three signatures name PS256 (-37) and nothing else.
+    /// Signatures aren't covered by any hash, so the rest of the store still validates.
+    pub fn with_unknown_alg(index: usize) -> Vec<u8> {
+        let mut test_asset =
+            include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg").to_vec();
+        let protected_header = [0x44, 0xa1, 0x01, 0x38, 0x24];
+        let position = test_asset
+            .windows(protected_header.len())
+            .enumerate()
+            .filter(|(_, bytes)| *bytes == protected_header)
+            .nth(index)
+            .unwrap()
+            .0;
+        test_asset[position + 4] = 0x2f;
+        test_asset
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_unsupported_algs() {
+        let test_asset = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+        let store_bytes = c2pa::jumbf_io::load_jumbf_from_memory("image/jpeg", test_asset).unwrap();
+        assert!(get_unsupported_algs(&store_bytes).unwrap().is_empty());
+
+        let order = get_manifest_order(&store_bytes).unwrap();
+        for (index, label) in order.iter().enumerate() {
+            let test_asset = with_unknown_alg(index);
+            let store_bytes =
+                c2pa::jumbf_io::load_jumbf_from_memory("image/jpeg", &test_asset).unwrap();
+            let unsupported_algs = get_unsupported_algs(&store_bytes).unwrap();
+            assert_eq!(
+                unsupported_algs.into_iter().collect::<Vec<_>>(),
+                vec![(label.clone(), -48)]
+            );
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test]
+    pub async fn test_on_unsupported_alg_option() {
+        use crate::get_path;
+        use js_sys::{Object, Reflect, Uint8Array};
+        use wasm_bindgen::JsValue;
+
+        async fn read(test_asset: &[u8], on_unsupported_alg: Option<&str>) -> JsValue {
+            let options = Object::new();
+            if let Some(on_unsupported_alg) = on_unsupported_alg {
+                Reflect::set(
+                    &options,
+                    &"onUnsupportedAlg".into(),
+                    &on_unsupported_alg.into(),
+                )
+                .unwrap();
+            }
+            let result = crate::get_manifest_store_from_array_buffer(
+                Uint8Array::from(test_asset).buffer().into(),
+                "image/jpeg".to_owned(),
+                options.into(),
+            );
+            match wasm_bindgen_futures::JsFuture::from(result).await {
+                Ok(result) => result,
+                Err(err) => err,
+            }
+        }
+        fn statuses(result: &JsValue, label: &str) -> Vec<String> {
+            crate::array_items(&get_path(result, &["validation_status"]))
+                .iter()
+                .filter(|status| {
+                    get_path(status, &["url"])
+                        .as_string()
+                        .and_then(|url| validation::manifest_label(&url).map(str::to_owned))
+                        .map_or(true, |status_label| status_label == label)
+                })
+                .filter_map(|status| get_path(status, &["code"]).as_string())
+                .collect()
+        }
+
+        const ACTIVE: &str = "adobetest:urn:uuid:825cf3cf-0127-4af3-b65c-c11d0f961e67";
+        const INGREDIENT: &str = "adobetest:urn:uuid:879beec2-74bb-4150-8245-9176dd6a8972";
+        let test_asset = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+        let unmodified = read(test_asset, None).await;
+
+        // By default the read fails
+        let err = read(&with_unknown_alg(2), None).await;
+        assert_eq!(
+            get_path(&err, &["code"]),
+            "coseSignatureAlgorithmNotSupported"
+        );
+
+        // Degraded, the active manifest is readable but doesn't validate
+        let result = read(&with_unknown_alg(2), Some("degrade")).await;
+        let active = get_path(&result, &["manifests", ACTIVE]);
+        assert_eq!(
+            get_path(&active, &["signatureVerification"]),
+            "unsupported-algorithm"
+        );
+        assert_eq!(get_path(&active, &["signatureAlgorithmId"]), -48);
+        assert_eq!(
+            get_path(&active, &["title"]),
+            get_path(&unmodified, &["manifests", ACTIVE, "title"])
+        );
+        assert_eq!(get_path(&active, &["worstSeverity"]), "error");
+        assert_eq!(get_path(&result, &["worstSeverity"]), "error");
+        assert_eq!(statuses(&result, ACTIVE), vec!["algorithm.unsupported"]);
+
+        // An ingredient's unverifiable signature leaves the active manifest as it was
+        let result = read(&with_unknown_alg(0), Some("degrade")).await;
+        assert_eq!(
+            get_path(&result, &["manifests", INGREDIENT, "signatureVerification"]),
+            "unsupported-algorithm"
+        );
+        assert_eq!(
+            get_path(&result, &["manifests", ACTIVE, "signatureVerification"]),
+            JsValue::UNDEFINED
+        );
+        assert_eq!(statuses(&result, ACTIVE), statuses(&unmodified, ACTIVE));
+        assert_eq!(
+            get_path(&result, &["manifests", ACTIVE, "worstSeverity"]),
+            get_path(&unmodified, &["manifests", ACTIVE, "worstSeverity"])
+        );
+        assert!(statuses(&result, INGREDIENT).contains(&"algorithm.unsupported".to_owned()));
     }
 }
@@ -0,0 +1,183 @@
+// Copyright 2024 Adobe
+// All Rights Reserved.
+//
+// NOTICE: Adobe permits you to use, modify, and distribute this file in
+// accordance with the terms of the Adobe license agreement accompanying
+// it.
+
+//! Tracks the toolkit's heap usage.
+//!
+//! In WebAssembly builds every allocation goes through [`TrackingAllocator`], which counts the
+//! bytes currently allocated and the most allocated at once since the last reset. WebAssembly
+//! memory can't shrink, so freed bytes are reused by later allocations but the memory's size stays
+//! at its high-water mark. Native builds, such as the benchmarks, keep their own allocator and
+//! report no allocations.
+use crate::cache;
+use serde::Serialize;
+#[cfg(target_arch = "wasm32")]
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The size of a WebAssembly memory page
+const PAGE_SIZE: u64 = 64 * 1024;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+/// Counts the bytes allocated through the system allocator
+#[cfg(target_arch = "wasm32")]
+pub struct TrackingAllocator;
+
+#[cfg(target_arch = "wasm32")]
+impl TrackingAllocator {
+    fn allocated(size: usize) {
+        let allocated = ALLOCATED.fetch_add(size, Ordering::Relaxed) + size;
+        PEAK.fetch_max(allocated, Ordering::Relaxed);
+    }
+
+    fn freed(size: usize) {
+        ALLOCATED.fetch_sub(size, Ordering::Relaxed);
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+unsafe impl GlobalAlloc for TrackingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            Self::allocated(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            Self::allocated(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        Self::freed(layout.size());
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            Self::freed(layout.size());
+            Self::allocated(new_size);
+        }
+        new_ptr
+    }
+}
+
+// Only the WebAssembly module owns its heap, crates linking the toolkit natively choose their own
+#[cfg(target_arch = "wasm32")]
+#[global_allocator]
+static ALLOCATOR: TrackingAllocator = TrackingAllocator;
+
+/// Result of `getMemoryStats`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MemoryStats {
+    /// The size of the WebAssembly memory, in 64 KiB pages
+    pub memory_pages: usize,
+    /// 4 GiB once the memory has grown to its largest, which doesn't fit in a 32-bit `usize`
+    pub memory_bytes: u64,
+    /// Bytes currently allocated on the toolkit's heap
+    pub allocated_bytes: usize,
+    /// The most bytes allocated at once since the toolkit loaded or the peak was last reset
+    pub peak_allocated_bytes: usize,
+    /// The size of the manifest stores behind the cached read results, which are held as
+    /// JavaScript objects
+    pub read_cache_bytes: usize,
+    pub read_cache_entries: usize,
+}
+
+#[cfg(target_arch = "wasm32")]
+fn memory_pages() -> usize {
+    core::arch::wasm32::memory_size(0)
+}
+
+/// Native builds, such as the benchmarks, have no WebAssembly memory
+#[cfg(not(target_arch = "wasm32"))]
+fn memory_pages() -> usize {
+    0
+}
+
+pub fn stats() -> MemoryStats {
+    let read_cache = cache::stats().unwrap_or_default();
+    MemoryStats {
+        memory_pages: memory_pages(),
+        memory_bytes: memory_pages() as u64 * PAGE_SIZE,
+        allocated_bytes: ALLOCATED.load(Ordering::Relaxed),
+        peak_allocated_bytes: PEAK.load(Ordering::Relaxed),
+        read_cache_bytes: read_cache.bytes,
+        read_cache_entries: read_cache.entries,
+    }
+}
+
+/// Starts tracking the peak again from the bytes currently allocated
+pub fn reset_peak() {
+    PEAK.store(ALLOCATED.load(Ordering::Relaxed), Ordering::Relaxed);
+}
+
+/// Drops the cached read results and the read cache's spare capacity, then resets the peak
+pub fn release() {
+    cache::clear();
+    cache::shrink();
+    reset_peak();
+}
+
+// Native builds don't track allocations, and run tests in parallel
+#[cfg(all(test, target_arch = "wasm32"))]
+pub mod tests {
+    use super::*;
+    use wasm_bindgen_futures::JsFuture;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    pub async fn test_stats() {
+        const SIZE: usize = 32 * 1024 * 1024;
+
+        let before = stats();
+        let buffer = vec![1u8; SIZE];
+        let during = stats();
+        assert!(during.allocated_bytes >= before.allocated_bytes + SIZE);
+        assert!(during.peak_allocated_bytes >= during.allocated_bytes);
+        drop(buffer);
+
+        // Freed bytes stop counting, but stay in the peak until it is reset
+        let after = stats();
+        assert!(after.allocated_bytes < during.allocated_bytes);
+        assert!(after.peak_allocated_bytes >= during.allocated_bytes);
+        assert_eq!(after.memory_bytes, after.memory_pages as u64 * PAGE_SIZE);
+
+        // A cached read
+        cache::enable(cache::ReadCacheOptions::default());
+        cache::clear();
+        let test_asset = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+        let buf = js_sys::Uint8Array::from(&test_asset[..]).buffer();
+        JsFuture::from(crate::get_manifest_store_from_array_buffer(
+            buf.into(),
+            "image/jpeg".to_owned(),
+            wasm_bindgen::JsValue::UNDEFINED,
+        ))
+        .await
+        .unwrap();
+        let cached = stats();
+        assert!(cached.read_cache_entries > 0);
+        assert!(cached.read_cache_bytes > 0);
+
+        release();
+        let released = stats();
+        assert_eq!(released.read_cache_entries, 0);
+        assert_eq!(released.read_cache_bytes, 0);
+        assert!(released.allocated_bytes < cached.allocated_bytes);
+        assert!(released.peak_allocated_bytes < during.allocated_bytes);
+    }
+}
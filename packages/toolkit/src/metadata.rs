@@ -0,0 +1,307 @@
+// Copyright 2024 Adobe
+// All Rights Reserved.
+//
+// NOTICE: Adobe permits you to use, modify, and distribute this file in
+// accordance with the terms of the Adobe license agreement accompanying
+// it.
+
+//! Reads the IPTC photo metadata of a manifest's metadata assertion into a typed shape.
+//!
+//! The assertion is JSON-LD whose keys are XMP properties, such as `photoshop:Credit`. As with the
+//! XMP attribution in `xmp`, properties are recognized by namespace, resolved through the
+//! assertion's `@context`, so a writer's choice of prefix doesn't matter. Properties outside the
+//! mapping, and mapped properties whose values can't be read as text, are kept as raw JSON in
+//! `other`.
+use crate::xmp::{self, Licensor};
+use c2pa::assertions::labels::IPTC_PHOTO_METADATA;
+use c2pa::ManifestStore;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+/// Label of the metadata assertion
+pub const METADATA: &str = "c2pa.metadata";
+
+/// The namespaces of the usual prefixes, for properties whose prefix `@context` doesn't declare
+const DEFAULT_NAMESPACES: &[(&str, &[u8])] = &[
+    ("dc", xmp::DC_NS),
+    ("photoshop", xmp::PHOTOSHOP_NS),
+    ("xmpRights", xmp::XMP_RIGHTS_NS),
+    ("plus", xmp::PLUS_NS),
+    ("Iptc4xmpExt", xmp::IPTC_EXT_NS),
+];
+
+/// The common IPTC Photo Metadata fields of a metadata assertion
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IptcMetadata {
+    /// `dc:creator`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub creators: Vec<String>,
+    /// `dc:title`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// `photoshop:Headline`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub headline: Option<String>,
+    /// `dc:description`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub caption: Option<String>,
+    /// `dc:subject`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub keywords: Vec<String>,
+    /// `photoshop:DateCreated`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date_created: Option<String>,
+    /// `photoshop:Credit`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub credit_line: Option<String>,
+    /// `photoshop:Source`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// `dc:rights`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub copyright_notice: Option<String>,
+    /// `xmpRights:UsageTerms`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage_terms: Option<String>,
+    /// `xmpRights:WebStatement`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub web_statement: Option<String>,
+    /// `plus:Licensor`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub licensors: Vec<Licensor>,
+    /// The properties outside the mapping, keyed as they appear in the assertion
+    #[serde(skip_serializing_if = "Map::is_empty")]
+    pub other: Map<String, Value>,
+}
+
+/// A manifest's IPTC metadata, merged into the manifest
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestMetadata {
+    pub iptc_metadata: IptcMetadata,
+}
+
+/// Reads the metadata assertion of each manifest that has one
+///
+/// Both the `c2pa.metadata` assertion and the `stds.iptc.photo-metadata` assertion of earlier
+/// versions of the C2PA specification are read. Only a manifest's first one is used.
+pub fn get_iptc_metadata(manifest_store: &ManifestStore) -> HashMap<String, ManifestMetadata> {
+    manifest_store
+        .manifests()
+        .iter()
+        .filter_map(|(label, manifest)| {
+            let data = manifest
+                .assertions()
+                .iter()
+                .find(|assertion| {
+                    let label = assertion.label();
+                    label == METADATA || label == IPTC_PHOTO_METADATA
+                })?
+                .value()
+                .ok()?;
+            let iptc_metadata = iptc_metadata(data)?;
+            Some((label.clone(), ManifestMetadata { iptc_metadata }))
+        })
+        .collect()
+}
+
+/// Maps the data of a metadata assertion, which must be a JSON object
+pub fn iptc_metadata(data: &Value) -> Option<IptcMetadata> {
+    let data = data.as_object()?;
+    let context = data.get("@context").and_then(Value::as_object);
+    let mut metadata = IptcMetadata::default();
+
+    for (key, value) in data {
+        if key.starts_with('@') {
+            continue;
+        }
+
+        let read = match property(context, key) {
+            Some((xmp::DC_NS, "creator")) => set_all(&mut metadata.creators, value),
+            Some((xmp::DC_NS, "title")) => set(&mut metadata.title, value),
+            Some((xmp::PHOTOSHOP_NS, "Headline")) => set(&mut metadata.headline, value),
+            Some((xmp::DC_NS, "description")) => set(&mut metadata.caption, value),
+            Some((xmp::DC_NS, "subject")) => set_all(&mut metadata.keywords, value),
+            Some((xmp::PHOTOSHOP_NS, "DateCreated")) => set(&mut metadata.date_created, value),
+            Some((xmp::PHOTOSHOP_NS, "Credit")) => set(&mut metadata.credit_line, value),
+            Some((xmp::PHOTOSHOP_NS, "Source")) => set(&mut metadata.source, value),
+            Some((xmp::DC_NS, "rights")) => set(&mut metadata.copyright_notice, value),
+            Some((xmp::XMP_RIGHTS_NS, "UsageTerms")) => set(&mut metadata.usage_terms, value),
+            Some((xmp::XMP_RIGHTS_NS, "WebStatement")) => set(&mut metadata.web_statement, value),
+            // Licensor is part of the PLUS schema, though some writers put it in IPTC's own
+            Some((xmp::PLUS_NS, "Licensor")) | Some((xmp::IPTC_EXT_NS, "Licensor")) => {
+                licensors(context, value).map(|licensors| metadata.licensors = licensors)
+            }
+            _ => None,
+        };
+        if read.is_none() {
+            metadata.other.insert(key.clone(), value.clone());
+        }
+    }
+
+    Some(metadata)
+}
+
+/// Resolves a prefixed key to its namespace and local name
+fn property<'a>(
+    context: Option<&'a Map<String, Value>>,
+    key: &'a str,
+) -> Option<(&'a [u8], &'a str)> {
+    let (prefix, local_name) = key.split_once(':')?;
+    let namespace = match context.and_then(|context| context.get(prefix)) {
+        Some(namespace) => namespace.as_str()?.as_bytes(),
+        None => DEFAULT_NAMESPACES
+            .iter()
+            .find(|(default_prefix, _)| *default_prefix == prefix)
+            .map(|(_, namespace)| *namespace)?,
+    };
+    Some((namespace, local_name))
+}
+
+fn set(field: &mut Option<String>, value: &Value) -> Option<()> {
+    *field = Some(text(value)?);
+    Some(())
+}
+
+fn set_all(field: &mut Vec<String>, value: &Value) -> Option<()> {
+    *field = match value {
+        Value::Array(items) => items.iter().map(text).collect::<Option<_>>()?,
+        value => vec![text(value)?],
+    };
+    Some(())
+}
+
+/// Reads a text value, which may be a JSON-LD value object, a language alternative keyed by
+/// language with `x-default` preferred, or a list whose first item is used
+fn text(value: &Value) -> Option<String> {
+    let text = match value {
+        Value::String(text) => text.trim().to_owned(),
+        Value::Array(items) => return items.first().and_then(text),
+        Value::Object(object) => {
+            let value = object
+                .get("@value")
+                .or_else(|| object.get("x-default"))
+                .or_else(|| object.values().next())?;
+            return text(value);
+        }
+        _ => return None,
+    };
+    Some(text).filter(|text| !text.is_empty())
+}
+
+fn licensors(context: Option<&Map<String, Value>>, value: &Value) -> Option<Vec<Licensor>> {
+    let items = match value {
+        Value::Array(items) => items.iter().collect(),
+        value => vec![value],
+    };
+
+    items
+        .into_iter()
+        .map(|item| {
+            let mut licensor = Licensor::default();
+            for (key, value) in item.as_object()? {
+                match property(context, key) {
+                    Some((xmp::PLUS_NS, "LicensorName"))
+                    | Some((xmp::IPTC_EXT_NS, "LicensorName")) => {
+                        licensor.name = text(value);
+                    }
+                    Some((xmp::PLUS_NS, "LicensorURL"))
+                    | Some((xmp::IPTC_EXT_NS, "LicensorURL")) => {
+                        licensor.url = text(value);
+                    }
+                    _ => {}
+                }
+            }
+            Some(licensor)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use serde_json::json;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    pub fn test_iptc_metadata() {
+        let data = json!({
+            "@context": {
+                "dc": "http://purl.org/dc/elements/1.1/",
+                "photoshop": "http://ns.adobe.com/photoshop/1.0/",
+                "rights": "http://ns.adobe.com/xap/1.0/rights/",
+                "plus": "http://ns.useplus.org/ldf/xmp/1.0/",
+                "exif": "http://ns.adobe.com/exif/1.0/",
+            },
+            "dc:creator": ["Jane Doe", "Ana Müller"],
+            "dc:title": { "x-default": "Harbour at dawn", "de": "Hafen im Morgengrauen" },
+            "dc:description": { "@value": "Fishing boats leaving the harbour", "@language": "en" },
+            "dc:subject": ["harbour", "boats"],
+            "photoshop:Credit": "Example News",
+            "photoshop:DateCreated": "2023-05-01",
+            "dc:rights": "© 2023 Example News",
+            "rights:WebStatement": "https://example.com/licensing",
+            "plus:Licensor": [{
+                "plus:LicensorName": "Example Licensing",
+                "plus:LicensorURL": "https://example.com/license",
+            }],
+            "exif:GPSLatitude": "39,21.102N",
+            "photoshop:Headline": 42,
+        });
+
+        let metadata = iptc_metadata(&data).unwrap();
+        assert_eq!(metadata.creators, ["Jane Doe", "Ana Müller"]);
+        assert_eq!(metadata.title.as_deref(), Some("Harbour at dawn"));
+        assert_eq!(
+            metadata.caption.as_deref(),
+            Some("Fishing boats leaving the harbour")
+        );
+        assert_eq!(metadata.keywords, ["harbour", "boats"]);
+        assert_eq!(metadata.credit_line.as_deref(), Some("Example News"));
+        assert_eq!(metadata.date_created.as_deref(), Some("2023-05-01"));
+        assert_eq!(
+            metadata.copyright_notice.as_deref(),
+            Some("© 2023 Example News")
+        );
+        // Found by namespace, though the prefix is not the usual one
+        assert_eq!(
+            metadata.web_statement.as_deref(),
+            Some("https://example.com/licensing")
+        );
+        assert_eq!(
+            metadata.licensors,
+            [Licensor {
+                name: Some("Example Licensing".to_owned()),
+                url: Some("https://example.com/license".to_owned()),
+            }]
+        );
+
+        // Unmapped properties and unreadable values are kept as they are
+        assert_eq!(metadata.other.len(), 2);
+        assert_eq!(metadata.other["exif:GPSLatitude"], "39,21.102N");
+        assert_eq!(metadata.other["photoshop:Headline"], 42);
+        assert_eq!(metadata.headline, None);
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_prefixes() {
+        // Undeclared prefixes fall back to their usual namespace
+        let metadata = iptc_metadata(&json!({ "photoshop:Credit": "Example" })).unwrap();
+        assert_eq!(metadata.credit_line.as_deref(), Some("Example"));
+
+        // A declared prefix is resolved through `@context`, even if it is a usual one
+        let metadata = iptc_metadata(&json!({
+            "@context": { "photoshop": "https://example.com/not-photoshop/" },
+            "photoshop:Credit": "Example",
+        }))
+        .unwrap();
+        assert_eq!(metadata.credit_line, None);
+        assert!(metadata.other.contains_key("photoshop:Credit"));
+
+        assert!(iptc_metadata(&json!(["not", "an", "object"])).is_none());
+    }
+}
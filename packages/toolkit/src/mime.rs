@@ -0,0 +1,172 @@
+// Copyright 2024 Adobe
+// All Rights Reserved.
+//
+// NOTICE: Adobe permits you to use, modify, and distribute this file in
+// accordance with the terms of the Adobe license agreement accompanying
+// it.
+
+//! Identifies asset formats from their leading bytes, regardless of the declared mime type.
+use crate::asset_info::ifd0_entry;
+
+/// Signatures of the formats we recognize, and the mime type each one maps to
+const SIGNATURES: &[(&[u8], &str)] = &[
+    (b"GIF87a", "image/gif"),
+    (b"GIF89a", "image/gif"),
+    (&[0xFF, 0xD8, 0xFF], "image/jpeg"),
+    (
+        &[0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A],
+        "image/png",
+    ),
+    (b"%PDF-", "application/pdf"),
+];
+
+/// Signatures of TIFF files, which DNG files share: little (`II`) and big (`MM`) endian, each in
+/// classic and BigTIFF form
+const TIFF_SIGNATURES: &[&[u8]] = &[b"II*\0", b"MM\0*", b"II+\0", b"MM\0+"];
+
+/// The `DNGVersion` tag, which sets DNG files apart from other TIFF files
+const DNG_VERSION_TAG: u64 = 50706;
+
+/// Formats that the c2pa crate has no asset handler for
+const UNSUPPORTED: &[&str] = &["image/gif"];
+
+/// Returns the mime type matching the asset's signature, if it is one we recognize
+pub fn sniff_mime_type(data: &[u8]) -> Option<&'static str> {
+    if TIFF_SIGNATURES
+        .iter()
+        .any(|signature| data.starts_with(signature))
+    {
+        return Some(if is_dng(data) {
+            "image/x-adobe-dng"
+        } else {
+            "image/tiff"
+        });
+    }
+
+    SIGNATURES
+        .iter()
+        .find(|(signature, _)| data.starts_with(signature))
+        .map(|(_, mime_type)| *mime_type)
+}
+
+/// Returns `true` if the asset is in a format that can't carry a manifest we can read
+///
+/// This catches misdeclared assets early instead of letting them fail in the declared
+/// format's parser with a less helpful error.
+pub fn is_unsupported_format(data: &[u8]) -> bool {
+    sniff_mime_type(data).is_some_and(|mime_type| UNSUPPORTED.contains(&mime_type))
+}
+
+/// Returns `true` if the first IFD of a TIFF file has a `DNGVersion` entry
+fn is_dng(data: &[u8]) -> bool {
+    ifd0_entry(data, DNG_VERSION_TAG).is_some()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    pub fn test_sniff_mime_type() {
+        let jpeg = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+
+        assert_eq!(sniff_mime_type(jpeg), Some("image/jpeg"));
+        assert_eq!(
+            sniff_mime_type(b"GIF89a\x01\x00\x01\x00"),
+            Some("image/gif")
+        );
+        assert_eq!(sniff_mime_type(b"RIFF"), None);
+        assert!(is_unsupported_format(b"GIF87a\x01\x00\x01\x00"));
+        assert!(!is_unsupported_format(jpeg));
+    }
+
+    /// Builds a classic TIFF whose first IFD holds entries with the given tags, followed by a
+    /// C2PA entry holding `store` if it isn't empty
+    fn tiff(big_endian: bool, tags: &[u16], store: &[u8]) -> Vec<u8> {
+        let u16_bytes = |value: u16| match big_endian {
+            true => value.to_be_bytes(),
+            false => value.to_le_bytes(),
+        };
+        let u32_bytes = |value: u32| match big_endian {
+            true => value.to_be_bytes(),
+            false => value.to_le_bytes(),
+        };
+
+        let mut out = if big_endian {
+            b"MM".to_vec()
+        } else {
+            b"II".to_vec()
+        };
+        out.extend_from_slice(&u16_bytes(42));
+        out.extend_from_slice(&u32_bytes(8));
+        let count = tags.len() + usize::from(!store.is_empty());
+        out.extend_from_slice(&u16_bytes(count as u16));
+        for tag in tags {
+            // A single BYTE value
+            out.extend_from_slice(&u16_bytes(*tag));
+            out.extend_from_slice(&u16_bytes(1));
+            out.extend_from_slice(&u32_bytes(1));
+            out.extend_from_slice(&u32_bytes(0));
+        }
+        if !store.is_empty() {
+            // UNDEFINED bytes, stored after the IFD
+            out.extend_from_slice(&u16_bytes(52545));
+            out.extend_from_slice(&u16_bytes(7));
+            out.extend_from_slice(&u32_bytes(store.len() as u32));
+            out.extend_from_slice(&u32_bytes((out.len() + 8) as u32));
+        }
+        out.extend_from_slice(&u32_bytes(0));
+        out.extend_from_slice(store);
+        out
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_sniff_tiff_and_dng() {
+        // ImageWidth, ImageLength, and DNGVersion
+        for big_endian in [false, true] {
+            assert_eq!(
+                sniff_mime_type(&tiff(big_endian, &[256, 257], &[])),
+                Some("image/tiff")
+            );
+            assert_eq!(
+                sniff_mime_type(&tiff(big_endian, &[256, 257, 50706], &[])),
+                Some("image/x-adobe-dng")
+            );
+        }
+
+        // An IFD that runs past the end of the data
+        let mut truncated = tiff(false, &[256, 50706], &[]);
+        truncated.truncate(20);
+        assert_eq!(sniff_mime_type(&truncated), Some("image/tiff"));
+    }
+
+    #[wasm_bindgen_test]
+    pub async fn test_read_tiff_and_dng() {
+        use crate::manifest_store::get_manifest_store_data;
+
+        let test_asset = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+        let store_bytes = c2pa::jumbf_io::load_jumbf_from_memory("image/jpeg", test_asset).unwrap();
+
+        // The sniffed mime type finds the store in either byte order
+        for big_endian in [false, true] {
+            for (tags, expected) in [
+                (&[256, 257][..], "image/tiff"),
+                (&[256, 257, 50706][..], "image/x-adobe-dng"),
+            ] {
+                let asset = tiff(big_endian, tags, &store_bytes);
+                let mime_type = sniff_mime_type(&asset).unwrap();
+                assert_eq!(mime_type, expected);
+                assert_eq!(
+                    c2pa::jumbf_io::load_jumbf_from_memory(mime_type, &asset).unwrap(),
+                    store_bytes
+                );
+                // The store was signed for another asset, but its manifests are still read
+                let manifest_store = get_manifest_store_data(&asset, mime_type).await.unwrap();
+                assert!(manifest_store.active_label().is_some());
+            }
+        }
+    }
+}
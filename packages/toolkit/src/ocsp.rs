@@ -0,0 +1,369 @@
+// Copyright 2024 Adobe
+// All Rights Reserved.
+//
+// NOTICE: Adobe permits you to use, modify, and distribute this file in
+// accordance with the terms of the Adobe license agreement accompanying
+// it.
+
+//! Checks OCSP responses stapled to a manifest signature (RFC 6960).
+//!
+//! The response is matched against the signing certificate and its status reported, but its own
+//! signature is not verified.
+use log::warn;
+use serde::Serialize;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+pub const TAG_INTEGER: u8 = 0x02;
+pub const TAG_OCTET_STRING: u8 = 0x04;
+pub const TAG_OID: u8 = 0x06;
+const TAG_ENUMERATED: u8 = 0x0A;
+pub const TAG_SEQUENCE: u8 = 0x30;
+pub const TAG_SET: u8 = 0x31;
+const TAG_UTC_TIME: u8 = 0x17;
+pub const TAG_GENERALIZED_TIME: u8 = 0x18;
+pub const TAG_EXPLICIT_0: u8 = 0xA0;
+const TAG_CERT_STATUS_GOOD: u8 = 0x80;
+const TAG_CERT_STATUS_REVOKED: u8 = 0xA1;
+const TAG_CERT_STATUS_UNKNOWN: u8 = 0x82;
+
+/// id-pkix-ocsp-basic (1.3.6.1.5.5.7.48.1.1)
+const OID_OCSP_BASIC: &[u8] = &[0x2B, 0x06, 0x01, 0x05, 0x05, 0x07, 0x30, 0x01, 0x01];
+/// id-sha1 (1.3.14.3.2.26)
+const OID_SHA1: &[u8] = &[0x2B, 0x0E, 0x03, 0x02, 0x1A];
+/// id-sha256 (2.16.840.1.101.3.4.2.1)
+const OID_SHA256: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01];
+/// 2.5.4.3, id-at-commonName
+const OID_COMMON_NAME: &[u8] = &[0x55, 0x04, 0x03];
+/// 2.5.4.10, id-at-organizationName
+const OID_ORGANIZATION: &[u8] = &[0x55, 0x04, 0x0A];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RevocationStatus {
+    Good,
+    Revoked,
+    /// The responder doesn't know the certificate, or the response couldn't be used
+    Unknown,
+    /// No OCSP response was stapled to the signature
+    NotPresent,
+}
+
+/// Revocation details that are merged into a manifest's `signature_info`
+#[derive(Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RevocationInfo {
+    pub revocation_status: RevocationStatus,
+    /// When the OCSP response was produced, as an RFC 3339 timestamp
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ocsp_produced_at: Option<String>,
+}
+
+impl RevocationInfo {
+    fn unknown() -> Self {
+        Self {
+            revocation_status: RevocationStatus::Unknown,
+            ocsp_produced_at: None,
+        }
+    }
+}
+
+/// Determines the revocation status of the signing certificate from the stapled OCSP responses
+///
+/// Responses that are malformed or don't cover the certificate result in an `Unknown` status.
+pub fn revocation_info(ocsp_responses: &[&[u8]], signing_cert: Option<&[u8]>) -> RevocationInfo {
+    let ocsp_response = match ocsp_responses.first() {
+        Some(ocsp_response) => ocsp_response,
+        None => {
+            return RevocationInfo {
+                revocation_status: RevocationStatus::NotPresent,
+                ocsp_produced_at: None,
+            }
+        }
+    };
+
+    let result = signing_cert
+        .ok_or("missing signing certificate")
+        .and_then(|cert| check_response(ocsp_response, cert));
+
+    result.unwrap_or_else(|err| {
+        warn!("Ignoring stapled OCSP response: {}", err);
+        RevocationInfo::unknown()
+    })
+}
+
+pub type DerResult<T> = std::result::Result<T, &'static str>;
+
+/// The relevant parts of the signing certificate
+struct Certificate<'a> {
+    serial_number: &'a [u8],
+    issuer: &'a [u8],
+    not_before: String,
+    not_after: String,
+    subject: &'a [u8],
+}
+
+/// The names in a certificate's subject
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Subject {
+    pub organizations: Vec<String>,
+    pub common_names: Vec<String>,
+}
+
+/// Returns `true` if `cert` names the subject of `issuer` as its issuer
+///
+/// Only the names are compared. The signature linking the two is left to the c2pa crate.
+pub fn is_issued_by(cert: &[u8], issuer: &[u8]) -> DerResult<bool> {
+    Ok(parse_certificate(cert)?.issuer == parse_certificate(issuer)?.subject)
+}
+
+/// Reads the organization and common names of a certificate's subject
+pub fn certificate_subject(cert: &[u8]) -> DerResult<Subject> {
+    let (mut rdns, _) = expect(parse_certificate(cert)?.subject, TAG_SEQUENCE)?;
+    let mut subject = Subject::default();
+
+    while !rdns.is_empty() {
+        let (mut attributes, remaining) = expect(rdns, TAG_SET)?;
+        rdns = remaining;
+        while !attributes.is_empty() {
+            let (attribute, remaining) = expect(attributes, TAG_SEQUENCE)?;
+            attributes = remaining;
+            let (oid, rest) = expect(attribute, TAG_OID)?;
+            // Any of the directory string types, which are read as UTF-8
+            let (_, value, _) = read(rest)?;
+            let value = String::from_utf8_lossy(value).into_owned();
+            match oid {
+                OID_ORGANIZATION => subject.organizations.push(value),
+                OID_COMMON_NAME => subject.common_names.push(value),
+                _ => {}
+            }
+        }
+    }
+
+    Ok(subject)
+}
+
+/// Reads the `notBefore` and `notAfter` of a certificate as RFC 3339 timestamps
+pub fn certificate_validity(cert: &[u8]) -> DerResult<(String, String)> {
+    let cert = parse_certificate(cert)?;
+    Ok((cert.not_before, cert.not_after))
+}
+
+fn check_response(ocsp_response: &[u8], cert: &[u8]) -> DerResult<RevocationInfo> {
+    let cert = parse_certificate(cert)?;
+
+    // OCSPResponse
+    let (response, _) = expect(ocsp_response, TAG_SEQUENCE)?;
+    let (status, rest) = expect(response, TAG_ENUMERATED)?;
+    if status != [0] {
+        return Err("unsuccessful OCSP response");
+    }
+    let (response_bytes, _) = expect(rest, TAG_EXPLICIT_0)?;
+    let (response_bytes, _) = expect(response_bytes, TAG_SEQUENCE)?;
+    let (response_type, rest) = expect(response_bytes, TAG_OID)?;
+    if response_type != OID_OCSP_BASIC {
+        return Err("unsupported OCSP response type");
+    }
+    let (basic_response, _) = expect(rest, TAG_OCTET_STRING)?;
+
+    // BasicOCSPResponse and ResponseData
+    let (basic_response, _) = expect(basic_response, TAG_SEQUENCE)?;
+    let (response_data, _) = expect(basic_response, TAG_SEQUENCE)?;
+    let mut rest = response_data;
+    if rest.first() == Some(&TAG_EXPLICIT_0) {
+        rest = read(rest)?.2;
+    }
+    // The responder ID can be either a name or a key hash
+    let (_, _, rest) = read(rest)?;
+    let (produced_at, rest) = read_time(rest)?;
+    let (mut responses, _) = expect(rest, TAG_SEQUENCE)?;
+
+    if produced_at < cert.not_before || produced_at > cert.not_after {
+        return Err("response was produced outside of the certificate's validity period");
+    }
+
+    while !responses.is_empty() {
+        let (single_response, remaining) = expect(responses, TAG_SEQUENCE)?;
+        responses = remaining;
+
+        let (cert_id, rest) = expect(single_response, TAG_SEQUENCE)?;
+        if !covers(cert_id, &cert)? {
+            continue;
+        }
+
+        let (status_tag, _, _) = read(rest)?;
+        let revocation_status = match status_tag {
+            TAG_CERT_STATUS_GOOD => RevocationStatus::Good,
+            TAG_CERT_STATUS_REVOKED => RevocationStatus::Revoked,
+            TAG_CERT_STATUS_UNKNOWN => RevocationStatus::Unknown,
+            _ => return Err("invalid certificate status"),
+        };
+
+        return Ok(RevocationInfo {
+            revocation_status,
+            ocsp_produced_at: Some(produced_at),
+        });
+    }
+
+    Err("response does not cover the signing certificate")
+}
+
+/// Checks whether a `CertID` identifies the given certificate
+fn covers(cert_id: &[u8], cert: &Certificate) -> DerResult<bool> {
+    let (hash_algorithm, rest) = expect(cert_id, TAG_SEQUENCE)?;
+    let (hash_algorithm, _) = expect(hash_algorithm, TAG_OID)?;
+    let (issuer_name_hash, rest) = expect(rest, TAG_OCTET_STRING)?;
+    let (_issuer_key_hash, rest) = expect(rest, TAG_OCTET_STRING)?;
+    let (serial_number, _) = expect(rest, TAG_INTEGER)?;
+
+    if serial_number != cert.serial_number {
+        return Ok(false);
+    }
+
+    // Only the serial number can be compared when the hash algorithm isn't supported
+    Ok(match hash_algorithm {
+        OID_SHA1 => Sha1::digest(cert.issuer).as_slice() == issuer_name_hash,
+        OID_SHA256 => Sha256::digest(cert.issuer).as_slice() == issuer_name_hash,
+        _ => true,
+    })
+}
+
+fn parse_certificate(cert: &[u8]) -> DerResult<Certificate<'_>> {
+    let (cert, _) = expect(cert, TAG_SEQUENCE)?;
+    let (tbs_certificate, _) = expect(cert, TAG_SEQUENCE)?;
+    let mut rest = tbs_certificate;
+    if rest.first() == Some(&TAG_EXPLICIT_0) {
+        rest = read(rest)?.2;
+    }
+    let (serial_number, rest) = expect(rest, TAG_INTEGER)?;
+    let (_signature, rest) = expect(rest, TAG_SEQUENCE)?;
+    let issuer_len = rest.len() - expect(rest, TAG_SEQUENCE)?.1.len();
+    let (issuer, rest) = rest.split_at(issuer_len);
+    let (validity, rest) = expect(rest, TAG_SEQUENCE)?;
+    let (not_before, validity_rest) = read_time(validity)?;
+    let (not_after, _) = read_time(validity_rest)?;
+    let subject_len = rest.len() - expect(rest, TAG_SEQUENCE)?.1.len();
+
+    Ok(Certificate {
+        serial_number,
+        issuer,
+        not_before,
+        not_after,
+        subject: &rest[..subject_len],
+    })
+}
+
+/// Reads a DER element, returning its tag, contents and the remaining bytes
+pub fn read(data: &[u8]) -> DerResult<(u8, &[u8], &[u8])> {
+    let (&tag, rest) = data.split_first().ok_or("truncated DER")?;
+    let (&first, rest) = rest.split_first().ok_or("truncated DER")?;
+
+    let (len, rest) = if first < 0x80 {
+        (first as usize, rest)
+    } else {
+        let count = (first & 0x7F) as usize;
+        if count == 0 || count > 4 || rest.len() < count {
+            return Err("invalid DER length");
+        }
+        let len = rest[..count]
+            .iter()
+            .fold(0usize, |len, b| (len << 8) | *b as usize);
+        (len, &rest[count..])
+    };
+
+    if rest.len() < len {
+        return Err("truncated DER");
+    }
+    Ok((tag, &rest[..len], &rest[len..]))
+}
+
+/// Reads a DER element that must have the given tag
+pub fn expect(data: &[u8], expected: u8) -> DerResult<(&[u8], &[u8])> {
+    let (tag, contents, rest) = read(data)?;
+    if tag != expected {
+        return Err("unexpected DER element");
+    }
+    Ok((contents, rest))
+}
+
+/// Reads a `UTCTime` or `GeneralizedTime` as an RFC 3339 timestamp, which compares chronologically
+pub fn read_time(data: &[u8]) -> DerResult<(String, &[u8])> {
+    let (tag, contents, rest) = read(data)?;
+    let time = std::str::from_utf8(contents).map_err(|_err| "invalid time")?;
+
+    let time = match tag {
+        TAG_UTC_TIME if time.len() >= 12 => {
+            let century = if &time[..2] < "50" { "20" } else { "19" };
+            format!("{}{}", century, time)
+        }
+        TAG_GENERALIZED_TIME if time.len() >= 14 => time.to_owned(),
+        _ => return Err("invalid time"),
+    };
+    if !time.is_char_boundary(14) || !time[..14].bytes().all(|b| b.is_ascii_digit()) {
+        return Err("invalid time");
+    }
+
+    Ok((
+        format!(
+            "{}-{}-{}T{}:{}:{}Z",
+            &time[..4],
+            &time[4..6],
+            &time[6..8],
+            &time[8..10],
+            &time[10..12],
+            &time[12..14]
+        ),
+        rest,
+    ))
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
+
+    const LEAF: &[u8] = include_bytes!("../../../tools/testing/fixtures/ocsp/leaf.der");
+    const OTHER_LEAF: &[u8] = include_bytes!("../../../tools/testing/fixtures/ocsp/other-leaf.der");
+    const GOOD: &[u8] = include_bytes!("../../../tools/testing/fixtures/ocsp/good.der");
+    const REVOKED: &[u8] = include_bytes!("../../../tools/testing/fixtures/ocsp/revoked.der");
+
+    #[wasm_bindgen_test]
+    pub fn test_revocation_status() {
+        let good = revocation_info(&[GOOD], Some(LEAF));
+        assert_eq!(good.revocation_status, RevocationStatus::Good);
+        assert!(good.ocsp_produced_at.is_some());
+
+        let revoked = revocation_info(&[REVOKED], Some(LEAF));
+        assert_eq!(revoked.revocation_status, RevocationStatus::Revoked);
+
+        let not_present = revocation_info(&[], Some(LEAF));
+        assert_eq!(not_present.revocation_status, RevocationStatus::NotPresent);
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_unusable_responses_are_unknown() {
+        assert_eq!(
+            revocation_info(&[GOOD], Some(OTHER_LEAF)),
+            RevocationInfo::unknown()
+        );
+        assert_eq!(
+            revocation_info(&[&GOOD[..GOOD.len() / 2]], Some(LEAF)),
+            RevocationInfo::unknown()
+        );
+        assert_eq!(revocation_info(&[GOOD], None), RevocationInfo::unknown());
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_certificate_subject() {
+        assert_eq!(
+            certificate_subject(LEAF),
+            Ok(Subject {
+                organizations: vec![],
+                common_names: vec!["Test Signer".to_owned()],
+            })
+        );
+        assert!(certificate_subject(&LEAF[..LEAF.len() / 2]).is_err());
+    }
+}
@@ -0,0 +1,198 @@
+// Copyright 2024 Adobe
+// All Rights Reserved.
+//
+// NOTICE: Adobe permits you to use, modify, and distribute this file in
+// accordance with the terms of the Adobe license agreement accompanying
+// it.
+use crate::manifest_store::{DEFAULT_MAX_DEPTH, MAX_CLAIM_VERSION};
+use crate::output_schema::SchemaVersion;
+use serde::{Deserialize, Serialize};
+
+/// Options accepted by the manifest store read APIs
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ReadOptions {
+    /// Attach the claim bytes, exactly as signed, to each manifest
+    pub include_raw_claim: bool,
+    pub raw_claim_encoding: BinaryEncoding,
+    /// Resolve the localized strings of each manifest for this BCP 47 locale
+    pub locale: Option<String>,
+    /// How many ingredients deep the provenance chain is followed from the active manifest
+    pub max_depth: usize,
+    /// Include the expected and actual digests behind each hashed URI check
+    pub include_hash_digests: bool,
+    /// Report manifests whose claim version is above this as unsupported
+    pub max_supported_claim_version: u32,
+    /// Count warnings as errors in each `worstSeverity`
+    pub treat_warnings_as_errors: bool,
+    pub parsing: Parsing,
+    /// Leave out the data of assertions larger than this many bytes of JSON
+    pub max_assertion_bytes: Option<usize>,
+    /// Attach the asset's dimensions and duration, read from its container headers
+    pub include_asset_info: bool,
+    /// Look for manifest stores embedded the way early CAI experiments did, when the asset has no
+    /// current-format store
+    pub legacy_embeddings: bool,
+    pub serialization: Serialization,
+    /// Attach a trace of the validation walk as `trace`
+    pub trace: bool,
+    pub on_unsupported_alg: OnUnsupportedAlg,
+    /// The shape of the output, which is version 1 if not given
+    pub schema_version: Option<SchemaVersion>,
+    /// Validate the asset as if the manifest with this label were the active one
+    pub active_manifest_override: Option<String>,
+    pub timestamp_skew: TimestampSkew,
+    /// Fetches ingredient thumbnails that are stored outside of the manifest store
+    ///
+    /// Functions can't be deserialized, so this is read from the options separately.
+    #[serde(skip)]
+    pub resource_resolver: Option<js_sys::Function>,
+    /// Ingredient assets to check against the ingredients with their instance or document ID
+    ///
+    /// These are left out of the cache key, since reads with them aren't cached.
+    #[serde(skip_serializing)]
+    pub ingredient_assets: Vec<IngredientAsset>,
+}
+
+impl Default for ReadOptions {
+    fn default() -> Self {
+        Self {
+            include_raw_claim: false,
+            raw_claim_encoding: BinaryEncoding::default(),
+            locale: None,
+            max_depth: DEFAULT_MAX_DEPTH,
+            include_hash_digests: false,
+            max_supported_claim_version: MAX_CLAIM_VERSION,
+            treat_warnings_as_errors: false,
+            parsing: Parsing::default(),
+            max_assertion_bytes: None,
+            include_asset_info: false,
+            legacy_embeddings: false,
+            serialization: Serialization::default(),
+            trace: false,
+            on_unsupported_alg: OnUnsupportedAlg::default(),
+            schema_version: None,
+            active_manifest_override: None,
+            timestamp_skew: TimestampSkew::default(),
+            resource_resolver: None,
+            ingredient_assets: Vec::new(),
+        }
+    }
+}
+
+/// An ingredient asset supplied alongside the main asset, identified like its ingredient
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IngredientAsset {
+    pub instance_id: Option<String>,
+    pub document_id: Option<String>,
+    pub buffer: serde_bytes::ByteBuf,
+    pub mime_type: String,
+}
+
+/// How binary data is returned to JavaScript
+#[derive(Debug, Default, Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BinaryEncoding {
+    #[default]
+    ArrayBuffer,
+    Base64,
+}
+
+/// How the manifest store output is shaped
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Serialization {
+    /// Return keyed collections as plain objects rather than `Map`s
+    pub maps_as_objects: bool,
+    /// Convert every byte field to this representation, instead of each keeping its own
+    pub bytes_as: Option<BytesAs>,
+    /// Encode hashes as strings, wherever they appear
+    pub binary_encoding: DigestEncoding,
+}
+
+impl Default for Serialization {
+    fn default() -> Self {
+        Self {
+            maps_as_objects: true,
+            bytes_as: None,
+            binary_encoding: DigestEncoding::default(),
+        }
+    }
+}
+
+/// How the `binaryEncoding` serialization option returns hashes
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DigestEncoding {
+    /// Each hash keeps its own representation
+    #[default]
+    None,
+    Hex,
+    Base64,
+    /// URL-safe base64 without padding
+    Base64url,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BytesAs {
+    Uint8Array,
+    Base64,
+    /// An array of numbers
+    Array,
+}
+
+/// How manifest stores with structural defects are handled
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Parsing {
+    /// Defects fail the read, as they do in the c2pa crate
+    #[default]
+    Strict,
+    /// Defects that can be repaired without touching signed or hashed data are tolerated and
+    /// reported as parse warnings
+    Lenient,
+}
+
+/// How signature timestamps dated too far from the time of validation or the signing certificate
+/// are handled
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TimestampSkew {
+    /// How far, in milliseconds, a timestamp may lie ahead of the time of validation or before the
+    /// signing certificate's `notBefore`
+    pub allowed_skew_ms: f64,
+    pub policy: SkewPolicy,
+}
+
+impl Default for TimestampSkew {
+    fn default() -> Self {
+        Self {
+            allowed_skew_ms: 5.0 * 60.0 * 1000.0,
+            policy: SkewPolicy::default(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SkewPolicy {
+    /// Report the timestamp and add a warning
+    #[default]
+    Warn,
+    /// Report the timestamp as untrusted, and check the signing certificate's validity at the time
+    /// of validation instead of the time the timestamp gives
+    Untrusted,
+}
+
+/// How manifests signed with an algorithm this build can't verify are handled
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum OnUnsupportedAlg {
+    /// Fail the read, as the c2pa crate would
+    #[default]
+    Error,
+    /// Return the manifests with their signatures marked as unverified
+    Degrade,
+}
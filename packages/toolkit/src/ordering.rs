@@ -0,0 +1,151 @@
+// Copyright 2024 Adobe
+// All Rights Reserved.
+//
+// NOTICE: Adobe permits you to use, modify, and distribute this file in
+// accordance with the terms of the Adobe license agreement accompanying
+// it.
+
+//! Puts the manifest store output in a deterministic order.
+//!
+//! Several parts of the c2pa crate's output come from hash maps, whose iteration order differs
+//! between reads. The output is ordered as follows:
+//! - manifests by their position in the manifest store,
+//! - assertions by label, then instance,
+//! - ingredients in the order the manifest declares them (which the c2pa crate preserves),
+//! - validation statuses by code, then URL,
+//! - resources, claim generator hints, and ingredient metadata by key.
+use crate::error::{Error, Result};
+use crate::{array_items, get_path};
+use js_sys::{Array, Object, Reflect};
+use wasm_bindgen::prelude::*;
+
+const VALIDATION_STATUS_ORDER: &[&str] = &["code", "url"];
+const ASSERTION_ORDER: &[&str] = &["label", "instance"];
+
+/// Orders the manifest store output, given the labels of the manifests in store order
+pub fn order_manifest_store(js_value: &JsValue, manifest_order: &[String]) -> Result<()> {
+    reorder_properties(js_value, "manifests", manifest_order)?;
+    sort_array(js_value, "validation_status", VALIDATION_STATUS_ORDER)?;
+
+    let manifests = get_path(js_value, &["manifests"]);
+    for manifest in Object::values(manifests.unchecked_ref()).iter() {
+        sort_array(&manifest, "assertions", ASSERTION_ORDER)?;
+        reorder_properties(&manifest, "claim_generator_hints", &[])?;
+        reorder_properties(&get_path(&manifest, &["resources"]), "resources", &[])?;
+
+        for ingredient in array_items(&get_path(&manifest, &["ingredients"])) {
+            sort_array(&ingredient, "validation_status", VALIDATION_STATUS_ORDER)?;
+            reorder_properties(&ingredient, "metadata", &[])?;
+            reorder_properties(&get_path(&ingredient, &["resources"]), "resources", &[])?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Rebuilds the object at `parent[key]` with its properties in the order of `order`, followed
+/// by any others sorted by key
+fn reorder_properties(parent: &JsValue, key: &str, order: &[String]) -> Result<()> {
+    let object = get_path(parent, &[key]);
+    let object = match object.dyn_ref::<Object>() {
+        Some(object) if !Array::is_array(object) => object,
+        _ => return Ok(()),
+    };
+
+    let mut keys: Vec<String> = Object::keys(object)
+        .iter()
+        .filter_map(|key| key.as_string())
+        .collect();
+    keys.sort_by_cached_key(|key| {
+        let position = order.iter().position(|label| label == key);
+        (position.unwrap_or(usize::MAX), key.clone())
+    });
+
+    let ordered = Object::new();
+    for key in keys {
+        let key = JsValue::from(key);
+        let value = Reflect::get(object, &key).map_err(|_err| Error::JavaScriptConversion)?;
+        Reflect::set(&ordered, &key, &value).map_err(|_err| Error::JavaScriptConversion)?;
+    }
+    Reflect::set(parent, &key.into(), &ordered).map_err(|_err| Error::JavaScriptConversion)?;
+
+    Ok(())
+}
+
+/// A property value that elements are sorted by
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum SortKey {
+    Missing,
+    Number(i64),
+    Text(String),
+}
+
+impl From<JsValue> for SortKey {
+    fn from(value: JsValue) -> Self {
+        if let Some(number) = value.as_f64() {
+            Self::Number(number as i64)
+        } else if let Some(text) = value.as_string() {
+            Self::Text(text)
+        } else {
+            Self::Missing
+        }
+    }
+}
+
+/// Sorts the array at `parent[key]` in place by the given properties of its elements
+///
+/// The sort is stable, so elements that compare equal keep their order.
+fn sort_array(parent: &JsValue, key: &str, properties: &[&str]) -> Result<()> {
+    let array = match get_path(parent, &[key]).dyn_into::<Array>() {
+        Ok(array) => array,
+        Err(_) => return Ok(()),
+    };
+
+    let mut items: Vec<(Vec<SortKey>, JsValue)> = array
+        .iter()
+        .map(|item| {
+            let sort_keys = properties
+                .iter()
+                .map(|property| get_path(&item, &[property]).into())
+                .collect();
+            (sort_keys, item)
+        })
+        .collect();
+    items.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    for (index, (_, item)) in items.into_iter().enumerate() {
+        array.set(index as u32, item);
+    }
+
+    Ok(())
+}
+
+// The output can only be built in a JavaScript environment
+#[cfg(all(test, target_arch = "wasm32"))]
+pub mod tests {
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    pub async fn test_output_is_deterministic() {
+        use wasm_bindgen::JsValue;
+
+        let test_asset = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+        let mut outputs = Vec::new();
+        for _ in 0..50 {
+            let buf = js_sys::Uint8Array::from(&test_asset[..]).buffer();
+            let result =
+                wasm_bindgen_futures::JsFuture::from(crate::get_manifest_store_from_array_buffer(
+                    buf.into(),
+                    "image/jpeg".to_owned(),
+                    JsValue::UNDEFINED,
+                ))
+                .await
+                .unwrap();
+            outputs.push(js_sys::JSON::stringify(&result).unwrap().as_string());
+        }
+
+        assert!(outputs.windows(2).all(|pair| pair[0] == pair[1]));
+    }
+}
@@ -0,0 +1,187 @@
+// Copyright 2024 Adobe
+// All Rights Reserved.
+//
+// NOTICE: Adobe permits you to use, modify, and distribute this file in
+// accordance with the terms of the Adobe license agreement accompanying
+// it.
+
+//! Versions of the manifest store output, for the `schemaVersion` read option.
+//!
+//! The read APIs always build the current shape, version 2. Version 1 is the shape the output had
+//! before it was versioned, and is mapped from version 2 by taking out what version 2 added, so
+//! that validation fixes reach both. Version 1 stays the default for a release so that consumers
+//! with snapshots of the output can move when they are ready; reads that don't ask for a version
+//! log a deprecation warning once.
+use crate::error::{Error, Result};
+use crate::get_path;
+use js_sys::{Object, Reflect};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::convert::TryFrom;
+use wasm_bindgen::prelude::*;
+
+/// The properties version 2 adds to the manifest store
+const STORE_ADDITIONS: &[&str] = &["schemaVersion"];
+
+/// The properties version 2 adds to each manifest
+const MANIFEST_ADDITIONS: &[&str] = &[];
+
+/// The properties version 2 adds to each manifest's `signature_info`
+const SIGNATURE_INFO_ADDITIONS: &[&str] = &["timestampSkew"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "u8", into = "u8")]
+pub enum SchemaVersion {
+    V1,
+    V2,
+}
+
+impl SchemaVersion {
+    /// The version the read APIs build
+    pub const CURRENT: SchemaVersion = SchemaVersion::V2;
+}
+
+impl TryFrom<u8> for SchemaVersion {
+    type Error = String;
+
+    fn try_from(version: u8) -> std::result::Result<Self, Self::Error> {
+        match version {
+            1 => Ok(SchemaVersion::V1),
+            2 => Ok(SchemaVersion::V2),
+            version => Err(format!("unsupported schemaVersion {}", version)),
+        }
+    }
+}
+
+impl From<SchemaVersion> for u8 {
+    fn from(version: SchemaVersion) -> Self {
+        match version {
+            SchemaVersion::V1 => 1,
+            SchemaVersion::V2 => 2,
+        }
+    }
+}
+
+thread_local! {
+    static DEPRECATION_LOGGED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Marks a manifest store output with the version the read APIs build
+pub fn stamp(js_value: &JsValue) -> Result<()> {
+    Reflect::set(
+        js_value,
+        &"schemaVersion".into(),
+        &u8::from(SchemaVersion::CURRENT).into(),
+    )
+    .map(|_| ())
+    .map_err(|_err| Error::JavaScriptConversion)
+}
+
+/// Maps a manifest store output to the requested version, which defaults to version 1
+pub fn apply(js_value: &JsValue, requested: Option<SchemaVersion>) -> Result<()> {
+    let version = requested.unwrap_or_else(|| {
+        if !DEPRECATION_LOGGED.with(|logged| logged.replace(true)) {
+            warn!(
+                "Reads without a schemaVersion return the version 1 output, which is deprecated \
+                 and will stop being the default in the next release. Pass schemaVersion: 1 to \
+                 keep it, or schemaVersion: 2 for the current output."
+            );
+        }
+        SchemaVersion::V1
+    });
+    if version == SchemaVersion::V2 || !js_value.is_object() {
+        return Ok(());
+    }
+
+    remove(js_value, STORE_ADDITIONS)?;
+    let manifests = get_path(js_value, &["manifests"]);
+    if manifests.is_object() {
+        for manifest in Object::values(manifests.unchecked_ref()).iter() {
+            remove(&manifest, MANIFEST_ADDITIONS)?;
+            let signature_info = get_path(&manifest, &["signature_info"]);
+            if signature_info.is_object() {
+                remove(&signature_info, SIGNATURE_INFO_ADDITIONS)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn remove(target: &JsValue, keys: &[&str]) -> Result<()> {
+    for key in keys {
+        Reflect::delete_property(target.unchecked_ref::<Object>(), &JsValue::from(*key))
+            .map_err(|_err| Error::JavaScriptConversion)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    pub fn test_schema_versions() {
+        assert_eq!(SchemaVersion::try_from(1), Ok(SchemaVersion::V1));
+        assert_eq!(SchemaVersion::try_from(2), Ok(SchemaVersion::V2));
+        assert!(SchemaVersion::try_from(3).is_err());
+        assert_eq!(serde_json::to_string(&SchemaVersion::CURRENT).unwrap(), "2");
+    }
+
+    /// Serializes an output the way a snapshot test would, without what depends on how the
+    /// signatures could be verified, and with resources reduced to their length
+    #[cfg(target_arch = "wasm32")]
+    fn snapshot(js_value: &JsValue) -> String {
+        let replacer = Closure::wrap(Box::new(|key: JsValue, value: JsValue| {
+            let key = key.as_string().unwrap_or_default();
+            if key == "validation_status" || key == "worstSeverity" {
+                return JsValue::UNDEFINED;
+            }
+            match value.dyn_ref::<js_sys::Array>() {
+                Some(bytes) if bytes.length() > 64 && bytes.get(0).as_f64().is_some() => {
+                    format!("<{} bytes>", bytes.length()).into()
+                }
+                _ => value,
+            }
+        }) as Box<dyn FnMut(JsValue, JsValue) -> JsValue>);
+        let json =
+            js_sys::JSON::stringify_with_replacer_and_space(js_value, replacer.as_ref(), &1.into())
+                .unwrap();
+        String::from(json) + "\n"
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test]
+    pub async fn test_version_1_golden() {
+        use crate::options::ReadOptions;
+
+        let asset = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+        let expected = include_str!("../../../tools/testing/fixtures/schema-v1/CAICAI.json");
+
+        for schema_version in [None, Some(SchemaVersion::V1)] {
+            let options = ReadOptions {
+                schema_version,
+                ..Default::default()
+            };
+            let (js_value, _) = crate::read_manifest_store(asset, "image/jpeg", &options)
+                .await
+                .unwrap();
+            assert_eq!(snapshot(&js_value), expected);
+        }
+
+        let options = ReadOptions {
+            schema_version: Some(SchemaVersion::V2),
+            ..Default::default()
+        };
+        let (js_value, _) = crate::read_manifest_store(asset, "image/jpeg", &options)
+            .await
+            .unwrap();
+        assert_eq!(get_path(&js_value, &["schemaVersion"]), 2);
+        apply(&js_value, Some(SchemaVersion::V1)).unwrap();
+        assert_eq!(snapshot(&js_value), expected);
+    }
+}
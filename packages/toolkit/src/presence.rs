@@ -0,0 +1,376 @@
+// Copyright 2024 Adobe
+// All Rights Reserved.
+//
+// NOTICE: Adobe permits you to use, modify, and distribute this file in
+// accordance with the terms of the Adobe license agreement accompanying
+// it.
+
+//! Detects whether an asset carries a manifest without parsing or validating it.
+//!
+//! Only the container structure is walked (JPEG markers, PNG chunks, BMFF top-level boxes), and
+//! bytes are read through [`ByteSource`] so a JavaScript buffer never needs to be copied in full.
+use crate::segments::{JpegSegments, PngChunks, JPEG_APP1, JPEG_APP11, JPEG_SOI, PNG_SIGNATURE};
+use serde::Serialize;
+use std::convert::TryFrom;
+
+// The C2PA Manifest Store shall have a label of c2pa, a UUID of 0x63327061-0011-0010-8000-00AA00389B71 (c2pa)
+pub(crate) const CAI_BLOCK_UUID: [u8; 16] = [
+    0x63, 0x32, 0x70, 0x61, 0x00, 0x11, 0x00, 0x10, 0x80, 0x00, 0x00, 0xAA, 0x00, 0x38, 0x9B, 0x71,
+];
+
+/// BMFF `uuid` box user type of a C2PA manifest store
+const BMFF_C2PA_UUID: [u8; 16] = [
+    0xD8, 0xFE, 0xC3, 0xD6, 0x1B, 0x0E, 0x48, 0x3C, 0x92, 0x97, 0x58, 0x28, 0x87, 0x7E, 0xC4, 0x81,
+];
+
+/// BMFF `uuid` box user type of an XMP packet
+const BMFF_XMP_UUID: [u8; 16] = [
+    0xBE, 0x7A, 0xCF, 0xCB, 0x97, 0xA9, 0x42, 0xE8, 0x9C, 0x71, 0x99, 0x94, 0x91, 0xE3, 0xAF, 0xAC,
+];
+
+const DCTERMS_PROVENANCE: &[u8] = b"dcterms:provenance";
+pub(crate) const JPEG_XMP_SIGNATURE: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+pub(crate) const PNG_XMP_KEYWORD: &[u8] = b"XML:com.adobe.xmp\0";
+
+/// Size of the window used when falling back to a byte search of the whole asset
+const SCAN_CHUNK_SIZE: usize = 64 * 1024;
+/// How far past `dcterms:provenance` to look for the URL when scanning raw bytes
+const PROVENANCE_WINDOW: usize = 4096;
+
+/// Random access to the bytes of an asset
+pub trait ByteSource {
+    fn len(&self) -> usize;
+
+    /// Copies the bytes starting at `offset` into `buf`, returning how many were copied
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Reads up to `len` bytes starting at `offset`
+    fn read_vec(&self, offset: usize, len: usize) -> Vec<u8> {
+        let len = len.min(self.len().saturating_sub(offset));
+        let mut buf = vec![0u8; len];
+        let read = self.read_at(offset, &mut buf);
+        buf.truncate(read);
+        buf
+    }
+}
+
+impl ByteSource for [u8] {
+    fn len(&self) -> usize {
+        <[u8]>::len(self)
+    }
+
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize {
+        let available = self.get(offset..).unwrap_or_default();
+        let len = buf.len().min(available.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        len
+    }
+}
+
+impl ByteSource for js_sys::Uint8Array {
+    fn len(&self) -> usize {
+        self.length() as usize
+    }
+
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize {
+        let start = offset.min(ByteSource::len(self));
+        let end = (start + buf.len()).min(ByteSource::len(self));
+        self.subarray(start as u32, end as u32)
+            .copy_to(&mut buf[..end - start]);
+        end - start
+    }
+}
+
+/// Where (if anywhere) an asset's manifest can be found
+#[derive(Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum ManifestPresence {
+    /// The asset contains a manifest store
+    Embedded,
+    /// The asset's XMP references a remote manifest store
+    Remote {
+        url: String,
+    },
+    None,
+}
+
+impl ManifestPresence {
+    fn from_remote(url: Option<String>) -> Self {
+        url.map_or(Self::None, |url| Self::Remote { url })
+    }
+}
+
+/// Checks for an embedded manifest store or a remote manifest reference
+pub fn has_manifest<S: ByteSource + ?Sized>(source: &S, mime_type: &str) -> ManifestPresence {
+    let structured = match mime_type.to_ascii_lowercase().as_str() {
+        "image/jpeg" | "jpg" | "jpeg" => scan_jpeg(source),
+        "image/png" | "png" => scan_png(source),
+        "video/mp4" | "audio/mp4" | "application/mp4" | "video/quicktime" | "image/heic"
+        | "image/heif" | "image/avif" | "mp4" | "m4a" | "mov" | "heic" | "heif" | "avif" => {
+            scan_bmff(source)
+        }
+        _ => None,
+    };
+
+    structured.unwrap_or_else(|| scan_bytes(source))
+}
+
+fn read_u32(source: &(impl ByteSource + ?Sized), offset: usize) -> Option<u32> {
+    let mut buf = [0u8; 4];
+    (source.read_at(offset, &mut buf) == 4).then(|| u32::from_be_bytes(buf))
+}
+
+fn read_u64(source: &(impl ByteSource + ?Sized), offset: usize) -> Option<u64> {
+    let mut buf = [0u8; 8];
+    (source.read_at(offset, &mut buf) == 8).then(|| u64::from_be_bytes(buf))
+}
+
+/// Walks the JPEG markers up to the start of scan, returning `None` if the data isn't a JPEG
+fn scan_jpeg<S: ByteSource + ?Sized>(source: &S) -> Option<ManifestPresence> {
+    if source.read_vec(0, JPEG_SOI.len()) != JPEG_SOI {
+        return None;
+    }
+
+    let mut remote = None;
+    for segment in JpegSegments::new(source) {
+        match segment.marker {
+            // APP11 carries JUMBF, whose description box UUID sits within the first bytes
+            JPEG_APP11 => {
+                let header = source.read_vec(segment.offset, segment.len.min(64));
+                if find(&header, &CAI_BLOCK_UUID).is_some() {
+                    return Some(ManifestPresence::Embedded);
+                }
+            }
+            // APP1 carries XMP
+            JPEG_APP1 if remote.is_none() => {
+                let signature = source.read_vec(segment.offset, JPEG_XMP_SIGNATURE.len());
+                if signature == JPEG_XMP_SIGNATURE {
+                    remote = provenance_url(&source.read_vec(segment.offset, segment.len));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Some(ManifestPresence::from_remote(remote))
+}
+
+/// Walks the PNG chunks up to `IEND`, returning `None` if the data isn't a PNG
+fn scan_png<S: ByteSource + ?Sized>(source: &S) -> Option<ManifestPresence> {
+    if source.read_vec(0, PNG_SIGNATURE.len()) != PNG_SIGNATURE {
+        return None;
+    }
+
+    let mut remote = None;
+    for chunk in PngChunks::new(source) {
+        match &chunk.chunk_type {
+            b"caBX" => return Some(ManifestPresence::Embedded),
+            b"iTXt" if remote.is_none() => {
+                let keyword = source.read_vec(chunk.offset, PNG_XMP_KEYWORD.len());
+                if keyword == PNG_XMP_KEYWORD {
+                    remote = provenance_url(&source.read_vec(chunk.offset, chunk.len));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Some(ManifestPresence::from_remote(remote))
+}
+
+/// Walks the top-level BMFF boxes, returning `None` if the data isn't BMFF
+fn scan_bmff<S: ByteSource + ?Sized>(source: &S) -> Option<ManifestPresence> {
+    if source.read_vec(4, 4) != b"ftyp" {
+        return None;
+    }
+
+    let mut remote = None;
+    let mut pos = 0;
+    while let (Some(size), Some(box_type)) = (read_u32(source, pos), read_u32(source, pos + 4)) {
+        let (size, header_len) = match size {
+            0 => (source.len() - pos, 8),
+            1 => match read_u64(source, pos + 8).map(usize::try_from) {
+                Some(Ok(size)) => (size, 16),
+                // A size that doesn't fit in memory on this target
+                Some(Err(_)) => break,
+                None => return None,
+            },
+            size => (size as usize, 8),
+        };
+        if size < header_len {
+            break;
+        }
+
+        if &box_type.to_be_bytes() == b"uuid" {
+            let user_type = source.read_vec(pos + header_len, 16);
+            if user_type == BMFF_C2PA_UUID {
+                return Some(ManifestPresence::Embedded);
+            }
+            if user_type == BMFF_XMP_UUID && remote.is_none() {
+                remote = provenance_url(&source.read_vec(pos, size));
+            }
+        }
+
+        pos = match next_offset(source, pos, Some(size)) {
+            Some(next) => next,
+            None => break,
+        };
+    }
+
+    Some(ManifestPresence::from_remote(remote))
+}
+
+/// The offset `step` bytes past `pos`, or `None` if that doesn't move forward or lies past the
+/// end of `source`, as the size of a crafted box can make it
+fn next_offset<S: ByteSource + ?Sized>(
+    source: &S,
+    pos: usize,
+    step: Option<usize>,
+) -> Option<usize> {
+    pos.checked_add(step?)
+        .filter(|next| *next > pos && *next <= source.len())
+}
+
+/// Searches the raw bytes for formats we don't walk the structure of
+fn scan_bytes<S: ByteSource + ?Sized>(source: &S) -> ManifestPresence {
+    let overlap = CAI_BLOCK_UUID.len().max(DCTERMS_PROVENANCE.len()) - 1;
+    let mut chunk = vec![0u8; SCAN_CHUNK_SIZE + overlap];
+    let mut remote = None;
+    let mut offset = 0;
+
+    while offset < source.len() {
+        let read = source.read_at(offset, &mut chunk);
+        let window = &chunk[..read];
+        if find(window, &CAI_BLOCK_UUID).is_some() {
+            return ManifestPresence::Embedded;
+        }
+        if remote.is_none() {
+            if let Some(pos) = find(window, DCTERMS_PROVENANCE) {
+                remote = provenance_url(&source.read_vec(offset + pos, PROVENANCE_WINDOW));
+            }
+        }
+        if read < chunk.len() {
+            break;
+        }
+        offset += SCAN_CHUNK_SIZE;
+    }
+
+    ManifestPresence::from_remote(remote)
+}
+
+/// Extracts the `dcterms:provenance` URL from an XMP packet, in either attribute or element form
+pub(crate) fn provenance_url(xmp: &[u8]) -> Option<String> {
+    let rest = &xmp[find(xmp, DCTERMS_PROVENANCE)? + DCTERMS_PROVENANCE.len()..];
+    let rest = trim_start(rest);
+
+    let (value, terminator) = match rest.first()? {
+        b'=' => {
+            let rest = trim_start(&rest[1..]);
+            let quote = *rest.first()?;
+            if quote != b'"' && quote != b'\'' {
+                return None;
+            }
+            (&rest[1..], quote)
+        }
+        b'>' => (&rest[1..], b'<'),
+        _ => return None,
+    };
+
+    let end = value.iter().position(|b| *b == terminator)?;
+    let url = std::str::from_utf8(&value[..end]).ok()?.trim();
+    (!url.is_empty()).then(|| url.to_owned())
+}
+
+fn trim_start(bytes: &[u8]) -> &[u8] {
+    let start = bytes
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .unwrap_or(bytes.len());
+    &bytes[start..]
+}
+
+pub(crate) fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    pub fn test_embedded() {
+        let jpeg = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+        let png = include_bytes!("../../../tools/testing/fixtures/images/crypto-social.png");
+        let pdf = include_bytes!("../../../tools/testing/fixtures/images/sample.pdf");
+
+        assert_eq!(
+            has_manifest(&jpeg[..], "image/jpeg"),
+            ManifestPresence::Embedded
+        );
+        assert_eq!(
+            has_manifest(&png[..], "image/png"),
+            ManifestPresence::Embedded
+        );
+        assert_eq!(
+            has_manifest(&pdf[..], "application/pdf"),
+            ManifestPresence::Embedded
+        );
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_none() {
+        let jpeg = include_bytes!("../../../tools/testing/fixtures/images/I.jpg");
+
+        assert_eq!(
+            has_manifest(&jpeg[..], "image/jpeg"),
+            ManifestPresence::None
+        );
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_oversized_chunks() {
+        // A PNG chunk whose length overflows the offset of the next chunk on 32-bit targets
+        let mut png = PNG_SIGNATURE.to_vec();
+        png.extend_from_slice(&0xFFFF_FFF4u32.to_be_bytes());
+        png.extend_from_slice(b"tEXt");
+        png.extend_from_slice(&[0; 16]);
+        assert_eq!(has_manifest(&png[..], "image/png"), ManifestPresence::None);
+
+        // A BMFF box whose 64-bit size wraps the offset of the next box back to the start
+        let mut bmff = Vec::new();
+        bmff.extend_from_slice(&16u32.to_be_bytes());
+        bmff.extend_from_slice(b"ftypisom");
+        bmff.extend_from_slice(&[0; 4]);
+        bmff.extend_from_slice(&1u32.to_be_bytes());
+        bmff.extend_from_slice(b"free");
+        bmff.extend_from_slice(&(u64::MAX - 15).to_be_bytes());
+        bmff.extend_from_slice(&[0; 16]);
+        assert_eq!(has_manifest(&bmff[..], "video/mp4"), ManifestPresence::None);
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_remote() {
+        let xmp = b"http://ns.adobe.com/xap/1.0/\0<rdf:Description \
+            dcterms:provenance=\"https://example.com/manifest.c2pa\"/>";
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE1];
+        jpeg.extend_from_slice(&((xmp.len() + 2) as u16).to_be_bytes());
+        jpeg.extend_from_slice(xmp);
+        jpeg.extend_from_slice(&[0xFF, 0xDA]);
+
+        assert_eq!(
+            has_manifest(&jpeg[..], "image/jpeg"),
+            ManifestPresence::Remote {
+                url: "https://example.com/manifest.c2pa".to_owned()
+            }
+        );
+    }
+}
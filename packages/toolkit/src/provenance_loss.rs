@@ -0,0 +1,304 @@
+// Copyright 2024 Adobe
+// All Rights Reserved.
+//
+// NOTICE: Adobe permits you to use, modify, and distribute this file in
+// accordance with the terms of the Adobe license agreement accompanying
+// it.
+
+//! Guesses why an asset doesn't carry valid Content Credentials.
+//!
+//! The guess is drawn from the asset's container and metadata, so it is a heuristic and is kept
+//! apart from validation: nothing here is a statement about the manifest's signature. A manifest
+//! whose hard binding no longer matches suggests the file was modified after signing. XMP that
+//! still references an embedded manifest, or manifest store boxes left in the file, suggest that
+//! the manifest was stripped, for instance by a platform re-encoding uploads.
+use crate::error::Result;
+use crate::presence::{find, provenance_url, CAI_BLOCK_UUID};
+use crate::xmp::extract_xmp;
+use c2pa::ManifestStore;
+use serde::Serialize;
+
+/// Validation codes of a hard binding that doesn't match the asset
+const BINDING_MISMATCH_CODES: &[&str] = &[
+    "assertion.dataHash.mismatch",
+    "assertion.bmffHash.mismatch",
+    "assertion.boxesHash.mismatch",
+];
+
+/// The prefix of `dcterms:provenance` references to a manifest embedded in the asset itself
+const SELF_REFERENCE_PREFIX: &str = "self#jumbf=";
+
+/// The IPTC vocabulary of digital source types, which generators record alongside manifests
+const DIGITAL_SOURCE_TYPE_VOCABULARY: &[u8] = b"cv.iptc.org/newscodes/digitalsourcetype/";
+
+/// The most likely reason for the asset's provenance being lost
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LossExplanation {
+    /// The asset has a readable manifest that is bound to it, so nothing was lost this way
+    ManifestPresent,
+    /// The asset references a manifest stored elsewhere instead of embedding one
+    RemoteManifest,
+    /// The asset has a manifest, but it doesn't match the asset or can't be read
+    BindingBroken,
+    /// The asset has no manifest, but its metadata or structure shows it once did
+    Stripped,
+    NoEvidence,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EvidenceKind {
+    /// A hard binding validation code, in `detail`
+    BindingMismatch,
+    /// The manifest store couldn't be read, with the error in `detail`
+    UnreadableManifest,
+    /// XMP `dcterms:provenance` references a manifest embedded in the asset, in `detail`
+    EmbeddedManifestReference,
+    /// XMP `dcterms:provenance` references a remote manifest, in `detail`
+    RemoteManifestReference,
+    /// Bytes of a manifest store found outside of the places a manifest is embedded
+    ManifestStoreRemnant,
+    /// An IPTC digital source type in the XMP, in `detail`
+    DigitalSourceType,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Evidence {
+    pub kind: EvidenceKind,
+    pub detail: Option<String>,
+}
+
+impl Evidence {
+    fn new(kind: EvidenceKind, detail: impl Into<String>) -> Self {
+        Self {
+            kind,
+            detail: Some(detail.into()),
+        }
+    }
+}
+
+/// Result of `probeProvenanceLoss`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProvenanceLossProbe {
+    pub explanation: LossExplanation,
+    pub evidence: Vec<Evidence>,
+}
+
+/// Looks for traces of a manifest in the asset's XMP and bytes
+fn traces(data: &[u8], embedded: bool) -> Vec<Evidence> {
+    let mut evidence = Vec::new();
+    if let Some(xmp) = extract_xmp(data) {
+        if let Some(url) = provenance_url(xmp) {
+            let kind = if url.starts_with(SELF_REFERENCE_PREFIX) {
+                EvidenceKind::EmbeddedManifestReference
+            } else {
+                EvidenceKind::RemoteManifestReference
+            };
+            evidence.push(Evidence::new(kind, url));
+        }
+        if let Some(source_type) = digital_source_type(xmp) {
+            evidence.push(Evidence::new(EvidenceKind::DigitalSourceType, source_type));
+        }
+    }
+    // An embedded store is expected to contain these bytes
+    if !embedded && find(data, &CAI_BLOCK_UUID).is_some() {
+        evidence.push(Evidence {
+            kind: EvidenceKind::ManifestStoreRemnant,
+            detail: None,
+        });
+    }
+
+    evidence
+}
+
+/// Returns the term of the first IPTC digital source type in an XMP packet
+fn digital_source_type(xmp: &[u8]) -> Option<String> {
+    let start = find(xmp, DIGITAL_SOURCE_TYPE_VOCABULARY)? + DIGITAL_SOURCE_TYPE_VOCABULARY.len();
+    let term: String = xmp[start..]
+        .iter()
+        .take_while(|b| b.is_ascii_alphanumeric())
+        .map(|b| char::from(*b))
+        .collect();
+    (!term.is_empty()).then_some(term)
+}
+
+/// Explains the outcome of reading the asset's manifest store
+///
+/// `embedded` is whether the asset's container holds a manifest store, readable or not.
+pub fn probe_provenance_loss(
+    data: &[u8],
+    embedded: bool,
+    read_result: &Result<ManifestStore>,
+) -> ProvenanceLossProbe {
+    let mut evidence = Vec::new();
+    match read_result {
+        Ok(manifest_store) => {
+            evidence.extend(
+                manifest_store
+                    .validation_status()
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|status| status.code())
+                    .filter(|code| BINDING_MISMATCH_CODES.contains(code))
+                    .map(|code| Evidence::new(EvidenceKind::BindingMismatch, code)),
+            );
+        }
+        Err(err) if embedded => {
+            evidence.push(Evidence::new(
+                EvidenceKind::UnreadableManifest,
+                err.to_string(),
+            ));
+        }
+        Err(_) => {}
+    }
+    let broken = !evidence.is_empty();
+    evidence.extend(traces(data, embedded));
+
+    let explanation = if broken {
+        LossExplanation::BindingBroken
+    } else if read_result.is_ok() {
+        LossExplanation::ManifestPresent
+    } else if evidence
+        .iter()
+        .any(|evidence| evidence.kind == EvidenceKind::RemoteManifestReference)
+    {
+        LossExplanation::RemoteManifest
+    } else if evidence.is_empty() {
+        LossExplanation::NoEvidence
+    } else {
+        LossExplanation::Stripped
+    };
+
+    ProvenanceLossProbe {
+        explanation,
+        evidence,
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::manifest_store::get_manifest_store_data;
+    use crate::presence::{has_manifest, ManifestPresence};
+    use crate::segments::{JpegSegments, JPEG_APP11, JPEG_SOS};
+    use crate::xmp::tests::jpeg_with_xmp;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
+
+    /// Removes the APP11 segments, which carry the manifest store, from a JPEG
+    fn strip_manifest(jpeg: &[u8]) -> Vec<u8> {
+        let mut result = jpeg[..2].to_vec();
+        let mut pos = 2;
+        for segment in JpegSegments::new(jpeg) {
+            if segment.marker == JPEG_SOS {
+                break;
+            }
+            if segment.marker != JPEG_APP11 {
+                result.extend_from_slice(&jpeg[pos..segment.end()]);
+            }
+            pos = segment.end();
+        }
+        result.extend_from_slice(&jpeg[pos..]);
+        result
+    }
+
+    async fn probe(data: &[u8]) -> ProvenanceLossProbe {
+        let embedded = has_manifest(data, "image/jpeg") == ManifestPresence::Embedded;
+        let read_result = get_manifest_store_data(data, "image/jpeg").await;
+        probe_provenance_loss(data, embedded, &read_result)
+    }
+
+    fn kinds(probe: &ProvenanceLossProbe) -> Vec<EvidenceKind> {
+        probe
+            .evidence
+            .iter()
+            .map(|evidence| evidence.kind)
+            .collect()
+    }
+
+    #[wasm_bindgen_test]
+    pub async fn test_binding_broken() {
+        let intact = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+        let result = probe(intact).await;
+        assert_eq!(result.explanation, LossExplanation::ManifestPresent);
+        assert_eq!(
+            kinds(&result),
+            vec![EvidenceKind::EmbeddedManifestReference]
+        );
+
+        // The image data was changed after signing
+        let modified = include_bytes!("../../../tools/testing/fixtures/images/E-dat-CA.jpg");
+        let result = probe(modified).await;
+        assert_eq!(result.explanation, LossExplanation::BindingBroken);
+        assert_eq!(
+            result.evidence[0],
+            Evidence::new(EvidenceKind::BindingMismatch, "assertion.dataHash.mismatch")
+        );
+
+        // A store that can't be read
+        let mut corrupted = intact.to_vec();
+        let claim = find(&corrupted, b"c2pa.claim").unwrap();
+        corrupted[claim + 64..claim + 128].fill(0xFF);
+        let result = probe(&corrupted).await;
+        assert_eq!(result.explanation, LossExplanation::BindingBroken);
+        assert_eq!(kinds(&result)[0], EvidenceKind::UnreadableManifest);
+    }
+
+    #[wasm_bindgen_test]
+    pub async fn test_stripped() {
+        let signed = include_bytes!("../../../tools/testing/fixtures/images/E-dat-CA.jpg");
+        let stripped = strip_manifest(signed);
+        let result = probe(&stripped).await;
+        assert_eq!(result.explanation, LossExplanation::Stripped);
+        assert_eq!(
+            kinds(&result),
+            vec![EvidenceKind::EmbeddedManifestReference]
+        );
+        assert!(result.evidence[0]
+            .detail
+            .as_deref()
+            .is_some_and(|url| url.starts_with("self#jumbf=/c2pa/adobetest:urn:uuid:879beec2")));
+
+        // The store's boxes left behind after the segments carrying them were rewritten
+        let firefly = include_bytes!("../../../tools/testing/fixtures/images/firefly-1.jpg");
+        let mut remnant = strip_manifest(firefly);
+        let comment_len = (CAI_BLOCK_UUID.len() + 2) as u16;
+        let mut comment = vec![0xFF, 0xFE];
+        comment.extend_from_slice(&comment_len.to_be_bytes());
+        comment.extend_from_slice(&CAI_BLOCK_UUID);
+        remnant.splice(2..2, comment);
+        let result = probe(&remnant).await;
+        assert_eq!(result.explanation, LossExplanation::Stripped);
+        assert_eq!(kinds(&result), vec![EvidenceKind::ManifestStoreRemnant]);
+
+        // Generators record the digital source type in the XMP as well as the manifest
+        let xmp = r#"<x:xmpmeta xmlns:x="adobe:ns:meta/"><rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#"><rdf:Description xmlns:Iptc4xmpExt="http://iptc.org/std/Iptc4xmpExt/2008-02-29/" Iptc4xmpExt:DigitalSourceType="http://cv.iptc.org/newscodes/digitalsourcetype/trainedAlgorithmicMedia"/></rdf:RDF></x:xmpmeta>"#;
+        let generated = jpeg_with_xmp(&strip_manifest(firefly), xmp);
+        let result = probe(&generated).await;
+        assert_eq!(result.explanation, LossExplanation::Stripped);
+        assert_eq!(
+            result.evidence,
+            vec![Evidence::new(
+                EvidenceKind::DigitalSourceType,
+                "trainedAlgorithmicMedia"
+            )]
+        );
+    }
+
+    #[wasm_bindgen_test]
+    pub async fn test_no_evidence() {
+        let firefly = include_bytes!("../../../tools/testing/fixtures/images/firefly-1.jpg");
+        let result = probe(&strip_manifest(firefly)).await;
+        assert_eq!(result.explanation, LossExplanation::NoEvidence);
+        assert!(result.evidence.is_empty());
+
+        let remote = include_bytes!("../../../tools/testing/fixtures/images/cloud.jpg");
+        let result = probe(remote).await;
+        assert_eq!(result.explanation, LossExplanation::RemoteManifest);
+        assert_eq!(kinds(&result), vec![EvidenceKind::RemoteManifestReference]);
+    }
+}
@@ -0,0 +1,258 @@
+// Copyright 2024 Adobe
+// All Rights Reserved.
+//
+// NOTICE: Adobe permits you to use, modify, and distribute this file in
+// accordance with the terms of the Adobe license agreement accompanying
+// it.
+
+//! Recovers the manifest of an asset whose embedded manifest was stripped, from a watermark that
+//! identifies a manifest stored elsewhere.
+//!
+//! Detecting the watermark and fetching the manifest are left to callbacks supplied by the
+//! application. The fetched manifest is then validated against the asset like a sidecar manifest.
+//! An asset that was transcoded after signing no longer matches its hard binding, which is
+//! expected when the manifest was found through the soft binding the manifest declares, so in that
+//! case the mismatch is reported as `bindingBroken` and counted as a warning rather than an error.
+use crate::error::{Error, Result};
+use crate::input;
+use crate::validation;
+use c2pa::assertions::labels::SOFT_BINDING;
+use js_sys::{Function, Promise, Uint8Array};
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+
+/// The stage that fails when the watermark can't be detected
+pub const STAGE_DETECT_WATERMARK: &str = "detectWatermark";
+/// The stage that fails when the manifest can't be fetched
+pub const STAGE_FETCH_MANIFEST: &str = "fetchManifest";
+
+/// How a recovered manifest binds to the asset, added to the manifest store output
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Recovery {
+    pub recovered: bool,
+    /// The identifier the watermark decoded to
+    pub recovery_id: String,
+    /// The asset no longer matches the hard binding of the active manifest
+    pub binding_broken: bool,
+    /// The active manifest declares a soft binding made with the detector's algorithm
+    pub soft_binding_matched: bool,
+}
+
+impl Recovery {
+    /// Checks the bindings of the active manifest, given the codes of its validation statuses,
+    /// the algorithms of its soft bindings, and the algorithm of the detector used
+    pub fn new(
+        recovery_id: String,
+        codes: &[String],
+        soft_binding_algs: &[String],
+        detector_alg: Option<&str>,
+    ) -> Self {
+        Self {
+            recovered: true,
+            recovery_id,
+            binding_broken: codes
+                .iter()
+                .any(|code| validation::is_hard_binding_mismatch(code)),
+            soft_binding_matched: detector_alg.is_some_and(|detector_alg| {
+                soft_binding_algs.iter().any(|alg| alg == detector_alg)
+            }),
+        }
+    }
+
+    /// Whether hard binding mismatches are explained by the recovery, and so are warnings
+    pub fn tolerates_hard_binding_mismatch(&self) -> bool {
+        self.binding_broken && self.soft_binding_matched
+    }
+}
+
+/// Returns `true` for the labels of soft binding assertions, including numbered instances
+pub fn is_soft_binding_label(label: &str) -> bool {
+    label.split("__").next() == Some(SOFT_BINDING)
+}
+
+/// Calls `detectWatermark` with a copy of the asset, returning the recovery identifier if a
+/// watermark was found
+pub async fn detect_watermark(detect_watermark: &Function, asset: &[u8]) -> Result<Option<String>> {
+    let buffer = Uint8Array::from(asset).buffer();
+    let result = call(detect_watermark, &buffer.into(), STAGE_DETECT_WATERMARK).await?;
+    if result.is_null() || result.is_undefined() {
+        return Ok(None);
+    }
+
+    result.as_string().map(Some).ok_or_else(|| {
+        Error::RecoveryFailed(
+            STAGE_DETECT_WATERMARK,
+            "expected a string or null".to_owned(),
+        )
+    })
+}
+
+/// Calls `fetchManifest` with the recovery identifier, returning the manifest bytes
+pub async fn fetch_manifest(fetch_manifest: &Function, recovery_id: &str) -> Result<Vec<u8>> {
+    let result = call(fetch_manifest, &recovery_id.into(), STAGE_FETCH_MANIFEST).await?;
+    if !result.is_instance_of::<js_sys::ArrayBuffer>() && !result.is_instance_of::<Uint8Array>() {
+        return Err(Error::RecoveryFailed(
+            STAGE_FETCH_MANIFEST,
+            "expected an ArrayBuffer".to_owned(),
+        ));
+    }
+    input::check_buffer(&result, STAGE_FETCH_MANIFEST)?;
+
+    Ok(Uint8Array::new(&result).to_vec())
+}
+
+/// Calls a callback and awaits its result, attributing a throw or rejection to `stage`
+async fn call(callback: &Function, arg: &JsValue, stage: &'static str) -> Result<JsValue> {
+    let result = callback
+        .call1(&JsValue::NULL, arg)
+        .map_err(|err| Error::RecoveryFailed(stage, describe(&err)))?;
+    JsFuture::from(Promise::resolve(&result))
+        .await
+        .map_err(|err| Error::RecoveryFailed(stage, describe(&err)))
+}
+
+/// The message of a thrown error, or the thrown value itself
+fn describe(err: &JsValue) -> String {
+    err.dyn_ref::<js_sys::Error>()
+        .map(|err| String::from(err.message()))
+        .or_else(|| err.as_string())
+        .unwrap_or_else(|| format!("{:?}", err))
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_recovery_bindings() {
+        let broken = strings(&["claimSignature.validated", "assertion.dataHash.mismatch"]);
+        let intact = strings(&["claimSignature.validated", "assertion.dataHash.match"]);
+        let algs = strings(&["com.example.watermark.1"]);
+
+        let recovery = Recovery::new(
+            "id".to_owned(),
+            &broken,
+            &algs,
+            Some("com.example.watermark.1"),
+        );
+        assert!(recovery.recovered);
+        assert!(recovery.binding_broken);
+        assert!(recovery.soft_binding_matched);
+        assert!(recovery.tolerates_hard_binding_mismatch());
+
+        // A soft binding made with another algorithm doesn't explain the mismatch
+        let recovery = Recovery::new("id".to_owned(), &broken, &algs, Some("com.example.other"));
+        assert!(recovery.binding_broken);
+        assert!(!recovery.tolerates_hard_binding_mismatch());
+        let recovery = Recovery::new("id".to_owned(), &broken, &algs, None);
+        assert!(!recovery.tolerates_hard_binding_mismatch());
+
+        let recovery = Recovery::new(
+            "id".to_owned(),
+            &intact,
+            &algs,
+            Some("com.example.watermark.1"),
+        );
+        assert!(!recovery.binding_broken);
+        assert!(recovery.soft_binding_matched);
+        assert!(!recovery.tolerates_hard_binding_mismatch());
+    }
+
+    /// Recovers CAICAI's own manifest store for `asset`, as if from its watermark
+    #[cfg(target_arch = "wasm32")]
+    async fn recover(
+        asset: &[u8],
+        detect: &str,
+        fetch: &str,
+    ) -> std::result::Result<JsValue, JsValue> {
+        let test_asset = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+        let store_bytes = c2pa::jumbf_io::load_jumbf_from_memory("image/jpeg", test_asset).unwrap();
+        let store = Uint8Array::from(store_bytes.as_slice()).buffer();
+
+        let options = js_sys::Object::new();
+        let detect = Function::new_with_args("buffer", detect);
+        let fetch = Function::new_with_args("id", fetch).bind(&store);
+        js_sys::Reflect::set(&options, &STAGE_DETECT_WATERMARK.into(), &detect).unwrap();
+        js_sys::Reflect::set(&options, &STAGE_FETCH_MANIFEST.into(), &fetch).unwrap();
+        let buf = Uint8Array::from(asset).buffer();
+        wasm_bindgen_futures::JsFuture::from(crate::recover_manifest(
+            buf.into(),
+            "image/jpeg".to_owned(),
+            options.into(),
+        ))
+        .await
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test]
+    pub async fn test_recover_manifest() {
+        use crate::get_path;
+
+        let test_asset = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+        let detect = "return Promise.resolve(buffer.byteLength > 0 ? 'urn:example:1' : null)";
+        let fetch = "return Promise.resolve(this)";
+
+        let result = recover(test_asset, detect, fetch).await.unwrap();
+        assert_eq!(get_path(&result, &["recovered"]), true);
+        assert_eq!(get_path(&result, &["recoveryId"]), "urn:example:1");
+        assert_eq!(get_path(&result, &["bindingBroken"]), false);
+        assert_eq!(get_path(&result, &["softBindingMatched"]), false);
+        assert!(get_path(&result, &["manifests"]).is_object());
+
+        // Without a soft binding to explain it, a changed asset is still invalid
+        let mut changed = test_asset.to_vec();
+        let len = changed.len();
+        changed[len - 100] ^= 0xff;
+        let result = recover(&changed, detect, fetch).await.unwrap();
+        assert_eq!(get_path(&result, &["bindingBroken"]), true);
+        assert_eq!(get_path(&result, &["worstSeverity"]), "error");
+
+        let result = recover(test_asset, "return null", fetch).await.unwrap();
+        assert!(result.is_null());
+
+        // Each failing callback is reported as the stage it failed at
+        let failures = [
+            ("throw new Error('no detector')", fetch, "detectWatermark"),
+            ("return 42", fetch, "detectWatermark"),
+            (
+                detect,
+                "return Promise.reject(new Error('offline'))",
+                "fetchManifest",
+            ),
+            (detect, "return 'not bytes'", "fetchManifest"),
+        ];
+        for (detect, fetch, stage) in failures.iter() {
+            let err = recover(test_asset, detect, fetch).await.unwrap_err();
+            assert_eq!(get_path(&err, &["name"]), "Toolkit(RecoveryFailed)");
+            assert_eq!(get_path(&err, &["stage"]), *stage);
+        }
+        let err = recover(
+            test_asset,
+            detect,
+            "return Promise.reject(new Error('offline'))",
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(
+            get_path(&err, &["message"]),
+            "manifest recovery failed at fetchManifest: offline"
+        );
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_soft_binding_label() {
+        assert!(is_soft_binding_label("c2pa.soft-binding"));
+        assert!(is_soft_binding_label("c2pa.soft-binding__1"));
+        assert!(!is_soft_binding_label("c2pa.soft-bindings"));
+        assert!(!is_soft_binding_label("c2pa.hash.data"));
+    }
+}
@@ -0,0 +1,297 @@
+// Copyright 2024 Adobe
+// All Rights Reserved.
+//
+// NOTICE: Adobe permits you to use, modify, and distribute this file in
+// accordance with the terms of the Adobe license agreement accompanying
+// it.
+
+//! Builds the detailed manifest store report that `c2patool --detailed` prints.
+//!
+//! The c2pa crate only builds this report from a store it has validated synchronously, which
+//! isn't possible in the browser, so the report is mapped here instead: the claims, assertions and
+//! credentials come from the store's JUMBF boxes, and the signature information and validation
+//! statuses from the manifest store read by the c2pa crate. The mapping follows c2pa's
+//! `ManifestStoreReport`:
+//! - the claim has the fields c2pa's `Claim` serializes, with `claim_generator_info` always present,
+//! - assertions are keyed by their label, with binary assertions replaced by their length,
+//! - every `hash` byte array becomes a base64 string and every `pad` array `"<omitted>"`.
+use crate::cose::CoseSign1;
+use crate::error::{Error, Result};
+use crate::jumbf::{self, SuperBox};
+use crate::limits;
+use crate::manifest_store::to_hex;
+use base64::Engine;
+use c2pa::validation_status::ValidationStatus;
+use c2pa::ManifestStore;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+/// Label of the credential store superbox within a manifest
+const CREDENTIALS_LABEL: &str = "c2pa.credentials";
+
+/// The claim fields that c2pa's `Claim` serializes, in addition to `claim_generator_info`
+const CLAIM_FIELDS: &[&str] = &[
+    "dc:title",
+    "dc:format",
+    "instanceID",
+    "claim_generator",
+    "signature",
+    "assertions",
+    "redacted_assertions",
+    "alg",
+    "alg_soft",
+    "claim_generator_hints",
+];
+
+/// The fields of a hashed URI
+const HASHED_URI_FIELDS: &[&str] = &["url", "alg", "hash"];
+
+#[derive(Debug, Serialize)]
+pub struct DetailedReport {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    active_manifest: Option<String>,
+    manifests: BTreeMap<String, ManifestReport>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    validation_status: Option<Vec<ValidationStatus>>,
+}
+
+#[derive(Debug, Serialize)]
+struct ManifestReport {
+    claim: Value,
+    assertion_store: BTreeMap<String, Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    credential_store: Option<Vec<Value>>,
+    signature: SignatureReport,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct SignatureReport {
+    alg: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    issuer: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    time: Option<String>,
+}
+
+impl DetailedReport {
+    /// Maps the manifest store read by the c2pa crate and the bytes of its JUMBF to a report
+    pub fn new(manifest_store: &ManifestStore, store_bytes: &[u8]) -> Result<Self> {
+        let store = limits::parse(store_bytes)?;
+        let mut manifests = BTreeMap::new();
+
+        for manifest_box in jumbf::manifests(&store) {
+            let label = match &manifest_box.label {
+                Some(label) => label,
+                None => continue,
+            };
+            let claim = match manifest_box.claim().and_then(SuperBox::payload) {
+                Some(claim) => claim_report(claim)?,
+                None => continue,
+            };
+
+            let mut signature = SignatureReport::default();
+            if let Some(alg) = manifest_box
+                .find(jumbf::SIGNATURE_LABEL)
+                .and_then(SuperBox::payload)
+                .and_then(|payload| CoseSign1::from_slice(payload).ok())
+                .and_then(|signature| signature.alg())
+            {
+                signature.alg = alg.to_string();
+            }
+            if let Some(manifest) = manifest_store.get(label) {
+                signature.issuer = manifest.issuer();
+                signature.time = manifest.time();
+            }
+
+            let credential_store: Vec<Value> = manifest_box
+                .find(CREDENTIALS_LABEL)
+                .map(|credentials| credentials.children.iter().collect::<Vec<_>>())
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|credential| serde_json::from_slice(credential.payload()?).ok())
+                .collect();
+
+            manifests.insert(
+                label.clone(),
+                ManifestReport {
+                    claim,
+                    assertion_store: assertion_store(manifest_box),
+                    credential_store: if credential_store.is_empty() {
+                        None
+                    } else {
+                        Some(credential_store)
+                    },
+                    signature,
+                },
+            );
+        }
+
+        Ok(Self {
+            active_manifest: manifest_store.active_label().map(str::to_owned),
+            manifests,
+            validation_status: manifest_store.validation_status().map(<[_]>::to_vec),
+        })
+    }
+
+    /// Serializes the report as canonical JSON: compact, with the keys of every object sorted
+    pub fn to_json(&self) -> Result<String> {
+        let mut value = serde_json::to_value(self).map_err(|_err| Error::JavaScriptConversion)?;
+        encode_bytes(&mut value);
+        Ok(value.to_string())
+    }
+}
+
+/// Decodes a claim, keeping the fields that c2pa's `Claim` serializes
+fn claim_report(claim: &[u8]) -> Result<Value> {
+    let claim = match cbor_to_json(claim) {
+        Some(Value::Object(claim)) => claim,
+        _ => return Err(Error::InvalidJumbf("invalid claim")),
+    };
+
+    let mut report: Map<String, Value> = claim
+        .into_iter()
+        .filter(|(key, _)| CLAIM_FIELDS.contains(&key.as_str()))
+        .collect();
+    report.entry("claim_generator_info").or_insert(Value::Null);
+    if let Some(Value::Array(assertions)) = report.get_mut("assertions") {
+        for assertion in assertions.iter_mut() {
+            if let Value::Object(assertion) = assertion {
+                assertion.retain(|key, _| HASHED_URI_FIELDS.contains(&key.as_str()));
+            }
+        }
+    }
+
+    Ok(Value::Object(report))
+}
+
+/// Decodes the assertions of a manifest, keyed by their label (including any instance)
+fn assertion_store(manifest: &SuperBox) -> BTreeMap<String, Value> {
+    let assertions = match manifest.find(jumbf::ASSERTIONS_LABEL) {
+        Some(assertions) => assertions,
+        None => return BTreeMap::new(),
+    };
+
+    assertions
+        .children
+        .iter()
+        .filter_map(|assertion| {
+            let label = assertion.label.clone()?;
+            let value = assertion.boxes.iter().find_map(|content| {
+                match &content.box_type {
+                    b"cbor" => cbor_to_json(content.payload),
+                    b"json" => serde_json::from_slice(content.payload).ok(),
+                    // An embedded file, such as a thumbnail, holds its data in a `bidb` box
+                    b"bidb" => Some(format!("<omitted> len = {}", content.payload.len()).into()),
+                    b"uuid" if content.payload.len() >= 16 => {
+                        let (uuid, data) = content.payload.split_at(16);
+                        Some(
+                            format!(
+                                "uuid: {}, data: {}",
+                                to_hex(uuid),
+                                base64::engine::general_purpose::STANDARD.encode(data)
+                            )
+                            .into(),
+                        )
+                    }
+                    _ => None,
+                }
+            })?;
+            Some((label, value))
+        })
+        .collect()
+}
+
+/// Transcodes CBOR to JSON, with byte strings as arrays of numbers
+fn cbor_to_json(cbor: &[u8]) -> Option<Value> {
+    let mut deserializer = serde_cbor::Deserializer::from_slice(cbor);
+    serde_transcode::transcode(&mut deserializer, serde_json::value::Serializer).ok()
+}
+
+/// Encodes every `hash` byte array as base64, and replaces every `pad` array with `"<omitted>"`
+fn encode_bytes(value: &mut Value) {
+    let mut pending = vec![value];
+    while let Some(value) = pending.pop() {
+        match value {
+            Value::Array(items) => pending.extend(items.iter_mut()),
+            Value::Object(object) => {
+                for (key, value) in object.iter_mut() {
+                    match (key.as_str(), &*value) {
+                        ("hash", Value::Array(items)) => {
+                            let bytes: Vec<u8> = items
+                                .iter()
+                                .filter_map(|item| item.as_u64())
+                                .map(|byte| byte as u8)
+                                .collect();
+                            *value = base64::engine::general_purpose::STANDARD
+                                .encode(bytes)
+                                .into();
+                        }
+                        ("pad", Value::Array(_)) => *value = "<omitted>".into(),
+                        _ => pending.push(value),
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::manifest_store::get_manifest_store_data;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    pub async fn test_detailed_report() {
+        let test_asset = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+        let expected: Value = serde_json::from_slice(include_bytes!(
+            "../../../tools/testing/fixtures/detailed-report/CAICAI.json"
+        ))
+        .unwrap();
+
+        let manifest_store = get_manifest_store_data(test_asset, "image/jpeg")
+            .await
+            .unwrap();
+        let store_bytes = c2pa::jumbf_io::load_jumbf_from_memory("image/jpeg", test_asset).unwrap();
+        let report = DetailedReport::new(&manifest_store, &store_bytes)
+            .unwrap()
+            .to_json()
+            .unwrap();
+        let report: Value = serde_json::from_str(&report).unwrap();
+
+        // The validation statuses depend on how signatures can be verified, and c2patool also
+        // lists the successful checks, so only the manifests are compared
+        assert_eq!(report["active_manifest"], expected["active_manifest"]);
+        let manifests = report["manifests"].as_object().unwrap();
+        let expected_manifests = expected["manifests"].as_object().unwrap();
+        assert_eq!(manifests.len(), 3);
+        for (label, expected_manifest) in expected_manifests {
+            let manifest = &manifests[label];
+            for key in ["claim", "assertion_store", "credential_store", "signature"].iter() {
+                assert_eq!(manifest[key], expected_manifest[key], "{} {}", label, key);
+            }
+        }
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_encode_bytes() {
+        let mut value = serde_json::json!({
+            "hash": [1, 2, 3],
+            "pad": [0, 0],
+            "items": [{ "hash": [] }, { "hash": "AQID", "pad": 1 }],
+        });
+        encode_bytes(&mut value);
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "hash": "AQID",
+                "pad": "<omitted>",
+                "items": [{ "hash": "" }, { "hash": "AQID", "pad": 1 }],
+            })
+        );
+    }
+}
@@ -0,0 +1,245 @@
+// Copyright 2024 Adobe
+// All Rights Reserved.
+//
+// NOTICE: Adobe permits you to use, modify, and distribute this file in
+// accordance with the terms of the Adobe license agreement accompanying
+// it.
+
+//! Generates the TypeScript definitions for the structures this crate serializes to JavaScript.
+//!
+//! The definitions are derived from the JSON schema of the Rust types (via `schemars`) so that
+//! `types/generated.ts` always matches what the serializer actually emits.
+use c2pa::ManifestStore;
+use schemars::schema::{InstanceType, RootSchema, Schema, SchemaObject, SingleOrVec};
+use schemars::schema_for;
+use serde_json::Value;
+
+const HEADER: &str =
+    "// This file is generated from the Rust types by `yarn build:schema`. Do not edit it by hand.
+
+";
+
+/// Returns the JSON schema of the manifest store as it is serialized by the toolkit
+pub fn manifest_store_schema() -> RootSchema {
+    schema_for!(ManifestStore)
+}
+
+/// Renders the TypeScript definitions for the manifest store structures
+pub fn manifest_store_types() -> String {
+    let root = manifest_store_schema();
+    let mut out = String::from(HEADER);
+
+    write_definition(&mut out, "ManifestStore", &root.schema);
+    for (name, schema) in root.definitions.iter() {
+        if let Schema::Object(obj) = schema {
+            write_definition(&mut out, name, obj);
+        }
+    }
+
+    out
+}
+
+fn write_definition(out: &mut String, name: &str, schema: &SchemaObject) {
+    write_doc(out, schema, "");
+
+    match schema.object.as_ref() {
+        Some(object) if !object.properties.is_empty() => {
+            out.push_str(&format!("export interface {} {{\n", name));
+            for (prop, prop_schema) in object.properties.iter() {
+                if let Schema::Object(prop_obj) = prop_schema {
+                    write_doc(out, prop_obj, "  ");
+                }
+                let optional = if object.required.contains(prop) {
+                    ""
+                } else {
+                    "?"
+                };
+                out.push_str(&format!(
+                    "  {}{}: {};\n",
+                    property_name(prop),
+                    optional,
+                    ts_type(prop_schema)
+                ));
+            }
+            out.push_str("}\n\n");
+        }
+        _ => {
+            out.push_str(&format!(
+                "export type {} = {};\n\n",
+                name,
+                ts_type_obj(schema)
+            ));
+        }
+    }
+}
+
+fn write_doc(out: &mut String, schema: &SchemaObject, indent: &str) {
+    let description = schema
+        .metadata
+        .as_ref()
+        .and_then(|m| m.description.as_deref())
+        .map(str::trim)
+        .filter(|d| !d.is_empty());
+
+    if let Some(description) = description {
+        out.push_str(&format!("{}/**\n", indent));
+        for line in description.lines() {
+            out.push_str(&format!("{} * {}\n", indent, line.trim_end()).replace(" * \n", " *\n"));
+        }
+        out.push_str(&format!("{} */\n", indent));
+    }
+}
+
+fn property_name(name: &str) -> String {
+    if name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+    {
+        name.to_owned()
+    } else {
+        format!("'{}'", name)
+    }
+}
+
+fn ts_type(schema: &Schema) -> String {
+    match schema {
+        Schema::Bool(true) => "unknown".to_owned(),
+        Schema::Bool(false) => "never".to_owned(),
+        Schema::Object(obj) => ts_type_obj(obj),
+    }
+}
+
+fn ts_type_obj(schema: &SchemaObject) -> String {
+    if let Some(reference) = &schema.reference {
+        return reference.rsplit('/').next().unwrap_or(reference).to_owned();
+    }
+
+    if let Some(values) = &schema.enum_values {
+        return union(values.iter().map(literal).collect());
+    }
+
+    if let Some(value) = &schema.const_value {
+        return literal(value);
+    }
+
+    if let Some(subschemas) = &schema.subschemas {
+        let variants = subschemas
+            .any_of
+            .as_ref()
+            .or(subschemas.one_of.as_ref())
+            .or(subschemas.all_of.as_ref());
+        if let Some(variants) = variants {
+            return union(variants.iter().map(ts_type).collect());
+        }
+    }
+
+    match &schema.instance_type {
+        Some(SingleOrVec::Single(instance_type)) => instance_ts_type(instance_type, schema),
+        Some(SingleOrVec::Vec(instance_types)) => union(
+            instance_types
+                .iter()
+                .map(|t| instance_ts_type(t, schema))
+                .collect(),
+        ),
+        None => "unknown".to_owned(),
+    }
+}
+
+fn instance_ts_type(instance_type: &InstanceType, schema: &SchemaObject) -> String {
+    match instance_type {
+        InstanceType::Null => "null".to_owned(),
+        InstanceType::Boolean => "boolean".to_owned(),
+        InstanceType::Integer | InstanceType::Number => "number".to_owned(),
+        InstanceType::String => "string".to_owned(),
+        InstanceType::Array => {
+            let items = match schema.array.as_ref().and_then(|a| a.items.as_ref()) {
+                Some(SingleOrVec::Single(item)) => ts_type(item),
+                Some(SingleOrVec::Vec(items)) => {
+                    return format!(
+                        "[{}]",
+                        items.iter().map(ts_type).collect::<Vec<_>>().join(", ")
+                    );
+                }
+                None => "unknown".to_owned(),
+            };
+            if items.contains(' ') {
+                format!("({})[]", items)
+            } else {
+                format!("{}[]", items)
+            }
+        }
+        InstanceType::Object => {
+            let object = schema.object.as_ref();
+            match object {
+                Some(object) if !object.properties.is_empty() => {
+                    let props = object
+                        .properties
+                        .iter()
+                        .map(|(prop, prop_schema)| {
+                            let optional = if object.required.contains(prop) {
+                                ""
+                            } else {
+                                "?"
+                            };
+                            format!(
+                                "{}{}: {}",
+                                property_name(prop),
+                                optional,
+                                ts_type(prop_schema)
+                            )
+                        })
+                        .collect::<Vec<_>>();
+                    format!("{{ {} }}", props.join("; "))
+                }
+                _ => {
+                    let values = object
+                        .and_then(|o| o.additional_properties.as_deref())
+                        .map(ts_type)
+                        .unwrap_or_else(|| "unknown".to_owned());
+                    format!("Record<string, {}>", values)
+                }
+            }
+        }
+    }
+}
+
+fn literal(value: &Value) -> String {
+    match value {
+        Value::String(s) => format!("'{}'", s.replace('\'', "\\'")),
+        other => other.to_string(),
+    }
+}
+
+fn union(mut variants: Vec<String>) -> String {
+    variants.dedup();
+    match variants.len() {
+        0 => "never".to_owned(),
+        1 => variants.remove(0),
+        _ => variants.join(" | "),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod tests {
+    use super::*;
+
+    const GENERATED_TYPES_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/types/generated.ts");
+
+    /// Fails when the committed TypeScript definitions no longer match the Rust types.
+    ///
+    /// Set `UPDATE_TYPES=1` to regenerate the committed file instead.
+    #[test]
+    pub fn test_generated_types_are_up_to_date() {
+        let generated = manifest_store_types();
+
+        if std::env::var_os("UPDATE_TYPES").is_some() {
+            std::fs::write(GENERATED_TYPES_PATH, &generated).unwrap();
+        }
+
+        let committed = std::fs::read_to_string(GENERATED_TYPES_PATH).unwrap_or_default();
+        assert!(
+            committed == generated,
+            "types/generated.ts is out of date, run `yarn build:schema` and commit the result"
+        );
+    }
+}
@@ -0,0 +1,262 @@
+// Copyright 2024 Adobe
+// All Rights Reserved.
+//
+// NOTICE: Adobe permits you to use, modify, and distribute this file in
+// accordance with the terms of the Adobe license agreement accompanying
+// it.
+
+//! Walks the marker segments of a JPEG and the chunks of a PNG.
+//!
+//! Every length is checked against the data before the walk moves past it, so a segment or chunk
+//! that runs past the end of the data, or whose length would overflow the offset of the next one,
+//! ends the walk. Bytes are read through [`ByteSource`], so a JavaScript buffer can be walked
+//! without copying it.
+use crate::presence::ByteSource;
+use std::convert::TryFrom;
+
+pub const JPEG_SOI: [u8; 2] = [0xFF, 0xD8];
+pub const JPEG_EOI: u8 = 0xD9;
+pub const JPEG_SOS: u8 = 0xDA;
+pub const JPEG_APP1: u8 = 0xE1;
+pub const JPEG_APP11: u8 = 0xEB;
+pub const PNG_SIGNATURE: [u8; 8] = [0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A];
+
+/// A JPEG marker segment, or the end of image marker, which has no data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JpegSegment {
+    pub marker: u8,
+    /// Offset of the marker
+    pub start: usize,
+    /// Offset of the data that follows the length
+    pub offset: usize,
+    /// Length of the data, without the two bytes of the length itself
+    pub len: usize,
+}
+
+impl JpegSegment {
+    /// Offset just past the segment
+    pub fn end(&self) -> usize {
+        self.offset + self.len
+    }
+
+    /// The data of the segment, out of the bytes it was walked in
+    pub fn data<'a>(&self, data: &'a [u8]) -> &'a [u8] {
+        &data[self.offset..self.end()]
+    }
+}
+
+/// Iterates over the segments of a JPEG, ending with the start of scan or end of image marker
+///
+/// Fill bytes and markers without a length are skipped. Anything other than a marker, or a
+/// segment that doesn't fit in the data, ends the walk early.
+pub struct JpegSegments<'a, S: ByteSource + ?Sized> {
+    source: &'a S,
+    pos: Option<usize>,
+}
+
+impl<'a, S: ByteSource + ?Sized> JpegSegments<'a, S> {
+    /// Walks the segments after the start of image marker, if the data starts with one
+    pub fn new(source: &'a S) -> Self {
+        let pos = (source.read_vec(0, JPEG_SOI.len()) == JPEG_SOI).then_some(JPEG_SOI.len());
+        Self { source, pos }
+    }
+
+    /// Walks the segments from `pos`, such as those after the entropy-coded data of a scan
+    pub fn at(source: &'a S, pos: usize) -> Self {
+        Self {
+            source,
+            pos: Some(pos),
+        }
+    }
+
+    fn read(&self) -> Option<JpegSegment> {
+        let mut start = self.pos?;
+        loop {
+            let mut marker = [0u8; 2];
+            if self.source.read_at(start, &mut marker) < 2 || marker[0] != 0xFF {
+                return None;
+            }
+            match marker[1] {
+                // Fill byte
+                0xFF => start += 1,
+                // Standalone markers
+                0x01 | 0xD0..=0xD8 => start += 2,
+                JPEG_EOI => {
+                    return Some(JpegSegment {
+                        marker: JPEG_EOI,
+                        start,
+                        offset: start + 2,
+                        len: 0,
+                    })
+                }
+                marker => {
+                    let mut len = [0u8; 2];
+                    if self.source.read_at(start + 2, &mut len) < 2 {
+                        return None;
+                    }
+                    // The length counts its own two bytes
+                    let len = usize::from(u16::from_be_bytes(len)).checked_sub(2)?;
+                    let offset = start + 4;
+                    offset
+                        .checked_add(len)
+                        .filter(|end| *end <= self.source.len())?;
+                    return Some(JpegSegment {
+                        marker,
+                        start,
+                        offset,
+                        len,
+                    });
+                }
+            }
+        }
+    }
+}
+
+impl<'a, S: ByteSource + ?Sized> Iterator for JpegSegments<'a, S> {
+    type Item = JpegSegment;
+
+    fn next(&mut self) -> Option<JpegSegment> {
+        let segment = self.read();
+        self.pos = segment
+            .filter(|segment| !matches!(segment.marker, JPEG_SOS | JPEG_EOI))
+            .map(|segment| segment.end());
+        segment
+    }
+}
+
+/// A PNG chunk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PngChunk {
+    pub chunk_type: [u8; 4],
+    /// Offset of the data that follows the type
+    pub offset: usize,
+    pub len: usize,
+}
+
+impl PngChunk {
+    /// The data of the chunk, out of the bytes it was walked in
+    pub fn data<'a>(&self, data: &'a [u8]) -> &'a [u8] {
+        &data[self.offset..self.offset + self.len]
+    }
+}
+
+/// Iterates over the chunks of a PNG, ending with `IEND`
+///
+/// A chunk that doesn't fit in the data ends the walk early.
+pub struct PngChunks<'a, S: ByteSource + ?Sized> {
+    source: &'a S,
+    pos: Option<usize>,
+}
+
+impl<'a, S: ByteSource + ?Sized> PngChunks<'a, S> {
+    /// Walks the chunks after the signature, if the data starts with one
+    pub fn new(source: &'a S) -> Self {
+        let pos = (source.read_vec(0, PNG_SIGNATURE.len()) == PNG_SIGNATURE)
+            .then_some(PNG_SIGNATURE.len());
+        Self { source, pos }
+    }
+}
+
+impl<'a, S: ByteSource + ?Sized> Iterator for PngChunks<'a, S> {
+    type Item = PngChunk;
+
+    fn next(&mut self) -> Option<PngChunk> {
+        let pos = self.pos.take()?;
+        let mut header = [0u8; 8];
+        if self.source.read_at(pos, &mut header) < 8 {
+            return None;
+        }
+        let len = u32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+        let len = usize::try_from(len).ok()?;
+        let offset = pos + 8;
+        let end = offset
+            .checked_add(len)
+            .filter(|end| *end <= self.source.len())?;
+        let chunk_type = [header[4], header[5], header[6], header[7]];
+        if &chunk_type != b"IEND" {
+            // Then the CRC
+            self.pos = end.checked_add(4);
+        }
+
+        Some(PngChunk {
+            chunk_type,
+            offset,
+            len,
+        })
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
+
+    fn markers(jpeg: &[u8]) -> Vec<u8> {
+        JpegSegments::new(jpeg)
+            .map(|segment| segment.marker)
+            .collect()
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_jpeg_segments() {
+        let jpeg = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+        let segments: Vec<_> = JpegSegments::new(&jpeg[..]).collect();
+        assert!(segments
+            .iter()
+            .any(|segment| segment.marker == JPEG_APP11 && segment.data(jpeg).starts_with(b"JP")));
+        assert_eq!(
+            segments.last().map(|segment| segment.marker),
+            Some(JPEG_SOS)
+        );
+        assert!(segments
+            .windows(2)
+            .all(|pair| pair[0].end() <= pair[1].start));
+
+        // Fill bytes and a restart marker, then an empty comment and the end of image
+        let jpeg = [
+            0xFF, 0xD8, 0xFF, 0xFF, 0xFF, 0xD0, 0xFF, 0xFE, 0x00, 0x02, 0xFF, 0xD9, 0xFF, 0xFE,
+        ];
+        assert_eq!(markers(&jpeg), vec![0xFE, JPEG_EOI]);
+        let end = JpegSegments::new(&jpeg[..]).last().unwrap().end();
+        assert_eq!(end, 12);
+
+        assert!(markers(b"GIF89a").is_empty());
+        // A segment running past the end of the data, and one whose length is too short to
+        // count itself
+        assert!(markers(&[0xFF, 0xD8, 0xFF, 0xC0, 0xFF, 0xFF, 0x08]).is_empty());
+        assert!(markers(&[0xFF, 0xD8, 0xFF, 0xE1, 0x00, 0x01, 0xFF, 0xD9]).is_empty());
+        // Data that isn't a marker
+        assert_eq!(
+            markers(&[0xFF, 0xD8, 0xFF, 0xFE, 0x00, 0x02, 0x00]),
+            vec![0xFE]
+        );
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_png_chunks() {
+        let png = include_bytes!("../../../tools/testing/fixtures/images/c2pa-actions-1.2.png");
+        let chunks: Vec<_> = PngChunks::new(&png[..]).collect();
+        assert_eq!(chunks.first().map(|chunk| &chunk.chunk_type), Some(b"IHDR"));
+        assert_eq!(chunks.last().map(|chunk| &chunk.chunk_type), Some(b"IEND"));
+        assert!(chunks.iter().any(|chunk| &chunk.chunk_type == b"caBX"));
+
+        // A chunk length that overflows the offset of the next chunk on 32-bit targets
+        let mut oversized = PNG_SIGNATURE.to_vec();
+        oversized.extend_from_slice(&0xFFFF_FFF4u32.to_be_bytes());
+        oversized.extend_from_slice(b"tEXt");
+        oversized.extend_from_slice(&[0; 16]);
+        assert_eq!(PngChunks::new(&oversized[..]).count(), 0);
+
+        // Chunks after `IEND` aren't walked
+        let mut trailing = PNG_SIGNATURE.to_vec();
+        for chunk_type in [b"IEND", b"tEXt"].iter() {
+            trailing.extend_from_slice(&0u32.to_be_bytes());
+            trailing.extend_from_slice(*chunk_type);
+            trailing.extend_from_slice(&[0; 4]);
+        }
+        assert_eq!(PngChunks::new(&trailing[..]).count(), 1);
+        assert_eq!(PngChunks::new(&b"GIF89a"[..]).count(), 0);
+    }
+}
@@ -0,0 +1,236 @@
+// Copyright 2024 Adobe
+// All Rights Reserved.
+//
+// NOTICE: Adobe permits you to use, modify, and distribute this file in
+// accordance with the terms of the Adobe license agreement accompanying
+// it.
+
+//! Known-answer tests that check the toolkit works in the JavaScript engine it is running in.
+//!
+//! Every check runs against data built into the toolkit, so no network access or signer is needed.
+//! The manifest check reads a 1 pixel PNG carrying the manifest store of `sample.pdf`. Its
+//! signature is valid but its data hash covers the PDF, so the expected result is a single
+//! `assertion.dataHash.mismatch` status.
+use crate::manifest_store::get_manifest_store_data;
+use c2pa::ManifestStore;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+/// The input of the digest checks, from FIPS 180-2
+pub const DIGEST_INPUT: &[u8] = b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq";
+/// The SHA-256 digest of `DIGEST_INPUT`
+pub const DIGEST_SHA256: [u8; 32] = [
+    0x24, 0x8d, 0x6a, 0x61, 0xd2, 0x06, 0x38, 0xb8, 0xe5, 0xc0, 0x26, 0x93, 0x0c, 0x3e, 0x60, 0x39,
+    0xa3, 0x3c, 0xe4, 0x59, 0x64, 0xff, 0x21, 0x67, 0xf6, 0xec, 0xed, 0xd4, 0x19, 0xdb, 0x06, 0xc1,
+];
+
+const FIXTURE: &[u8] = include_bytes!("../../../tools/testing/fixtures/self-test/1px.png");
+const FIXTURE_LABEL: &str = "urn:uuid:a276e355-4273-4628-8956-5d8d4f9021b8";
+const FIXTURE_CODES: &[&str] = &["assertion.dataHash.mismatch"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CheckStatus {
+    Passed,
+    Failed,
+    /// The check can't run in this build or wasn't given what it needs
+    Skipped,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SelfTestCheck {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    /// How long the check took, in milliseconds
+    pub duration: f64,
+    /// Why the check failed or was skipped
+    pub message: Option<String>,
+}
+
+/// Result of `selfTest`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SelfTestResult {
+    /// Whether no check failed
+    pub passed: bool,
+    pub checks: Vec<SelfTestCheck>,
+}
+
+/// Collects the outcome of each check, timed with `clock`
+pub struct SelfTest<C> {
+    clock: C,
+    checks: Vec<SelfTestCheck>,
+}
+
+impl<C: Fn() -> f64> SelfTest<C> {
+    pub fn new(clock: C) -> Self {
+        Self {
+            clock,
+            checks: Vec::new(),
+        }
+    }
+
+    /// Returns the time to pass to `record` once the check is done
+    pub fn start(&self) -> f64 {
+        (self.clock)()
+    }
+
+    pub fn record(
+        &mut self,
+        name: &'static str,
+        started: f64,
+        outcome: std::result::Result<(), String>,
+    ) {
+        let (status, message) = match outcome {
+            Ok(()) => (CheckStatus::Passed, None),
+            Err(message) => (CheckStatus::Failed, Some(message)),
+        };
+        self.checks.push(SelfTestCheck {
+            name,
+            status,
+            duration: (self.clock)() - started,
+            message,
+        });
+    }
+
+    pub fn skip(&mut self, name: &'static str, reason: &str) {
+        self.checks.push(SelfTestCheck {
+            name,
+            status: CheckStatus::Skipped,
+            duration: 0.0,
+            message: Some(reason.to_owned()),
+        });
+    }
+
+    pub fn finish(self) -> SelfTestResult {
+        SelfTestResult {
+            passed: self
+                .checks
+                .iter()
+                .all(|check| check.status != CheckStatus::Failed),
+            checks: self.checks,
+        }
+    }
+}
+
+/// Compares a digest of `DIGEST_INPUT` with the known answer
+pub fn check_digest(digest: &[u8]) -> std::result::Result<(), String> {
+    if digest == DIGEST_SHA256 {
+        Ok(())
+    } else {
+        Err(format!("unexpected SHA-256 digest {:02x?}", digest))
+    }
+}
+
+/// Digests `DIGEST_INPUT` with the toolkit's own SHA-256
+pub fn check_rust_digest() -> std::result::Result<(), String> {
+    check_digest(&Sha256::digest(DIGEST_INPUT))
+}
+
+/// Reads the manifest store of the bundled fixture
+pub async fn read_fixture() -> std::result::Result<ManifestStore, String> {
+    get_manifest_store_data(FIXTURE, "image/png")
+        .await
+        .map_err(|err| err.to_string())
+}
+
+/// Checks the fixture's manifest and validation statuses against the known answer
+pub fn check_manifest(manifest_store: &ManifestStore) -> std::result::Result<(), String> {
+    if manifest_store.active_label() != Some(FIXTURE_LABEL) || manifest_store.manifests().len() != 1
+    {
+        return Err(format!(
+            "unexpected active manifest {:?}",
+            manifest_store.active_label()
+        ));
+    }
+
+    let codes: Vec<&str> = manifest_store
+        .validation_status()
+        .unwrap_or_default()
+        .iter()
+        .map(|status| status.code())
+        .collect();
+    if codes == FIXTURE_CODES {
+        Ok(())
+    } else {
+        Err(format!("unexpected validation statuses {:?}", codes))
+    }
+}
+
+/// Checks that the fixture's claim signature and signing certificate verified
+pub fn check_signature(manifest_store: &ManifestStore) -> std::result::Result<(), String> {
+    let failures: Vec<&str> = manifest_store
+        .validation_status()
+        .unwrap_or_default()
+        .iter()
+        .map(|status| status.code())
+        .filter(|code| {
+            code.starts_with("claimSignature.") || code.starts_with("signingCredential.")
+        })
+        .collect();
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("signature did not verify: {:?}", failures))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    pub async fn test_known_answers() {
+        assert_eq!(check_rust_digest(), Ok(()));
+        assert!(check_digest(&[0; 32]).is_err());
+
+        let manifest_store = read_fixture().await.unwrap();
+        assert_eq!(check_manifest(&manifest_store), Ok(()));
+        assert_eq!(check_signature(&manifest_store), Ok(()));
+
+        // A store that validates differently doesn't match the known answer
+        let test_asset = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+        let other = get_manifest_store_data(test_asset, "image/jpeg")
+            .await
+            .unwrap();
+        assert!(check_manifest(&other).is_err());
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_self_test_result() {
+        let time = std::cell::Cell::new(0.0);
+        let mut self_test = SelfTest::new(|| {
+            time.set(time.get() + 2.0);
+            time.get()
+        });
+
+        let started = self_test.start();
+        self_test.record("passes", started, Ok(()));
+        self_test.skip("skips", "not available");
+        let started = self_test.start();
+        self_test.record("fails", started, Err("wrong answer".to_owned()));
+
+        let result = self_test.finish();
+        assert!(!result.passed);
+        let statuses: Vec<_> = result.checks.iter().map(|check| check.status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                CheckStatus::Passed,
+                CheckStatus::Skipped,
+                CheckStatus::Failed
+            ]
+        );
+        assert_eq!(result.checks[0].duration, 2.0);
+        assert_eq!(result.checks[2].message.as_deref(), Some("wrong answer"));
+
+        // Skipped checks don't fail the self test
+        let mut self_test = SelfTest::new(|| 0.0);
+        self_test.skip("skips", "not available");
+        assert!(self_test.finish().passed);
+    }
+}
@@ -0,0 +1,387 @@
+// Copyright 2024 Adobe
+// All Rights Reserved.
+//
+// NOTICE: Adobe permits you to use, modify, and distribute this file in
+// accordance with the terms of the Adobe license agreement accompanying
+// it.
+
+//! Reshapes the manifest store output for the `serialization` read option.
+//!
+//! The output is built with keyed collections as plain objects, resources and hashed URI hashes as
+//! arrays of numbers, and raw claims as `ArrayBuffer`s. The steps that build it address manifests
+//! and resources by key, so the option is applied once the output is complete:
+//! - with `mapsAsObjects: false`, `manifests` and every resource store become `Map`s,
+//! - with `bytesAs`, every byte field is converted to a `Uint8Array`, a base64 string, or an array
+//!   of numbers,
+//! - with `binaryEncoding`, every hash is converted to a hex or base64 string. This runs before
+//!   `bytesAs`, so hashes take the string encoding and the other byte fields the representation.
+use crate::error::{Error, Result};
+use crate::manifest_store::{from_hex, to_hex};
+use crate::options::{BytesAs, DigestEncoding, Serialization};
+use crate::{array_items, get_path};
+use base64::Engine;
+use js_sys::{Array, ArrayBuffer, Map, Object, Reflect, Uint8Array};
+use wasm_bindgen::prelude::*;
+
+/// The key of the objects that `arbitrary_precision` wraps the numbers within assertion data in,
+/// outside of the `compat` build
+const NUMBER_TOKEN: &str = "$serde_json::private::Number";
+
+/// The hashes returned as bytes: those of hashed URIs and hard bindings
+const BYTE_DIGESTS: &[&str] = &["hash"];
+
+/// The hashes returned as hex: the digests behind hashed URI and collection hash checks, and the
+/// hashes of what validation steps read
+const HEX_DIGESTS: &[&str] = &["expectedHash", "actualHash", "inputHash"];
+
+/// Applies the `serialization` read option to the manifest store output
+pub fn apply(js_value: &JsValue, serialization: &Serialization) -> Result<()> {
+    let manifests = get_path(js_value, &["manifests"]);
+    if !manifests.is_object() {
+        return Ok(());
+    }
+    let manifests: Vec<JsValue> = Object::values(manifests.unchecked_ref()).iter().collect();
+
+    if serialization.binary_encoding != DigestEncoding::None {
+        encode_digests(js_value, serialization.binary_encoding)?;
+    }
+
+    if let Some(bytes_as) = serialization.bytes_as {
+        for manifest in &manifests {
+            convert_resources(manifest, bytes_as)?;
+            for ingredient in array_items(&get_path(manifest, &["ingredients"])) {
+                convert_resources(&ingredient, bytes_as)?;
+            }
+            for assertion in array_items(&get_path(manifest, &["assertions"])) {
+                convert_hashes(&get_path(&assertion, &["data"]), bytes_as)?;
+            }
+            convert_property(manifest, "rawClaimBytes", bytes_as)?;
+        }
+    }
+
+    if !serialization.maps_as_objects {
+        for manifest in &manifests {
+            to_map(&get_path(manifest, &["resources"]), "resources")?;
+            for ingredient in array_items(&get_path(manifest, &["ingredients"])) {
+                to_map(&get_path(&ingredient, &["resources"]), "resources")?;
+            }
+        }
+        to_map(js_value, "manifests")?;
+    }
+
+    Ok(())
+}
+
+/// Replaces the object at `parent[key]` with a `Map` of its properties, in the same order
+fn to_map(parent: &JsValue, key: &str) -> Result<()> {
+    let object = get_path(parent, &[key]);
+    let object = match object.dyn_ref::<Object>() {
+        Some(object) if !Array::is_array(object) && !object.is_instance_of::<Map>() => object,
+        _ => return Ok(()),
+    };
+
+    let map = Map::new();
+    for entry in Object::entries(object).iter() {
+        let entry: Array = entry.unchecked_into();
+        map.set(&entry.get(0), &entry.get(1));
+    }
+    Reflect::set(parent, &key.into(), &map).map_err(|_err| Error::JavaScriptConversion)?;
+
+    Ok(())
+}
+
+/// Converts the data of each resource of a manifest or ingredient
+fn convert_resources(parent: &JsValue, bytes_as: BytesAs) -> Result<()> {
+    let resources = get_path(parent, &["resources", "resources"]);
+    if let Some(resources) = resources.dyn_ref::<Object>() {
+        for key in Object::keys(resources).iter() {
+            if let Some(key) = key.as_string() {
+                convert_property(resources, &key, bytes_as)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Converts the hashes of the hashed URIs found anywhere within assertion data
+///
+/// A hashed URI is an object with a `url` string and a `hash`.
+fn convert_hashes(data: &JsValue, bytes_as: BytesAs) -> Result<()> {
+    let mut pending = vec![data.clone()];
+    while let Some(value) = pending.pop() {
+        if let Some(array) = value.dyn_ref::<Array>() {
+            pending.extend(array.iter());
+        } else if let Some(object) = value.dyn_ref::<Object>() {
+            if get_path(object, &["url"]).is_string() {
+                convert_property(object, "hash", bytes_as)?;
+            }
+            pending.extend(Object::values(object).iter());
+        }
+    }
+
+    Ok(())
+}
+
+/// Encodes every hash found within the output, leaving out resources
+fn encode_digests(js_value: &JsValue, encoding: DigestEncoding) -> Result<()> {
+    let mut pending = vec![js_value.clone()];
+    while let Some(value) = pending.pop() {
+        if value.is_instance_of::<Uint8Array>() || value.is_instance_of::<ArrayBuffer>() {
+            continue;
+        }
+        if let Some(array) = value.dyn_ref::<Array>() {
+            pending.extend(array.iter());
+        } else if let Some(object) = value.dyn_ref::<Object>() {
+            for entry in Object::entries(object).iter() {
+                let entry: Array = entry.unchecked_into();
+                let key = entry.get(0).as_string().unwrap_or_default();
+                let field = entry.get(1);
+                let encoded = if BYTE_DIGESTS.contains(&key.as_str()) {
+                    bytes_of(&field).map(|bytes| encode(&bytes, encoding))
+                } else if HEX_DIGESTS.contains(&key.as_str()) {
+                    field
+                        .as_string()
+                        .and_then(|hex| from_hex(&hex))
+                        .map(|bytes| encode(&bytes, encoding))
+                } else {
+                    None
+                };
+                match encoded {
+                    Some(encoded) => {
+                        Reflect::set(object, &key.into(), &encoded.into())
+                            .map_err(|_err| Error::JavaScriptConversion)?;
+                    }
+                    None if key != "resources" => pending.push(field),
+                    None => (),
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn encode(bytes: &[u8], encoding: DigestEncoding) -> String {
+    match encoding {
+        DigestEncoding::None | DigestEncoding::Hex => to_hex(bytes),
+        DigestEncoding::Base64 => base64::engine::general_purpose::STANDARD.encode(bytes),
+        DigestEncoding::Base64url => base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes),
+    }
+}
+
+/// Converts `object[key]` if it holds bytes
+fn convert_property(object: &JsValue, key: &str, bytes_as: BytesAs) -> Result<()> {
+    let key = JsValue::from(key);
+    let value = Reflect::get(object, &key).map_err(|_err| Error::JavaScriptConversion)?;
+    if let Some(bytes) = bytes_of(&value) {
+        Reflect::set(object, &key, &convert(&bytes, bytes_as))
+            .map_err(|_err| Error::JavaScriptConversion)?;
+    }
+
+    Ok(())
+}
+
+/// Returns the bytes held by a `Uint8Array`, an `ArrayBuffer`, or an array of numbers
+fn bytes_of(value: &JsValue) -> Option<Vec<u8>> {
+    if value.is_instance_of::<Uint8Array>() || value.is_instance_of::<ArrayBuffer>() {
+        return Some(Uint8Array::new(value).to_vec());
+    }
+
+    value
+        .dyn_ref::<Array>()?
+        .iter()
+        .map(|item| byte_of(&item))
+        .collect()
+}
+
+fn byte_of(item: &JsValue) -> Option<u8> {
+    match item.as_f64() {
+        Some(n) if n.fract() == 0.0 && (0.0..=255.0).contains(&n) => Some(n as u8),
+        Some(_) => None,
+        None => get_path(item, &[NUMBER_TOKEN]).as_string()?.parse().ok(),
+    }
+}
+
+fn convert(bytes: &[u8], bytes_as: BytesAs) -> JsValue {
+    match bytes_as {
+        BytesAs::Uint8Array => Uint8Array::from(bytes).into(),
+        BytesAs::Base64 => base64::engine::general_purpose::STANDARD
+            .encode(bytes)
+            .into(),
+        BytesAs::Array => bytes
+            .iter()
+            .map(|b| JsValue::from(*b))
+            .collect::<Array>()
+            .into(),
+    }
+}
+
+// The output can only be built in a JavaScript environment
+#[cfg(all(test, target_arch = "wasm32"))]
+pub mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
+
+    const MANIFEST_LABEL: &str = "adobetest:urn:uuid:825cf3cf-0127-4af3-b65c-c11d0f961e67";
+    const THUMBNAIL: &str = "self#jumbf=c2pa.assertions/c2pa.thumbnail.claim.jpeg";
+
+    async fn read(serialization: &str) -> JsValue {
+        let test_asset = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+        let buf = Uint8Array::from(&test_asset[..]).buffer();
+        let options = js_sys::JSON::parse(&format!(
+            r#"{{"includeRawClaim": true, "includeHashDigests": true, "serialization": {}}}"#,
+            serialization
+        ))
+        .unwrap();
+        wasm_bindgen_futures::JsFuture::from(crate::get_manifest_store_from_array_buffer(
+            buf.into(),
+            "image/jpeg".to_owned(),
+            options,
+        ))
+        .await
+        .unwrap()
+    }
+
+    /// Returns the manifest's thumbnail, the hash of its first credential, and its raw claim
+    fn byte_fields(manifest: &JsValue, resources: &JsValue) -> [JsValue; 3] {
+        let thumbnail = match resources.dyn_ref::<Map>() {
+            Some(map) => map.get(&THUMBNAIL.into()),
+            None => get_path(resources, &[THUMBNAIL]),
+        };
+        let author = array_items(&get_path(manifest, &["assertions"]))
+            .into_iter()
+            .find(|assertion| get_path(assertion, &["label"]) == "stds.schema-org.CreativeWork")
+            .unwrap();
+        let hash = get_path(&author, &["data", "author", "0", "credential", "0", "hash"]);
+        [thumbnail, hash, get_path(manifest, &["rawClaimBytes"])]
+    }
+
+    #[wasm_bindgen_test]
+    pub async fn test_serialization() {
+        let default = read("{}").await;
+        let manifest = get_path(&default, &["manifests", MANIFEST_LABEL]);
+        let [thumbnail, hash, raw_claim] =
+            byte_fields(&manifest, &get_path(&manifest, &["resources", "resources"]));
+        assert!(Array::is_array(&thumbnail));
+        assert!(Array::is_array(&hash));
+        assert!(raw_claim.is_instance_of::<ArrayBuffer>());
+        let expected_thumbnail = bytes_of(&thumbnail).unwrap();
+        let expected_hash = bytes_of(&hash).unwrap();
+
+        for maps_as_objects in [true, false].iter() {
+            for bytes_as in ["uint8array", "base64", "array"].iter() {
+                let result = read(&format!(
+                    r#"{{"mapsAsObjects": {}, "bytesAs": "{}"}}"#,
+                    maps_as_objects, bytes_as
+                ))
+                .await;
+
+                let manifests = get_path(&result, &["manifests"]);
+                let manifest = match manifests.dyn_ref::<Map>() {
+                    Some(map) => map.get(&MANIFEST_LABEL.into()),
+                    None => get_path(&manifests, &[MANIFEST_LABEL]),
+                };
+                let resources = get_path(&manifest, &["resources", "resources"]);
+                assert_eq!(manifests.is_instance_of::<Map>(), !maps_as_objects);
+                assert_eq!(resources.is_instance_of::<Map>(), !maps_as_objects);
+
+                for field in byte_fields(&manifest, &resources).iter() {
+                    let shape_matches = match *bytes_as {
+                        "uint8array" => field.is_instance_of::<Uint8Array>(),
+                        "base64" => field.is_string(),
+                        _ => Array::is_array(field),
+                    };
+                    assert!(shape_matches, "{} {:?}", bytes_as, field);
+                }
+
+                let [thumbnail, hash, _] = byte_fields(&manifest, &resources);
+                let decode = |value: JsValue| match value.as_string() {
+                    Some(base64) => base64::engine::general_purpose::STANDARD
+                        .decode(base64)
+                        .unwrap(),
+                    None => bytes_of(&value).unwrap(),
+                };
+                assert_eq!(decode(thumbnail), expected_thumbnail);
+                assert_eq!(decode(hash), expected_hash);
+            }
+        }
+    }
+
+    /// Returns every hash within the output, with the property it was found under
+    fn digests(value: &JsValue, found: &mut Vec<(String, JsValue)>) {
+        if let Some(array) = value.dyn_ref::<Array>() {
+            array.iter().for_each(|item| digests(&item, found));
+        } else if let Some(object) = value.dyn_ref::<Object>() {
+            for entry in Object::entries(object).iter() {
+                let entry: Array = entry.unchecked_into();
+                let key = entry.get(0).as_string().unwrap();
+                if BYTE_DIGESTS.contains(&key.as_str()) || HEX_DIGESTS.contains(&key.as_str()) {
+                    found.push((key, entry.get(1)));
+                } else if key != "resources" {
+                    digests(&entry.get(1), found);
+                }
+            }
+        }
+    }
+
+    #[wasm_bindgen_test]
+    pub async fn test_binary_encoding() {
+        let default = read(r#"{"bytesAs": "array"}"#).await;
+        let manifest = get_path(&default, &["manifests", MANIFEST_LABEL]);
+        let [_, hash, _] =
+            byte_fields(&manifest, &get_path(&manifest, &["resources", "resources"]));
+        let expected_hash = bytes_of(&hash).unwrap();
+
+        for (binary_encoding, decode) in [
+            (
+                "hex",
+                (|hash: &str| from_hex(hash)) as fn(&str) -> Option<Vec<u8>>,
+            ),
+            ("base64", |hash| {
+                base64::engine::general_purpose::STANDARD.decode(hash).ok()
+            }),
+            ("base64url", |hash| {
+                base64::engine::general_purpose::URL_SAFE_NO_PAD
+                    .decode(hash)
+                    .ok()
+            }),
+        ] {
+            // Byte fields that aren't hashes keep the representation of `bytesAs`
+            let result = read(&format!(
+                r#"{{"bytesAs": "uint8array", "binaryEncoding": "{}"}}"#,
+                binary_encoding
+            ))
+            .await;
+            let manifest = get_path(&result, &["manifests", MANIFEST_LABEL]);
+            let [thumbnail, hash, raw_claim] =
+                byte_fields(&manifest, &get_path(&manifest, &["resources", "resources"]));
+            assert!(thumbnail.is_instance_of::<Uint8Array>());
+            assert!(raw_claim.is_instance_of::<Uint8Array>());
+            assert_eq!(
+                decode(&hash.as_string().unwrap()),
+                Some(expected_hash.clone())
+            );
+
+            // Every hash, in assertions, ingredients and hashed URI checks, takes the encoding
+            let mut found = Vec::new();
+            digests(&get_path(&result, &["manifests"]), &mut found);
+            assert!(found.iter().any(|(key, _)| key == "expectedHash"));
+            assert!(found.len() > 10);
+            for (key, digest) in found {
+                let digest = digest
+                    .as_string()
+                    .unwrap_or_else(|| panic!("{} {} {:?}", binary_encoding, key, digest));
+                assert!(
+                    decode(&digest).is_some(),
+                    "{} {} {}",
+                    binary_encoding,
+                    key,
+                    digest
+                );
+            }
+        }
+    }
+}
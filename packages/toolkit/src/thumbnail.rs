@@ -0,0 +1,408 @@
+// Copyright 2024 Adobe
+// All Rights Reserved.
+//
+// NOTICE: Adobe permits you to use, modify, and distribute this file in
+// accordance with the terms of the Adobe license agreement accompanying
+// it.
+
+//! Compares the claim thumbnail of the active manifest with the asset it is attached to.
+//!
+//! Both images are reduced to a difference hash (dHash): the image is shrunk to 9 by 8 grayscale
+//! pixels, and each bit of the 64 bit hash tells whether a pixel is darker than its right
+//! neighbor. Images showing the same content have hashes a few bits apart whatever their size and
+//! compression. This is advisory and separate from validation, since the thumbnail isn't required
+//! to match the asset.
+use crate::asset_info::asset_info;
+use crate::error::Result;
+use crate::manifest_store::get_manifest_store_data;
+use crate::mime::sniff_mime_type;
+use serde::{Deserialize, Serialize};
+
+/// The distance at or below which the thumbnail is considered to match, out of 64 bits
+pub const DEFAULT_THRESHOLD: u32 = 10;
+
+/// JPEGs are decoded at a reduced scale of at least this size, which is plenty for a 9 by 8 hash
+const DECODE_SIZE: u16 = 64;
+
+/// Options of `verifyThumbnailConsistency`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ThumbnailOptions {
+    /// The largest distance between the hashes at which the thumbnail still matches
+    pub threshold: u32,
+}
+
+impl Default for ThumbnailOptions {
+    fn default() -> Self {
+        Self {
+            threshold: DEFAULT_THRESHOLD,
+        }
+    }
+}
+
+/// Why the thumbnail couldn't be compared with the asset
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum NotEvaluable {
+    /// The active manifest has no thumbnail
+    NoThumbnail,
+    /// The thumbnail or the asset is in a format that can't be decoded, such as a video
+    UnsupportedFormat,
+    /// The thumbnail or the asset is corrupt
+    DecodingFailed,
+}
+
+/// Result of `verifyThumbnailConsistency`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThumbnailConsistency {
+    /// Whether the distance is within the threshold, or `None` if it couldn't be computed
+    pub consistent: Option<bool>,
+    /// The number of bits the hashes of the thumbnail and the asset differ by, out of 64
+    pub distance: Option<u32>,
+    pub threshold: u32,
+    pub not_evaluable: Option<NotEvaluable>,
+    /// The EXIF orientation the thumbnail was turned upright with before hashing, if it has one
+    pub thumbnail_orientation: Option<u16>,
+    /// The EXIF orientation the asset was turned upright with before hashing, if it has one
+    pub asset_orientation: Option<u16>,
+}
+
+impl ThumbnailConsistency {
+    pub fn not_evaluable(threshold: u32, reason: NotEvaluable) -> Self {
+        Self {
+            consistent: None,
+            distance: None,
+            threshold,
+            not_evaluable: Some(reason),
+            thumbnail_orientation: None,
+            asset_orientation: None,
+        }
+    }
+}
+
+/// An 8 bit grayscale image
+struct Gray {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+/// Luma of an RGB pixel, following ITU-R BT.601
+fn luma(r: u8, g: u8, b: u8) -> u8 {
+    ((r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000) as u8
+}
+
+fn decode_jpeg(data: &[u8]) -> Option<Gray> {
+    use jpeg_decoder::PixelFormat;
+
+    let mut decoder = jpeg_decoder::Decoder::new(data);
+    let (width, height) = decoder.scale(DECODE_SIZE, DECODE_SIZE).ok()?;
+    let pixels = decoder.decode().ok()?;
+    let pixels = match decoder.info()?.pixel_format {
+        PixelFormat::L8 => pixels,
+        // Big endian, so the first byte is the most significant
+        PixelFormat::L16 => pixels.chunks_exact(2).map(|pixel| pixel[0]).collect(),
+        PixelFormat::RGB24 => pixels
+            .chunks_exact(3)
+            .map(|pixel| luma(pixel[0], pixel[1], pixel[2]))
+            .collect(),
+        PixelFormat::CMYK32 => pixels
+            .chunks_exact(4)
+            .map(|pixel| {
+                let ink = |value: u8| ((255 - value as u32) * (255 - pixel[3] as u32) / 255) as u8;
+                luma(ink(pixel[0]), ink(pixel[1]), ink(pixel[2]))
+            })
+            .collect(),
+    };
+
+    Some(Gray {
+        width: width as usize,
+        height: height as usize,
+        pixels,
+    })
+}
+
+fn decode_png(data: &[u8]) -> Option<Gray> {
+    use png::{ColorType, Transformations};
+
+    let mut decoder = png::Decoder::new(data);
+    // Palettes are expanded and 16 bit samples reduced to 8 bits
+    decoder.set_transformations(Transformations::EXPAND | Transformations::STRIP_16);
+    let mut reader = decoder.read_info().ok()?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let frame = reader.next_frame(&mut buf).ok()?;
+    let samples = frame.color_type.samples();
+    let rows = buf[..frame.buffer_size()].chunks_exact(frame.line_size);
+
+    // Alpha is ignored
+    let pixels = rows
+        .flat_map(|row| row[..frame.width as usize * samples].chunks_exact(samples))
+        .map(|pixel| match frame.color_type {
+            ColorType::Rgb | ColorType::Rgba => luma(pixel[0], pixel[1], pixel[2]),
+            _ => pixel[0],
+        })
+        .collect();
+
+    Some(Gray {
+        width: frame.width as usize,
+        height: frame.height as usize,
+        pixels,
+    })
+}
+
+/// Decodes a JPEG or PNG image, or returns why it can't be
+fn decode(data: &[u8]) -> std::result::Result<Gray, NotEvaluable> {
+    let gray = match sniff_mime_type(data) {
+        Some("image/jpeg") => decode_jpeg(data),
+        Some("image/png") => decode_png(data),
+        _ => return Err(NotEvaluable::UnsupportedFormat),
+    };
+
+    gray.filter(|gray| {
+        gray.width > 0 && gray.height > 0 && gray.pixels.len() >= gray.width * gray.height
+    })
+    .ok_or(NotEvaluable::DecodingFailed)
+}
+
+/// Turns an image stored with an EXIF orientation the way it is meant to be displayed
+fn orient(gray: Gray, orientation: u16) -> Gray {
+    if !(2..=8).contains(&orientation) {
+        return gray;
+    }
+
+    // Orientations 5 to 8 swap the axes
+    let transposed = orientation >= 5;
+    let (width, height) = if transposed {
+        (gray.height, gray.width)
+    } else {
+        (gray.width, gray.height)
+    };
+    let mut pixels = Vec::with_capacity(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            // The stored pixel displayed at (x, y)
+            let (sx, sy) = match orientation {
+                2 => (gray.width - 1 - x, y),
+                3 => (gray.width - 1 - x, gray.height - 1 - y),
+                4 => (x, gray.height - 1 - y),
+                5 => (y, x),
+                6 => (y, gray.height - 1 - x),
+                7 => (gray.width - 1 - y, gray.height - 1 - x),
+                _ => (gray.width - 1 - y, x),
+            };
+            pixels.push(gray.pixels[sy * gray.width + sx]);
+        }
+    }
+
+    Gray {
+        width,
+        height,
+        pixels,
+    }
+}
+
+/// Computes the difference hash of an image
+fn dhash(gray: &Gray) -> u64 {
+    // Each of the 9 by 8 cells is the average of the pixels it covers
+    let cell = |x: usize, y: usize| -> u32 {
+        let (x0, x1) = (
+            x * gray.width / 9,
+            ((x + 1) * gray.width / 9).max(x * gray.width / 9 + 1),
+        );
+        let (y0, y1) = (
+            y * gray.height / 8,
+            ((y + 1) * gray.height / 8).max(y * gray.height / 8 + 1),
+        );
+        let sum: u32 = (y0..y1.min(gray.height))
+            .flat_map(|y| (x0..x1.min(gray.width)).map(move |x| (x, y)))
+            .map(|(x, y)| gray.pixels[y * gray.width + x] as u32)
+            .sum();
+        sum / ((x1 - x0) * (y1 - y0)) as u32
+    };
+
+    let mut hash = 0;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash = (hash << 1) | (cell(x, y) < cell(x + 1, y)) as u64;
+        }
+    }
+    hash
+}
+
+/// Decodes an image and turns it upright according to its EXIF orientation, returning it along
+/// with the orientation
+fn decode_upright(data: &[u8]) -> std::result::Result<(Gray, Option<u16>), NotEvaluable> {
+    let gray = decode(data)?;
+    let orientation = asset_info(data).orientation;
+    Ok((orient(gray, orientation.unwrap_or(1)), orientation))
+}
+
+/// Compares a thumbnail with the asset, turning each upright according to its EXIF orientation
+/// first
+pub fn compare(thumbnail: &[u8], asset: &[u8], threshold: u32) -> ThumbnailConsistency {
+    let images = decode_upright(thumbnail)
+        .and_then(|thumbnail| decode_upright(asset).map(|asset| (thumbnail, asset)));
+
+    match images {
+        Ok(((thumbnail, thumbnail_orientation), (asset, asset_orientation))) => {
+            let distance = (dhash(&thumbnail) ^ dhash(&asset)).count_ones();
+            ThumbnailConsistency {
+                consistent: Some(distance <= threshold),
+                distance: Some(distance),
+                threshold,
+                not_evaluable: None,
+                thumbnail_orientation,
+                asset_orientation,
+            }
+        }
+        Err(reason) => ThumbnailConsistency::not_evaluable(threshold, reason),
+    }
+}
+
+/// Compares the claim thumbnail of the asset's active manifest with the asset
+pub async fn verify_thumbnail_consistency(
+    asset: &[u8],
+    mime_type: &str,
+    options: &ThumbnailOptions,
+) -> Result<ThumbnailConsistency> {
+    let manifest_store = get_manifest_store_data(asset, mime_type).await?;
+    let thumbnail = manifest_store
+        .get_active()
+        .and_then(|manifest| manifest.thumbnail());
+
+    Ok(match thumbnail {
+        Some((_, thumbnail)) => compare(&thumbnail, asset, options.threshold),
+        None => ThumbnailConsistency::not_evaluable(options.threshold, NotEvaluable::NoThumbnail),
+    })
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    pub async fn test_matching_thumbnail() {
+        let test_asset = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+
+        let result =
+            verify_thumbnail_consistency(test_asset, "image/jpeg", &ThumbnailOptions::default())
+                .await
+                .unwrap();
+        assert_eq!(result.consistent, Some(true));
+        assert!(result.distance.unwrap() <= DEFAULT_THRESHOLD);
+        assert_eq!(result.not_evaluable, None);
+    }
+
+    #[wasm_bindgen_test]
+    pub async fn test_mismatching_thumbnail() {
+        let test_asset = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+        let other_asset = include_bytes!("../../../tools/testing/fixtures/images/firefly-1.jpg");
+        let manifest_store = get_manifest_store_data(test_asset, "image/jpeg")
+            .await
+            .unwrap();
+        let (_, thumbnail) = manifest_store.get_active().unwrap().thumbnail().unwrap();
+
+        let result = compare(&thumbnail, other_asset, DEFAULT_THRESHOLD);
+        assert_eq!(result.consistent, Some(false));
+        let distance = result.distance.unwrap();
+        assert!(distance > DEFAULT_THRESHOLD);
+
+        // The threshold decides, the distance doesn't change
+        let lenient = compare(&thumbnail, other_asset, distance);
+        assert_eq!(lenient.consistent, Some(true));
+        assert_eq!(lenient.distance, Some(distance));
+
+        let png = include_bytes!("../../../tools/testing/fixtures/images/c2pa-actions-1.2.png");
+        assert_eq!(compare(png, png, 0).distance, Some(0));
+
+        let video = include_bytes!("../../../tools/testing/fixtures/images/sample.avi");
+        assert_eq!(
+            compare(&thumbnail, video, DEFAULT_THRESHOLD).not_evaluable,
+            Some(NotEvaluable::UnsupportedFormat)
+        );
+        assert_eq!(
+            compare(&thumbnail, &test_asset[..1000], DEFAULT_THRESHOLD).not_evaluable,
+            Some(NotEvaluable::DecodingFailed)
+        );
+    }
+
+    /// Builds a JPEG with an EXIF orientation, inserted after the start of image marker of `jpeg`
+    fn jpeg_with_orientation(jpeg: &[u8], orientation: u16) -> Vec<u8> {
+        let mut tiff = b"II*\0".to_vec();
+        tiff.extend_from_slice(&8u32.to_le_bytes());
+        tiff.extend_from_slice(&1u16.to_le_bytes());
+        tiff.extend_from_slice(&0x0112u16.to_le_bytes());
+        tiff.extend_from_slice(&3u16.to_le_bytes());
+        tiff.extend_from_slice(&1u32.to_le_bytes());
+        tiff.extend_from_slice(&orientation.to_le_bytes());
+        tiff.extend_from_slice(&[0, 0, 0, 0, 0, 0]);
+
+        let mut segment = b"Exif\0\0".to_vec();
+        segment.extend_from_slice(&tiff);
+        let mut result = vec![0xFF, 0xD8, 0xFF, 0xE1];
+        result.extend_from_slice(&((segment.len() + 2) as u16).to_be_bytes());
+        result.extend_from_slice(&segment);
+        result.extend_from_slice(&jpeg[2..]);
+        result
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_orientation() {
+        // A 3 by 2 image with distinct pixels
+        let gray = || Gray {
+            width: 3,
+            height: 2,
+            pixels: vec![1, 2, 3, 4, 5, 6],
+        };
+
+        let rotated = orient(gray(), 6);
+        assert_eq!((rotated.width, rotated.height), (2, 3));
+        assert_eq!(rotated.pixels, vec![4, 1, 5, 2, 6, 3]);
+        assert_eq!(orient(gray(), 3).pixels, vec![6, 5, 4, 3, 2, 1]);
+        assert_eq!(orient(gray(), 8).pixels, vec![3, 6, 2, 5, 1, 4]);
+        assert_eq!(orient(gray(), 1).pixels, gray().pixels);
+
+        // An upright image stored with each orientation is turned back upright exactly. Rotating
+        // by a quarter turn one way is undone by a quarter turn the other way, and every other
+        // orientation undoes itself.
+        for orientation in 1..=8 {
+            let stored_with = match orientation {
+                6 => 8,
+                8 => 6,
+                other => other,
+            };
+            let upright = orient(orient(gray(), stored_with), orientation);
+            assert_eq!((upright.width, upright.height), (3, 2));
+            assert_eq!(upright.pixels, gray().pixels, "orientation {}", orientation);
+        }
+    }
+
+    #[wasm_bindgen_test]
+    pub async fn test_orientation_is_applied() {
+        let test_asset = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+        let manifest_store = get_manifest_store_data(test_asset, "image/jpeg")
+            .await
+            .unwrap();
+        let (_, thumbnail) = manifest_store.get_active().unwrap().thumbnail().unwrap();
+
+        let upright = compare(&thumbnail, test_asset, DEFAULT_THRESHOLD);
+        assert_eq!(upright.consistent, Some(true));
+        assert_eq!(upright.asset_orientation, None);
+
+        // The same pixels tagged as rotated no longer look like the thumbnail once turned
+        let tagged = jpeg_with_orientation(test_asset, 6);
+        let rotated = compare(&thumbnail, &tagged, DEFAULT_THRESHOLD);
+        assert_eq!(rotated.asset_orientation, Some(6));
+        assert_eq!(rotated.consistent, Some(false));
+
+        // A thumbnail carrying the same orientation as the asset is turned the same way
+        let tagged_thumbnail = jpeg_with_orientation(&thumbnail, 6);
+        let both = compare(&tagged_thumbnail, &tagged, DEFAULT_THRESHOLD);
+        assert_eq!(both.thumbnail_orientation, Some(6));
+        assert_eq!(both.consistent, Some(true));
+    }
+}
@@ -0,0 +1,515 @@
+// Copyright 2024 Adobe
+// All Rights Reserved.
+//
+// NOTICE: Adobe permits you to use, modify, and distribute this file in
+// accordance with the terms of the Adobe license agreement accompanying
+// it.
+
+//! Lays the fragments of a fragmented MP4 out on a timeline, along with whether each validates,
+//! so that a player can show which parts of the video are covered by provenance.
+//!
+//! Each fragment's time range is read from the `tfdt` and `trun` boxes of one track: the first
+//! video track of the init segment, or else its first track. Ranges are converted to milliseconds
+//! with that track's `mdhd` timescale. Fragments are validated one at a time against the manifest
+//! in the init segment. Ranges are never merged: time not covered by any fragment is listed as a
+//! gap, and each fragment lists the fragments it overlaps.
+use crate::asset_info::{bmff_boxes, read_be};
+use crate::error::Error;
+use crate::limits;
+use crate::validation::{self, Severity};
+use c2pa::ManifestStore;
+use serde::Serialize;
+
+/// An entry of the coverage timeline
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum TimelineEntry {
+    #[serde(rename_all = "camelCase")]
+    Fragment {
+        /// `None` if the fragment's timing couldn't be read, in which case it is listed last
+        start_time_ms: Option<f64>,
+        end_time_ms: Option<f64>,
+        fragment_index: usize,
+        /// Whether the fragment validated without errors
+        validated: bool,
+        /// The codes of the fragment's validation statuses, or of the error that failed it
+        validation_codes: Vec<String>,
+        /// The indices of the fragments whose time ranges overlap this one
+        overlaps: Vec<usize>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Gap {
+        start_time_ms: f64,
+        end_time_ms: f64,
+    },
+}
+
+/// The track that fragment times are read from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Track {
+    id: u64,
+    timescale: u64,
+    /// The sample duration for fragments that don't give their own, from `trex`
+    default_sample_duration: Option<u64>,
+}
+
+/// Finds the first video track of the init segment, or else its first track
+fn reference_track(init_segment: &[u8]) -> Option<Track> {
+    let mut tracks = Vec::new();
+    let mut trex_durations = Vec::new();
+
+    bmff_boxes(init_segment, |box_type, moov| {
+        if box_type != b"moov" {
+            return;
+        }
+        bmff_boxes(moov, |box_type, contents| match box_type {
+            b"trak" => {
+                if let Some(track) = trak(contents) {
+                    tracks.push(track);
+                }
+            }
+            b"mvex" => bmff_boxes(contents, |box_type, trex| {
+                // The track ID and default sample description index precede the duration
+                if let (b"trex", Some(id), Some(duration)) =
+                    (box_type, read_be(trex, 4, 4), read_be(trex, 12, 4))
+                {
+                    trex_durations.push((id, duration));
+                }
+            }),
+            _ => {}
+        });
+    });
+
+    let (track, _) = tracks
+        .iter()
+        .find(|(_, is_video)| *is_video)
+        .or_else(|| tracks.first())?;
+    let default_sample_duration = trex_durations
+        .iter()
+        .find(|(id, _)| *id == track.id)
+        .map(|(_, duration)| *duration);
+    Some(Track {
+        default_sample_duration,
+        ..*track
+    })
+}
+
+/// Reads the ID and timescale of a track, and whether it is a video track
+fn trak(trak: &[u8]) -> Option<(Track, bool)> {
+    let (mut id, mut timescale, mut is_video) = (None, None, false);
+
+    bmff_boxes(trak, |box_type, contents| match box_type {
+        // Version 1 widens the creation and modification times
+        b"tkhd" => {
+            id = match contents.first() {
+                Some(1) => read_be(contents, 20, 4),
+                _ => read_be(contents, 12, 4),
+            }
+        }
+        b"mdia" => bmff_boxes(contents, |box_type, contents| match box_type {
+            b"mdhd" => {
+                timescale = match contents.first() {
+                    Some(1) => read_be(contents, 20, 4),
+                    _ => read_be(contents, 12, 4),
+                }
+            }
+            b"hdlr" => is_video = contents.get(8..12) == Some(b"vide"),
+            _ => {}
+        }),
+        _ => {}
+    });
+
+    let track = Track {
+        id: id?,
+        timescale: timescale.filter(|timescale| *timescale > 0)?,
+        default_sample_duration: None,
+    };
+    Some((track, is_video))
+}
+
+/// Reads the time range a fragment covers on `track`, in the track's timescale
+fn fragment_range(fragment: &[u8], track: &Track) -> Option<(u64, u64)> {
+    let mut range: Option<(u64, u64)> = None;
+    let mut valid = true;
+
+    bmff_boxes(fragment, |box_type, moof| {
+        if box_type != b"moof" {
+            return;
+        }
+        bmff_boxes(moof, |box_type, traf| {
+            if box_type != b"traf" {
+                return;
+            }
+            match traf_range(traf, track) {
+                Some(Some((start, end))) => {
+                    range = Some(match range {
+                        Some((range_start, range_end)) => {
+                            (range_start.min(start), range_end.max(end))
+                        }
+                        None => (start, end),
+                    })
+                }
+                Some(None) => {}
+                None => valid = false,
+            }
+        });
+    });
+
+    range.filter(|_| valid)
+}
+
+/// Reads the time range of a track fragment, or `Some(None)` if it belongs to another track
+fn traf_range(traf: &[u8], track: &Track) -> Option<Option<(u64, u64)>> {
+    let mut tfhd = None;
+    let mut start = None;
+    let mut truns = Vec::new();
+    bmff_boxes(traf, |box_type, contents| match box_type {
+        b"tfhd" => tfhd = Some(contents),
+        b"tfdt" => {
+            start = match contents.first() {
+                Some(1) => read_be(contents, 4, 8),
+                _ => read_be(contents, 4, 4),
+            }
+        }
+        b"trun" => truns.push(contents),
+        _ => {}
+    });
+
+    let tfhd = tfhd?;
+    if read_be(tfhd, 4, 4)? != track.id {
+        return Some(None);
+    }
+    let flags = read_be(tfhd, 1, 3)?;
+    // Skip the base data offset and sample description index if present
+    let mut offset = 8;
+    if flags & 0x01 != 0 {
+        offset += 8;
+    }
+    if flags & 0x02 != 0 {
+        offset += 4;
+    }
+    let default_duration = if flags & 0x08 != 0 {
+        read_be(tfhd, offset, 4)
+    } else {
+        track.default_sample_duration
+    };
+
+    let mut duration: u64 = 0;
+    for trun in truns {
+        duration = duration.checked_add(trun_duration(trun, default_duration)?)?;
+    }
+    let start = start?;
+    Some(Some((start, start.checked_add(duration)?)))
+}
+
+/// Adds up the durations of the samples of a track run
+fn trun_duration(trun: &[u8], default_duration: Option<u64>) -> Option<u64> {
+    let flags = read_be(trun, 1, 3)?;
+    let sample_count = read_be(trun, 4, 4)?;
+    // Skip the data offset and first sample flags if present
+    let mut offset = 8;
+    if flags & 0x01 != 0 {
+        offset += 4;
+    }
+    if flags & 0x04 != 0 {
+        offset += 4;
+    }
+    if flags & 0x100 == 0 {
+        return sample_count.checked_mul(default_duration?);
+    }
+
+    // Each sample has a duration, size, flags and composition time offset if flagged
+    let sample_len = [0x100, 0x200, 0x400, 0x800]
+        .iter()
+        .filter(|flag| flags & **flag != 0)
+        .count()
+        * 4;
+    let mut duration: u64 = 0;
+    for _ in 0..sample_count {
+        duration = duration.checked_add(read_be(trun, offset, 4)?)?;
+        offset += sample_len;
+    }
+    Some(duration)
+}
+
+/// Validates a fragment against the manifest in the init segment, returning whether it validated
+/// and the codes of its statuses, or of the error that failed it
+async fn validate_fragment(
+    init_segment: &[u8],
+    fragment: &[u8],
+    mime_type: &str,
+) -> (bool, Vec<String>) {
+    if let Err(err) = limits::check_asset(init_segment, mime_type) {
+        return (false, vec![err.code().code.to_owned()]);
+    }
+    match ManifestStore::from_fragment_bytes_async(mime_type, init_segment, fragment, true).await {
+        Ok(manifest_store) => {
+            let codes: Vec<String> = manifest_store
+                .validation_status()
+                .unwrap_or_default()
+                .iter()
+                .map(|status| status.code().to_owned())
+                .collect();
+            let validated = codes
+                .iter()
+                .all(|code| validation::severity(code) != Severity::Error);
+            (validated, codes)
+        }
+        Err(err) => (false, vec![Error::from(err).code().code.to_owned()]),
+    }
+}
+
+/// Builds the coverage timeline of the fragments of a fragmented MP4
+pub async fn coverage_timeline(
+    init_segment: &[u8],
+    fragments: &[impl AsRef<[u8]>],
+    mime_type: &str,
+) -> Vec<TimelineEntry> {
+    let track = reference_track(init_segment);
+    let mut ranges = Vec::new();
+    let mut validations = Vec::new();
+    for fragment in fragments {
+        let range = track.as_ref().and_then(|track| {
+            let (start, end) = fragment_range(fragment.as_ref(), track)?;
+            let to_ms = |time: u64| time as f64 * 1000.0 / track.timescale as f64;
+            Some((to_ms(start), to_ms(end)))
+        });
+        ranges.push(range);
+        validations.push(validate_fragment(init_segment, fragment.as_ref(), mime_type).await);
+    }
+
+    lay_out(&ranges, validations)
+}
+
+/// Orders the fragments by time, with the gaps between them and the fragments each overlaps
+fn lay_out(
+    ranges: &[Option<(f64, f64)>],
+    validations: Vec<(bool, Vec<String>)>,
+) -> Vec<TimelineEntry> {
+    let mut order: Vec<usize> = (0..ranges.len()).collect();
+    // Untimed fragments sort last, each group in fragment order
+    order.sort_by(|a, b| match (ranges[*a], ranges[*b]) {
+        (Some((a_start, _)), Some((b_start, _))) => a_start.total_cmp(&b_start).then(a.cmp(b)),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => a.cmp(b),
+    });
+
+    let mut validations: Vec<_> = validations.into_iter().map(Some).collect();
+    let mut entries = Vec::new();
+    let mut covered_until: Option<f64> = None;
+    for index in order {
+        let range = ranges[index];
+        if let (Some((start, end)), Some(covered)) = (range, covered_until) {
+            if start > covered {
+                entries.push(TimelineEntry::Gap {
+                    start_time_ms: covered,
+                    end_time_ms: start,
+                });
+            }
+            covered_until = Some(covered.max(end));
+        } else if let Some((_, end)) = range {
+            covered_until = Some(end);
+        }
+
+        let overlaps = match range {
+            Some((start, end)) => (0..ranges.len())
+                .filter(|other| *other != index)
+                .filter(|other| {
+                    ranges[*other].is_some_and(|(other_start, other_end)| {
+                        start < other_end && other_start < end
+                    })
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+        let (validated, validation_codes) = validations[index].take().unwrap_or_default();
+        entries.push(TimelineEntry::Fragment {
+            start_time_ms: range.map(|(start, _)| start),
+            end_time_ms: range.map(|(_, end)| end),
+            fragment_index: index,
+            validated,
+            validation_codes,
+            overlaps,
+        });
+    }
+
+    entries
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
+
+    fn bmff_box(box_type: &[u8], contents: &[&[u8]]) -> Vec<u8> {
+        let len: usize = contents.iter().map(|content| content.len()).sum();
+        let mut result = ((8 + len) as u32).to_be_bytes().to_vec();
+        result.extend_from_slice(box_type);
+        for content in contents {
+            result.extend_from_slice(content);
+        }
+        result
+    }
+
+    /// Builds a full box's version and flags followed by 32 bit fields
+    fn full_box(box_type: &[u8], version_and_flags: u32, fields: &[u32]) -> Vec<u8> {
+        let mut contents = version_and_flags.to_be_bytes().to_vec();
+        for field in fields {
+            contents.extend_from_slice(&field.to_be_bytes());
+        }
+        bmff_box(box_type, &[&contents])
+    }
+
+    /// Builds a track with a version 0 `tkhd`, `mdhd` and `hdlr`
+    fn trak(id: u32, timescale: u32, handler: &[u8; 4]) -> Vec<u8> {
+        let tkhd = full_box(b"tkhd", 0, &[0, 0, id]);
+        let mdhd = full_box(b"mdhd", 0, &[0, 0, timescale, 0]);
+        let mut hdlr = vec![0; 8];
+        hdlr.extend_from_slice(handler);
+        let hdlr = bmff_box(b"hdlr", &[&hdlr]);
+        let mdia = bmff_box(b"mdia", &[&mdhd, &hdlr]);
+        bmff_box(b"trak", &[&tkhd, &mdia])
+    }
+
+    /// An init segment with an audio track ahead of a 90 kHz video track, whose samples last 3000
+    /// ticks by default
+    fn init_segment() -> Vec<u8> {
+        let trex = full_box(b"trex", 0, &[2, 1, 3000, 0, 0]);
+        let mvex = bmff_box(b"mvex", &[&trex]);
+        let moov = bmff_box(
+            b"moov",
+            &[&trak(1, 48000, b"soun"), &trak(2, 90000, b"vide"), &mvex],
+        );
+        [bmff_box(b"ftyp", &[b"iso6"]), moov].concat()
+    }
+
+    /// A fragment of `samples` samples of the video track starting at `start`, each lasting
+    /// `duration` ticks or the default duration, preceded by a fragment of the audio track
+    fn fragment(start: u64, samples: u32, duration: Option<u32>) -> Vec<u8> {
+        let audio = bmff_box(
+            b"traf",
+            &[
+                &full_box(b"tfhd", 0x08, &[1, 1024]),
+                &full_box(b"tfdt", 0, &[0]),
+                &full_box(b"trun", 0, &[1000]),
+            ],
+        );
+        let trun = match duration {
+            // Durations and sizes of each sample, after the data offset
+            Some(duration) => {
+                let mut fields = vec![samples, 0];
+                for _ in 0..samples {
+                    fields.extend_from_slice(&[duration, 100]);
+                }
+                full_box(b"trun", 0x301, &fields)
+            }
+            // Only the sizes of each sample
+            None => {
+                let mut fields = vec![samples];
+                fields.extend(std::iter::repeat_n(100, samples as usize));
+                full_box(b"trun", 0x200, &fields)
+            }
+        };
+        // Version 1, with a 64 bit decode time
+        let mut tfdt = 0x0100_0000u32.to_be_bytes().to_vec();
+        tfdt.extend_from_slice(&start.to_be_bytes());
+        let video = bmff_box(
+            b"traf",
+            &[
+                &full_box(b"tfhd", 0, &[2]),
+                &bmff_box(b"tfdt", &[&tfdt]),
+                &trun,
+            ],
+        );
+        let moof = bmff_box(b"moof", &[&audio, &video]);
+        [moof, bmff_box(b"mdat", &[&[0; 16]])].concat()
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_fragment_ranges() {
+        let track = reference_track(&init_segment()).unwrap();
+        assert_eq!(
+            track,
+            Track {
+                id: 2,
+                timescale: 90000,
+                default_sample_duration: Some(3000),
+            }
+        );
+
+        assert_eq!(
+            fragment_range(&fragment(0, 30, None), &track),
+            Some((0, 90000))
+        );
+        let mut timed = fragment(90000, 10, Some(4500));
+        assert_eq!(fragment_range(&timed, &track), Some((90000, 135000)));
+
+        // A run missing the durations of its last samples has no range
+        let len = timed.len();
+        timed.truncate(len - 40);
+        assert_eq!(fragment_range(&timed, &track), None);
+        assert_eq!(fragment_range(b"not a fragment", &track), None);
+    }
+
+    #[wasm_bindgen_test]
+    pub async fn test_coverage_timeline() {
+        let fragments = [
+            fragment(90000, 30, None),
+            fragment(0, 30, None),
+            // A second of the timeline is missing before this, and the next overlaps it
+            fragment(270000, 30, None),
+            fragment(315000, 20, Some(4500)),
+            b"corrupt".to_vec(),
+        ];
+        let timeline = coverage_timeline(&init_segment(), &fragments, "video/mp4").await;
+
+        let layout: Vec<_> = timeline
+            .iter()
+            .map(|entry| match entry {
+                TimelineEntry::Fragment {
+                    start_time_ms,
+                    end_time_ms,
+                    fragment_index,
+                    overlaps,
+                    ..
+                } => (
+                    Some(*fragment_index),
+                    *start_time_ms,
+                    *end_time_ms,
+                    overlaps.clone(),
+                ),
+                TimelineEntry::Gap {
+                    start_time_ms,
+                    end_time_ms,
+                } => (None, Some(*start_time_ms), Some(*end_time_ms), Vec::new()),
+            })
+            .collect();
+        assert_eq!(
+            layout,
+            vec![
+                (Some(1), Some(0.0), Some(1000.0), vec![]),
+                (Some(0), Some(1000.0), Some(2000.0), vec![]),
+                (None, Some(2000.0), Some(3000.0), vec![]),
+                (Some(2), Some(3000.0), Some(4000.0), vec![3]),
+                (Some(3), Some(3500.0), Some(4500.0), vec![2]),
+                (Some(4), None, None, vec![]),
+            ]
+        );
+
+        // The init segment carries no manifest, so no fragment validates
+        for entry in &timeline {
+            if let TimelineEntry::Fragment {
+                validated,
+                validation_codes,
+                ..
+            } = entry
+            {
+                assert!(!validated);
+                assert_eq!(validation_codes.len(), 1);
+            }
+        }
+    }
+}
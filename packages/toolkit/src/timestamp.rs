@@ -0,0 +1,422 @@
+// Copyright 2024 Adobe
+// All Rights Reserved.
+//
+// NOTICE: Adobe permits you to use, modify, and distribute this file in
+// accordance with the terms of the Adobe license agreement accompanying
+// it.
+
+//! Checks the time of a signature's timestamp against the clock and the signing certificate.
+//!
+//! The c2pa crate checks the signing certificate's validity at the time the timestamp gives, however
+//! far that lies from the time of validation. A time stamping authority with a wrong clock can
+//! produce tokens dated in the future, which validate differently once that time has passed, or
+//! dated before the signing certificate was issued. Tokens outside the allowed skew are classified
+//! along with how far outside they lie, and the `timestampSkew` read option decides whether that
+//! is only reported or the timestamp stops counting as the time of signing.
+use crate::cose::CoseSign1;
+use crate::ocsp::{
+    self, DerResult, TAG_EXPLICIT_0, TAG_GENERALIZED_TIME, TAG_INTEGER, TAG_OCTET_STRING, TAG_OID,
+    TAG_SEQUENCE, TAG_SET,
+};
+use crate::options::{SkewPolicy, TimestampSkew};
+use log::warn;
+use serde::Serialize;
+use serde_cbor::Value;
+
+/// id-signedData (1.2.840.113549.1.7.2)
+const OID_SIGNED_DATA: &[u8] = &[0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x07, 0x02];
+/// id-ct-TSTInfo (1.2.840.113549.1.9.16.1.4)
+const OID_TST_INFO: &[u8] = &[
+    0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x09, 0x10, 0x01, 0x04,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SkewClassification {
+    WithinSkew,
+    /// Dated further ahead of the time of validation than the allowed skew
+    FutureDated,
+    /// Dated further before the signing certificate's `notBefore` than the allowed skew
+    StaleRelativeToSignature,
+}
+
+/// How a signature's timestamp compares with the clock, merged into a manifest's `signature_info`
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimestampCheck {
+    pub timestamp_skew: SkewInfo,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkewInfo {
+    pub classification: SkewClassification,
+    /// The time the timestamp gives, as an RFC 3339 timestamp
+    pub timestamp_time: String,
+    /// How far the timestamp lies from the time it was checked against, in milliseconds: ahead of
+    /// the time of validation when future dated, or behind the certificate's `notBefore` when stale
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delta_ms: Option<f64>,
+    pub allowed_skew_ms: f64,
+    /// Whether the timestamp no longer counts as the time of signing, under the `untrusted` policy
+    pub downgraded: bool,
+    /// Whether the signing certificate is valid at the time of validation, which is what its
+    /// validity is checked against once the timestamp is downgraded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub certificate_valid_at_validation: Option<bool>,
+}
+
+/// Checks the timestamp of `signature`, if it has one that can be read, against `now_ms`
+pub fn check(
+    signature: &CoseSign1,
+    options: &TimestampSkew,
+    now_ms: f64,
+) -> Option<TimestampCheck> {
+    let token = first_token(signature)?;
+    let (timestamp_time, time_ms) = match gen_time(token) {
+        Ok(gen_time) => gen_time,
+        Err(err) => {
+            warn!("Could not read the signature's timestamp: {}", err);
+            return None;
+        }
+    };
+    let validity = signature
+        .certificate_chain()
+        .first()
+        .and_then(|cert| ocsp::certificate_validity(cert).ok())
+        .and_then(|(not_before, not_after)| {
+            Some((to_millis(&not_before)?, to_millis(&not_after)?))
+        });
+
+    let (classification, delta_ms) = classify(
+        time_ms,
+        validity.map(|(not_before, _)| not_before),
+        now_ms,
+        options.allowed_skew_ms,
+    );
+    let downgraded =
+        classification != SkewClassification::WithinSkew && options.policy == SkewPolicy::Untrusted;
+    let certificate_valid_at_validation = validity
+        .filter(|_| downgraded)
+        .map(|(not_before, not_after)| not_before <= now_ms && now_ms <= not_after);
+
+    Some(TimestampCheck {
+        timestamp_skew: SkewInfo {
+            classification,
+            timestamp_time,
+            delta_ms,
+            allowed_skew_ms: options.allowed_skew_ms,
+            downgraded,
+            certificate_valid_at_validation,
+        },
+    })
+}
+
+/// Classifies a timestamp at `time_ms`, returning how far it lies outside the allowed skew
+pub fn classify(
+    time_ms: f64,
+    not_before_ms: Option<f64>,
+    now_ms: f64,
+    allowed_skew_ms: f64,
+) -> (SkewClassification, Option<f64>) {
+    if time_ms - now_ms > allowed_skew_ms {
+        return (SkewClassification::FutureDated, Some(time_ms - now_ms));
+    }
+    match not_before_ms {
+        Some(not_before_ms) if not_before_ms - time_ms > allowed_skew_ms => (
+            SkewClassification::StaleRelativeToSignature,
+            Some(time_ms - not_before_ms),
+        ),
+        _ => (SkewClassification::WithinSkew, None),
+    }
+}
+
+/// The first token of the signature's `sigTst` header
+fn first_token(signature: &CoseSign1) -> Option<&[u8]> {
+    let tokens = match signature.header(&Value::Text("sigTst".to_owned()))? {
+        Value::Map(header) => header.get(&Value::Text("tstTokens".to_owned()))?,
+        _ => return None,
+    };
+    match tokens {
+        Value::Array(tokens) => match tokens.first()? {
+            Value::Map(token) => match token.get(&Value::Text("val".to_owned()))? {
+                Value::Bytes(token) => Some(token),
+                _ => None,
+            },
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Reads the `genTime` of a timestamp token (RFC 3161) as an RFC 3339 timestamp and in
+/// milliseconds since the Unix epoch
+///
+/// The token can also be wrapped in the `TimeStampResp` it came in, as the c2pa crate stores it.
+pub fn gen_time(token: &[u8]) -> DerResult<(String, f64)> {
+    let (outer, _) = ocsp::expect(token, TAG_SEQUENCE)?;
+    let content_info = if outer.first() == Some(&TAG_SEQUENCE) {
+        let (_status, rest) = ocsp::expect(outer, TAG_SEQUENCE)?;
+        ocsp::expect(rest, TAG_SEQUENCE)?.0
+    } else {
+        outer
+    };
+
+    // ContentInfo and SignedData
+    let (content_type, rest) = ocsp::expect(content_info, TAG_OID)?;
+    if content_type != OID_SIGNED_DATA {
+        return Err("timestamp token is not signed data");
+    }
+    let (signed_data, _) = ocsp::expect(rest, TAG_EXPLICIT_0)?;
+    let (signed_data, _) = ocsp::expect(signed_data, TAG_SEQUENCE)?;
+    let (_version, rest) = ocsp::expect(signed_data, TAG_INTEGER)?;
+    let (_digest_algorithms, rest) = ocsp::expect(rest, TAG_SET)?;
+    let (encapsulated, _) = ocsp::expect(rest, TAG_SEQUENCE)?;
+    let (content_type, rest) = ocsp::expect(encapsulated, TAG_OID)?;
+    if content_type != OID_TST_INFO {
+        return Err("timestamp token does not hold a TSTInfo");
+    }
+    let (content, _) = ocsp::expect(rest, TAG_EXPLICIT_0)?;
+    let (tst_info, _) = ocsp::expect(content, TAG_OCTET_STRING)?;
+
+    // TSTInfo
+    let (tst_info, _) = ocsp::expect(tst_info, TAG_SEQUENCE)?;
+    let (_version, rest) = ocsp::expect(tst_info, TAG_INTEGER)?;
+    let (_policy, rest) = ocsp::expect(rest, TAG_OID)?;
+    let (_message_imprint, rest) = ocsp::expect(rest, TAG_SEQUENCE)?;
+    let (_serial_number, rest) = ocsp::expect(rest, TAG_INTEGER)?;
+    let (tag, contents, _) = ocsp::read(rest)?;
+    if tag != TAG_GENERALIZED_TIME {
+        return Err("invalid time");
+    }
+    let (time, _) = ocsp::read_time(rest)?;
+
+    // Fractions of a second follow the seconds, e.g. 20240101120000.25Z
+    let millis = match contents.get(14..) {
+        Some([b'.', fraction @ ..]) => {
+            let digits: Vec<u8> = fraction
+                .iter()
+                .take_while(|b| b.is_ascii_digit())
+                .take(3)
+                .map(|b| b - b'0')
+                .collect();
+            digits
+                .iter()
+                .fold(0.0, |millis, digit| millis * 10.0 + *digit as f64)
+                * 10f64.powi(3 - digits.len() as i32)
+        }
+        _ => 0.0,
+    };
+    let time_ms = to_millis(&time).ok_or("invalid time")? + millis;
+
+    Ok((time, time_ms))
+}
+
+/// Converts an RFC 3339 timestamp, as `ocsp::read_time` returns them, to milliseconds since the
+/// Unix epoch
+pub fn to_millis(time: &str) -> Option<f64> {
+    let field = |start: usize, end: usize| time.get(start..end)?.parse::<i64>().ok();
+    let (year, month, day) = (field(0, 4)?, field(5, 7)?, field(8, 10)?);
+    let (hour, minute, second) = (field(11, 13)?, field(14, 16)?, field(17, 19)?);
+
+    // Days since the epoch of the proleptic Gregorian date, counting years from March
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+
+    Some(((days * 86_400 + hour * 3_600 + minute * 60 + second) * 1_000) as f64)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
+
+    const SIGNING_CERT: &[u8] =
+        include_bytes!("../../../tools/testing/fixtures/timestamp/signing-cert.der");
+    const WITHIN_SKEW: &[u8] =
+        include_bytes!("../../../tools/testing/fixtures/timestamp/within-skew.der");
+    const FUTURE_DATED: &[u8] =
+        include_bytes!("../../../tools/testing/fixtures/timestamp/future-dated.der");
+    const BEFORE_NOT_BEFORE: &[u8] =
+        include_bytes!("../../../tools/testing/fixtures/timestamp/before-not-before.der");
+
+    const ALLOWED_SKEW_MS: f64 = 5.0 * 60.0 * 1000.0;
+
+    /// Classifies `token` as if it were validated when the signature was made
+    fn classify_token(token: &[u8]) -> (SkewClassification, Option<f64>) {
+        let (_, time_ms) = gen_time(token).unwrap();
+        let (not_before, _) = ocsp::certificate_validity(SIGNING_CERT).unwrap();
+        let now_ms = to_millis("2022-04-20T22:44:41Z").unwrap();
+        classify(time_ms, to_millis(&not_before), now_ms, ALLOWED_SKEW_MS)
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_gen_time() {
+        let (time, time_ms) = gen_time(WITHIN_SKEW).unwrap();
+        assert_eq!(time, "2022-04-20T22:44:41Z");
+        assert_eq!(time_ms, 1_650_494_681_000.0);
+        assert_eq!(gen_time(FUTURE_DATED).unwrap().0, "2022-04-21T01:44:41Z");
+        assert!(gen_time(SIGNING_CERT).is_err());
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_to_millis() {
+        assert_eq!(to_millis("1970-01-01T00:00:00Z"), Some(0.0));
+        assert_eq!(to_millis("2000-03-01T00:00:01Z"), Some(951_868_801_000.0));
+        assert_eq!(to_millis("1969-12-31T23:59:59Z"), Some(-1_000.0));
+        assert_eq!(to_millis("not a time"), None);
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_classify_tokens() {
+        assert_eq!(
+            classify_token(WITHIN_SKEW),
+            (SkewClassification::WithinSkew, None)
+        );
+        // Three hours ahead of the time of validation
+        assert_eq!(
+            classify_token(FUTURE_DATED),
+            (SkewClassification::FutureDated, Some(10_800_000.0))
+        );
+        // 15 minutes and 42 seconds before the certificate's notBefore of 20:00:23
+        assert_eq!(
+            classify_token(BEFORE_NOT_BEFORE),
+            (
+                SkewClassification::StaleRelativeToSignature,
+                Some(-942_000.0)
+            )
+        );
+
+        // Offsets within the allowed skew are tolerated either way
+        let now_ms = 1_000_000.0;
+        assert_eq!(
+            classify(
+                now_ms + ALLOWED_SKEW_MS,
+                Some(now_ms),
+                now_ms,
+                ALLOWED_SKEW_MS
+            ),
+            (SkewClassification::WithinSkew, None)
+        );
+        assert_eq!(
+            classify(
+                now_ms - ALLOWED_SKEW_MS,
+                Some(now_ms),
+                now_ms,
+                ALLOWED_SKEW_MS
+            ),
+            (SkewClassification::WithinSkew, None)
+        );
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test]
+    pub async fn test_read_skewed_timestamp() {
+        use crate::options::ReadOptions;
+        use crate::output_schema::SchemaVersion;
+        use crate::{array_items, get_path};
+
+        // Version 1 of the output leaves out `timestampSkew`
+        let read_options = || ReadOptions {
+            schema_version: Some(SchemaVersion::V2),
+            ..ReadOptions::default()
+        };
+
+        let codes = |js_value: &wasm_bindgen::JsValue| -> Vec<String> {
+            array_items(&get_path(js_value, &["validation_status"]))
+                .iter()
+                .map(|status| get_path(status, &["code"]).as_string().unwrap())
+                .collect()
+        };
+        let asset = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+        let (js_value, _) = crate::read_manifest_store(asset, "image/jpeg", &read_options())
+            .await
+            .unwrap();
+        let active = get_path(&js_value, &["active_manifest"])
+            .as_string()
+            .unwrap();
+        let skew = get_path(
+            &js_value,
+            &["manifests", &active, "signature_info", "timestampSkew"],
+        );
+        assert_eq!(get_path(&skew, &["classification"]), "withinSkew");
+        assert_eq!(get_path(&skew, &["timestampTime"]), "2022-04-20T22:44:41Z");
+        assert!(get_path(&skew, &["deltaMs"]).is_undefined());
+
+        // The active manifest's timestamp, moved to before its certificate's notBefore
+        let mut asset = asset.to_vec();
+        assert_eq!(&asset[880081..880096], b"20220420224441Z");
+        asset[880081..880096].copy_from_slice(b"20220420194441Z");
+
+        let (js_value, _) = crate::read_manifest_store(&asset, "image/jpeg", &read_options())
+            .await
+            .unwrap();
+        let skew = get_path(
+            &js_value,
+            &["manifests", &active, "signature_info", "timestampSkew"],
+        );
+        assert_eq!(
+            get_path(&skew, &["classification"]),
+            "staleRelativeToSignature"
+        );
+        assert_eq!(get_path(&skew, &["deltaMs"]), -942_000.0);
+        assert_eq!(get_path(&skew, &["downgraded"]), false);
+        assert!(array_items(&get_path(&js_value, &["warnings"]))
+            .iter()
+            .any(|warning| warning.as_string().unwrap().contains("942000 ms before")));
+        assert!(!codes(&js_value).contains(&"timeStamp.untrusted".to_owned()));
+
+        let options = ReadOptions {
+            timestamp_skew: TimestampSkew {
+                policy: SkewPolicy::Untrusted,
+                ..TimestampSkew::default()
+            },
+            ..read_options()
+        };
+        let (js_value, _) = crate::read_manifest_store(&asset, "image/jpeg", &options)
+            .await
+            .unwrap();
+        let skew = get_path(
+            &js_value,
+            &["manifests", &active, "signature_info", "timestampSkew"],
+        );
+        assert_eq!(get_path(&skew, &["downgraded"]), true);
+        // The certificate expired in October 2022
+        assert_eq!(get_path(&skew, &["certificateValidAtValidation"]), false);
+        let statuses = codes(&js_value);
+        assert!(
+            statuses.contains(&"timeStamp.untrusted".to_owned()),
+            "{:?}",
+            statuses
+        );
+        assert!(
+            statuses.contains(&"signingCredential.expired".to_owned()),
+            "{:?}",
+            statuses
+        );
+
+        // A wider skew tolerates the same timestamp
+        let options = ReadOptions {
+            timestamp_skew: TimestampSkew {
+                allowed_skew_ms: 60.0 * 60.0 * 1000.0,
+                policy: SkewPolicy::Untrusted,
+            },
+            ..read_options()
+        };
+        let (js_value, _) = crate::read_manifest_store(&asset, "image/jpeg", &options)
+            .await
+            .unwrap();
+        let skew = get_path(
+            &js_value,
+            &["manifests", &active, "signature_info", "timestampSkew"],
+        );
+        assert_eq!(get_path(&skew, &["classification"]), "withinSkew");
+        assert!(!codes(&js_value).contains(&"timeStamp.untrusted".to_owned()));
+    }
+}
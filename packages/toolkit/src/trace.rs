@@ -0,0 +1,187 @@
+// Copyright 2024 Adobe
+// All Rights Reserved.
+//
+// NOTICE: Adobe permits you to use, modify, and distribute this file in
+// accordance with the terms of the Adobe license agreement accompanying
+// it.
+
+//! Records the steps of validating a manifest store, so that the walks of two toolkit versions or
+//! two tools over the same asset can be compared step by step.
+//!
+//! The steps the toolkit performs itself, such as locating boxes and checking hashed URIs, are
+//! timed as they run. The c2pa crate verifies signatures, trust, timestamps and hard bindings
+//! without exposing its steps, so those are reported from the validation statuses it returns and
+//! have no duration. Steps only carry hashes of what they read, never the bytes themselves, so no
+//! key material or signature ends up in a trace.
+use crate::manifest_store::to_hex;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+/// The most steps a trace records, after which further steps are only counted
+pub const MAX_TRACE_STEPS: usize = 1000;
+
+/// What a step of the validation walk did
+///
+/// These names are stable, so that traces from different toolkit versions can be diffed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StepKind {
+    StoreLocated,
+    ManifestLocated,
+    ClaimDecoded,
+    AssertionHashChecked,
+    IngredientReferenceChecked,
+    SignatureDecoded,
+    /// A certificate of the signing chain was read and names the next one as its issuer
+    CertificateChainLinkChecked,
+    RevocationChecked,
+    SignatureVerified,
+    CredentialTrustChecked,
+    TimestampChecked,
+    HardBindingChecked,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Outcome {
+    Passed,
+    Failed,
+    /// The step found only informational problems, or couldn't reach a conclusion
+    Inconclusive,
+    /// There was nothing to check, such as an unstamped signature
+    NotPresent,
+}
+
+/// A step of the validation walk
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TraceStep {
+    pub step: StepKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub manifest: Option<String>,
+    /// What within the manifest the step looked at, such as an assertion label
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    /// The SHA-256 of the bytes the step read, as hex
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input_hash: Option<String>,
+    pub outcome: Outcome,
+    /// The validation statuses the outcome was taken from
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub codes: Vec<String>,
+    /// How long the step took, or `None` if it was performed by the c2pa crate
+    pub duration_ms: Option<f64>,
+}
+
+impl TraceStep {
+    pub fn new(step: StepKind, manifest: Option<&str>, outcome: Outcome) -> Self {
+        Self {
+            step,
+            manifest: manifest.map(str::to_owned),
+            target: None,
+            input_hash: None,
+            outcome,
+            codes: Vec::new(),
+            duration_ms: None,
+        }
+    }
+
+    pub fn target(mut self, target: impl Into<String>) -> Self {
+        self.target = Some(target.into());
+        self
+    }
+
+    pub fn input(mut self, input: &[u8]) -> Self {
+        self.input_hash = Some(to_hex(&Sha256::digest(input)));
+        self
+    }
+}
+
+/// The trace returned with the `trace` read option
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Trace {
+    pub steps: Vec<TraceStep>,
+    /// How many steps were left out once the trace reached `MAX_TRACE_STEPS`
+    pub dropped_steps: usize,
+}
+
+/// Collects the steps of a validation walk, up to a limit
+#[derive(Debug)]
+pub struct Tracer {
+    trace: Trace,
+    max_steps: usize,
+}
+
+impl Tracer {
+    pub fn new(max_steps: usize) -> Self {
+        Self {
+            trace: Trace::default(),
+            max_steps,
+        }
+    }
+
+    pub fn push(&mut self, step: TraceStep) {
+        if self.trace.steps.len() < self.max_steps {
+            self.trace.steps.push(step);
+        } else {
+            self.trace.dropped_steps += 1;
+        }
+    }
+
+    /// Runs a step, recording how long it took along with what it returns
+    pub fn timed<T>(&mut self, run: impl FnOnce() -> (T, TraceStep)) -> T {
+        let start = now();
+        let (result, mut step) = run();
+        step.duration_ms = Some(now() - start);
+        self.push(step);
+        result
+    }
+
+    pub fn finish(self) -> Trace {
+        self.trace
+    }
+}
+
+/// Milliseconds from an arbitrary origin
+#[cfg(target_arch = "wasm32")]
+fn now() -> f64 {
+    use wasm_bindgen::JsCast;
+
+    // `performance` is a global in workers as well as windows
+    js_sys::Reflect::get(&js_sys::global(), &"performance".into())
+        .ok()
+        .and_then(|performance| performance.dyn_into::<web_sys::Performance>().ok())
+        .map_or_else(js_sys::Date::now, |performance| performance.now())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn now() -> f64 {
+    thread_local!(static ORIGIN: std::time::Instant = std::time::Instant::now());
+    ORIGIN.with(|origin| origin.elapsed().as_secs_f64() * 1000.0)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    pub fn test_tracer_limit() {
+        let mut tracer = Tracer::new(2);
+        for _ in 0..5 {
+            tracer.timed(|| {
+                (
+                    (),
+                    TraceStep::new(StepKind::StoreLocated, None, Outcome::Passed),
+                )
+            });
+        }
+        let trace = tracer.finish();
+        assert_eq!(trace.steps.len(), 2);
+        assert_eq!(trace.dropped_steps, 3);
+        assert!(trace.steps[0].duration_ms.is_some());
+    }
+}
@@ -0,0 +1,510 @@
+// Copyright 2024 Adobe
+// All Rights Reserved.
+//
+// NOTICE: Adobe permits you to use, modify, and distribute this file in
+// accordance with the terms of the Adobe license agreement accompanying
+// it.
+
+//! Text in manifests that isn't valid UTF-8, such as Latin-1 written by a faulty encoder.
+//!
+//! The c2pa crate can't decode a claim or CBOR assertion with such text, which fails the whole
+//! read. Before it reads a store, each invalid sequence in the text strings of claims and CBOR
+//! assertions is replaced with U+FFFD, and every field that was repaired is reported. Byte strings
+//! are copied as they are.
+//!
+//! The toolkit's own checks read the store as it was signed, decoding its CBOR the same lossy way
+//! with `from_cbor`, so the hashes of repaired assertions are still checked against the bytes that
+//! were hashed. Claim signatures are only verified by the c2pa crate, which sees the repaired
+//! claim, so the signature of a repaired claim is reported as not verified.
+use crate::error::Result;
+use crate::jumbf::{self, SuperBox};
+use crate::limits;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::convert::TryFrom;
+use std::ops::Range;
+
+/// The status of a manifest whose claim had invalid UTF-8, so its signature wasn't verified
+pub const CLAIM_NOT_VERIFIED: &str = "utf8.claimNotVerified";
+
+/// How deeply CBOR items may be nested before the repair gives up and leaves the data as it is
+const MAX_CBOR_DEPTH: usize = 64;
+
+const CBOR_BOX: &[u8; 4] = b"cbor";
+
+/// A text field that had invalid UTF-8
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct InvalidUtf8 {
+    /// The label of the manifest
+    #[serde(skip)]
+    pub manifest: String,
+    /// Whether the field is in the claim rather than in an assertion
+    #[serde(skip)]
+    pub in_claim: bool,
+    /// The JUMBF URI of the claim or assertion
+    pub url: String,
+    /// A JSON pointer to the field within it
+    pub path: String,
+}
+
+/// Decodes CBOR, replacing invalid UTF-8 in its text strings if it can't be decoded as it is
+pub fn from_cbor<T: DeserializeOwned>(data: &[u8]) -> serde_cbor::Result<T> {
+    serde_cbor::from_slice(data).or_else(|err| match repair_cbor(data) {
+        Some((repaired, _)) => serde_cbor::from_slice(&repaired),
+        None => Err(err),
+    })
+}
+
+/// Replaces invalid UTF-8 in the text strings of CBOR data, returning the repaired data along with
+/// a JSON pointer to each string that was repaired
+///
+/// Returns `None` if no string needed repairing, or if the data isn't CBOR this can walk, such as
+/// strings of indefinite length, so that the decoder reports it.
+pub fn repair_cbor(data: &[u8]) -> Option<(Vec<u8>, Vec<String>)> {
+    let mut repair = CborRepair {
+        data,
+        pos: 0,
+        out: Vec::with_capacity(data.len()),
+        paths: Vec::new(),
+    };
+    repair.item("", 0)?;
+    if repair.pos != data.len() || repair.paths.is_empty() {
+        return None;
+    }
+    Some((repair.out, repair.paths))
+}
+
+struct CborRepair<'a> {
+    data: &'a [u8],
+    pos: usize,
+    out: Vec<u8>,
+    paths: Vec<String>,
+}
+
+impl CborRepair<'_> {
+    /// Copies the item at the current position, returning its text if it is a text string
+    fn item(&mut self, path: &str, depth: usize) -> Option<Option<String>> {
+        if depth > MAX_CBOR_DEPTH {
+            return None;
+        }
+
+        let start = self.pos;
+        let (major, value) = self.header()?;
+        match major {
+            // Integers, and simple values and floats
+            0 | 1 | 7 => self.out.extend_from_slice(&self.data[start..self.pos]),
+            // Byte strings
+            2 => {
+                self.take(value)?;
+                self.out.extend_from_slice(&self.data[start..self.pos]);
+            }
+            3 => {
+                let bytes = &self.data[self.take(value)?];
+                let text = String::from_utf8_lossy(bytes).into_owned();
+                if std::str::from_utf8(bytes).is_err() {
+                    self.paths.push(path.to_owned());
+                }
+                write_header(&mut self.out, 3, text.len() as u64);
+                self.out.extend_from_slice(text.as_bytes());
+                return Some(Some(text));
+            }
+            4 => {
+                self.out.extend_from_slice(&self.data[start..self.pos]);
+                for index in 0..value {
+                    self.item(&format!("{}/{}", path, index), depth + 1)?;
+                }
+            }
+            5 => {
+                self.out.extend_from_slice(&self.data[start..self.pos]);
+                for _ in 0..value {
+                    let key = self.item(path, depth + 1)?;
+                    let key = key.as_deref().unwrap_or("?");
+                    let key = key.replace('~', "~0").replace('/', "~1");
+                    self.item(&format!("{}/{}", path, key), depth + 1)?;
+                }
+            }
+            // Tags
+            6 => {
+                self.out.extend_from_slice(&self.data[start..self.pos]);
+                self.item(path, depth + 1)?;
+            }
+            _ => return None,
+        }
+        Some(None)
+    }
+
+    /// Reads the header of an item, returning its major type and its argument
+    ///
+    /// Indefinite lengths and reserved values aren't supported.
+    fn header(&mut self) -> Option<(u8, u64)> {
+        let initial = *self.data.get(self.pos)?;
+        self.pos += 1;
+        let (major, info) = (initial >> 5, initial & 0x1f);
+        let len = match info {
+            0..=23 => return Some((major, info as u64)),
+            24 => 1,
+            25 => 2,
+            26 => 4,
+            27 => 8,
+            _ => return None,
+        };
+        let bytes = &self.data[self.take(len)?];
+        let value = bytes.iter().fold(0u64, |value, b| (value << 8) | *b as u64);
+        Some((major, value))
+    }
+
+    /// Skips `len` bytes, returning their range
+    fn take(&mut self, len: u64) -> Option<Range<usize>> {
+        let end = self.pos.checked_add(usize::try_from(len).ok()?)?;
+        if end > self.data.len() {
+            return None;
+        }
+        let range = self.pos..end;
+        self.pos = end;
+        Some(range)
+    }
+}
+
+fn write_header(out: &mut Vec<u8>, major: u8, value: u64) {
+    let major = major << 5;
+    match value {
+        0..=23 => out.push(major | value as u8),
+        24..=0xff => out.extend_from_slice(&[major | 24, value as u8]),
+        0x100..=0xffff => {
+            out.push(major | 25);
+            out.extend_from_slice(&(value as u16).to_be_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            out.push(major | 26);
+            out.extend_from_slice(&(value as u32).to_be_bytes());
+        }
+        _ => {
+            out.push(major | 27);
+            out.extend_from_slice(&value.to_be_bytes());
+        }
+    }
+}
+
+/// Repairs the claims and CBOR assertions of a manifest store that have invalid UTF-8, returning
+/// the repaired store along with each field that was repaired
+///
+/// Returns `None` if nothing needed repairing. Superboxes holding anything other than what the
+/// C2PA specification puts in them are left as they are.
+pub fn repair_store(store_bytes: &[u8]) -> Result<Option<(Vec<u8>, Vec<InvalidUtf8>)>> {
+    let store = limits::parse(store_bytes)?;
+    if !store.boxes.is_empty() {
+        return Ok(None);
+    }
+
+    let mut invalid = Vec::new();
+    let manifests: Vec<Vec<u8>> = store
+        .children
+        .iter()
+        .map(|child| {
+            match &child.label {
+                Some(label) if child.is_manifest() => repair_manifest(child, label, &mut invalid),
+                _ => None,
+            }
+            .unwrap_or_else(|| child.bytes.to_vec())
+        })
+        .collect();
+    if invalid.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some((rebuild(&store, &manifests), invalid)))
+}
+
+fn repair_manifest(
+    manifest: &SuperBox,
+    label: &str,
+    invalid: &mut Vec<InvalidUtf8>,
+) -> Option<Vec<u8>> {
+    if !manifest.boxes.is_empty() {
+        return None;
+    }
+
+    let claim = manifest.claim();
+    let mut changed = false;
+    let children: Vec<Vec<u8>> = manifest
+        .children
+        .iter()
+        .map(|child| {
+            let url = format!(
+                "self#jumbf=/c2pa/{}/{}",
+                label,
+                child.label.as_deref().unwrap_or_default()
+            );
+            let repaired = if claim.is_some_and(|claim| std::ptr::eq(claim, child)) {
+                repair_content(child, label, url, true, invalid)
+            } else if child.label.as_deref() == Some(jumbf::ASSERTIONS_LABEL) {
+                repair_assertions(child, label, &url, invalid)
+            } else {
+                None
+            };
+            changed |= repaired.is_some();
+            repaired.unwrap_or_else(|| child.bytes.to_vec())
+        })
+        .collect();
+
+    changed.then(|| rebuild(manifest, &children))
+}
+
+fn repair_assertions(
+    assertions: &SuperBox,
+    label: &str,
+    url: &str,
+    invalid: &mut Vec<InvalidUtf8>,
+) -> Option<Vec<u8>> {
+    if !assertions.boxes.is_empty() {
+        return None;
+    }
+
+    let mut changed = false;
+    let children: Vec<Vec<u8>> = assertions
+        .children
+        .iter()
+        .map(|assertion| {
+            let assertion_url =
+                format!("{}/{}", url, assertion.label.as_deref().unwrap_or_default());
+            let repaired = repair_content(assertion, label, assertion_url, false, invalid);
+            changed |= repaired.is_some();
+            repaired.unwrap_or_else(|| assertion.bytes.to_vec())
+        })
+        .collect();
+
+    changed.then(|| rebuild(assertions, &children))
+}
+
+/// Repairs a superbox holding a single CBOR content box, as claims and CBOR assertions do
+fn repair_content(
+    super_box: &SuperBox,
+    label: &str,
+    url: String,
+    in_claim: bool,
+    invalid: &mut Vec<InvalidUtf8>,
+) -> Option<Vec<u8>> {
+    let content = match (super_box.boxes.as_slice(), super_box.children.is_empty()) {
+        ([content], true) if &content.box_type == CBOR_BOX => content,
+        _ => return None,
+    };
+    let (repaired, paths) = repair_cbor(content.payload)?;
+
+    invalid.extend(paths.into_iter().map(|path| InvalidUtf8 {
+        manifest: label.to_owned(),
+        in_claim,
+        url: url.clone(),
+        path,
+    }));
+    let content = jumbf::build_box(CBOR_BOX, &[&repaired]);
+    Some(jumbf::build_box(
+        jumbf::JUMB,
+        &[super_box.description, &content],
+    ))
+}
+
+/// Builds a superbox from the description box of `super_box` and the given children
+fn rebuild(super_box: &SuperBox, children: &[Vec<u8>]) -> Vec<u8> {
+    let mut contents = vec![super_box.description];
+    contents.extend(children.iter().map(Vec::as_slice));
+    jumbf::build_box(jumbf::JUMB, &contents)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::jumbf::tests::{content_box, manifest, map, store, super_box, text, uuid_of};
+    use serde_cbor::Value;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
+
+    /// CBOR text whose characters are encoded as Latin-1 rather than UTF-8
+    pub fn latin1(s: &str) -> Vec<u8> {
+        let mut out = Vec::new();
+        write_header(&mut out, 3, s.chars().count() as u64);
+        out.extend(s.chars().map(|c| c as u8));
+        out
+    }
+
+    /// CBOR for a map, from the CBOR of its keys and values
+    pub fn raw_map(entries: &[(&str, Vec<u8>)]) -> Vec<u8> {
+        let mut out = Vec::new();
+        write_header(&mut out, 5, entries.len() as u64);
+        for (key, value) in entries {
+            out.extend_from_slice(&serde_cbor::to_vec(&text(key)).unwrap());
+            out.extend_from_slice(value);
+        }
+        out
+    }
+
+    fn cbor(value: &Value) -> Vec<u8> {
+        serde_cbor::to_vec(value).unwrap()
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_repair_cbor() {
+        let long = format!("{}café", "x".repeat(300));
+        let mut list = vec![0x82];
+        list.extend_from_slice(&cbor(&Value::Integer(1)));
+        list.extend_from_slice(&latin1("café"));
+        let bytes = raw_map(&[
+            ("title", latin1("café")),
+            ("a/b", list),
+            ("hash", cbor(&Value::Bytes(vec![0xE9; 40]))),
+            ("long", latin1(&long)),
+            ("name", cbor(&text("café"))),
+        ]);
+        assert!(serde_cbor::from_slice::<Value>(&bytes).is_err());
+
+        let (repaired, paths) = repair_cbor(&bytes).unwrap();
+        assert_eq!(paths, vec!["/title", "/a~1b/1", "/long"]);
+        let expected = raw_map(&[
+            ("title", cbor(&text("caf\u{FFFD}"))),
+            (
+                "a/b",
+                cbor(&Value::Array(vec![Value::Integer(1), text("caf\u{FFFD}")])),
+            ),
+            ("hash", cbor(&Value::Bytes(vec![0xE9; 40]))),
+            ("long", cbor(&text(&long.replace('é', "\u{FFFD}")))),
+            ("name", cbor(&text("café"))),
+        ]);
+        assert_eq!(repaired, expected);
+        assert_eq!(
+            from_cbor::<Value>(&bytes).unwrap(),
+            serde_cbor::from_slice::<Value>(&expected).unwrap()
+        );
+
+        // Valid data, and data this can't walk, are left to the decoder
+        assert_eq!(repair_cbor(&expected), None);
+        assert_eq!(repair_cbor(&[0x7F, 0x61, 0xE9, 0xFF]), None);
+        assert_eq!(repair_cbor(&[0x65, b'c']), None);
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_repair_store() {
+        let valid = manifest(jumbf::MANIFEST_UUID, "urn:uuid:1", &[]);
+        let claim = raw_map(&[("dc:title", latin1("café"))]);
+        let assertion = raw_map(&[
+            ("title", latin1("café")),
+            ("relationship", cbor(&text("parentOf"))),
+        ]);
+        let repairable = super_box(
+            jumbf::MANIFEST_UUID,
+            "urn:uuid:2",
+            &[
+                super_box(
+                    uuid_of(*b"c2as"),
+                    jumbf::ASSERTIONS_LABEL,
+                    &[super_box(
+                        uuid_of(*b"cbor"),
+                        "c2pa.ingredient",
+                        &[content_box(*b"cbor", &assertion)],
+                    )],
+                ),
+                super_box(
+                    uuid_of(*b"c2cl"),
+                    jumbf::CLAIM_LABEL,
+                    &[content_box(*b"cbor", &claim)],
+                ),
+            ],
+        );
+        let store_bytes = store(&[valid.clone(), repairable]);
+
+        let (repaired, invalid) = repair_store(&store_bytes).unwrap().unwrap();
+        assert_eq!(
+            invalid,
+            vec![
+                InvalidUtf8 {
+                    manifest: "urn:uuid:2".to_owned(),
+                    in_claim: false,
+                    url: "self#jumbf=/c2pa/urn:uuid:2/c2pa.assertions/c2pa.ingredient".to_owned(),
+                    path: "/title".to_owned(),
+                },
+                InvalidUtf8 {
+                    manifest: "urn:uuid:2".to_owned(),
+                    in_claim: true,
+                    url: "self#jumbf=/c2pa/urn:uuid:2/c2pa.claim".to_owned(),
+                    path: "/dc:title".to_owned(),
+                },
+            ]
+        );
+
+        let store = SuperBox::parse(&repaired).unwrap();
+        assert_eq!(store.children[0].bytes, valid.as_slice());
+        let manifest = &store.children[1];
+        let claim: Value =
+            serde_cbor::from_slice(manifest.claim().unwrap().payload().unwrap()).unwrap();
+        assert_eq!(claim, map(vec![("dc:title", text("caf\u{FFFD}"))]));
+        let ingredient = manifest
+            .find(jumbf::ASSERTIONS_LABEL)
+            .and_then(|assertions| assertions.find("c2pa.ingredient"))
+            .and_then(SuperBox::payload)
+            .unwrap();
+        let ingredient: Value = serde_cbor::from_slice(ingredient).unwrap();
+        assert_eq!(
+            ingredient,
+            map(vec![
+                ("title", text("caf\u{FFFD}")),
+                ("relationship", text("parentOf")),
+            ])
+        );
+
+        assert_eq!(repair_store(&repaired).unwrap(), None);
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test]
+    pub async fn test_read_invalid_utf8() {
+        use crate::get_path;
+        use crate::options::ReadOptions;
+
+        // The titles of the active manifest's ingredient and claim, with a byte that isn't UTF-8
+        let mut asset =
+            include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg").to_vec();
+        assert_eq!(&asset[875181..875188], b"CAI.jpg");
+        asset[875182] = 0xE9;
+        assert_eq!(&asset[877072..877082], b"CAICAI.jpg");
+        asset[877073] = 0xE9;
+
+        let (js_value, _) =
+            crate::read_manifest_store(&asset, "image/jpeg", &ReadOptions::default())
+                .await
+                .unwrap();
+        let active = get_path(&js_value, &["active_manifest"])
+            .as_string()
+            .unwrap();
+        let manifest = get_path(&js_value, &["manifests", &active]);
+        assert_eq!(get_path(&manifest, &["hadInvalidUtf8"]), true);
+        assert_eq!(
+            get_path(&manifest, &["signatureVerification"]),
+            "invalid-utf8"
+        );
+        assert_eq!(get_path(&manifest, &["title"]), "C\u{FFFD}ICAI.jpg");
+        let ingredients = js_sys::Array::from(&get_path(&manifest, &["ingredients"]));
+        assert!(ingredients
+            .iter()
+            .any(|ingredient| get_path(&ingredient, &["title"]) == "C\u{FFFD}I.jpg"));
+        let invalid: Vec<String> = js_sys::Array::from(&get_path(&manifest, &["invalidUtf8"]))
+            .iter()
+            .map(|field| get_path(&field, &["path"]).as_string().unwrap())
+            .collect();
+        assert_eq!(invalid, ["/dc:title", "/dc:title"]);
+
+        let codes: Vec<String> = js_sys::Array::from(&get_path(&js_value, &["validation_status"]))
+            .iter()
+            .map(|status| get_path(&status, &["code"]).as_string().unwrap())
+            .collect();
+        assert!(
+            codes.contains(&CLAIM_NOT_VERIFIED.to_owned()),
+            "{:?}",
+            codes
+        );
+        // Changing the ingredient after it was signed breaks its hash as well as its text
+        assert!(
+            codes.contains(&c2pa::validation_status::ASSERTION_HASHEDURI_MISMATCH.to_owned()),
+            "{:?}",
+            codes
+        );
+    }
+}
@@ -4,6 +4,13 @@
 // NOTICE: Adobe permits you to use, modify, and distribute this file in
 // accordance with the terms of the Adobe license agreement accompanying
 // it.
+use crate::error::{Error, Result};
+use js_sys::{Object, Reflect};
+use log::warn;
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+use serde::Serialize;
+use std::cell::Cell;
+use wasm_bindgen::{JsCast, JsValue};
 
 /// @TODO: Re-enable once we can get this working in a Worker environment
 pub fn log_time(_name: &str) {
@@ -14,3 +21,326 @@ pub fn log_time(_name: &str) {
     //     perf.mark(name).expect("Mark was not called successfully");
     // }
 }
+
+thread_local! {
+    static STRICT_OPTIONS: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Sets whether options objects with keys an API doesn't accept are rejected rather than logged
+pub fn set_strict_options(strict: bool) {
+    STRICT_OPTIONS.with(|strict_options| strict_options.set(strict));
+}
+
+/// The keys an options object accepts
+#[derive(Debug, Clone, Default)]
+pub struct OptionKeys {
+    keys: Vec<&'static str>,
+    /// Keys whose value is an options object, or an array of them, with the keys it accepts
+    nested: Vec<(&'static str, OptionKeys)>,
+}
+
+impl OptionKeys {
+    /// The fields of a struct that options are deserialized into, as they are named in JavaScript
+    pub fn of<'de, T: Deserialize<'de>>() -> Self {
+        let mut fields: &'static [&'static str] = &[];
+        let _ = T::deserialize(FieldNames(&mut fields));
+        Self {
+            keys: fields.to_vec(),
+            nested: Vec::new(),
+        }
+    }
+
+    /// Adds keys that are read from the options object directly, such as callbacks
+    pub fn with(mut self, keys: &[&'static str]) -> Self {
+        self.keys.extend_from_slice(keys);
+        self
+    }
+
+    /// Adds the keys of another options struct read from the same object
+    pub fn and(mut self, other: OptionKeys) -> Self {
+        self.keys.extend(other.keys);
+        self.nested.extend(other.nested);
+        self
+    }
+
+    /// Checks the options object or array of them under `key` against `keys`
+    pub fn nest(mut self, key: &'static str, keys: OptionKeys) -> Self {
+        self.nested.push((key, keys));
+        self
+    }
+}
+
+/// A deserializer that only records the field names of the struct deserialized from it
+struct FieldNames<'a>(&'a mut &'static [&'static str]);
+
+impl<'de, 'a> Deserializer<'de> for FieldNames<'a> {
+    type Error = de::value::Error;
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        _visitor: V,
+    ) -> std::result::Result<V::Value, Self::Error> {
+        *self.0 = fields;
+        Err(de::Error::custom("only the field names are read"))
+    }
+
+    fn deserialize_any<V: Visitor<'de>>(
+        self,
+        _visitor: V,
+    ) -> std::result::Result<V::Value, Self::Error> {
+        Err(de::Error::custom("not a struct"))
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf option
+        unit unit_struct newtype_struct seq tuple tuple_struct map enum identifier ignored_any
+    }
+}
+
+/// A key an API doesn't accept, with the accepted key it is closest to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UnknownOption {
+    /// The path of the key, such as `serialization.byteAs`
+    pub key: String,
+    pub suggestion: Option<String>,
+}
+
+impl UnknownOption {
+    /// The paths of unknown keys, for an error message
+    pub fn keys(unknown: &[UnknownOption]) -> String {
+        let keys: Vec<&str> = unknown.iter().map(|option| option.key.as_str()).collect();
+        keys.join(", ")
+    }
+}
+
+/// Checks the keys of an options object against those `api` accepts
+///
+/// Unknown keys are rejected with `strictOptions(true)`, and logged otherwise. Values that aren't
+/// objects are left for deserialization to reject.
+pub fn check_options(options: &JsValue, api: &'static str, accepted: &OptionKeys) -> Result<()> {
+    let mut unknown = Vec::new();
+    unknown_options(options, "", accepted, &mut unknown);
+    if unknown.is_empty() {
+        return Ok(());
+    }
+
+    if STRICT_OPTIONS.with(Cell::get) {
+        return Err(Error::UnknownOptions { api, unknown });
+    }
+    for option in unknown {
+        match option.suggestion {
+            Some(suggestion) => warn!(
+                "{} ignored the unknown option {}, did you mean {}?",
+                api, option.key, suggestion
+            ),
+            None => warn!("{} ignored the unknown option {}", api, option.key),
+        }
+    }
+
+    Ok(())
+}
+
+fn unknown_options(
+    options: &JsValue,
+    path: &str,
+    accepted: &OptionKeys,
+    unknown: &mut Vec<UnknownOption>,
+) {
+    if js_sys::Array::is_array(options) {
+        for item in js_sys::Array::from(options).iter() {
+            unknown_options(&item, path, accepted, unknown);
+        }
+        return;
+    }
+    let object = match options.dyn_ref::<Object>() {
+        Some(object) if !options.is_function() => object,
+        _ => return,
+    };
+
+    let keys = Reflect::own_keys(object).unwrap_or_else(|_err| js_sys::Array::new());
+    for key in keys.iter() {
+        let key = match key.as_string() {
+            Some(key) => key,
+            // Symbols can't be options
+            None => continue,
+        };
+        let key_path = format!("{}{}", path, key);
+        if let Some((_, nested)) = accepted.nested.iter().find(|(nested, _)| *nested == key) {
+            let value = Reflect::get(object, &key.as_str().into()).unwrap_or_default();
+            unknown_options(&value, &format!("{}.", key_path), nested, unknown);
+        } else if !accepted.keys.contains(&key.as_str()) {
+            unknown.push(UnknownOption {
+                suggestion: nearest_key(&key, accepted)
+                    .map(|nearest| format!("{}{}", path, nearest)),
+                key: key_path,
+            });
+        }
+    }
+}
+
+/// The accepted key closest to `key`, if it is close enough to be a typo of it
+fn nearest_key(key: &str, accepted: &OptionKeys) -> Option<&'static str> {
+    let max_distance = (key.chars().count() / 3).max(1);
+    accepted
+        .keys
+        .iter()
+        .chain(accepted.nested.iter().map(|(key, _)| key))
+        .map(|candidate| (edit_distance(key, candidate), *candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+/// The Levenshtein distance between two strings, ignoring case
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.to_lowercase().chars().collect();
+    let b: Vec<char> = b.to_lowercase().chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.iter().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    pub fn test_option_keys() {
+        let keys = OptionKeys::of::<crate::limits::ParserLimits>();
+        assert_eq!(
+            keys.keys,
+            [
+                "maxBoxes",
+                "maxBoxDepth",
+                "maxDeclaredSizeRatio",
+                "maxStoreBytes"
+            ]
+        );
+
+        let keys = OptionKeys::of::<crate::options::ReadOptions>().with(&["resourceResolver"]);
+        assert!(keys.keys.contains(&"includeRawClaim"));
+        assert!(keys.keys.contains(&"ingredientAssets"));
+        assert!(keys.keys.contains(&"resourceResolver"));
+        assert_eq!(
+            nearest_key("includeRawClaims", &keys),
+            Some("includeRawClaim")
+        );
+        assert_eq!(
+            nearest_key("resourceresolver", &keys),
+            Some("resourceResolver")
+        );
+        assert_eq!(nearest_key("strict", &keys), None);
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_edit_distance() {
+        assert_eq!(edit_distance("remoteManfestUrl", "remoteManifestUrl"), 1);
+        assert_eq!(edit_distance("maxBoxes", "maxBoxes"), 0);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test]
+    pub async fn test_unknown_options() {
+        use crate::get_path;
+        use wasm_bindgen_futures::JsFuture;
+
+        let test_asset = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+        let buffer = || -> JsValue { js_sys::Uint8Array::from(&test_asset[..]).buffer().into() };
+        let parse = |json: &str| js_sys::JSON::parse(json).unwrap();
+        let read_options = r#"{"includeRawClaims": true, "serialization": {"byteAs": "base64"}}"#;
+        let limits = r#"{"maxBox": 10}"#;
+        let thumbnail_options = r#"{"treshold": 4}"#;
+
+        // Unknown keys are only logged by default
+        let read = crate::get_manifest_store_from_array_buffer(
+            buffer(),
+            "image/jpeg".to_owned(),
+            parse(read_options),
+        );
+        assert!(JsFuture::from(read).await.is_ok());
+        assert!(crate::set_parser_limits(parse(limits)).is_ok());
+        let verify = crate::verify_thumbnail_consistency(
+            buffer(),
+            "image/jpeg".to_owned(),
+            parse(thumbnail_options),
+        );
+        assert!(JsFuture::from(verify).await.is_ok());
+
+        crate::strict_options(true);
+        let read = crate::get_manifest_store_from_array_buffer(
+            buffer(),
+            "image/jpeg".to_owned(),
+            parse(read_options),
+        );
+        let read_err = JsFuture::from(read).await.unwrap_err();
+        let limits_err: JsValue = crate::set_parser_limits(parse(limits)).unwrap_err().into();
+        let verify = crate::verify_thumbnail_consistency(
+            buffer(),
+            "image/jpeg".to_owned(),
+            parse(thumbnail_options),
+        );
+        let verify_err = JsFuture::from(verify).await.unwrap_err();
+        // Known keys are still accepted
+        let read = crate::get_manifest_store_from_array_buffer(
+            buffer(),
+            "image/jpeg".to_owned(),
+            parse(r#"{"includeRawClaim": true, "serialization": {"bytesAs": "base64"}}"#),
+        );
+        assert!(JsFuture::from(read).await.is_ok());
+        crate::strict_options(false);
+        crate::set_parser_limits(JsValue::NULL).unwrap();
+
+        let unknown = |err: &JsValue| -> Vec<(String, Option<String>)> {
+            assert_eq!(get_path(err, &["name"]), "Toolkit(UnknownOptions)");
+            assert_eq!(get_path(err, &["code"]), "unknownOptions");
+            crate::array_items(&get_path(err, &["unknown"]))
+                .iter()
+                .map(|option| {
+                    (
+                        get_path(option, &["key"]).as_string().unwrap(),
+                        get_path(option, &["suggestion"]).as_string(),
+                    )
+                })
+                .collect()
+        };
+        assert_eq!(
+            get_path(&read_err, &["api"]),
+            "getManifestStoreFromArrayBuffer"
+        );
+        assert_eq!(
+            unknown(&read_err),
+            [
+                (
+                    "includeRawClaims".to_owned(),
+                    Some("includeRawClaim".to_owned())
+                ),
+                (
+                    "serialization.byteAs".to_owned(),
+                    Some("serialization.bytesAs".to_owned())
+                ),
+            ]
+        );
+        assert_eq!(
+            unknown(&limits_err),
+            [("maxBox".to_owned(), Some("maxBoxes".to_owned()))]
+        );
+        assert_eq!(
+            unknown(&verify_err),
+            [("treshold".to_owned(), Some("threshold".to_owned()))]
+        );
+    }
+}
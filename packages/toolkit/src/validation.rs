@@ -0,0 +1,188 @@
+// Copyright 2024 Adobe
+// All Rights Reserved.
+//
+// NOTICE: Adobe permits you to use, modify, and distribute this file in
+// accordance with the terms of the Adobe license agreement accompanying
+// it.
+
+//! Classifies validation status codes by severity, following the C2PA specification's lists of
+//! success, informational, and failure codes.
+use c2pa::validation_status::*;
+use serde::Serialize;
+
+/// Informational codes, which don't make a manifest invalid but are worth pointing out
+const WARNING_CODES: &[&str] = &[
+    SIGNING_CREDENTIAL_UNTRUSTED,
+    TIMESTAMP_UNTRUSTED,
+    TIMESTAMP_OUTSIDE_VALIDITY,
+    "algorithm.deprecated",
+    "signingCredential.ocsp.skipped",
+    "signingCredential.ocsp.inaccessible",
+    "signingCredential.ocsp.unknown",
+    crate::generator_trust::GENERATOR_CERT_MISMATCH,
+];
+
+/// Codes reporting that the asset doesn't match a hard binding
+const HARD_BINDING_MISMATCH_CODES: &[&str] = &[
+    ASSERTION_DATAHASH_MISMATCH,
+    ASSERTION_BMFFHASH_MISMATCH,
+    "assertion.boxesHash.mismatch",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+/// Returns the severity of a validation status code
+///
+/// Codes that aren't listed are treated as errors, so that a new failure code is never shown as
+/// harmless.
+pub fn severity(code: &str) -> Severity {
+    if is_success(code) {
+        Severity::Info
+    } else if WARNING_CODES.contains(&code) {
+        Severity::Warning
+    } else {
+        Severity::Error
+    }
+}
+
+/// Returns `true` for the codes reporting that the asset doesn't match a hard binding
+pub fn is_hard_binding_mismatch(code: &str) -> bool {
+    HARD_BINDING_MISMATCH_CODES.contains(&code)
+}
+
+/// Rolls the severities of a set of validation statuses up into an overall severity
+///
+/// With `treat_warnings_as_errors`, warnings count as errors. This only affects the rollup.
+pub fn worst_severity(
+    severities: impl IntoIterator<Item = Severity>,
+    treat_warnings_as_errors: bool,
+) -> Severity {
+    severities
+        .into_iter()
+        .map(|severity| match severity {
+            Severity::Warning if treat_warnings_as_errors => Severity::Error,
+            severity => severity,
+        })
+        .max()
+        .unwrap_or(Severity::Info)
+}
+
+/// The label of the manifest a validation status URL refers to, if it is an absolute JUMBF URI
+pub fn manifest_label(url: &str) -> Option<&str> {
+    url.strip_prefix("self#jumbf=")?
+        .strip_prefix("/c2pa/")?
+        .split('/')
+        .next()
+        .filter(|label| !label.is_empty())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    pub fn test_severity_of_known_codes() {
+        let info = [
+            CLAIM_SIGNATURE_VALIDATED,
+            SIGNING_CREDENTIAL_TRUSTED,
+            TIMESTAMP_TRUSTED,
+            ASSERTION_HASHEDURI_MATCH,
+            ASSERTION_DATAHASH_MATCH,
+            ASSERTION_BMFFHASH_MATCH,
+            ASSERTION_ACCESSIBLE,
+        ];
+        let warning = [
+            SIGNING_CREDENTIAL_UNTRUSTED,
+            TIMESTAMP_UNTRUSTED,
+            TIMESTAMP_OUTSIDE_VALIDITY,
+            "algorithm.deprecated",
+            "signingCredential.ocsp.skipped",
+            "signingCredential.ocsp.inaccessible",
+            "signingCredential.ocsp.unknown",
+            "generatorCertMismatch",
+        ];
+        let error = [
+            CLAIM_MISSING,
+            CLAIM_MULTIPLE,
+            HARD_BINDINGS_MISSING,
+            CLAIM_REQUIRED_MISSING,
+            CLAIM_CBOR_INVALID,
+            INGREDIENT_HASHEDURI_MISMATCH,
+            CLAIM_SIGNATURE_MISSING,
+            CLAIM_SIGNATURE_MISMATCH,
+            MANIFEST_INACCESSIBLE,
+            MANIFEST_MULTIPLE_PARENTS,
+            MANIFEST_UPDATE_INVALID,
+            MANIFEST_UPDATE_WRONG_PARENTS,
+            SIGNING_CREDENTIAL_INVALID,
+            SIGNING_CREDENTIAL_REVOKED,
+            SIGNING_CREDENTIAL_EXPIRED,
+            TIMESTAMP_MISMATCH,
+            ASSERTION_HASHEDURI_MISMATCH,
+            ASSERTION_MISSING,
+            ASSERTION_UNDECLARED,
+            ASSERTION_INACCESSIBLE,
+            ASSERTION_NOT_REDACTED,
+            ASSERTION_SELF_REDACTED,
+            ASSERTION_REQUIRED_MISSING,
+            ASSERTION_JSON_INVALID,
+            ASSERTION_CBOR_INVALID,
+            ACTION_ASSERTION_INGREDIENT_MISMATCH,
+            ACTION_ASSERTION_REDACTED,
+            ASSERTION_DATAHASH_MISMATCH,
+            ASSERTION_BMFFHASH_MISMATCH,
+            ASSERTION_CLOUD_DATA_HARD_BINDING,
+            ASSERTION_CLOUD_DATA_ACTIONS,
+            ALGORITHM_UNSUPPORTED,
+            GENERAL_ERROR,
+            "some.future.failure",
+        ];
+
+        assert!(info.iter().all(|code| severity(code) == Severity::Info));
+        assert!(warning
+            .iter()
+            .all(|code| severity(code) == Severity::Warning));
+        assert!(error.iter().all(|code| severity(code) == Severity::Error));
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_hard_binding_mismatch() {
+        assert!(is_hard_binding_mismatch(ASSERTION_DATAHASH_MISMATCH));
+        assert!(is_hard_binding_mismatch(ASSERTION_BMFFHASH_MISMATCH));
+        assert!(is_hard_binding_mismatch("assertion.boxesHash.mismatch"));
+        assert!(!is_hard_binding_mismatch(ASSERTION_DATAHASH_MATCH));
+        assert!(!is_hard_binding_mismatch(ASSERTION_HASHEDURI_MISMATCH));
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_worst_severity() {
+        use Severity::*;
+
+        assert_eq!(worst_severity([], false), Info);
+        assert_eq!(worst_severity([Info, Warning, Info], false), Warning);
+        assert_eq!(worst_severity([Info, Warning, Info], true), Error);
+        assert_eq!(worst_severity([Warning, Error], false), Error);
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_manifest_label() {
+        assert_eq!(
+            manifest_label("self#jumbf=/c2pa/urn:uuid:1/c2pa.assertions/c2pa.hash.data"),
+            Some("urn:uuid:1")
+        );
+        assert_eq!(
+            manifest_label("self#jumbf=c2pa.assertions/c2pa.hash.data"),
+            None
+        );
+        assert_eq!(manifest_label("c2pa.ingredient"), None);
+    }
+}
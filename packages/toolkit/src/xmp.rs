@@ -0,0 +1,443 @@
+// Copyright 2024 Adobe
+// All Rights Reserved.
+//
+// NOTICE: Adobe permits you to use, modify, and distribute this file in
+// accordance with the terms of the Adobe license agreement accompanying
+// it.
+
+//! Reads the attribution that assets without a manifest often carry in their XMP.
+//!
+//! Only a few IPTC and Dublin Core properties are read, by namespace rather than by prefix, from
+//! the XMP packet of JPEG, PNG and TIFF files.
+use crate::error::{Error, Result};
+use crate::mime::sniff_mime_type;
+use crate::presence::{JPEG_XMP_SIGNATURE, PNG_XMP_KEYWORD};
+use crate::segments::{JpegSegments, PngChunks, JPEG_APP1};
+use fast_xml::events::{BytesStart, Event};
+use fast_xml::Reader;
+use serde::Serialize;
+use std::convert::TryFrom;
+
+const RDF_NS: &[u8] = b"http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+pub const DC_NS: &[u8] = b"http://purl.org/dc/elements/1.1/";
+pub const PHOTOSHOP_NS: &[u8] = b"http://ns.adobe.com/photoshop/1.0/";
+pub const XMP_RIGHTS_NS: &[u8] = b"http://ns.adobe.com/xap/1.0/rights/";
+pub const PLUS_NS: &[u8] = b"http://ns.useplus.org/ldf/xmp/1.0/";
+pub const IPTC_EXT_NS: &[u8] = b"http://iptc.org/std/Iptc4xmpExt/2008-02-29/";
+
+/// The TIFF tag holding the XMP packet
+const TIFF_XMP_TAG: u64 = 700;
+
+/// Attribution read from an asset's XMP
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LegacyMetadata {
+    /// `dc:creator`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub creators: Vec<String>,
+    /// `photoshop:Credit`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub credit_line: Option<String>,
+    /// `xmpRights:WebStatement`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub web_statement: Option<String>,
+    /// `plus:Licensor`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub licensors: Vec<Licensor>,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Licensor {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+/// The properties we read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Property {
+    Creator,
+    Credit,
+    WebStatement,
+    Licensor,
+    LicensorName,
+    LicensorUrl,
+}
+
+impl Property {
+    fn from_name(namespace: Option<&[u8]>, local_name: &[u8]) -> Option<Self> {
+        Some(match (namespace?, local_name) {
+            (DC_NS, b"creator") => Self::Creator,
+            (PHOTOSHOP_NS, b"Credit") => Self::Credit,
+            (XMP_RIGHTS_NS, b"WebStatement") => Self::WebStatement,
+            // Licensor is part of the PLUS schema, though some writers put it in IPTC's own
+            (PLUS_NS, b"Licensor") | (IPTC_EXT_NS, b"Licensor") => Self::Licensor,
+            (PLUS_NS, b"LicensorName") | (IPTC_EXT_NS, b"LicensorName") => Self::LicensorName,
+            (PLUS_NS, b"LicensorURL") | (IPTC_EXT_NS, b"LicensorURL") => Self::LicensorUrl,
+            _ => return None,
+        })
+    }
+}
+
+impl LegacyMetadata {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Records the value of the innermost property in `properties`
+    fn set(&mut self, properties: &[Property], value: String) {
+        let value = value.trim();
+        if value.is_empty() {
+            return;
+        }
+        let value = value.to_owned();
+
+        match properties.last() {
+            Some(Property::Creator) => self.creators.push(value),
+            Some(Property::Credit) => {
+                self.credit_line.get_or_insert(value);
+            }
+            Some(Property::WebStatement) => {
+                self.web_statement.get_or_insert(value);
+            }
+            Some(Property::LicensorName) if properties.contains(&Property::Licensor) => {
+                self.last_licensor().name.get_or_insert(value);
+            }
+            Some(Property::LicensorUrl) if properties.contains(&Property::Licensor) => {
+                self.last_licensor().url.get_or_insert(value);
+            }
+            _ => {}
+        }
+    }
+
+    fn last_licensor(&mut self) -> &mut Licensor {
+        if self.licensors.is_empty() {
+            self.licensors.push(Licensor::default());
+        }
+        let last = self.licensors.len() - 1;
+        &mut self.licensors[last]
+    }
+}
+
+/// Returns the XMP packet of a JPEG, PNG or TIFF file, if it has one
+pub fn extract_xmp(data: &[u8]) -> Option<&[u8]> {
+    match sniff_mime_type(data)? {
+        "image/jpeg" => jpeg_xmp(data),
+        "image/png" => png_xmp(data),
+        "image/tiff" | "image/x-adobe-dng" => tiff_xmp(data),
+        _ => None,
+    }
+}
+
+/// Reads the attribution from an XMP packet
+pub fn parse_xmp(xmp: &[u8]) -> Result<LegacyMetadata> {
+    let mut reader = Reader::from_bytes(xmp);
+    reader.trim_text(true);
+
+    let mut metadata = LegacyMetadata::default();
+    // The property (if any) of each open element
+    let mut open: Vec<Option<Property>> = Vec::new();
+    let mut buf = Vec::new();
+    let mut ns_buf = Vec::new();
+
+    loop {
+        let (namespace, event) = reader
+            .read_namespaced_event(&mut buf, &mut ns_buf)
+            .map_err(|_err| Error::InvalidXmp("not well-formed XML"))?;
+        let namespace = namespace.map(<[u8]>::to_vec);
+
+        match event {
+            Event::Start(ref element) | Event::Empty(ref element) => {
+                let property = Property::from_name(namespace.as_deref(), element.local_name());
+                let mut properties: Vec<_> = open.iter().flatten().copied().collect();
+                properties.extend(property);
+
+                // Each item of a licensor array is a new licensor
+                if namespace.as_deref() == Some(RDF_NS)
+                    && element.local_name() == b"li"
+                    && properties.last() == Some(&Property::Licensor)
+                {
+                    metadata.licensors.push(Licensor::default());
+                }
+                read_attributes(&reader, &ns_buf, element, &properties, &mut metadata)?;
+
+                if let Event::Start(_) = event {
+                    open.push(property);
+                }
+            }
+            Event::End(_) => {
+                open.pop();
+            }
+            Event::Text(ref text) => {
+                let text = text
+                    .unescaped()
+                    .map_err(|_err| Error::InvalidXmp("invalid text"))?;
+                let properties: Vec<_> = open.iter().flatten().copied().collect();
+                metadata.set(&properties, String::from_utf8_lossy(&text).into_owned());
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    Ok(metadata)
+}
+
+/// Records the properties given in attribute form, such as `photoshop:Credit="..."`
+fn read_attributes(
+    reader: &Reader<&[u8]>,
+    ns_buf: &[u8],
+    element: &BytesStart,
+    properties: &[Property],
+    metadata: &mut LegacyMetadata,
+) -> Result<()> {
+    for attribute in element.attributes().with_checks(false) {
+        let attribute = attribute.map_err(|_err| Error::InvalidXmp("invalid attribute"))?;
+        let (namespace, local_name) = reader.attribute_namespace(attribute.key, ns_buf);
+        let property = match Property::from_name(namespace, local_name) {
+            Some(property) => property,
+            None => continue,
+        };
+        let value = attribute
+            .unescaped_value()
+            .map_err(|_err| Error::InvalidXmp("invalid attribute"))?;
+
+        let mut properties = properties.to_vec();
+        properties.push(property);
+        metadata.set(&properties, String::from_utf8_lossy(&value).into_owned());
+    }
+
+    Ok(())
+}
+
+/// Finds the XMP in the APP1 segments before the start of scan
+fn jpeg_xmp(data: &[u8]) -> Option<&[u8]> {
+    JpegSegments::new(data)
+        .filter(|segment| segment.marker == JPEG_APP1)
+        .find_map(|segment| segment.data(data).strip_prefix(JPEG_XMP_SIGNATURE))
+}
+
+/// Finds the XMP in the `iTXt` chunk with the XMP keyword
+fn png_xmp(data: &[u8]) -> Option<&[u8]> {
+    let text = PngChunks::new(data)
+        .filter(|chunk| &chunk.chunk_type == b"iTXt")
+        .find_map(|chunk| chunk.data(data).strip_prefix(PNG_XMP_KEYWORD))?;
+    // Compression flag and method, then the language tag and translated keyword
+    let (compressed, rest) = (*text.first()?, text.get(2..)?);
+    if compressed != 0 {
+        return None;
+    }
+    let mut fields = rest.splitn(3, |b| *b == 0);
+    fields.next();
+    fields.next();
+    fields.next()
+}
+
+/// Finds the XMP tag in the first IFD
+fn tiff_xmp(data: &[u8]) -> Option<&[u8]> {
+    let big_endian = data.starts_with(b"MM");
+    let read = |offset: u64, len: usize| -> Option<u64> {
+        let offset = usize::try_from(offset).ok()?;
+        let bytes = data.get(offset..offset.checked_add(len)?)?;
+        let mut bytes = bytes.to_vec();
+        if !big_endian {
+            bytes.reverse();
+        }
+        Some(bytes.iter().fold(0, |value, b| (value << 8) | *b as u64))
+    };
+
+    // BigTIFF widens the IFD offset, entry count, and entries
+    let (ifd_offset, count_len, entry_len, value_len) = match read(2, 2)? {
+        43 => (read(8, 8)?, 8, 20, 8),
+        _ => (read(4, 4)?, 2, 12, 4),
+    };
+    let entries = read(ifd_offset, count_len)?;
+
+    for index in 0..entries {
+        let entry = ifd_offset + count_len as u64 + index * entry_len;
+        if read(entry, 2)? != TIFF_XMP_TAG {
+            continue;
+        }
+        // The XMP is stored as bytes, so the count is its length
+        let len = usize::try_from(read(entry + 4, value_len)?).ok()?;
+        let value = entry + 4 + value_len as u64;
+        let offset = if len <= value_len {
+            value
+        } else {
+            read(value, value_len)?
+        };
+        let offset = usize::try_from(offset).ok()?;
+        return data.get(offset..offset.checked_add(len)?);
+    }
+
+    None
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
+
+    pub const XMP: &str = r#"<x:xmpmeta xmlns:x="adobe:ns:meta/">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description rdf:about=""
+    xmlns:dc="http://purl.org/dc/elements/1.1/"
+    xmlns:ps="http://ns.adobe.com/photoshop/1.0/"
+    xmlns:xmpRights="http://ns.adobe.com/xap/1.0/rights/"
+    xmlns:plus="http://ns.useplus.org/ldf/xmp/1.0/"
+    ps:Credit="Example News &amp; Co."
+    xmpRights:WebStatement="https://example.com/license">
+   <dc:creator>
+    <rdf:Seq>
+     <rdf:li>Jane Doe</rdf:li>
+     <rdf:li>John Roe</rdf:li>
+    </rdf:Seq>
+   </dc:creator>
+   <plus:Licensor>
+    <rdf:Seq>
+     <rdf:li rdf:parseType="Resource">
+      <plus:LicensorName>Example Images</plus:LicensorName>
+      <plus:LicensorURL>https://example.com/images</plus:LicensorURL>
+     </rdf:li>
+     <rdf:li plus:LicensorName="Another Agency"/>
+    </rdf:Seq>
+   </plus:Licensor>
+  </rdf:Description>
+ </rdf:RDF>
+</x:xmpmeta>"#;
+
+    pub fn expected() -> LegacyMetadata {
+        LegacyMetadata {
+            creators: vec!["Jane Doe".to_owned(), "John Roe".to_owned()],
+            credit_line: Some("Example News & Co.".to_owned()),
+            web_statement: Some("https://example.com/license".to_owned()),
+            licensors: vec![
+                Licensor {
+                    name: Some("Example Images".to_owned()),
+                    url: Some("https://example.com/images".to_owned()),
+                },
+                Licensor {
+                    name: Some("Another Agency".to_owned()),
+                    url: None,
+                },
+            ],
+        }
+    }
+
+    /// Builds a JPEG with an XMP segment, inserted after the start of image marker of `jpeg`
+    pub fn jpeg_with_xmp(jpeg: &[u8], xmp: &str) -> Vec<u8> {
+        let mut segment = JPEG_XMP_SIGNATURE.to_vec();
+        segment.extend_from_slice(xmp.as_bytes());
+
+        let mut result = vec![0xFF, 0xD8, 0xFF, 0xE1];
+        result.extend_from_slice(&((segment.len() + 2) as u16).to_be_bytes());
+        result.extend_from_slice(&segment);
+        result.extend_from_slice(&jpeg[2..]);
+        result
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_parse_xmp() {
+        assert_eq!(parse_xmp(XMP.as_bytes()).unwrap(), expected());
+
+        // Properties are matched by namespace, not by prefix
+        let other_namespace = XMP.replace(
+            "http://ns.adobe.com/photoshop/1.0/",
+            "https://example.com/ns/",
+        );
+        assert_eq!(
+            parse_xmp(other_namespace.as_bytes()).unwrap().credit_line,
+            None
+        );
+
+        assert!(parse_xmp(b"<x:xmpmeta><rdf:RDF></x:xmpmeta>").is_err());
+        assert!(parse_xmp(b"<x:xmpmeta/>").unwrap().is_empty());
+    }
+
+    #[wasm_bindgen_test]
+    pub fn test_extract_xmp() {
+        let jpeg = jpeg_with_xmp(&[0xFF, 0xD8, 0xFF, 0xDA], XMP);
+        assert_eq!(extract_xmp(&jpeg), Some(XMP.as_bytes()));
+
+        let mut png = vec![0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A];
+        let mut chunk = PNG_XMP_KEYWORD.to_vec();
+        chunk.extend_from_slice(&[0, 0, 0, 0]);
+        chunk.extend_from_slice(XMP.as_bytes());
+        png.extend_from_slice(&(chunk.len() as u32).to_be_bytes());
+        png.extend_from_slice(b"iTXt");
+        png.extend_from_slice(&chunk);
+        png.extend_from_slice(&[0; 4]);
+        assert_eq!(extract_xmp(&png), Some(XMP.as_bytes()));
+
+        // A little endian TIFF whose only IFD entry points to the XMP after it
+        let mut tiff = b"II*\0".to_vec();
+        tiff.extend_from_slice(&8u32.to_le_bytes());
+        tiff.extend_from_slice(&1u16.to_le_bytes());
+        tiff.extend_from_slice(&700u16.to_le_bytes());
+        tiff.extend_from_slice(&7u16.to_le_bytes());
+        tiff.extend_from_slice(&(XMP.len() as u32).to_le_bytes());
+        tiff.extend_from_slice(&26u32.to_le_bytes());
+        tiff.extend_from_slice(&0u32.to_le_bytes());
+        tiff.extend_from_slice(XMP.as_bytes());
+        assert_eq!(extract_xmp(&tiff), Some(XMP.as_bytes()));
+
+        let jpeg = include_bytes!("../../../tools/testing/fixtures/images/firefly-1.jpg");
+        assert_eq!(extract_xmp(jpeg), None);
+    }
+
+    // The summary holds JavaScript values, so it can only be read in a JavaScript environment
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test]
+    pub async fn test_metadata_summary() {
+        use wasm_bindgen::JsValue;
+
+        let summary = |asset: Vec<u8>| async move {
+            let buf = js_sys::Uint8Array::from(asset.as_slice()).buffer();
+            let summary = wasm_bindgen_futures::JsFuture::from(crate::get_metadata_summary(
+                buf.into(),
+                "image/jpeg".to_owned(),
+                JsValue::UNDEFINED,
+            ))
+            .await
+            .unwrap();
+            (
+                crate::get_path(&summary, &["c2pa"]),
+                crate::get_path(&summary, &["legacyMetadata", "creditLine"]),
+            )
+        };
+        let c2pa = include_bytes!("../../../tools/testing/fixtures/images/CAICAI.jpg");
+        let neither = include_bytes!("../../../tools/testing/fixtures/images/I.jpg");
+
+        let (manifest_store, credit_line) = summary(jpeg_with_xmp(neither, XMP)).await;
+        assert!(manifest_store.is_null());
+        assert_eq!(
+            credit_line.as_string().as_deref(),
+            Some("Example News & Co.")
+        );
+
+        let (manifest_store, credit_line) = summary(c2pa.to_vec()).await;
+        assert!(manifest_store.is_object());
+        assert!(credit_line.is_undefined());
+
+        let (manifest_store, credit_line) = summary(jpeg_with_xmp(c2pa, XMP)).await;
+        assert!(manifest_store.is_object());
+        assert_eq!(
+            credit_line.as_string().as_deref(),
+            Some("Example News & Co.")
+        );
+
+        // XMP that can't be parsed is left out
+        let (manifest_store, credit_line) = summary(jpeg_with_xmp(c2pa, "<x:xmpmeta>")).await;
+        assert!(manifest_store.is_object());
+        assert!(credit_line.is_undefined());
+
+        let (manifest_store, credit_line) = summary(neither.to_vec()).await;
+        assert!(manifest_store.is_null());
+        assert!(credit_line.is_undefined());
+    }
+}
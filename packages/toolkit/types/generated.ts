@@ -0,0 +1,340 @@
+// This file is generated from the Rust types by `yarn build:schema`. Do not edit it by hand.
+
+/**
+ * A Container for a set of Manifests and a ValidationStatus list
+ */
+export interface ManifestStore {
+  /**
+   * A label for the active (most recent) manifest in the store
+   */
+  active_manifest?: string | null;
+  /**
+   * A HashMap of Manifests
+   */
+  manifests: Record<string, Manifest>;
+  /**
+   * ValidationStatus generated when loading the ManifestStore from an asset
+   */
+  validation_status?: ValidationStatus[] | null;
+}
+
+/**
+ * Identifies a person responsible for an action.
+ */
+export interface Actor {
+  /**
+   * List of references to W3C Verifiable Credentials.
+   */
+  credentials?: HashedUri[] | null;
+  /**
+   * An identifier for a human actor, used when the "type" is `humanEntry.identified`.
+   */
+  identifier?: string | null;
+}
+
+export interface AssetType {
+  type: string;
+  version?: string | null;
+}
+
+/**
+ * Description of the claim generator, or the software used in generating the claim.
+ *
+ * This structure is also used for actions softwareAgent
+ */
+export interface ClaimGeneratorInfo {
+  /**
+   * hashed URI to the icon (either embedded or remote)
+   */
+  icon?: UriOrResource | null;
+  /**
+   * A human readable string naming the claim_generator
+   */
+  name: string;
+  /**
+   * A human readable string of the product's version
+   */
+  version?: string | null;
+}
+
+/**
+ * A description of the source for assertion data
+ */
+export interface DataSource {
+  /**
+   * A list of [`Actor`]s associated with this source.
+   */
+  actors?: Actor[] | null;
+  /**
+   * A human-readable string giving details about the source of the assertion data.
+   */
+  details?: string | null;
+  /**
+   * A value from among the enumerated list indicating the source of the assertion.
+   */
+  type: string;
+}
+
+export type DateT = string;
+
+/**
+ * Hashed Uri structure as defined by C2PA spec It is annotated to produce the correctly tagged cbor serialization
+ */
+export interface HashedUri {
+  alg?: string | null;
+  hash: number[];
+  url: string;
+}
+
+/**
+ * An `Ingredient` is any external asset that has been used in the creation of an image.
+ */
+export interface Ingredient {
+  /**
+   * The active manifest label (if one exists).
+   *
+   * If this ingredient has a [`ManifestStore`], this will hold the label of the active [`Manifest`].
+   *
+   * [`Manifest`]: crate::Manifest [`ManifestStore`]: crate::ManifestStore
+   */
+  active_manifest?: string | null;
+  /**
+   * A reference to the actual data of the ingredient.
+   */
+  data?: ResourceRef | null;
+  /**
+   * Additional description of the ingredient.
+   */
+  description?: string | null;
+  /**
+   * Document ID from `xmpMM:DocumentID` in XMP metadata.
+   */
+  document_id?: string | null;
+  /**
+   * The format of the source file as a MIME type.
+   */
+  format?: string;
+  /**
+   * An optional hash of the asset to prevent duplicates.
+   */
+  hash?: string | null;
+  /**
+   * URI to an informational page about the ingredient or its data.
+   */
+  informational_URI?: string | null;
+  /**
+   * Instance ID from `xmpMM:InstanceID` in XMP metadata.
+   */
+  instance_id?: string | null;
+  /**
+   * A [`ManifestStore`] from the source asset extracted as a binary C2PA blob.
+   *
+   * [`ManifestStore`]: crate::ManifestStore
+   */
+  manifest_data?: ResourceRef | null;
+  /**
+   * Any additional [`Metadata`] as defined in the C2PA spec.
+   *
+   * [`Manifest`]: crate::Manifest
+   */
+  metadata?: Metadata | null;
+  /**
+   * URI from `dcterms:provenance` in XMP metadata.
+   */
+  provenance?: string | null;
+  /**
+   * Set to `ParentOf` if this is the parent ingredient.
+   *
+   * There can only be one parent ingredient in the ingredients.
+   */
+  relationship?: Relationship;
+  resources?: ResourceStore;
+  /**
+   * A thumbnail image capturing the visual state at the time of import.
+   *
+   * A tuple of thumbnail MIME format (i.e. `image/jpeg`) and binary bits of the image.
+   */
+  thumbnail?: ResourceRef | null;
+  /**
+   * A human-readable title, generally source filename.
+   */
+  title: string;
+  /**
+   * Validation results.
+   */
+  validation_status?: ValidationStatus[] | null;
+}
+
+/**
+ * A Manifest represents all the information in a c2pa manifest
+ */
+export interface Manifest {
+  /**
+   * A list of assertions
+   */
+  assertions?: ManifestAssertion[];
+  /**
+   * A User Agent formatted string identifying the software/hardware/system produced this claim Spaces are not allowed in names, versions can be specified with product/1.0 syntax
+   */
+  claim_generator?: string;
+  claim_generator_hints?: Record<string, unknown> | null;
+  claim_generator_info?: ClaimGeneratorInfo[] | null;
+  /**
+   * A List of verified credentials
+   */
+  credentials?: unknown[] | null;
+  /**
+   * The format of the source file as a MIME type.
+   */
+  format?: string;
+  /**
+   * A List of ingredients
+   */
+  ingredients?: Ingredient[];
+  /**
+   * Instance ID from `xmpMM:InstanceID` in XMP metadata.
+   */
+  instance_id?: string;
+  label?: string | null;
+  /**
+   * A list of redactions - URIs to a redacted assertions
+   */
+  redactions?: string[] | null;
+  /**
+   * container for binary assets (like thumbnails)
+   */
+  resources?: ResourceStore;
+  /**
+   * Signature data (only used for reporting)
+   */
+  signature_info?: SignatureInfo | null;
+  thumbnail?: ResourceRef | null;
+  /**
+   * A human-readable title, generally source filename.
+   */
+  title?: string | null;
+  /**
+   * Optional prefix added to the generated Manifest Label This is typically Internet domain name for the vendor (i.e. `adobe`)
+   */
+  vendor?: string | null;
+}
+
+/**
+ * A labeled container for an Assertion value in a Manifest
+ */
+export interface ManifestAssertion {
+  /**
+   * The data of the assertion as Value
+   */
+  data: ManifestData;
+  /**
+   * There can be more than one assertion for any label
+   */
+  instance?: number | null;
+  /**
+   * The [ManifestAssertionKind] for this assertion (as stored in c2pa content)
+   */
+  kind?: ManifestAssertionKind | null;
+  /**
+   * An assertion label in reverse domain format
+   */
+  label: string;
+}
+
+/**
+ * Assertions in C2PA can be stored in several formats
+ */
+export type ManifestAssertionKind = 'Cbor' | 'Json' | 'Binary' | 'Uri';
+
+export type ManifestData = unknown | number[];
+
+/**
+ * The Metadata structure can be used as part of other assertions or on its own to reference others
+ */
+export interface Metadata {
+  data_source?: DataSource | null;
+  dateTime?: DateT | null;
+  reference?: HashedUri | null;
+  reviewRatings?: ReviewRating[] | null;
+}
+
+export type Relationship = 'parentOf' | 'componentOf' | 'inputTo';
+
+/**
+ * A reference to a resource to be used in JSON serialization.
+ */
+export interface ResourceRef {
+  /**
+   * The algorithm used to hash the resource (if applicable).
+   */
+  alg?: string | null;
+  /**
+   * More detailed data types as defined in the C2PA spec.
+   */
+  data_types?: AssetType[] | null;
+  /**
+   * The mime type of the referenced resource.
+   */
+  format: string;
+  /**
+   * The hash of the resource (if applicable).
+   */
+  hash?: string | null;
+  /**
+   * A URI that identifies the resource as referenced from the manifest.
+   *
+   * This may be a JUMBF URI, a file path, a URL or any other string. Relative JUMBF URIs will be resolved with the manifest label. Relative file paths will be resolved with the base path if provided.
+   */
+  identifier: string;
+}
+
+/**
+ * Resource store to contain binary objects referenced from JSON serializable structures
+ */
+export interface ResourceStore {
+  label?: string | null;
+  resources: Record<string, number[]>;
+}
+
+/**
+ * A rating on an [`Assertion`].
+ *
+ * See <https://c2pa.org/specifications/specifications/1.0/specs/C2PA_Specification.html#_claim_review>.
+ */
+export interface ReviewRating {
+  code?: string | null;
+  explanation: string;
+  value: number;
+}
+
+/**
+ * Holds information about a signature
+ */
+export interface SignatureInfo {
+  /**
+   * The serial number of the certificate
+   */
+  cert_serial_number?: string | null;
+  /**
+   * human readable issuing authority for this signature
+   */
+  issuer?: string | null;
+  /**
+   * the time the signature was created
+   */
+  time?: string | null;
+}
+
+export type UriOrResource = ResourceRef | HashedUri;
+
+/**
+ * A `ValidationStatus` struct describes the validation status of a specific part of a manifest.
+ *
+ * See <https://c2pa.org/specifications/specifications/1.0/specs/C2PA_Specification.html#_existing_manifests>.
+ */
+export interface ValidationStatus {
+  code: string;
+  explanation?: string | null;
+  url?: string | null;
+}
+
@@ -5,7 +5,16 @@
 // accordance with the terms of the Adobe license agreement accompanying
 // it.
 
-export interface ResourceStore {
+import type * as Generated from './generated';
+
+export * as Generated from './generated';
+
+/**
+ * The interfaces below refine the types generated from the Rust structures in `./generated`. Since
+ * they extend the generated types, any drift between the two is a compile error.
+ */
+
+export interface ResourceStore extends Generated.ResourceStore {
   resources: Record<string, number[]>;
 }
 
@@ -13,30 +22,141 @@ export interface ResourceParent {
   resources: ResourceStore;
 }
 
-export interface ResourceReference {
-  content_type: string;
-  identifier: string;
+export interface ResourceReference extends Generated.ResourceRef {
+  /**
+   * Set on thumbnails stored outside of the manifest store, which are fetched with the
+   * `resourceResolver` read option
+   */
+  resolved?: boolean;
+  /**
+   * `true` if the resource was fetched by the `resourceResolver`
+   */
+  resolvedExternally?: boolean;
+  /**
+   * The size in bytes of a resource fetched by the `resourceResolver`
+   */
+  size?: number;
 }
 
-export interface ManifestStore {
+export type Severity = 'error' | 'warning' | 'info';
+
+/**
+ * The manifest store read from an asset
+ *
+ * Reading the same asset always produces the same output, in the same order: manifests appear in
+ * the order they are stored in the asset, assertions are sorted by label and instance, validation
+ * statuses by code and URL, and resources, claim generator hints and ingredient metadata by key.
+ */
+export interface ManifestStore extends Generated.ManifestStore {
   active_manifest: string;
   manifests: Record<string, Manifest>;
   validation_status?: ValidationStatus[];
+  /**
+   * The most severe of the store's validation statuses, `info` if there are none
+   */
+  worstSeverity: Severity;
+  /**
+   * Problems that didn't prevent reading the manifest store
+   */
+  warnings?: string[];
+  /**
+   * The form of a separately supplied manifest: a bare manifest store (`store`), a store nested in
+   * another JUMBF superbox (`wrapped`), or a store preceded by other data (`prefixed`)
+   */
+  sidecarFormat?: 'store' | 'wrapped' | 'prefixed';
+  /**
+   * Present when the store was found somewhere other than where the C2PA specification puts it.
+   * `legacy-itxt` is base64 text in a PNG `iTXt` chunk, found with `legacyEmbeddings`.
+   * `trailing-app11` is JPEG `APP11` segments after the end of the image data, found with
+   * `lenient` parsing.
+   */
+  embeddingFormat?: 'legacy-itxt' | 'trailing-app11';
+  /**
+   * Set along with `embeddingFormat: 'trailing-app11'`, which a `jpeg.trailingApp11` parse warning
+   * also reports
+   */
+  nonStandardPlacement?: true;
+  /**
+   * Set when the store was read with the `activeManifestOverride` option. The output then describes
+   * the asset as if that manifest were the active one, and isn't the asset's own validation result.
+   */
+  overrideApplied?: true;
+  /**
+   * The structural defects tolerated with the `lenient` parsing option, empty if there were none
+   */
+  parseWarnings?: ParseWarning[];
+  /**
+   * The version of this shape, present from version 2 on (see `schemaVersion` in `ReadOptions`)
+   */
+  schemaVersion?: 2;
+  /**
+   * The asset's dimensions and duration, present when reading with `includeAssetInfo`
+   */
+  assetInfo?: AssetInfo;
+  /**
+   * The assets supplied with `ingredientAssets` that no ingredient has the identifiers of
+   */
+  unmatchedIngredientAssets?: { instanceId: string | null; documentId: string | null }[];
+  /**
+   * The steps of the validation walk, present when reading with `trace`
+   */
+  trace?: ValidationTrace;
+}
+
+/**
+ * What the asset's container headers tell about it, with `null` for anything they don't give
+ */
+export interface AssetInfo {
+  width: number | null;
+  height: number | null;
+  /**
+   * The EXIF orientation, from 1 to 8, which `width` and `height` don't take into account
+   */
+  orientation: number | null;
+  /**
+   * The duration of video and audio, in seconds
+   */
+  duration: number | null;
+  trackCount: number | null;
 }
 
-export interface ValidationStatus {
+/**
+ * A structural defect of the manifest store that lenient parsing repaired
+ */
+export interface ParseWarning {
+  code:
+    | 'jumbf.description.notRequestable'
+    | 'jumbf.description.trailingData'
+    | 'jumbf.unexpectedBox'
+    | 'jpeg.trailingApp11';
+  /**
+   * The JUMBF URI of the box with the defect
+   */
+  location: string;
+}
+
+export interface ValidationStatus extends Generated.ValidationStatus {
   code: string;
   url?: string;
   explanation?: string;
+  /**
+   * `error` for failures, `warning` for informational codes such as an untrusted signer, and
+   * `info` for successes
+   */
+  severity: Severity;
 }
 
-export interface HashedUri {
+export interface HashedUri extends Generated.HashedUri {
   url: string;
   alg?: string;
   hash: number[];
 }
 
-export interface Ingredient extends ResourceParent {
+export interface Ingredient
+  extends ResourceParent,
+    Generated.Ingredient,
+    Partial<HashedUriCheck> {
+  resources: ResourceStore;
   title: string;
   format: string;
   document_id?: string;
@@ -48,20 +168,137 @@ export interface Ingredient extends ResourceParent {
   active_manifest?: string;
   validation_status?: ValidationStatus[];
   metadata?: Metadata;
+  /**
+   * Set to the `maxDepth` read option if this ingredient's manifest was left out for being too
+   * deep in the provenance chain
+   */
+  truncatedAtDepth?: number;
+  /**
+   * Whether the hash of this ingredient's manifest still matches the one it records
+   */
+  manifestReference?: HashedUriCheck;
+  /**
+   * Why this ingredient's manifest couldn't be read, in which case it is left out of the manifest
+   * store instead of failing the whole read
+   */
+  error?: ManifestError;
+  /**
+   * Whether the asset supplied for this ingredient with the `ingredientAssets` read option is the
+   * asset the ingredient records
+   */
+  suppliedAssetMatches?: boolean;
+  /**
+   * How the supplied asset was checked, present along with `suppliedAssetMatches`
+   */
+  details?: SuppliedAssetDetails;
+}
+
+/**
+ * How an asset supplied with the `ingredientAssets` read option was checked against its ingredient
+ *
+ * Ingredients don't record a hash of the asset they were made from. When the ingredient references
+ * a manifest (`manifest`), the supplied asset must carry that manifest, unchanged, as its active
+ * manifest and must still match its hard binding. Otherwise (`thumbnail`) the asset is compared
+ * with the ingredient thumbnail, which is a perceptual match rather than a cryptographic one.
+ */
+export interface SuppliedAssetDetails {
+  method: 'manifest' | 'thumbnail';
+  /**
+   * The ingredient's reference to its manifest, checked against the supplied asset's active
+   * manifest
+   */
+  manifestReference?: HashedUriCheck;
+  hardBindingValid?: boolean;
+  thumbnail?: ThumbnailConsistency;
+  /**
+   * Why the supplied asset couldn't be read
+   */
+  error?: string;
 }
 
-export interface SignatureInfo {
+/**
+ * An ingredient asset supplied alongside the main asset, identified by the instance or document ID
+ * its ingredient records
+ */
+export interface IngredientAsset {
+  instanceId?: string;
+  documentId?: string;
+  buffer: ArrayBuffer | Uint8Array;
+  mimeType: string;
+}
+
+/**
+ * An error reading one manifest of the store
+ */
+export interface ManifestError {
+  /**
+   * The name of the error, as the toolkit's thrown errors have it (e.g. `C2pa(ClaimDecoding)`)
+   */
+  code: string;
+  message: string;
+}
+
+export interface SignatureInfo extends Generated.SignatureInfo {
   issuer?: string;
   time?: string;
   cert_serial_number?: string;
+  /**
+   * Revocation status of the signing certificate, from the OCSP response stapled to the signature
+   */
+  revocationStatus?: RevocationStatus;
+  /**
+   * When the stapled OCSP response was produced (RFC 3339)
+   */
+  ocspProducedAt?: string;
+  /**
+   * How the signature's timestamp compares with the time of validation and the signing
+   * certificate, see the `timestampSkew` read option. Absent when the signature has no timestamp,
+   * and in version 1 of the output.
+   */
+  timestampSkew?: TimestampSkew;
+}
+
+export type RevocationStatus = 'good' | 'revoked' | 'unknown' | 'not-present';
+
+export interface TimestampSkew {
+  /**
+   * `futureDated` timestamps lie further ahead of the time of validation than the allowed skew, and
+   * `staleRelativeToSignature` ones further before the signing certificate's `notBefore`
+   */
+  classification: 'withinSkew' | 'futureDated' | 'staleRelativeToSignature';
+  /**
+   * The time the timestamp gives (RFC 3339)
+   */
+  timestampTime: string;
+  /**
+   * How far, in milliseconds, the timestamp lies ahead of the time of validation when future
+   * dated, or from the certificate's `notBefore` when stale, which is negative. Absent within the
+   * allowed skew.
+   */
+  deltaMs?: number;
+  allowedSkewMs: number;
+  /**
+   * Set when the `untrusted` policy stopped the timestamp counting as the time of signing
+   */
+  downgraded: boolean;
+  /**
+   * Whether the signing certificate is valid at the time of validation, present when the
+   * timestamp was downgraded
+   */
+  certificateValidAtValidation?: boolean;
 }
 
-export interface ClaimGeneratorInfo {
+export interface ClaimGeneratorInfo extends Generated.ClaimGeneratorInfo {
   name: string;
   version: string;
+  /**
+   * The generator's icon, stored in the manifest's resources
+   */
+  icon?: ResourceReference;
 }
 
-export interface Manifest extends ResourceParent {
+export interface Manifest extends ResourceParent, Generated.Manifest {
+  resources: ResourceStore;
   vendor?: string;
   claim_generator: string;
   claim_generator_hints?: Record<string, unknown>;
@@ -76,6 +313,88 @@ export interface Manifest extends ResourceParent {
   redactions?: string[];
   label?: string;
   signature_info?: SignatureInfo;
+  /**
+   * The claim exactly as signed, present when reading with `includeRawClaim`
+   */
+  rawClaimBytes?: ArrayBuffer | string;
+  /**
+   * The CDDL version of the claim, present when reading with `includeRawClaim`
+   */
+  claimCddlVersion?: number;
+  /**
+   * The CDDL version of the claim
+   */
+  claimVersion?: number;
+  /**
+   * The major version of the C2PA specification the claim was produced against
+   */
+  specVersion?: number;
+  /**
+   * `full` if the claim has every field its version requires, `partial` if it doesn't, and
+   * `unsupportedVersion` if its version is above the `maxSupportedClaimVersion` read option
+   */
+  complianceLevel?: 'full' | 'partial' | 'unsupportedVersion';
+  /**
+   * The best match for the `locale` read option from each localized string in the manifest, keyed
+   * by the JSON pointer of the localized string within the manifest
+   */
+  displayStrings?: Record<string, string>;
+  /**
+   * `true` for update manifests, which carry no hard binding and update the manifest of their
+   * `parentOf` ingredient
+   */
+  isUpdateManifest?: boolean;
+  /**
+   * The label of the manifest that an update manifest updates
+   */
+  updatedManifest?: string;
+  /**
+   * The hash algorithm used by the manifest's hard binding (e.g. `sha256`), absent for manifests
+   * without one
+   */
+  hardBindingAlg?: string;
+  /**
+   * The files the manifest's `c2pa.hash.collection.data` assertion binds it to, absent for
+   * manifests without one. Check a file against its entry with `verifyAssetInCollection`.
+   */
+  collectionHash?: CollectionEntry[];
+  /**
+   * The common IPTC Photo Metadata fields of the manifest's `c2pa.metadata` (or
+   * `stds.iptc.photo-metadata`) assertion, absent for manifests without one
+   */
+  iptcMetadata?: IptcMetadata;
+  /**
+   * Set when the manifest is signed with an algorithm this build can't verify and the read used
+   * `onUnsupportedAlg: 'degrade'`, or to `invalid-utf8` when its claim had text that isn't valid
+   * UTF-8. The signature wasn't verified, which an `algorithm.unsupported` or
+   * `utf8.claimNotVerified` status against the manifest reports.
+   */
+  signatureVerification?: 'unsupported-algorithm' | 'invalid-utf8';
+  /**
+   * The COSE identifier of the algorithm, alongside `signatureVerification`
+   */
+  signatureAlgorithmId?: number;
+  /**
+   * Set when text in the manifest's claim or CBOR assertions wasn't valid UTF-8. Each invalid
+   * sequence was replaced with U+FFFD so the manifest could be read, and `invalidUtf8` lists the
+   * fields that were repaired.
+   */
+  hadInvalidUtf8?: true;
+  /**
+   * The fields that had invalid UTF-8: the URL of the claim or assertion, and a JSON pointer to the
+   * field within it
+   */
+  invalidUtf8?: InvalidUtf8Field[];
+  /**
+   * The most severe of the store's validation statuses that refer to this manifest, `info` if
+   * there are none. Statuses that don't refer to a manifest count for the active manifest.
+   */
+  worstSeverity: Severity;
+}
+
+export interface InvalidUtf8Field {
+  url: string;
+  path: string;
 }
 
 type ManifestAssertionKind = 'Cbor' | 'Json' | 'Binary' | 'Uri';
@@ -84,19 +403,57 @@ export type AssertionData<U = unknown> = {
   metadata?: Metadata;
 } & U;
 
-export interface Assertion<T = string, U = unknown> {
+/**
+ * Whether the hash a claim records for a box still matches the box
+ */
+export interface HashedUriCheck {
+  hashedUriValid: boolean;
+  /**
+   * The recorded hash as hex, with the `includeHashDigests` read option
+   */
+  expectedHash?: string;
+  /**
+   * The hash of the box as read as hex, with the `includeHashDigests` read option, unless the box
+   * is missing or its hash algorithm isn't supported
+   */
+  actualHash?: string;
+}
+
+export interface Assertion<T = string, U = unknown>
+  extends Partial<HashedUriCheck> {
   label: T;
+  /**
+   * Left out when the assertion is `truncated`
+   */
   data: AssertionData<U>;
   instance?: number;
   kind?: ManifestAssertionKind;
+  /**
+   * Set when the assertion's data is larger than the `maxAssertionBytes` read option and was left
+   * out. Use `getAssertion` to read it.
+   */
+  truncated?: true;
+  /**
+   * The size of the assertion's data as JSON, in bytes, when it is `truncated`
+   */
+  size?: number;
 }
 
-export type C2paActionsAssertion = Assertion<
-  'c2pa.actions',
-  {
-    actions: ActionV1[];
-  }
->;
+export interface C2paActionsData {
+  /**
+   * 1 for `c2pa.actions` and 2 for `c2pa.actions.v2`
+   */
+  version: 1 | 2;
+  actions: Action[];
+  /**
+   * Properties shared by the actions of the same type, only in version 2
+   */
+  templates?: ActionTemplate[];
+}
+
+export type C2paActionsAssertion =
+  | Assertion<'c2pa.actions', C2paActionsData>
+  | Assertion<'c2pa.actions.v2', C2paActionsData>;
 
 export type C2paHashDataAssertion = Assertion<
   'c2pa.hash.data',
@@ -131,6 +488,38 @@ export interface ActionV1 {
   instanceId?: string;
   parameters?: Parameters;
   digitalSourceType?: string;
+  description?: string | LocalizedString;
+  /**
+   * `true` for custom actions registered with `registerActionVocabulary`
+   */
+  registered?: boolean;
+  /**
+   * The description the custom action was registered with
+   */
+  registeredDescription?: string;
+  /**
+   * The parameters the custom action was registered as requiring that this action lacks
+   */
+  missingParameters?: string[];
+}
+
+export interface ActionV2 extends Omit<ActionV1, 'softwareAgent'> {
+  softwareAgent?: ClaimGeneratorInfo;
+  reason?: string;
+}
+
+export type Action = ActionV1 | ActionV2;
+
+export interface ActionTemplate {
+  /**
+   * The action the template applies to, or `*` for all actions
+   */
+  action: string;
+  softwareAgent?: ClaimGeneratorInfo;
+  description?: string | LocalizedString;
+  digitalSourceType?: string;
+  icon?: ResourceReference;
+  templateParameters?: Record<string, unknown>;
 }
 
 export interface Parameters {
@@ -172,7 +561,7 @@ export interface Proof {
   verification_method: string;
 }
 
-export interface Metadata {
+export interface Metadata extends Generated.Metadata {
   reviewRatings?: ReviewRating[];
   dateTime?: string;
   reference?: HashedUri;
@@ -180,7 +569,7 @@ export interface Metadata {
   [key: string]: unknown;
 }
 
-export interface ReviewRating {
+export interface ReviewRating extends Generated.ReviewRating {
   value: 1 | 2 | 3 | 4 | 5;
   code?: ReviewCode;
   explanation: string;
@@ -198,7 +587,7 @@ export type ReviewCode =
   | 'stds.schema-org.CreativeWork.misattributed'
   | 'stds.schema-org.CreativeWork.missingAttribution';
 
-export interface DataSource {
+export interface DataSource extends Generated.DataSource {
   type: SourceType;
   details?: string;
   actors?: Actor[];
@@ -215,7 +604,7 @@ export type SourceType =
   | 'humanEntry.anonymous'
   | 'humanEntry.identified';
 
-export interface Actor {
+export interface Actor extends Generated.Actor {
   identifier?: string;
   credentials?: HashedUri[];
 }
@@ -227,3 +616,819 @@ export interface Actor {
 export interface ToolkitError extends Error {
   url?: string;
 }
+
+/**
+ * Options for reading a manifest store
+ */
+export interface ReadOptions {
+  /**
+   * Attach each manifest's claim bytes, exactly as signed, as `rawClaimBytes`
+   */
+  includeRawClaim?: boolean;
+  /**
+   * Return `rawClaimBytes` as an `ArrayBuffer` (the default) or a base64 string
+   */
+  rawClaimEncoding?: 'arrayBuffer' | 'base64';
+  /**
+   * Fetches ingredient thumbnails that are referenced by URI instead of being embedded. Return
+   * `null` if the resource isn't available.
+   */
+  resourceResolver?: (uri: string) => Promise<ArrayBuffer | null>;
+  /**
+   * A BCP 47 locale to resolve localized strings for, see `Manifest.displayStrings`
+   */
+  locale?: string;
+  /**
+   * How many ingredients deep the provenance chain is followed from the active manifest
+   * (32 by default). Deeper manifests are left out, the ingredients referencing them are marked
+   * with `truncatedAtDepth`, and a warning is added to the manifest store.
+   */
+  maxDepth?: number;
+  /**
+   * Include the expected and actual digests behind each `hashedUriValid`
+   */
+  includeHashDigests?: boolean;
+  /**
+   * Report manifests with a higher claim version with a `complianceLevel` of `unsupportedVersion`.
+   * Defaults to the highest claim version the toolkit understands.
+   */
+  maxSupportedClaimVersion?: number;
+  /**
+   * Count warnings as errors in `worstSeverity`, without changing the severity of each status
+   */
+  treatWarningsAsErrors?: boolean;
+  /**
+   * `strict` (the default) fails to read manifest stores with structural defects. `lenient`
+   * repairs the defects that don't affect anything signed or hashed and lists them in
+   * `parseWarnings`. It also finds stores that JPEGs carry after the end of their image data,
+   * which `strict` reports as missing. Cryptographic checks are the same in either mode.
+   */
+  parsing?: 'strict' | 'lenient';
+  /**
+   * Leave out the data of assertions whose JSON is larger than this many bytes, marking them as
+   * `truncated` instead. Assertions are returned in full by default.
+   */
+  maxAssertionBytes?: number;
+  /**
+   * Attach the asset's dimensions, orientation and duration as `assetInfo`. They are read from the
+   * container headers of JPEG, PNG, WebP, ISO BMFF (e.g. MP4) and WAV files without decoding them.
+   */
+  includeAssetInfo?: boolean;
+  /**
+   * When a PNG has no `caBX` chunk, look for a manifest store embedded as base64 text in an `iTXt`
+   * chunk, as early CAI experiments did. The store is validated as usual. Defaults to `false`.
+   */
+  legacyEmbeddings?: boolean;
+  /**
+   * Changes the shape of the manifest store. The default output is unchanged.
+   */
+  serialization?: SerializationOptions;
+  /**
+   * Ingredient assets to check against the ingredients with their instance or document ID, see
+   * `Ingredient.suppliedAssetMatches`. Reads with ingredient assets aren't cached.
+   */
+  ingredientAssets?: IngredientAsset[];
+  /**
+   * Attach a trace of each step of validating the manifest store as `trace`, for comparing how two
+   * versions of the toolkit, or two tools, validate the same asset. Traced reads aren't cached.
+   */
+  trace?: boolean;
+  /**
+   * What to do when a manifest is signed with an algorithm this build can't verify. `error` (the
+   * default) fails the read. `degrade` returns the store with the manifest's content intact and
+   * its `signatureVerification` set to `unsupported-algorithm`. Only that manifest is affected, so
+   * an ingredient's unverifiable signature doesn't change how the active manifest validates.
+   */
+  onUnsupportedAlg?: 'error' | 'degrade';
+  /**
+   * The shape of the output. Version 1 is the output as it was before it was versioned, and
+   * doesn't change. Version 2 is the current output, which new features extend; it carries
+   * `schemaVersion: 2`. Defaults to 1 for now, with a deprecation warning logged on the first
+   * read that doesn't pass it; the default will become 2 in the next release.
+   */
+  schemaVersion?: 1 | 2;
+  /**
+   * Validates the asset as if the manifest with this label were the active one, for reviewing what
+   * the asset claimed before later manifests were added. The manifests after it in the store are
+   * left out, and its hard binding usually no longer matches the asset, which is reported like any
+   * other validation failure. The output is marked `overrideApplied: true`. A label that isn't in
+   * the store fails the read with an `ActiveManifestNotFound` error whose `available` lists the
+   * labels that are.
+   */
+  activeManifestOverride?: string;
+  /**
+   * How signature timestamps dated too far from the time of validation are handled. A timestamp
+   * more than `allowedSkewMs` (5 minutes by default) ahead of the time of validation, or before
+   * its signing certificate's `notBefore`, is reported in `signature_info.timestampSkew`. With the
+   * `warn` policy (the default), it also adds a warning. With `untrusted`, it adds a
+   * `timeStamp.untrusted` status instead, and the signing certificate's validity is checked at the
+   * time of validation, adding `signingCredential.expired` if it isn't valid then.
+   */
+  timestampSkew?: {
+    allowedSkewMs?: number;
+    policy?: 'warn' | 'untrusted';
+  };
+}
+
+/**
+ * The steps of validating a manifest store, in the order they were taken
+ *
+ * At most 1000 steps are recorded, after which further steps are only counted.
+ */
+export interface ValidationTrace {
+  steps: ValidationTraceStep[];
+  droppedSteps: number;
+}
+
+/**
+ * A step of validating a manifest store. Steps carry the hash of what they read rather than the
+ * bytes themselves.
+ */
+export interface ValidationTraceStep {
+  step:
+    | 'storeLocated'
+    | 'manifestLocated'
+    | 'claimDecoded'
+    | 'assertionHashChecked'
+    | 'ingredientReferenceChecked'
+    | 'signatureDecoded'
+    | 'certificateChainLinkChecked'
+    | 'revocationChecked'
+    | 'signatureVerified'
+    | 'credentialTrustChecked'
+    | 'timestampChecked'
+    | 'hardBindingChecked';
+  manifest?: string;
+  /**
+   * What within the manifest the step looked at, such as an assertion label, the signature
+   * algorithm, or the index of a certificate in the chain
+   */
+  target?: string;
+  /**
+   * The SHA-256 of the bytes the step read, as hex
+   */
+  inputHash?: string;
+  /**
+   * `inconclusive` when only informational statuses were reported or a stapled OCSP response
+   * couldn't be used, and `notPresent` when there was nothing to check
+   */
+  outcome: 'passed' | 'failed' | 'inconclusive' | 'notPresent';
+  /**
+   * The validation statuses the outcome was taken from
+   */
+  codes?: string[];
+  /**
+   * How long the step took, or `null` for the signature, trust, timestamp and hard binding steps,
+   * which the c2pa library performs without reporting them individually
+   */
+  durationMs: number | null;
+}
+
+/**
+ * Options for `recoverManifest`, along with the read options
+ */
+export interface RecoveryOptions extends ReadOptions {
+  /**
+   * Returns the recovery identifier encoded by the asset's watermark, or `null` if it has none
+   */
+  detectWatermark: (buffer: ArrayBuffer) => Promise<string | null>;
+  /**
+   * Returns the manifest store stored for a recovery identifier
+   */
+  fetchManifest: (id: string) => Promise<ArrayBuffer>;
+  /**
+   * The soft binding algorithm of the watermark detector, such as `com.example.watermark.1`. When
+   * the active manifest declares a soft binding with it, a hard binding mismatch is a warning.
+   */
+  detectorAlg?: string;
+}
+
+/**
+ * A manifest store found through the asset's watermark, returned by `recoverManifest`
+ */
+export interface RecoveredManifestStore extends ManifestStore {
+  recovered: true;
+  /**
+   * The identifier the watermark decoded to
+   */
+  recoveryId: string;
+  /**
+   * `true` if the asset no longer matches the hard binding of the active manifest, as happens
+   * when it was transcoded after signing
+   */
+  bindingBroken: boolean;
+  /**
+   * `true` if the active manifest declares a soft binding made with `detectorAlg`
+   */
+  softBindingMatched: boolean;
+}
+
+/**
+ * How the manifest store returned by the read APIs is shaped
+ */
+export interface SerializationOptions {
+  /**
+   * `true` (the default) returns `manifests` and each `resources.resources` as plain objects.
+   * With `false` they are `Map`s, keyed the same way and in the same order.
+   */
+  mapsAsObjects?: boolean;
+  /**
+   * Converts every byte field to one representation: resource data, the `hash` of hashed URIs
+   * within assertion data, and `rawClaimBytes`. Without it, resources and hashes are arrays of
+   * numbers and `rawClaimBytes` follows `rawClaimEncoding`.
+   */
+  bytesAs?: 'uint8array' | 'base64' | 'array';
+  /**
+   * Returns every hash as a string in this encoding, wherever it appears: the `hash` of hashed
+   * URIs and hard bindings in assertions and ingredients, and the `expectedHash`, `actualHash` and
+   * `inputHash` of checks and trace steps. `base64url` has no padding. With `none` (the default)
+   * hashes keep their own representation, and with `bytesAs` the other byte fields still follow
+   * it. Resources and `rawClaimBytes` aren't hashes and are left as they are.
+   */
+  binaryEncoding?: 'none' | 'hex' | 'base64' | 'base64url';
+}
+
+/**
+ * Options for `initToolkit`
+ */
+export interface InitOptions {
+  /**
+   * Log to the console (the default). Without it, nothing is logged.
+   */
+  installLogger?: boolean;
+  /**
+   * Defaults to `info`
+   */
+  logLevel?: 'off' | 'error' | 'warn' | 'info' | 'debug' | 'trace';
+  /**
+   * `console` (the default) logs panics with `console.error` before the module traps, while
+   * `none` leaves them unreported
+   */
+  panicHook?: 'console' | 'none';
+}
+
+/**
+ * For `setGeneratorTrustHints`: the organizations or domains expected to sign for each claim
+ * generator, keyed by generator prefix, e.g. `{ 'Adobe Photoshop': ['Adobe', 'adobe.com'] }`.
+ *
+ * Prefixes match whole words at the start of `claim_generator` or a `claim_generator_info` name,
+ * ignoring case and treating `_` and `-` as spaces. The longest matching prefix applies. Entries
+ * with a dot and no spaces are domains, matching the signing certificate's common name or a
+ * subdomain of it. Other entries are organizations, matching a subject organization that starts
+ * with the same words, so `Adobe` matches `Adobe, Inc.`. A manifest whose signer matches none of
+ * the entries gets a `generatorCertMismatch` validation status with `warning` severity.
+ */
+export type GeneratorTrustHints = Record<string, string | string[]>;
+
+/**
+ * For `setParserLimits`: quotas on the JUMBF structure of a manifest store, checked before it is
+ * parsed. A store over any of them fails the read with a `LimitExceeded` error whose `limit` names
+ * the limit, with the `observed` value and the `max` allowed.
+ */
+export interface ParserLimits {
+  /**
+   * The most boxes a store may hold, counting superboxes, description boxes and content boxes.
+   * Defaults to 10000.
+   */
+  maxBoxes?: number;
+  /**
+   * How deeply superboxes may be nested, counting the store itself. Defaults to 32.
+   */
+  maxBoxDepth?: number;
+  /**
+   * How many times the bytes actually left for it a box may declare. Defaults to 1, so a box
+   * can't claim more bytes than there are.
+   */
+  maxDeclaredSizeRatio?: number;
+  /**
+   * Defaults to 64 MiB
+   */
+  maxStoreBytes?: number;
+}
+
+/**
+ * A custom action for `registerActionVocabulary`, e.g. `com.example.reviewed`. Actions in the
+ * `c2pa.` and `stds.` namespaces can't be registered.
+ */
+export interface ActionVocabularyEntry {
+  action: string;
+  /**
+   * Actions without one of these parameters list it in `missingParameters`
+   */
+  requiredParameters?: string[];
+  description?: string;
+}
+
+/**
+ * Limits of the `getManifestStoreFromArrayBuffer` result cache
+ *
+ * Reads of the same asset with the same options resolve to the same object while it is cached, so
+ * callers sharing the cache mustn't modify the manifest stores they get. Copy one with
+ * `structuredClone` before changing it.
+ */
+export interface ReadCacheOptions {
+  /**
+   * Defaults to 64
+   */
+  maxEntries?: number;
+  /**
+   * The combined size of the manifest stores the cached results were read from. Defaults to 64 MiB.
+   */
+  maxBytes?: number;
+  /**
+   * How long a failed read is cached for, in milliseconds. Defaults to 10 seconds.
+   */
+  errorTtlMs?: number;
+}
+
+export interface ReadCacheStats {
+  hits: number;
+  misses: number;
+  /**
+   * Reads that waited for an identical read already in progress
+   */
+  coalesced: number;
+  entries: number;
+  bytes: number;
+}
+
+/**
+ * A guess at why an asset has no valid manifest, returned by `probeProvenanceLoss`
+ *
+ * This is drawn from the asset's container and metadata and says nothing about whether a
+ * manifest's signature is valid. Use `validation_status` for that.
+ */
+export interface ProvenanceLossProbe {
+  /**
+   * - `manifestPresent`: the asset has a readable manifest bound to it
+   * - `remoteManifest`: the asset references a manifest stored elsewhere
+   * - `bindingBroken`: the asset has a manifest that doesn't match it or can't be read, suggesting
+   *   the file was modified
+   * - `stripped`: the asset has no manifest, but its metadata or structure shows it once did
+   * - `noEvidence`: nothing suggests the asset ever had a manifest
+   */
+  explanation: 'manifestPresent' | 'remoteManifest' | 'bindingBroken' | 'stripped' | 'noEvidence';
+  evidence: ProvenanceLossEvidence[];
+}
+
+export interface ProvenanceLossEvidence {
+  /**
+   * - `bindingMismatch`: a hard binding validation code
+   * - `unreadableManifest`: the error reading the manifest store
+   * - `embeddedManifestReference`: an XMP `dcterms:provenance` reference into the asset itself
+   * - `remoteManifestReference`: an XMP `dcterms:provenance` reference to a remote manifest
+   * - `manifestStoreRemnant`: manifest store bytes outside of where a manifest is embedded
+   * - `digitalSourceType`: an IPTC digital source type in the XMP
+   */
+  kind:
+    | 'bindingMismatch'
+    | 'unreadableManifest'
+    | 'embeddedManifestReference'
+    | 'remoteManifestReference'
+    | 'manifestStoreRemnant'
+    | 'digitalSourceType';
+  detail: string | null;
+}
+
+export interface SelfTestOptions {
+  /**
+   * A SHA-256 implementation, such as one wrapping `crypto.subtle.digest`, to check against the
+   * toolkit's own
+   */
+  digest?: (data: Uint8Array) => ArrayBuffer | Uint8Array | Promise<ArrayBuffer | Uint8Array>;
+}
+
+export interface SelfTestCheck {
+  /**
+   * `bufferRoundTrip`, `digest`, `jsDigest`, `manifest`, `signatureVerification`, `signing`,
+   * `timestampRequest` or `errorConversion`
+   */
+  name: string;
+  status: 'passed' | 'failed' | 'skipped';
+  /**
+   * How long the check took, in milliseconds
+   */
+  duration: number;
+  /**
+   * Why the check failed or was skipped
+   */
+  message: string | null;
+}
+
+export interface SelfTestResult {
+  /**
+   * Whether no check failed. Skipped checks don't count as failures.
+   */
+  passed: boolean;
+  checks: SelfTestCheck[];
+}
+
+export interface MemoryStats {
+  /**
+   * The size of the WebAssembly memory, in 64 KiB pages. It never shrinks.
+   */
+  memoryPages: number;
+  memoryBytes: number;
+  /**
+   * Bytes currently allocated by the toolkit
+   */
+  allocatedBytes: number;
+  /**
+   * The most bytes allocated at once since the toolkit loaded or `releaseInternalBuffers` was called
+   */
+  peakAllocatedBytes: number;
+  /**
+   * The size of the manifest stores behind the cached read results
+   */
+  readCacheBytes: number;
+  readCacheEntries: number;
+}
+
+/**
+ * A string given in several languages
+ */
+export type LocalizedString = { lang: string; value: string }[];
+
+/**
+ * An assertion returned by `getAssertions`
+ */
+export interface AssertionMatch {
+  manifestLabel: string;
+  /**
+   * The assertion's label, including its instance suffix (e.g. `c2pa.actions__1`)
+   */
+  assertionLabel: string;
+  data: unknown;
+  isFromActiveManifest: boolean;
+}
+
+/**
+ * Result of `getMetadataSummary`
+ */
+export interface MetadataSummary {
+  /**
+   * The manifest store, or `null` if the asset has no C2PA provenance
+   */
+  c2pa: ManifestStore | null;
+  /**
+   * Attribution from the asset's XMP, or `null` if it has none or its XMP can't be parsed
+   */
+  legacyMetadata: LegacyMetadata | null;
+}
+
+/**
+ * Attribution read from the XMP of JPEG, PNG and TIFF files
+ */
+export interface LegacyMetadata {
+  /**
+   * `dc:creator`
+   */
+  creators?: string[];
+  /**
+   * `photoshop:Credit`
+   */
+  creditLine?: string;
+  /**
+   * `xmpRights:WebStatement`
+   */
+  webStatement?: string;
+  /**
+   * `plus:Licensor`
+   */
+  licensors?: { name?: string; url?: string }[];
+}
+
+/**
+ * IPTC Photo Metadata read from a manifest's metadata assertion. Properties are matched by XMP
+ * namespace, whatever prefix the assertion uses.
+ */
+export interface IptcMetadata {
+  /**
+   * `dc:creator`
+   */
+  creators?: string[];
+  /**
+   * `dc:title`
+   */
+  title?: string;
+  /**
+   * `photoshop:Headline`
+   */
+  headline?: string;
+  /**
+   * `dc:description`
+   */
+  caption?: string;
+  /**
+   * `dc:subject`
+   */
+  keywords?: string[];
+  /**
+   * `photoshop:DateCreated`
+   */
+  dateCreated?: string;
+  /**
+   * `photoshop:Credit`
+   */
+  creditLine?: string;
+  /**
+   * `photoshop:Source`
+   */
+  source?: string;
+  /**
+   * `dc:rights`
+   */
+  copyrightNotice?: string;
+  /**
+   * `xmpRights:UsageTerms`
+   */
+  usageTerms?: string;
+  /**
+   * `xmpRights:WebStatement`
+   */
+  webStatement?: string;
+  /**
+   * `plus:Licensor`
+   */
+  licensors?: { name?: string; url?: string }[];
+  /**
+   * The properties outside this mapping, and those whose values aren't text, as they appear in
+   * the assertion
+   */
+  other?: Record<string, unknown>;
+}
+
+/**
+ * A file listed in a manifest's collection hash
+ */
+export interface CollectionEntry {
+  /**
+   * The file's URI relative to the collection
+   */
+  name: string;
+  /**
+   * The recorded hash as hex
+   */
+  hash: string;
+  alg: string;
+  size?: number;
+  format?: string;
+  /**
+   * `true` if another entry has the same name, in which case neither can be verified
+   */
+  duplicate: boolean;
+}
+
+/**
+ * Result of `verifyAssetInCollection`
+ */
+export interface CollectionEntryCheck {
+  manifestLabel: string;
+  name: string;
+  hashValid: boolean;
+  expectedHash: string;
+  /**
+   * The hash of the file as hex, unless the hash algorithm isn't supported
+   */
+  actualHash?: string;
+}
+
+/**
+ * The parts of a manifest's COSE_Sign1 signature, returned by `getSignatureMaterial`
+ *
+ * `signature` verifies against `toBeSigned`, the COSE `Sig_structure` built from `protectedHeader`
+ * and `payload`, with the public key of the first certificate.
+ */
+export interface SignatureMaterial {
+  protectedHeader: ArrayBuffer;
+  /**
+   * The claim that was signed
+   */
+  payload: ArrayBuffer;
+  signature: ArrayBuffer;
+  toBeSigned: ArrayBuffer;
+  /**
+   * The DER encoded certificate chain, starting with the signing certificate
+   */
+  certificates: ArrayBuffer[];
+  /**
+   * The signing algorithm, such as `es256` or `ps256`
+   */
+  alg?: string;
+}
+
+/**
+ * The properties the toolkit adds to the errors it throws
+ */
+export interface ToolkitError extends Error {
+  /**
+   * A stable code for the error, such as `jumbfNotFound` or `coseCertRevoked`
+   */
+  code: string;
+  category: 'input' | 'format' | 'crypto' | 'trust' | 'internal';
+  /**
+   * Whether the same call may succeed if tried again
+   */
+  retriable: boolean;
+  /**
+   * For an `unknownUpstream` code, the error the c2pa library raised
+   */
+  upstream?: string;
+}
+
+/**
+ * Options of `getManifestStoresFromContainer`, along with the read options applied to each entry
+ */
+export interface ContainerOptions extends ReadOptions {
+  /**
+   * Skip entries larger than this many bytes once uncompressed (100 MiB by default)
+   */
+  maxEntrySize?: number;
+}
+
+/**
+ * An image or other media file packaged in a container, as read by
+ * `getManifestStoresFromContainer`
+ */
+export type ContainerEntry =
+  | {
+      /**
+       * The format of the entry, from its leading bytes or else its extension
+       */
+      mimeType: string;
+      manifestStore: ManifestStore | null;
+    }
+  | { mimeType?: string; error: Error }
+  | { skipped: 'tooLarge'; size: number };
+
+/**
+ * A hash identifying an asset, as hex
+ */
+export interface IdentityHash {
+  alg: string;
+  hash: string;
+}
+
+/**
+ * Result of `getAssetIdentity`, for keying cached verification results
+ *
+ * For an asset with a manifest store, these are the hashes its active manifest's hard bindings
+ * declare, which only identify the asset once a verification has found them to match, and the
+ * SHA-256 hash of the manifest store. An asset without one gets an XXH3 fingerprint of its bytes,
+ * which is fast but not cryptographic.
+ */
+export type AssetIdentity =
+  | {
+      kind: 'manifest';
+      activeManifest: string;
+      bindings: {
+        label: string;
+        alg?: string;
+        /**
+         * A single hash for data and BMFF hashes, and one per box for box hashes
+         */
+        hashes: string[];
+      }[];
+      manifestStore: IdentityHash;
+    }
+  | { kind: 'fingerprint'; fingerprint: IdentityHash; cryptographic: false };
+
+/**
+ * Result of `hasManifest`, describing where an asset's manifest can be found
+ */
+export type ManifestPresence =
+  | { kind: 'embedded' }
+  | { kind: 'remote'; url: string }
+  | { kind: 'none' };
+
+/**
+ * Options of `verifyThumbnailConsistency`
+ */
+export interface ThumbnailOptions {
+  /**
+   * The largest distance, out of 64 bits, at which the thumbnail still matches the asset (10 by
+   * default)
+   */
+  threshold?: number;
+}
+
+/**
+ * How closely the claim thumbnail of the active manifest resembles the asset, from a perceptual
+ * hash of each. This is advisory and doesn't affect validation.
+ */
+export interface ThumbnailConsistency {
+  /**
+   * Whether `distance` is within `threshold`, or `null` if the images couldn't be compared
+   */
+  consistent: boolean | null;
+  /**
+   * The number of bits the hashes of the thumbnail and the asset differ by, out of 64
+   */
+  distance: number | null;
+  threshold: number;
+  /**
+   * Why the images couldn't be compared. Only JPEG and PNG images are decoded, so videos are
+   * `unsupportedFormat`.
+   */
+  notEvaluable: 'noThumbnail' | 'unsupportedFormat' | 'decodingFailed' | null;
+  /**
+   * The EXIF orientation the thumbnail was turned upright with before comparing, if it has one
+   */
+  thumbnailOrientation: number | null;
+  /**
+   * The EXIF orientation the asset was turned upright with before comparing, if it has one
+   */
+  assetOrientation: number | null;
+}
+
+/**
+ * The provenance chain as returned by `getProvenanceGraph`, with each manifest appearing once
+ */
+export interface ProvenanceGraph {
+  activeManifest: string | null;
+  /**
+   * The manifests of the store, in the order they are reached from the active manifest, and the
+   * ingredients that have no manifest in the store
+   */
+  nodes: ProvenanceNode[];
+  /**
+   * One edge from each manifest to each of its ingredients
+   */
+  edges: ProvenanceEdge[];
+  /**
+   * Edges to a manifest that is its own ingredient, directly or further down the chain, which
+   * are left out of `edges`
+   */
+  backEdges: ProvenanceEdge[];
+}
+
+export interface ProvenanceNode {
+  /**
+   * The manifest label, or `<manifest label>/ingredients/<index>` for an ingredient without a
+   * manifest in the store
+   */
+  id: string;
+  /**
+   * The manifest label, or for an ingredient, the label of the manifest it references if any
+   */
+  label: string | null;
+  title: string | null;
+  /**
+   * The claim generator, `null` for ingredients
+   */
+  generator: string | null;
+  /**
+   * The issuer of the signing certificate, `null` for ingredients
+   */
+  signer: string | null;
+  validation: ValidationSummary;
+}
+
+export interface ValidationSummary {
+  worstSeverity: Severity;
+  /**
+   * The codes of the statuses that are warnings or errors
+   */
+  codes: string[];
+}
+
+export interface ProvenanceEdge {
+  /**
+   * The `id` of the manifest the ingredient belongs to
+   */
+  from: string;
+  /**
+   * The `id` of the ingredient's node
+   */
+  to: string;
+  relationship: 'parentOf' | 'componentOf' | 'inputTo';
+}
+
+/**
+ * A fragment of a fragmented MP4 on the coverage timeline, or time that no fragment covers.
+ * Fragments are ordered by start time, with those whose timing couldn't be read last.
+ */
+export type CoverageTimelineEntry =
+  | {
+      kind: 'fragment';
+      startTimeMs: number | null;
+      endTimeMs: number | null;
+      fragmentIndex: number;
+      /**
+       * Whether the fragment validated without errors against the manifest in the init segment
+       */
+      validated: boolean;
+      /**
+       * The codes of the fragment's validation statuses, or of the error that failed it
+       */
+      validationCodes: string[];
+      /**
+       * The indices of the fragments whose time ranges overlap this one
+       */
+      overlaps: number[];
+    }
+  | {
+      kind: 'gap';
+      startTimeMs: number;
+      endTimeMs: number;
+    };